@@ -1,9 +1,12 @@
 use nalgebra::{Vector2, Vector4};
 pub use speedy::{Readable, Writable};
+use std::io::{BufRead, Read};
 use std::iter::Sum;
 
 pub use nalgebra;
 
+pub mod louvain;
+
 // 24bpp color structure
 #[derive(Copy, Clone, Readable, Writable)]
 #[repr(C)]
@@ -38,6 +41,101 @@ impl Color3b {
     pub fn to_u32(self) -> u32 {
         (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
     }
+
+    /// Converts to the Oklab perceptual color space, as (lightness, a, b).
+    #[allow(clippy::excessive_precision)]
+    fn to_oklab(self) -> [f32; 3] {
+        fn linear(c: u8) -> f32 {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        let (r, g, b) = (linear(self.r), linear(self.g), linear(self.b));
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        [
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        ]
+    }
+
+    /// Converts from Oklab, clamping to the sRGB gamut.
+    #[allow(clippy::excessive_precision)]
+    fn from_oklab([lightness, a, b]: [f32; 3]) -> Color3b {
+        fn srgb(c: f32) -> u8 {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            };
+            (c * 255.0).round() as u8
+        }
+        let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+        Color3b {
+            r: srgb(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+            g: srgb(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+            b: srgb(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s),
+        }
+    }
+
+    /// Perceptual lightness, from 0 (black) to 1 (white).
+    pub fn lightness(self) -> f32 {
+        self.to_oklab()[0]
+    }
+
+    /// Brightens the color to at least the given perceptual lightness, keeping its hue.
+    pub fn with_min_lightness(self, min: f32) -> Color3b {
+        let [lightness, a, b] = self.to_oklab();
+        if lightness >= min {
+            return self;
+        }
+        Color3b::from_oklab([min, a, b])
+    }
+
+    /// Color with the given Oklch lightness, chroma and hue (in radians).
+    pub fn from_oklch(lightness: f32, chroma: f32, hue: f32) -> Color3b {
+        Color3b::from_oklab([lightness, chroma * hue.cos(), chroma * hue.sin()])
+    }
+
+    /// Perceptually spaced colors. Hues are spread with the golden angle, so that the first colors
+    /// are the furthest apart, and the lightness cycles through a few levels to tell apart the
+    /// colors whose hues end up close.
+    pub fn palette(count: usize) -> Vec<Color3b> {
+        const GOLDEN_ANGLE: f32 = 2.399_963;
+        const LIGHTNESS: [f32; 3] = [0.72, 0.58, 0.86];
+        (0..count)
+            .map(|i| {
+                Color3b::from_oklch(
+                    LIGHTNESS[i % LIGHTNESS.len()],
+                    0.14,
+                    i as f32 * GOLDEN_ANGLE,
+                )
+            })
+            .collect()
+    }
+
+    /// Formats the color as `#rrggbb`.
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// Parses a color formatted as `#rrggbb`.
+    pub fn from_hex(hex: &str) -> Option<Color3b> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Color3b::new(channel(0)?, channel(2)?, channel(4)?))
+    }
 }
 
 impl Color3f {
@@ -204,9 +302,58 @@ pub type LenType = u64;
 #[cfg(target_pointer_width = "64")]
 pub type LenType = usize;
 
+/// Magic number at the start of every versioned graph file.
+pub const GRAPH_MAGIC: [u8; 4] = *b"GRPH";
+
+/// Version of the graph file format written by the importer.
+///
+/// Version 1 is the legacy layout, which had no header at all. Version 2 introduced the header,
+/// and version 3 the `directed` flag.
+pub const GRAPH_VERSION: u16 = 3;
+
+/// Most classes a file can hold, since their count and the class of each node take 16 bits.
+pub const MAX_CLASSES: usize = u16::MAX as usize;
+
+/// Highest degree of a node in a file, since the edge counts of each node take 16 bits.
+pub const MAX_DEGREE: usize = u16::MAX as usize;
+
+/// Checks that `count` classes fit in a file, see [`MAX_CLASSES`].
+pub fn check_class_count(count: usize) -> Result<(), FormatError> {
+    if count > MAX_CLASSES {
+        return Err(FormatError::TooManyClasses(count));
+    }
+    Ok(())
+}
+
 #[derive(Readable, Default)]
 #[cfg_attr(target_pointer_width = "64", derive(Writable))]
 pub struct GraphFile {
+    pub magic: [u8; 4],
+    pub version: u16,
+    /// Whether edges are directed. An edge `e` in the list of node `i` goes from `e` to `i`.
+    pub directed: bool,
+
+    pub class_count: u16,
+    #[speedy(length = class_count)]
+    pub classes: Vec<Color3b>,
+
+    pub node_count: LenType,
+    #[speedy(length = node_count)]
+    pub nodes: Vec<NodeStore>,
+
+    pub ids_size: LenType,
+    #[speedy(length = ids_size)]
+    pub ids: Vec<u8>,
+
+    pub names_size: LenType,
+    #[speedy(length = names_size)]
+    pub names: Vec<u8>,
+}
+
+/// Graph file layout used before the header was introduced (version 1).
+#[derive(Readable)]
+#[cfg_attr(target_pointer_width = "64", derive(Writable))]
+pub struct LegacyGraphFile {
     pub class_count: u16,
     #[speedy(length = class_count)]
     pub classes: Vec<Color3b>,
@@ -224,7 +371,217 @@ pub struct GraphFile {
     pub names: Vec<u8>,
 }
 
+impl From<LegacyGraphFile> for GraphFile {
+    fn from(f: LegacyGraphFile) -> GraphFile {
+        GraphFile {
+            magic: GRAPH_MAGIC,
+            version: GRAPH_VERSION,
+            directed: false,
+            class_count: f.class_count,
+            classes: f.classes,
+            node_count: f.node_count,
+            nodes: f.nodes,
+            ids_size: f.ids_size,
+            ids: f.ids,
+            names_size: f.names_size,
+            names: f.names,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The file was written by a newer version of the importer.
+    NewerVersion(u16),
+    /// The graph has more classes than a file can hold, see [`MAX_CLASSES`].
+    TooManyClasses(usize),
+    /// A node has more edges than a file can hold, see [`MAX_DEGREE`].
+    DegreeTooHigh { node: usize, degree: usize },
+    Io(std::io::Error),
+    Speedy(speedy::Error),
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::NewerVersion(v) => write!(
+                f,
+                "file format version {} is newer than the supported version {}",
+                v, GRAPH_VERSION
+            ),
+            FormatError::TooManyClasses(count) => write!(
+                f,
+                "{} classes, but a graph file holds at most {}",
+                count, MAX_CLASSES
+            ),
+            FormatError::DegreeTooHigh { node, degree } => write!(
+                f,
+                "node {} has {} edges, but a graph file holds at most {} per node",
+                node, degree, MAX_DEGREE
+            ),
+            FormatError::Io(e) => write!(f, "{}", e),
+            FormatError::Speedy(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<std::io::Error> for FormatError {
+    fn from(e: std::io::Error) -> FormatError {
+        FormatError::Io(e)
+    }
+}
+
+impl From<speedy::Error> for FormatError {
+    fn from(e: speedy::Error) -> FormatError {
+        FormatError::Speedy(e)
+    }
+}
+
+/// Problems found in the edge lists of a graph file by [`GraphFile::validate`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Edges going from a node to itself.
+    pub self_loops: usize,
+    /// Edges appearing more than once, in either list for undirected graphs.
+    pub duplicate_edges: usize,
+    /// Edges pointing to a node index past `node_count`.
+    pub out_of_range: usize,
+}
+
+impl ValidationReport {
+    /// Whether the file can be loaded as is.
+    pub fn is_valid(&self) -> bool {
+        self.out_of_range == 0
+    }
+}
+
 impl GraphFile {
+    /// Checks the edge lists of the nodes for self-loops, duplicates and out-of-range indices.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut seen = std::collections::HashSet::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            for &e in node.edges.iter() {
+                let e = e as usize;
+                if e == i {
+                    report.self_loops += 1;
+                } else if e >= self.nodes.len() {
+                    report.out_of_range += 1;
+                } else {
+                    let key = if self.directed {
+                        (e, i)
+                    } else {
+                        (e.min(i), e.max(i))
+                    };
+                    if !seen.insert(key) {
+                        report.duplicate_edges += 1;
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns the format version of a serialized graph file, 1 meaning the legacy header-less
+    /// layout.
+    pub fn detect_version(buf: &[u8]) -> u16 {
+        match buf {
+            [m0, m1, m2, m3, v0, v1, ..] if [*m0, *m1, *m2, *m3] == GRAPH_MAGIC => {
+                u16::from_le_bytes([*v0, *v1])
+            }
+            _ => 1,
+        }
+    }
+
+    /// Reads a graph file of any supported version.
+    pub fn read_versioned(buf: &[u8]) -> Result<GraphFile, FormatError> {
+        let mut stream = GraphFileStream::new(buf)?;
+        let nodes = (0..stream.node_count)
+            .map(|_| stream.read_node())
+            .collect::<Result<Vec<_>, _>>()?;
+        let classes = std::mem::take(&mut stream.classes);
+        let directed = stream.directed;
+        let (ids, names) = stream.finish()?;
+        Ok(GraphFile {
+            magic: GRAPH_MAGIC,
+            version: GRAPH_VERSION,
+            directed,
+            class_count: classes.len() as u16,
+            classes,
+            node_count: nodes.len() as LenType,
+            nodes,
+            ids_size: ids.len() as LenType,
+            ids,
+            names_size: names.len() as LenType,
+            names,
+        })
+    }
+
+    pub fn read_versioned_from_file(path: impl AsRef<std::path::Path>) -> Result<GraphFile, FormatError> {
+        let buf = std::fs::read(path)?;
+        GraphFile::read_versioned(&buf)
+    }
+
+    /// Sorts the edges and stores them in the edge lists of the nodes, updating their degrees. The
+    /// edge `(a, b)` is stored in the list of `b`, so in directed graphs it goes from `a` to `b`.
+    ///
+    /// Fails without changing the nodes if one of them would have more than [`MAX_DEGREE`] edges.
+    pub fn set_edges(&mut self, edges: &mut [(usize, usize)]) -> Result<(), FormatError> {
+        let mut degrees = vec![0usize; self.nodes.len()];
+        for &(a, b) in edges.iter() {
+            degrees[a] += 1;
+            degrees[b] += 1;
+        }
+        if let Some((node, &degree)) = degrees.iter().enumerate().find(|(_, &d)| d > MAX_DEGREE) {
+            return Err(FormatError::DegreeTooHigh { node, degree });
+        }
+
+        edges.sort_unstable();
+        for &(a, b) in edges.iter() {
+            self.nodes[a].total_edge_count += 1;
+            self.nodes[b].edges.push(a as u32);
+            self.nodes[b].total_edge_count += 1;
+        }
+        for n in self.nodes.iter_mut() {
+            n.edge_count = n.edges.len() as u16;
+        }
+        Ok(())
+    }
+
+    /// Fills in the header and the lengths of the variable-size fields, before writing the file.
+    pub fn finish_header(&mut self) {
+        self.magic = GRAPH_MAGIC;
+        self.version = GRAPH_VERSION;
+        self.class_count = self.classes.len() as u16;
+        self.node_count = self.nodes.len() as LenType;
+        self.ids_size = self.ids.len() as LenType;
+        self.names_size = self.names.len() as LenType;
+    }
+
+    /// Serializes the file. Unlike the derived [`Writable`], which is only available on 64-bit
+    /// targets, this also works in the browser, where the lengths don't fit in a `usize`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&self.magic);
+        buf.extend_from_slice(&self.version.to_le_bytes());
+        buf.push(self.directed as u8);
+        buf.extend_from_slice(&(self.classes.len() as u16).to_le_bytes());
+        for class in self.classes.iter() {
+            buf.extend_from_slice(&[class.r, class.g, class.b]);
+        }
+        buf.extend_from_slice(&(self.nodes.len() as u64).to_le_bytes());
+        for node in self.nodes.iter() {
+            node.write_to_stream(&mut buf)?;
+        }
+        for table in [&self.ids, &self.names] {
+            buf.extend_from_slice(&(table.len() as u64).to_le_bytes());
+            buf.extend_from_slice(table);
+        }
+        Ok(buf)
+    }
+
     pub fn get_adjacency(&self) -> Vec<Vec<u32>> {
         let mut persons: Vec<_> = self.nodes.iter().map(|n| Vec::with_capacity(n.total_edge_count as usize)).collect();
         for (i, n) in self.nodes.iter().enumerate() {
@@ -236,3 +593,277 @@ impl GraphFile {
         persons
     }
 }
+
+/// Counts the bytes consumed from a reader.
+struct CountingReader<R> {
+    inner: R,
+    position: usize,
+    /// Start of the file, taken from `inner` to detect the version and served again before it.
+    header: Vec<u8>,
+    header_pos: usize,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = if self.header_pos < self.header.len() {
+            let n = (self.header.len() - self.header_pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.header[self.header_pos..][..n]);
+            self.header_pos += n;
+            n
+        } else {
+            self.inner.read(buf)?
+        };
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.header_pos < self.header.len() {
+            Ok(&self.header[self.header_pos..])
+        } else {
+            self.inner.fill_buf()
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position += amt;
+        if self.header_pos < self.header.len() {
+            self.header_pos += amt;
+        } else {
+            self.inner.consume(amt)
+        }
+    }
+}
+
+/// Incremental graph file reader.
+///
+/// Decodes the header and classes upfront, then the nodes one at a time, so that the whole file
+/// never has to be held in memory at once.
+pub struct GraphFileStream<R> {
+    reader: CountingReader<R>,
+    pub version: u16,
+    pub directed: bool,
+    pub classes: Vec<Color3b>,
+    pub node_count: usize,
+    nodes_read: usize,
+}
+
+impl<R: BufRead> GraphFileStream<R> {
+    pub fn new(reader: R) -> Result<Self, FormatError> {
+        let mut inner = reader;
+        let header_size = GRAPH_MAGIC.len() + size_of::<u16>();
+        // the reader may hand out less than the whole header at once
+        let mut header = Vec::with_capacity(header_size);
+        while header.len() < header_size {
+            let avail = inner.fill_buf()?;
+            if avail.is_empty() {
+                break;
+            }
+            let n = avail.len().min(header_size - header.len());
+            header.extend_from_slice(&avail[..n]);
+            inner.consume(n);
+        }
+        let version = GraphFile::detect_version(&header);
+        let mut reader = CountingReader {
+            inner,
+            position: 0,
+            header,
+            header_pos: 0,
+        };
+        let directed = match version {
+            1 => false,
+            2 => {
+                reader.consume(header_size);
+                false
+            }
+            GRAPH_VERSION => {
+                reader.consume(header_size);
+                bool::read_from_stream_unbuffered(&mut reader)?
+            }
+            v => return Err(FormatError::NewerVersion(v)),
+        };
+        let class_count = u16::read_from_stream_unbuffered(&mut reader)?;
+        let classes = (0..class_count)
+            .map(|_| Color3b::read_from_stream_unbuffered(&mut reader))
+            .collect::<Result<_, _>>()?;
+        let node_count = LenType::read_from_stream_unbuffered(&mut reader)? as usize;
+        Ok(GraphFileStream {
+            reader,
+            version,
+            directed,
+            classes,
+            node_count,
+            nodes_read: 0,
+        })
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.reader.position
+    }
+
+    /// Reads the next node. Must be called exactly `node_count` times before [`Self::finish`].
+    pub fn read_node(&mut self) -> Result<NodeStore, FormatError> {
+        assert!(self.nodes_read < self.node_count, "all nodes have already been read");
+        self.nodes_read += 1;
+        Ok(NodeStore::read_from_stream_unbuffered(&mut self.reader)?)
+    }
+
+    /// Reads the string tables at the end of the file, returning `(ids, names)`.
+    pub fn finish(mut self) -> Result<(Vec<u8>, Vec<u8>), FormatError> {
+        assert_eq!(self.nodes_read, self.node_count, "not all nodes have been read");
+        let mut read_table = || -> Result<Vec<u8>, FormatError> {
+            let size = LenType::read_from_stream_unbuffered(&mut self.reader)? as usize;
+            let mut table = vec![0; size];
+            self.reader.read_exact(&mut table)?;
+            Ok(table)
+        };
+        let ids = read_table()?;
+        let names = read_table()?;
+        Ok((ids, names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn node(edges: Vec<u32>) -> NodeStore {
+        NodeStore {
+            position: Point::new(0.0, 0.0),
+            size: 1.0,
+            class: 0,
+            offset_id: 0,
+            offset_name: 0,
+            total_edge_count: 0,
+            edge_count: edges.len() as u16,
+            edges,
+        }
+    }
+
+    fn file(directed: bool, nodes: Vec<NodeStore>) -> GraphFile {
+        let mut file = GraphFile {
+            directed,
+            nodes,
+            ..Default::default()
+        };
+        file.finish_header();
+        file
+    }
+
+    #[test]
+    fn min_lightness() {
+        let navy = Color3b::new(0, 0, 128);
+        let brightened = navy.with_min_lightness(0.6);
+        assert!((brightened.lightness() - 0.6).abs() < 0.01);
+        // still blue
+        assert!(brightened.b > brightened.r && brightened.b > brightened.g);
+
+        let yellow = Color3b::new(255, 255, 0);
+        assert_eq!(yellow.with_min_lightness(0.6).to_u32(), yellow.to_u32());
+    }
+
+    #[test]
+    fn palette_colors() {
+        let palette = Color3b::palette(30);
+        let distinct: HashSet<u32> = palette.iter().map(|c| c.to_u32()).collect();
+        assert_eq!(distinct.len(), palette.len());
+
+        let color = Color3b::new(18, 52, 255);
+        assert_eq!(color.to_hex(), "#1234ff");
+        assert_eq!(
+            Color3b::from_hex("#1234ff").map(Color3b::to_u32),
+            Some(color.to_u32())
+        );
+        assert!(Color3b::from_hex("1234ff").is_none());
+        assert!(Color3b::from_hex("#12345").is_none());
+        assert!(Color3b::from_hex("#12345g").is_none());
+    }
+
+    #[test]
+    fn valid_file() {
+        let report = file(false, vec![node(vec![]), node(vec![0]), node(vec![0, 1])]).validate();
+        assert_eq!(report, ValidationReport::default());
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn invalid_edges_are_counted() {
+        let report = file(
+            false,
+            vec![node(vec![0, 1]), node(vec![0, 7]), node(vec![1, 3, 1])],
+        )
+        .validate();
+        assert_eq!(
+            report,
+            ValidationReport {
+                self_loops: 1,
+                duplicate_edges: 2,
+                out_of_range: 2,
+            }
+        );
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn directed_edges_are_distinct() {
+        let report = file(true, vec![node(vec![1]), node(vec![0])]).validate();
+        assert_eq!(report.duplicate_edges, 0);
+        let report = file(false, vec![node(vec![1]), node(vec![0])]).validate();
+        assert_eq!(report.duplicate_edges, 1);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn validated_after_reading() {
+        let buf = file(false, vec![node(vec![1]), node(vec![2])])
+            .write_to_vec()
+            .unwrap();
+        let report = GraphFile::read_versioned(&buf).unwrap().validate();
+        assert_eq!(report.out_of_range, 1);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn bytes_match_the_derived_writer() {
+        let mut graph = file(true, vec![node(vec![]), node(vec![0]), node(vec![0, 1])]);
+        graph.classes = vec![Color3b::new(1, 2, 3), Color3b::new(4, 5, 6)];
+        graph.ids = b"a\0b\0c\0".to_vec();
+        graph.names = b"Alice\0".to_vec();
+        graph.finish_header();
+        let bytes = graph.to_bytes().unwrap();
+        assert_eq!(bytes, graph.write_to_vec().unwrap());
+        let read = GraphFile::read_versioned(&bytes).unwrap();
+        assert_eq!(read.nodes.len(), 3);
+        assert_eq!(read.nodes[2].edges, vec![0, 1]);
+        assert_eq!(read.names, graph.names);
+    }
+
+    #[test]
+    fn degree_limit() {
+        // a star whose center has one edge too many
+        let mut star = file(false, (0..=MAX_DEGREE + 1).map(|_| node(vec![])).collect());
+        let mut edges = (1..=MAX_DEGREE + 1).map(|i| (0, i)).collect::<Vec<_>>();
+        assert!(matches!(
+            star.set_edges(&mut edges),
+            Err(FormatError::DegreeTooHigh { node: 0, degree }) if degree == MAX_DEGREE + 1
+        ));
+        assert!(star.nodes.iter().all(|n| n.total_edge_count == 0));
+
+        edges.pop();
+        star.set_edges(&mut edges).unwrap();
+        assert_eq!(star.nodes[0].total_edge_count as usize, MAX_DEGREE);
+    }
+
+    #[test]
+    fn class_limit() {
+        assert!(check_class_count(MAX_CLASSES).is_ok());
+        assert!(matches!(
+            check_class_count(MAX_CLASSES + 1),
+            Err(FormatError::TooManyClasses(_))
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+//! Louvain algorithm
+//! Ported from https://github.com/ledyba/cpp-louvain-fast
+//! Licensed under the AGPLv3 license, see https://github.com/ledyba/cpp-louvain-fast/blob/master/LICENSE
+//!
+//! Shared by the viewer and the importers, which don't have the same node types, so the graph is
+//! built from plain neighbor lists.
+
+use ahash::AHashMap;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+pub struct Graph {
+    pub nodes: Vec<Community>,
+    pub total_links: usize,
+}
+
+#[derive(Copy, Clone, Default)]
+pub struct NodeId(pub usize);
+#[derive(Copy, Clone)]
+pub struct CommunityId(pub usize);
+
+const PRECISION: f32 = 0.0;
+pub const ITERATIONS: usize = 100; // iterations before giving up
+
+fn merge(nodes: &[Community], idxs: &[CommunityId]) -> Vec<NodeId> {
+    idxs.iter()
+        .flat_map(|i| nodes[i.0].payload.as_ref().unwrap())
+        .copied()
+        .collect()
+}
+
+impl Graph {
+    /// Builds the graph from the neighbors of each node. Undirected edges are expected in the
+    /// lists of both of their ends.
+    pub fn new<N: IntoIterator<Item = usize>>(neighbors: impl IntoIterator<Item = N>) -> Self {
+        let neighbors = neighbors.into_iter();
+        let mut nodes = Vec::with_capacity(neighbors.size_hint().0);
+        let mut total_links = 0;
+        for (i, list) in neighbors.enumerate() {
+            let mut comm = Community::new(Some(vec![NodeId(i)]));
+            comm.neighbors = list
+                .into_iter()
+                .map(|x| Edge {
+                    other: CommunityId(x),
+                    weight: 1,
+                })
+                .collect();
+            total_links += comm.neighbors.len();
+            nodes.push(comm);
+        }
+        Self { nodes, total_links }
+    }
+
+    pub fn next(mut self, precision: f32) -> Self {
+        const MAX: usize = 50;
+
+        let n_nodes = self.nodes.len();
+        let mut tmp_comm = (0..n_nodes).collect::<Vec<_>>();
+        {
+            let g_total = self.total_links;
+            let mut order = (0..n_nodes).collect::<Vec<_>>();
+            let mut comm_total = self.nodes.iter().map(|n| n.degree).collect::<Vec<_>>();
+
+            let mut neigh_links = vec![0; n_nodes];
+            let mut neigh_comm = Vec::with_capacity(n_nodes);
+
+            let mut changed = n_nodes;
+            let mut cnt = 0;
+            let change_limit = n_nodes / 100;
+            order.shuffle(&mut thread_rng());
+            while changed > change_limit {
+                if MAX > 0 && cnt >= MAX {
+                    println!("Exceed limit pass");
+                    break;
+                }
+                cnt += 1;
+                changed = 0;
+                for &pos in &order {
+                    let node = &self.nodes[pos];
+                    let node_tmp_comm = tmp_comm[pos];
+                    let node_degree = node.degree;
+                    for &comm in &neigh_comm {
+                        neigh_links[comm] = 0;
+                    }
+                    neigh_comm.clear();
+                    for link in &node.neighbors {
+                        let to = tmp_comm[link.other.0];
+                        let weight = link.weight;
+                        if neigh_links[to] <= 0 {
+                            neigh_comm.push(to);
+                            neigh_links[to] = weight;
+                        } else {
+                            neigh_links[to] += weight;
+                        }
+                    }
+                    let mut best_comm = node_tmp_comm;
+                    let mut best_gain = precision;
+                    for &comm in &neigh_comm {
+                        let gain = if comm == node_tmp_comm {
+                            neigh_links[comm] as f32
+                                - (comm_total[comm] - node_degree) as f32 * node_degree as f32
+                                    / g_total as f32
+                        } else {
+                            neigh_links[comm] as f32
+                                - comm_total[comm] as f32 * node_degree as f32 / g_total as f32
+                        };
+                        if gain > best_gain {
+                            best_gain = gain;
+                            best_comm = comm;
+                        }
+                    }
+                    if node_tmp_comm != best_comm {
+                        changed += 1;
+                        tmp_comm[pos] = best_comm;
+                        comm_total[node_tmp_comm] -= node_degree;
+                        comm_total[best_comm] += node_degree;
+                    }
+                }
+            }
+        }
+        let mut old_comm_idx = Vec::with_capacity(self.nodes.len() / 10);
+        let mut c2i = vec![0; n_nodes];
+        let mut communities = Vec::with_capacity(self.nodes.len() / 10);
+        for (i, node_tmp_comm) in tmp_comm.iter().copied().enumerate() {
+            let c = c2i[node_tmp_comm];
+            if c <= 0 {
+                c2i[node_tmp_comm] = communities.len() + 1;
+                old_comm_idx.push(node_tmp_comm);
+                communities.push(Community {
+                    children: vec![CommunityId(i)],
+                    ..Community::new(None)
+                });
+            } else {
+                communities[c - 1].children.push(CommunityId(i));
+            }
+        }
+        for (i, comm) in communities.iter_mut().enumerate() {
+            let old_comm = old_comm_idx[i];
+            let mut links = AHashMap::new();
+            for cidx in &comm.children {
+                let child = &mut self.nodes[cidx.0];
+                child.parent = Some(i);
+                comm.self_loops += child.self_loops;
+                comm.degree += child.self_loops;
+                for link in &child.neighbors {
+                    let link_to_idx = link.other.0;
+                    let weight = link.weight;
+                    let c_link_to_comm_now = tmp_comm[link_to_idx];
+                    comm.degree += weight;
+                    if c_link_to_comm_now == old_comm {
+                        comm.self_loops += weight;
+                    } else {
+                        *links.entry(c2i[c_link_to_comm_now] - 1).or_default() += weight;
+                    }
+                }
+            }
+
+            comm.neighbors = comm
+                .neighbors
+                .splice(
+                    0..0,
+                    links.iter().map(|(&comm, &weight)| Edge {
+                        other: CommunityId(comm),
+                        weight,
+                    }),
+                )
+                .collect();
+            comm.payload = Some(merge(&self.nodes, &comm.children));
+        }
+        Self {
+            nodes: communities,
+            total_links: self.total_links,
+        }
+    }
+
+    pub fn stats(&self) -> (usize, usize) {
+        (self.nodes.len(), self.total_links)
+    }
+
+    pub fn louvain(mut self) -> Self {
+        for _ in 0..ITERATIONS {
+            let old_stats = self.stats();
+            self = self.next(PRECISION);
+            if old_stats == self.stats() {
+                return self;
+            }
+        }
+        panic!("Graph did not converge after {} iterations", ITERATIONS);
+    }
+
+    /// Runs passes until the communities stop changing, calling `on_pass` with the index of each
+    /// pass before running it. An error from `on_pass` stops the algorithm.
+    pub fn run<E>(
+        mut self,
+        precision: f32,
+        mut on_pass: impl FnMut(usize) -> Result<(), E>,
+    ) -> Result<Self, E> {
+        for i in 0..ITERATIONS {
+            on_pass(i)?;
+            let old_stats = self.stats();
+            self = self.next(precision);
+            if old_stats == self.stats() {
+                break;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Like [`Graph::run`], but keeps the communities found by each pass, from the finest to the
+    /// coarsest. There is always at least one level.
+    pub fn run_levels<E>(
+        mut self,
+        precision: f32,
+        node_count: usize,
+        mut on_pass: impl FnMut(usize) -> Result<(), E>,
+    ) -> Result<Vec<Level>, E> {
+        let mut levels = Vec::new();
+        for i in 0..ITERATIONS {
+            on_pass(i)?;
+            let old_stats = self.stats();
+            self = self.next(precision);
+            if old_stats == self.stats() {
+                break;
+            }
+            levels.push(Level::new(&self, node_count));
+        }
+        if levels.is_empty() {
+            // no pass merged anything, each node is its own community
+            levels.push(Level::new(&self, node_count));
+        }
+        Ok(levels)
+    }
+
+    /// Index of the community of each node.
+    pub fn membership(&self, node_count: usize) -> Vec<u32> {
+        let mut membership = vec![u32::MAX; node_count];
+        for (i, comm) in self.nodes.iter().enumerate() {
+            for node in comm.payload.as_ref().unwrap() {
+                membership[node.0] = i as u32;
+            }
+        }
+        membership
+    }
+}
+
+/// Communities found by one pass of the algorithm.
+pub struct Level {
+    /// Number of communities.
+    pub count: usize,
+    /// Index of the community of each node, see [`Graph::membership`].
+    pub membership: Vec<u32>,
+}
+
+impl Level {
+    fn new(graph: &Graph, node_count: usize) -> Self {
+        Self {
+            count: graph.nodes.len(),
+            membership: graph.membership(node_count),
+        }
+    }
+}
+
+pub struct Edge {
+    other: CommunityId,
+    weight: usize, // TODO: always 1?
+}
+
+pub struct Community {
+    pub payload: Option<Vec<NodeId>>,
+    pub children: Vec<CommunityId>,
+    neighbors: Vec<Edge>,
+    degree: usize,
+    parent: Option<usize>,
+    self_loops: usize,
+}
+
+impl Community {
+    fn new(payload: Option<Vec<NodeId>>) -> Self {
+        Self {
+            payload,
+            children: Vec::new(),
+            neighbors: Vec::new(),
+            degree: 0,
+            parent: None,
+            self_loops: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separate_cliques() {
+        // two 4-cliques, 0-3 and 4-7, with no link between them
+        let neighbors = (0..8usize).map(|i| {
+            let base = i / 4 * 4;
+            (base..base + 4).filter(move |&j| j != i)
+        });
+        let graph = Graph::new(neighbors).run(0.0, |_| Ok::<_, ()>(())).unwrap();
+        let membership = graph.membership(8);
+        assert!(membership[..4].iter().all(|&c| c == membership[0]));
+        assert!(membership[4..].iter().all(|&c| c == membership[4]));
+        assert_ne!(membership[0], membership[4]);
+    }
+
+    #[test]
+    fn levels_get_coarser() {
+        // a ring of 8 triangles, each linked to the next by a single edge
+        let mut neighbors = vec![Vec::new(); 24];
+        let mut link = |a: usize, b: usize| {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        };
+        for t in 0..8 {
+            let base = t * 3;
+            link(base, base + 1);
+            link(base + 1, base + 2);
+            link(base, base + 2);
+            link(base + 2, (base + 3) % 24);
+        }
+        let levels = Graph::new(neighbors)
+            .run_levels(0.0, 24, |_| Ok::<_, ()>(()))
+            .unwrap();
+        assert!(!levels.is_empty());
+        for pair in levels.windows(2) {
+            assert!(pair[1].count < pair[0].count);
+        }
+        for level in &levels {
+            assert_eq!(level.membership.len(), 24);
+            assert!(level.membership.iter().all(|&c| (c as usize) < level.count));
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Converts a Gephi `.gexf` file to the graph file format.
+//!
+//! Usage: `import_gexf <input.gexf> [output.bin]`
+//!
+//! Node positions, sizes and colors are taken from the `viz` extension. Nodes are grouped by
+//! their modularity class attribute if there is one (as added by Gephi's "Modularity"
+//! statistic), and by color otherwise.
+
+use ahash::AHashMap;
+use colourado::{ColorPalette, PaletteType};
+use graph_format::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::io::BufReader;
+
+macro_rules! log {
+    ($($arg:tt)*) => {
+        println!(
+            "[{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S.%3f"),
+            format_args!($($arg)*)
+        );
+    };
+}
+
+type Error = Box<dyn std::error::Error>;
+
+#[derive(Default)]
+struct GexfNode {
+    id: String,
+    label: Option<String>,
+    position: Option<Point>,
+    size: Option<f32>,
+    color: Option<Color3b>,
+    /// Value of the modularity class attribute.
+    class: Option<String>,
+}
+
+#[derive(Default)]
+struct Gexf {
+    directed: bool,
+    nodes: Vec<GexfNode>,
+    edges: Vec<(String, String)>,
+}
+
+/// Returns the value of an attribute, ignoring its namespace.
+fn attr(e: &BytesStart, name: &[u8]) -> Result<Option<String>, Error> {
+    for a in e.attributes() {
+        let a = a?;
+        if a.key.local_name().as_ref() == name {
+            return Ok(Some(a.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn attr_f32(e: &BytesStart, name: &[u8]) -> Result<Option<f32>, Error> {
+    Ok(attr(e, name)?.map(|v| v.parse()).transpose()?)
+}
+
+/// Whether a node attribute is the one Gephi creates for modularity classes.
+fn is_modularity(name: &str) -> bool {
+    name.to_lowercase().replace(' ', "_") == "modularity_class"
+}
+
+fn parse(path: &str) -> Result<Gexf, Error> {
+    let mut reader = Reader::from_reader(BufReader::new(std::fs::File::open(path)?));
+    reader.config_mut().trim_text(true);
+
+    let mut gexf = Gexf::default();
+    let mut in_node_attributes = false;
+    let mut in_node = false;
+    let mut modularity_attr = None;
+    let mut buf = Vec::new();
+    loop {
+        let event = reader.read_event_into(&mut buf)?;
+        let empty = matches!(event, Event::Empty(_));
+        match event {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"graph" => {
+                    gexf.directed = attr(&e, b"defaultedgetype")?.as_deref() == Some("directed");
+                }
+                b"attributes" => {
+                    in_node_attributes = attr(&e, b"class")?.as_deref() == Some("node");
+                }
+                b"attribute" if in_node_attributes => {
+                    let id = attr(&e, b"id")?;
+                    let title = attr(&e, b"title")?;
+                    if [&id, &title]
+                        .into_iter()
+                        .flatten()
+                        .any(|n| is_modularity(n))
+                    {
+                        modularity_attr = id;
+                    }
+                }
+                b"node" => {
+                    let id = attr(&e, b"id")?.ok_or("node without id")?;
+                    gexf.nodes.push(GexfNode {
+                        id,
+                        label: attr(&e, b"label")?,
+                        ..Default::default()
+                    });
+                    in_node = !empty;
+                }
+                b"attvalue" if in_node => {
+                    // GEXF 1.0 uses `id`, later versions `for`
+                    let key = match attr(&e, b"for")? {
+                        Some(key) => Some(key),
+                        None => attr(&e, b"id")?,
+                    };
+                    if key.is_some() && key == modularity_attr {
+                        gexf.nodes.last_mut().unwrap().class = attr(&e, b"value")?;
+                    }
+                }
+                b"position" if in_node => {
+                    gexf.nodes.last_mut().unwrap().position = Some(Point::new(
+                        attr_f32(&e, b"x")?.unwrap_or(0.0),
+                        attr_f32(&e, b"y")?.unwrap_or(0.0),
+                    ));
+                }
+                b"size" if in_node => {
+                    gexf.nodes.last_mut().unwrap().size = attr_f32(&e, b"value")?;
+                }
+                b"color" if in_node => {
+                    let channel = |name| -> Result<u8, Error> {
+                        Ok(attr(&e, name)?.map(|v| v.parse()).transpose()?.unwrap_or(0))
+                    };
+                    gexf.nodes.last_mut().unwrap().color =
+                        Some(Color3b::new(channel(b"r")?, channel(b"g")?, channel(b"b")?));
+                }
+                b"edge" => {
+                    let source = attr(&e, b"source")?.ok_or("edge without source")?;
+                    let target = attr(&e, b"target")?.ok_or("edge without target")?;
+                    gexf.edges.push((source, target));
+                }
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"attributes" => in_node_attributes = false,
+                b"node" => in_node = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(gexf)
+}
+
+fn random_colors(count: usize) -> impl Iterator<Item = Color3b> {
+    ColorPalette::new(count as u32, PaletteType::Random, false)
+        .colors
+        .into_iter()
+        .map(|color| Color3b {
+            r: (color.red * 255.0) as u8,
+            g: (color.green * 255.0) as u8,
+            b: (color.blue * 255.0) as u8,
+        })
+}
+
+/// Assigns a class to each node, from the modularity class attribute, or from the node colors
+/// when there is none. Returns the class of each node and the color of each class, or an error if
+/// there are more classes than a graph file can hold.
+fn classes(nodes: &[GexfNode]) -> Result<(Vec<u16>, Vec<Color3b>), FormatError> {
+    let mut ids = AHashMap::new();
+    let mut colors: Vec<Option<Color3b>> = Vec::new();
+    let node_classes = nodes
+        .iter()
+        .map(|node| {
+            let key = match (&node.class, node.color) {
+                (Some(class), _) => Some(class.clone()),
+                (None, Some(Color3b { r, g, b })) => Some(format!("#{r:02x}{g:02x}{b:02x}")),
+                (None, None) => None,
+            };
+            let next = ids.len();
+            let class = *ids.entry(key).or_insert(next);
+            if class == next {
+                colors.push(node.color);
+            }
+            class
+        })
+        .collect::<Vec<_>>();
+    check_class_count(colors.len())?;
+
+    // classes whose nodes have no color get a random one
+    let mut palette = random_colors(colors.iter().filter(|c| c.is_none()).count());
+    let colors = colors
+        .into_iter()
+        .map(|c| c.unwrap_or_else(|| palette.next().unwrap()))
+        .collect();
+    Ok((node_classes.into_iter().map(|c| c as u16).collect(), colors))
+}
+
+fn main() -> Result<(), Error> {
+    let mut args = std::env::args().skip(1);
+    let input = args
+        .next()
+        .ok_or("usage: import_gexf <input.gexf> [output.bin]")?;
+    let output = args.next().unwrap_or_else(|| "graph.bin".to_string());
+
+    log!("Parsing {}", input);
+    let gexf = parse(&input)?;
+    log!("{} nodes, {} edges", gexf.nodes.len(), gexf.edges.len());
+
+    let mut file = GraphFile::default();
+    let (node_classes, class_colors) = classes(&gexf.nodes)?;
+    log!("{} classes", class_colors.len());
+    file.classes = class_colors;
+
+    let mut nodes_ids = AHashMap::with_capacity(gexf.nodes.len());
+    for (node, class) in gexf.nodes.iter().zip(node_classes) {
+        nodes_ids.insert(node.id.as_str(), file.nodes.len());
+        file.nodes.push(NodeStore {
+            position: node.position.unwrap_or(Point::new(0.0, 0.0)),
+            size: node.size.unwrap_or(0.0),
+            class,
+            offset_id: file.ids.len() as u32,
+            offset_name: file.names.len() as u32,
+            total_edge_count: 0,
+            edge_count: 0,
+            edges: vec![],
+        });
+        file.ids.extend(node.id.as_bytes());
+        file.ids.push(0);
+        file.names
+            .extend(node.label.as_deref().unwrap_or(&node.id).as_bytes());
+        file.names.push(0);
+    }
+    if gexf.nodes.iter().all(|n| n.position.is_none()) {
+        log!("Warning: the file has no node positions, run a layout in Gephi first");
+    }
+
+    let mut edges = Vec::with_capacity(gexf.edges.len());
+    for (source, target) in &gexf.edges {
+        let (Some(&a), Some(&b)) = (
+            nodes_ids.get(source.as_str()),
+            nodes_ids.get(target.as_str()),
+        ) else {
+            log!("Node not found for edge {} -> {}", source, target);
+            continue;
+        };
+        if a == b {
+            continue;
+        }
+        edges.push(if gexf.directed {
+            (a, b)
+        } else {
+            (a.min(b), a.max(b))
+        });
+    }
+    // Gephi allows parallel edges, which the viewer doesn't
+    edges.sort_unstable();
+    edges.dedup();
+
+    log!("Writing neighbour lists");
+    file.set_edges(&mut edges)?;
+
+    log!("Writing metadata");
+    file.directed = gexf.directed;
+    file.finish_header();
+
+    log!("Writing to {}", output);
+    file.write_to_file(&output)?;
+
+    log!("Done");
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Community detection, by GPU Louvain on this machine or another one, or in-process with the
+//! Louvain implementation shared with the viewer.
+
+use crate::{log, run_command, Backend, Config};
+use graph_format::louvain;
+use graph_format::*;
+use speedy::Readable;
+use std::convert::Infallible;
+use std::path::Path;
+use std::process::Command;
+
+/// Folder of GPU Louvain's binary, relative to the tools folder.
+const GPU_LOUVAIN_DIR: &str = "gpu-louvain";
+
+pub trait CommunityProvider {
+    /// Community of each node, along with the number of communities. The edges are in
+    /// `edges.txt`, for the external tools. Fails if there are more communities than a file can
+    /// hold.
+    fn communities(&self, file: &GraphFile) -> Result<(u16, Vec<u16>), FormatError>;
+}
+
+/// Runs GPU Louvain on another machine, which has its own copy of `edges.txt`.
+struct Remote {
+    host: String,
+    tools: String,
+    min_gain: f32,
+}
+
+/// Runs GPU Louvain on this machine.
+struct Local {
+    tools: String,
+    min_gain: f32,
+}
+
+/// Runs the Louvain algorithm in-process.
+struct Builtin;
+
+pub fn provider(config: &Config) -> Box<dyn CommunityProvider> {
+    let min_gain = config.community_min_gain;
+    match config.communities {
+        Backend::Ssh => Box::new(Remote {
+            host: config.ssh_host.clone(),
+            tools: config.remote_tools.clone(),
+            min_gain,
+        }),
+        Backend::Local => Box::new(Local {
+            tools: config.local_tools.clone(),
+            min_gain,
+        }),
+        Backend::Builtin => Box::new(Builtin),
+    }
+}
+
+#[derive(Readable)]
+struct GPULouvainFile {
+    num_comms: u16,
+    #[speedy(length =..)]
+    nodes: Vec<u16>,
+}
+
+fn read_comms(path: impl AsRef<Path>) -> (u16, Vec<u16>) {
+    let comm_file = GPULouvainFile::read_from_file(path).unwrap();
+    (comm_file.num_comms, comm_file.nodes)
+}
+
+impl CommunityProvider for Remote {
+    fn communities(&self, _: &GraphFile) -> Result<(u16, Vec<u16>), FormatError> {
+        log!(
+            "gpulouvain ssh exited with: {}",
+            run_command(Command::new("ssh").arg(&self.host).arg(format!(
+                r"
+                cd {}/{};
+                rm *.bin;
+                unbuffer ./gpulouvain -f ../edges.txt -g {}",
+                self.tools, GPU_LOUVAIN_DIR, self.min_gain
+            )))
+        );
+        log!(
+            "comms.bin scp exited with: {}",
+            run_command(
+                Command::new("scp")
+                    .arg(format!(
+                        "{}:{}/{}/comms.bin",
+                        self.host, self.tools, GPU_LOUVAIN_DIR
+                    ))
+                    .arg("comms.bin")
+            )
+        );
+        Ok(read_comms("comms.bin"))
+    }
+}
+
+impl CommunityProvider for Local {
+    fn communities(&self, _: &GraphFile) -> Result<(u16, Vec<u16>), FormatError> {
+        let dir = Path::new(&self.tools).join(GPU_LOUVAIN_DIR);
+        let output = dir.join("comms.bin");
+        let _ = std::fs::remove_file(&output);
+        let edges = std::fs::canonicalize("edges.txt").unwrap();
+        log!(
+            "gpulouvain exited with: {}",
+            run_command(
+                Command::new(dir.join("gpulouvain"))
+                    .current_dir(&dir)
+                    .arg("-f")
+                    .arg(edges)
+                    .arg("-g")
+                    .arg(self.min_gain.to_string())
+            )
+        );
+        Ok(read_comms(output))
+    }
+}
+
+impl CommunityProvider for Builtin {
+    fn communities(&self, file: &GraphFile) -> Result<(u16, Vec<u16>), FormatError> {
+        let adjacency = file.get_adjacency();
+        let graph = louvain::Graph::new(
+            adjacency
+                .iter()
+                .map(|neighbors| neighbors.iter().map(|&n| n as usize)),
+        );
+        let communities = graph
+            .run(0.0, |i| {
+                log!("Louvain pass {}", i + 1);
+                Ok::<_, Infallible>(())
+            })
+            .unwrap();
+        check_class_count(communities.nodes.len())?;
+        Ok((
+            communities.nodes.len() as u16,
+            communities
+                .membership(file.nodes.len())
+                .into_iter()
+                .map(|c| c as u16)
+                .collect(),
+        ))
+    }
+}
@@ -0,0 +1,191 @@
+//! Layout of the nodes, computed by GPUGraphLayout on this machine or another one, or in-process
+//! with ForceAtlas2.
+
+use crate::{log, run_command, Backend, Config};
+use forceatlas2::{Layout, Node, Settings, VecN};
+use graph_format::*;
+use speedy::Readable;
+use std::path::Path;
+use std::process::Command;
+
+/// Folder of GPUGraphLayout's binary, relative to the tools folder.
+const GPU_LAYOUT_DIR: &str = "GPUGraphLayout/builds/linux";
+
+pub trait LayoutProvider {
+    /// Sets the position of each node. The edges are also in `edges.txt`, for the external tools.
+    fn layout(&self, file: &mut GraphFile, edges: &[(usize, usize)]);
+}
+
+/// Runs GPUGraphLayout on another machine, which has its own copy of `edges.txt`.
+struct Remote {
+    host: String,
+    tools: String,
+    iterations: usize,
+}
+
+/// Runs GPUGraphLayout on this machine.
+struct Local {
+    tools: String,
+    iterations: usize,
+}
+
+/// Runs ForceAtlas2 in-process.
+struct Builtin {
+    iterations: usize,
+}
+
+pub fn provider(config: &Config) -> Box<dyn LayoutProvider> {
+    let iterations = config.layout_iterations;
+    match config.layout {
+        Backend::Ssh => Box::new(Remote {
+            host: config.ssh_host.clone(),
+            tools: config.remote_tools.clone(),
+            iterations,
+        }),
+        Backend::Local => Box::new(Local {
+            tools: config.local_tools.clone(),
+            iterations,
+        }),
+        Backend::Builtin => Box::new(Builtin { iterations }),
+    }
+}
+
+#[derive(Readable)]
+struct GGLNode {
+    id: u32,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Readable)]
+struct GGLFile {
+    #[speedy(length =..)]
+    nodes: Vec<GGLNode>,
+}
+
+/// Reads the positions written by GPUGraphLayout.
+fn apply_ggl(file: &mut GraphFile, path: impl AsRef<Path>) {
+    for layout_node in GGLFile::read_from_file(path).unwrap().nodes.into_iter() {
+        file.nodes[layout_node.id as usize].position = Point {
+            x: layout_node.x,
+            y: layout_node.y,
+        };
+    }
+}
+
+impl LayoutProvider for Remote {
+    fn layout(&self, file: &mut GraphFile, _: &[(usize, usize)]) {
+        log!(
+            "graph_viewer ssh exited with: {}\r\n",
+            run_command(Command::new("ssh").arg(&self.host).arg(format!(
+                r"
+                cd {}/{};
+                rm *.bin;
+                unbuffer ./graph_viewer gpu {} 1 sg 1 1 approximate ../../../edges.txt . bin",
+                self.tools, GPU_LAYOUT_DIR, self.iterations
+            )))
+        );
+        log!(
+            "layout.bin scp exited with: {}",
+            run_command(
+                Command::new("scp")
+                    .arg(format!(
+                        "{}:{}/{}/edges.txt_{}.bin",
+                        self.host, self.tools, GPU_LAYOUT_DIR, self.iterations
+                    ))
+                    .arg("layout.bin")
+            )
+        );
+        apply_ggl(file, "layout.bin");
+        log!("Layout done");
+    }
+}
+
+impl LayoutProvider for Local {
+    fn layout(&self, file: &mut GraphFile, _: &[(usize, usize)]) {
+        let dir = Path::new(&self.tools).join(GPU_LAYOUT_DIR);
+        // the output is named after the input file
+        let output = dir.join(format!("edges.txt_{}.bin", self.iterations));
+        let _ = std::fs::remove_file(&output);
+        let edges = std::fs::canonicalize("edges.txt").unwrap();
+        log!(
+            "graph_viewer exited with: {}",
+            run_command(
+                Command::new(dir.join("graph_viewer"))
+                    .current_dir(&dir)
+                    .args(["gpu", &self.iterations.to_string(), "1", "sg", "1", "1"])
+                    .arg("approximate")
+                    .arg(edges)
+                    .args([".", "bin"])
+            )
+        );
+        apply_ggl(file, output);
+        log!("Layout done");
+    }
+}
+
+/// Average distance between nodes once laid out, like in the viewer's own layout.
+const NODE_SPACING: f32 = 100.0;
+
+impl LayoutProvider for Builtin {
+    fn layout(&self, file: &mut GraphFile, edges: &[(usize, usize)]) {
+        let settings = Settings {
+            theta: 0.5,
+            ka: 0.1,
+            kg: 0.1,
+            kr: 0.02,
+            lin_log: false,
+            speed: 0.01,
+            prevent_overlapping: None,
+            strong_gravity: false,
+        };
+        // nodes start on a sunflower spiral, which spreads them evenly without randomness
+        let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+        let mut layout = Layout::<f32, 2>::from_positioned(
+            settings,
+            (0..file.nodes.len())
+                .map(|i| {
+                    let (r, theta) = ((i as f32).sqrt(), i as f32 * golden_angle);
+                    Node {
+                        pos: VecN([r * theta.cos(), r * theta.sin()]),
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+            edges.iter().map(|&e| (e, 1.0)).collect(),
+        );
+
+        for i in 0..self.iterations {
+            layout.iteration();
+            log!("ForceAtlas2 iteration {}/{}", i + 1, self.iterations);
+        }
+
+        let positions = layout
+            .nodes
+            .iter()
+            .map(|node| Point::new(node.pos[0], node.pos[1]))
+            .collect::<Vec<_>>();
+        let (min, max) = positions.iter().fold(
+            (
+                Point::new(f32::INFINITY, f32::INFINITY),
+                Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), p| {
+                (
+                    Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    Point::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        );
+        let extent = (max - min).x.max((max - min).y);
+        let scale = if extent > 0.0 {
+            NODE_SPACING * (positions.len() as f32).sqrt() / extent
+        } else {
+            1.0
+        };
+        for (node, pos) in file.nodes.iter_mut().zip(positions) {
+            node.position = (pos - min) * scale;
+        }
+        log!("Layout done");
+    }
+}
@@ -3,13 +3,12 @@ use colourado::{ColorPalette, PaletteType};
 use derivative::Derivative;
 use figment::providers::{Env, Format, Toml};
 use figment::Figment;
-use std::ffi::{CStr, OsStr};
+use std::ffi::CStr;
 use std::process::{Command, ExitStatus};
 
 use graph_format::*;
 use neo4rs::{query, ConfigBuilder, Graph};
 use serde::Deserialize;
-use speedy::Readable;
 use std::io::{BufRead, BufReader, Write};
 use std::sync::Mutex;
 
@@ -34,6 +33,34 @@ struct Config {
     #[derivative(Default(value = "0.01"))]
     community_min_gain: f32,
     only_bfs: bool,
+    /// Keep the direction of relationships instead of treating them as undirected.
+    directed: bool,
+    /// Where the layout is computed.
+    layout: Backend,
+    /// Where the communities are detected.
+    communities: Backend,
+    /// Host the tools are run on with the `ssh` backend.
+    #[derivative(Default(value = "\"zdimension@domino\".to_string()"))]
+    ssh_host: String,
+    /// Folder of the tools on the SSH host, where `edges.txt` is copied.
+    #[derivative(Default(value = "\"/home/zdimension/graphrust_tools\".to_string()"))]
+    remote_tools: String,
+    /// Folder of the tools on this machine, for the `local` backend.
+    #[derivative(Default(value = "\"graphrust_tools\".to_string()"))]
+    local_tools: String,
+}
+
+/// Where an external step of the import is run.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Backend {
+    /// The GPU tools, on another machine over SSH.
+    Ssh,
+    /// The GPU tools, on this machine.
+    Local,
+    /// In-process, which needs no external tool but is much slower on large graphs.
+    #[default]
+    Builtin,
 }
 
 static LAST_LOG_TIME: Mutex<std::time::Instant> =
@@ -72,6 +99,9 @@ macro_rules! log
     }
 }
 
+mod communities;
+mod layout;
+
 fn run_command(cmd: &mut Command) -> ExitStatus {
     let mut res = cmd.stdout(std::process::Stdio::piped()).spawn().unwrap();
     if let Some(stdout) = res.stdout.take() {
@@ -83,86 +113,12 @@ fn run_command(cmd: &mut Command) -> ExitStatus {
     res.wait().unwrap()
 }
 
-fn do_layout(file: &mut GraphFile, config: &Config) {
-    log!(
-        "graph_viewer ssh exited with: {}\r\n",
-        run_command(Command::new("ssh").arg("zdimension@domino").arg(format!(
-            r"
-            cd /home/zdimension/graphrust_tools/GPUGraphLayout/builds/linux;
-            rm *.bin;
-            unbuffer ./graph_viewer gpu {} 1 sg 1 1 approximate ../../../edges.txt . bin",
-            config.layout_iterations
-        )))
-    );
-    log!(
-        "layout.bin scp exited with: {}",
-        run_command(Command::new("scp")
-            .arg(format!(
-                "zdimension@domino:/home/zdimension/graphrust_tools/GPUGraphLayout/builds/linux/edges.txt_{}.bin",
-                config.layout_iterations
-            ))
-            .arg("layout.bin")
-            )
-    );
-
-    #[derive(Readable)]
-    struct GGLNode {
-        id: u32,
-        x: f32,
-        y: f32,
-    }
-    #[derive(Readable)]
-    struct GGLFile {
-        #[speedy(length =..)]
-        nodes: Vec<GGLNode>,
-    }
-
-    for layout_node in GGLFile::read_from_file("layout.bin")
-        .unwrap()
-        .nodes
-        .into_iter()
-    {
-        file.nodes[layout_node.id as usize].position = Point {
-            x: layout_node.x,
-            y: layout_node.y,
-        };
-    }
-
-    log!("Layout done");
-}
-
-fn do_modularity(file: &mut GraphFile, config: &Config) {
-    log!(
-        "gpulouvain ssh exited with: {}",
-        run_command(Command::new("ssh").arg("zdimension@domino").arg(format!(
-            r"
-            cd /home/zdimension/graphrust_tools/gpu-louvain;
-            rm *.bin;
-            unbuffer ./gpulouvain -f ../edges.txt -g {}",
-            config.community_min_gain
-        )))
-    );
-    log!(
-        "comms.bin scp exited with: {}",
-        run_command(
-            Command::new("scp")
-                .arg("zdimension@domino:/home/zdimension/graphrust_tools/gpu-louvain/comms.bin")
-                .arg("comms.bin")
-        )
-    );
-    #[derive(Readable)]
-    struct GPULouvainFile {
-        num_comms: u16,
-        #[speedy(length =..)]
-        nodes: Vec<u16>,
-    }
-
-    let comm_file = GPULouvainFile::read_from_file("comms.bin").unwrap();
-
+/// Sets the class of each node to its community, and gives each community a color.
+fn apply_communities(file: &mut GraphFile, (num_comms, membership): (u16, Vec<u16>)) {
     log!("Creating color palette");
-    let top_comms = (comm_file.num_comms as f32 * 0.1).ceil() as u16;
+    let top_comms = (num_comms as f32 * 0.1).ceil() as u16;
     let top_palette = ColorPalette::new(top_comms as u32, PaletteType::Random, false);
-    let rest_comms = comm_file.num_comms - top_comms;
+    let rest_comms = num_comms - top_comms;
     let rest_palette = ColorPalette::new(rest_comms as u32, PaletteType::Random, false);
     let colors = top_palette.colors.iter().chain(rest_palette.colors.iter());
 
@@ -173,7 +129,7 @@ fn do_modularity(file: &mut GraphFile, config: &Config) {
     }));
 
     log!("Applying modularity classes");
-    for (i, comm) in comm_file.nodes.iter().copied().enumerate() {
+    for (i, comm) in membership.into_iter().enumerate() {
         file.nodes[i].class = comm;
     }
 }
@@ -300,7 +256,12 @@ async fn main() {
             log!("Node not found: {}", uid2);
             continue;
         };
-        edges.push((a, b));
+        if config.directed {
+            edges.push((a, b));
+        } else {
+            // the same relationship can be stored in either direction
+            edges.push((a.min(b), a.max(b)));
+        }
         /*file.edges.push(EdgeStore {
             a: a as u32,
             b: b as u32,
@@ -309,18 +270,15 @@ async fn main() {
     }
     log!("{} edges", edges.len());
 
-    log!("Sorting edges");
-    edges.sort_unstable_by_key(|e| (e.0, e.1));
+    edges.sort_unstable();
+    let before = edges.len();
+    edges.dedup();
+    log!("Removed {} duplicate edges", before - edges.len());
 
     log!("Writing neighbour lists");
-    for (a, b) in edges.iter().copied() {
-        file.nodes[a].total_edge_count += 1;
-        file.nodes[b].edges.push(a as u32);
-        file.nodes[b].total_edge_count += 1;
-    }
-
-    for n in file.nodes.iter_mut() {
-        n.edge_count = n.edges.len() as u16;
+    if let Err(e) = file.set_edges(&mut edges) {
+        log!("{}", e);
+        std::process::exit(1);
     }
 
     log!("Computing adjacency matrix");
@@ -374,28 +332,34 @@ async fn main() {
 
     log!("Wrote edges file");
 
-    log!(
-        "Edges file copied; scp exited with: {}",
-        Command::new("scp")
-            .arg("edges.txt")
-            .arg("zdimension@domino:/home/zdimension/graphrust_tools")
-            .stdout(std::process::Stdio::null())
-            .spawn()
-            .unwrap()
-            .wait()
-            .unwrap()
-    );
+    if config.layout == Backend::Ssh || config.communities == Backend::Ssh {
+        log!(
+            "Edges file copied; scp exited with: {}",
+            Command::new("scp")
+                .arg("edges.txt")
+                .arg(format!("{}:{}", config.ssh_host, config.remote_tools))
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+                .wait()
+                .unwrap()
+        );
+    }
 
-    do_layout(&mut file, &config);
+    layout::provider(&config).layout(&mut file, &edges);
 
-    do_modularity(&mut file, &config);
+    match communities::provider(&config).communities(&file) {
+        Ok(communities) => apply_communities(&mut file, communities),
+        Err(e) => {
+            log!("{}", e);
+            std::process::exit(1);
+        }
+    }
 
     log!("Writing metadata");
 
-    file.class_count = file.classes.len() as u16;
-    file.node_count = file.nodes.len() as LenType;
-    file.ids_size = file.ids.len() as LenType;
-    file.names_size = file.names.len() as LenType;
+    file.directed = config.directed;
+    file.finish_header();
 
     log!("Writing to file");
     file.write_to_file("graph_n4j.bin").unwrap();
@@ -20,7 +20,7 @@ macro_rules! log
 }
 
 fn main() {
-    let file = GraphFile::read_from_file("graph_n4j.bin").unwrap();
+    let file = GraphFile::read_versioned_from_file("graph_n4j.bin").unwrap();
 
     let avg_id = stats::mean(file.nodes.iter().map(|n|
         unsafe { CStr::from_ptr(file.ids.as_ptr().add(n.offset_id as usize) as *const _) }.count_bytes()));
@@ -75,7 +75,7 @@ pub unsafe fn str_from_null_terminated_utf8<'a>(s: *const u8) -> &'a str {
 }
 
 fn main() {
-    let f = GraphFile::read_from_file("graph_n4j.bin").unwrap();
+    let f = GraphFile::read_versioned_from_file("graph_n4j.bin").unwrap();
 
     const LIMIT: usize = 10000;
 
@@ -26,6 +26,9 @@ fn criterion_benchmark(c: &mut Criterion) {
                     exclude_ids: vec![],
                     path_no_direct: false,
                     path_no_mutual: false,
+                    path_directed: false,
+                    max_paths: 1,
+                    exclude_classes: vec![],
                 },
                 &viewer.persons,
             )
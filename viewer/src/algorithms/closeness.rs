@@ -0,0 +1,99 @@
+use crate::algorithms::AbstractNode;
+use crate::threading::{CancelFlag, Cancelable};
+use std::collections::VecDeque;
+
+/// Computes the distance from `src` to every node, or `None` for nodes that can't be reached.
+pub fn bfs_distances(
+    data: &[impl AbstractNode],
+    src: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<Vec<Option<u32>>> {
+    let mut dist = vec![None; data.len()];
+    let mut queue = VecDeque::new();
+    dist[src] = Some(0);
+    queue.push_back(src);
+
+    while let Some(current) = queue.pop_front() {
+        cancel.check()?;
+        let next = dist[current].unwrap() + 1;
+        for &nb in data[current].neighbors() {
+            if dist[nb].is_none() {
+                dist[nb] = Some(next);
+                queue.push_back(nb);
+            }
+        }
+    }
+
+    Ok(dist)
+}
+
+/// Number of nodes at each distance from a node.
+#[derive(Debug, Clone, Default)]
+pub struct DistanceDistribution {
+    /// Number of nodes at distance `d + 1` from the node, for each `d`.
+    pub counts: Vec<u64>,
+    /// Number of nodes that can't be reached from the node.
+    pub unreachable: u64,
+}
+
+impl DistanceDistribution {
+    /// Counts the nodes at each distance, from the distances given by [`bfs_distances`].
+    pub fn from_distances(dist: &[Option<u32>]) -> Self {
+        let mut res = Self::default();
+        for d in dist {
+            match *d {
+                None => res.unreachable += 1,
+                Some(0) => {}
+                Some(d) => {
+                    let d = d as usize;
+                    if res.counts.len() < d {
+                        res.counts.resize(d, 0);
+                    }
+                    res.counts[d - 1] += 1;
+                }
+            }
+        }
+        res
+    }
+
+    /// Closeness centrality of the node, i.e. the reciprocal of the mean distance to the other
+    /// nodes.
+    ///
+    /// Only the nodes reachable from the node are taken into account, since the graph may not be
+    /// connected. An isolated node has a centrality of 0.
+    pub fn closeness(&self) -> f32 {
+        let (count, sum) = self
+            .counts
+            .iter()
+            .zip(1u64..)
+            .fold((0u64, 0u64), |(count, sum), (&n, d)| {
+                (count + n, sum + n * d)
+            });
+
+        if sum == 0 {
+            0.0
+        } else {
+            count as f32 / sum as f32
+        }
+    }
+}
+
+/// Counts the nodes at each distance from `src`.
+pub fn distance_distribution(
+    data: &[impl AbstractNode],
+    src: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<DistanceDistribution> {
+    Ok(DistanceDistribution::from_distances(&bfs_distances(
+        data, src, cancel,
+    )?))
+}
+
+/// Computes the closeness centrality of `src`, see [`DistanceDistribution::closeness`].
+pub fn closeness_centrality(
+    data: &[impl AbstractNode],
+    src: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<f32> {
+    Ok(distance_distribution(data, src, cancel)?.closeness())
+}
@@ -0,0 +1,158 @@
+use crate::algorithms::AbstractNode;
+use crate::threading::{CancelFlag, Cancelable, StatusWriterInterface};
+use crate::{for_progress, log, log_progress};
+use ahash::AHashSet;
+use itertools::Itertools;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+/// Computes the local clustering coefficient of `node`, i.e. the fraction of the pairs of its
+/// neighbors that are linked together. Edge directions are ignored.
+///
+/// A node with less than two neighbors has a coefficient of 0.
+pub fn local_clustering(data: &[impl AbstractNode], node: usize) -> f32 {
+    let neighbors: AHashSet<usize> = data[node]
+        .neighbors()
+        .iter()
+        .copied()
+        .filter(|&n| n != node)
+        .collect();
+    let k = neighbors.len();
+    if k < 2 {
+        return 0.0;
+    }
+
+    let links: usize = neighbors
+        .iter()
+        .map(|&n| {
+            // each link is only counted from its lowest end, and a link in both directions of a
+            // directed graph appears twice in the list
+            data[n]
+                .neighbors()
+                .iter()
+                .filter(|&&m| m > n && neighbors.contains(&m))
+                .unique()
+                .count()
+        })
+        .sum();
+
+    links as f32 / (k * (k - 1) / 2) as f32
+}
+
+/// Estimates the average clustering coefficient of the graph from `sample` nodes picked at random,
+/// or from all of them when there are fewer.
+pub fn average_clustering(
+    status_tx: &impl StatusWriterInterface,
+    data: &[impl AbstractNode],
+    sample: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<f32> {
+    let nodes =
+        rand::seq::index::sample(&mut rand::thread_rng(), data.len(), sample.min(data.len()))
+            .into_iter();
+    let count = nodes.len();
+    if count == 0 {
+        return Ok(0.0);
+    }
+    let mut sum = 0.0;
+    for_progress!(status_tx, node in nodes, {
+        cancel.check()?;
+        sum += local_clustering(data, node) as f64;
+    });
+    Ok((sum / count as f64) as f32)
+}
+
+/// Triangles of a graph, and its global clustering coefficient, i.e. the fraction of the pairs of
+/// links sharing a node that are closed by a third link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triangles {
+    pub count: u64,
+    pub global_clustering: f64,
+}
+
+/// Number of values in both sorted lists, found by merging them.
+fn intersection_count(a: &[usize], b: &[usize]) -> u64 {
+    let (mut i, mut j, mut count) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                count += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Number of links between the neighbors of `node`, given the sorted neighbor lists of the graph.
+fn neighbor_links(adjacency: &[Vec<usize>], node: usize) -> u64 {
+    let neighbors = &adjacency[node];
+    neighbors
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| {
+            // each link is only counted from its lowest end
+            let higher = &adjacency[n];
+            let higher = &higher[higher.partition_point(|&m| m <= n)..];
+            intersection_count(&neighbors[i + 1..], higher)
+        })
+        .sum()
+}
+
+/// Counts the triangles of the graph by iterating over the nodes: each triangle closes a pair of
+/// neighbors of each of its three nodes. Edge directions are ignored.
+pub fn count_triangles(
+    status_tx: &impl StatusWriterInterface,
+    data: &[impl AbstractNode + Sync],
+    cancel: &CancelFlag,
+) -> Cancelable<Triangles> {
+    log!(status_tx, t!("Sorting the neighbor lists"));
+    // a link in both directions of a directed graph appears twice in the list
+    let adjacency: Vec<Vec<usize>> = data
+        .par_iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let mut neighbors = node.neighbors().clone();
+            neighbors.sort_unstable();
+            neighbors.dedup();
+            neighbors.retain(|&n| n != i);
+            neighbors
+        })
+        .collect();
+
+    log!(
+        status_tx,
+        t!("Counting triangles, in time proportional to the sum of the squared degrees")
+    );
+    let n = data.len();
+    let chunk = (n / 100).max(1);
+    let (mut links, mut pairs) = (0, 0);
+    for start in (0..n).step_by(chunk) {
+        cancel.check()?;
+        log_progress!(status_tx, start, n);
+        let (chunk_links, chunk_pairs) = (start..(start + chunk).min(n))
+            .into_par_iter()
+            .map(|node| {
+                let k = adjacency[node].len() as u64;
+                (
+                    neighbor_links(&adjacency, node),
+                    k * k.saturating_sub(1) / 2,
+                )
+            })
+            .reduce(|| (0, 0), |(l1, p1), (l2, p2)| (l1 + l2, p1 + p2));
+        links += chunk_links;
+        pairs += chunk_pairs;
+    }
+
+    Ok(Triangles {
+        count: links / 3,
+        global_clustering: if pairs > 0 {
+            links as f64 / pairs as f64
+        } else {
+            0.0
+        },
+    })
+}
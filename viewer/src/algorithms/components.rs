@@ -0,0 +1,76 @@
+use crate::algorithms::AbstractNode;
+use itertools::Itertools;
+use std::collections::VecDeque;
+
+/// Connected components of a graph. Edges are followed in both directions, so for directed graphs
+/// these are the weakly connected components.
+pub struct Components {
+    /// Component of each node. Components are numbered by decreasing size, so the largest one is 0.
+    pub ids: Vec<usize>,
+    /// Number of nodes in each component.
+    pub sizes: Vec<usize>,
+}
+
+impl Components {
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn largest(&self) -> usize {
+        self.sizes.first().copied().unwrap_or(0)
+    }
+
+    /// Number of components of each size, as `(size, count)` pairs by decreasing size.
+    pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+        // sizes are already sorted
+        self.sizes
+            .iter()
+            .chunk_by(|&&s| s)
+            .into_iter()
+            .map(|(size, group)| (size, group.count()))
+            .collect()
+    }
+}
+
+/// Labels the connected components of the graph with a BFS from each node not yet visited.
+pub fn connected_components(data: &[impl AbstractNode]) -> Components {
+    const UNVISITED: usize = usize::MAX;
+    let mut ids = vec![UNVISITED; data.len()];
+    let mut sizes = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for start in 0..data.len() {
+        if ids[start] != UNVISITED {
+            continue;
+        }
+        let component = sizes.len();
+        let mut size = 0;
+        ids[start] = component;
+        queue.push_back(start);
+        while let Some(current) = queue.pop_front() {
+            size += 1;
+            for &nb in data[current].neighbors() {
+                if ids[nb] == UNVISITED {
+                    ids[nb] = component;
+                    queue.push_back(nb);
+                }
+            }
+        }
+        sizes.push(size);
+    }
+
+    // renumber by decreasing size
+    let order = (0..sizes.len())
+        .sorted_by_key(|&c| std::cmp::Reverse(sizes[c]))
+        .collect_vec();
+    let mut rank = vec![0; sizes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        rank[old] = new;
+    }
+    for id in ids.iter_mut() {
+        *id = rank[*id];
+    }
+    let sizes = order.into_iter().map(|c| sizes[c]).collect();
+
+    Components { ids, sizes }
+}
@@ -0,0 +1,70 @@
+use crate::algorithms::closeness::bfs_distances;
+use crate::algorithms::AbstractNode;
+use crate::log_progress;
+use crate::threading::{CancelFlag, Cancelable, StatusWriterInterface};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Diameter {
+    /// Length of the longest shortest path found, a lower bound of the actual diameter.
+    pub length: u32,
+    /// Ends of the path.
+    pub endpoints: (usize, usize),
+    /// Eccentricity of the node the search started from. The diameter is at most twice this.
+    pub start_eccentricity: u32,
+}
+
+impl Diameter {
+    /// Upper bound of the diameter of the component the path is in.
+    pub fn upper_bound(&self) -> u32 {
+        (self.start_eccentricity * 2).max(self.length)
+    }
+}
+
+/// Returns the node furthest from `src`, and its distance.
+pub fn furthest_node(
+    data: &[impl AbstractNode],
+    src: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<(usize, u32)> {
+    Ok(bfs_distances(data, src, cancel)?
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, d)| d.map(|d| (i, d)))
+        .max_by_key(|&(_, d)| d)
+        .unwrap_or((src, 0)))
+}
+
+/// Estimates the diameter of the component containing `start` with a double sweep: the node
+/// furthest from `start` is found, then the node furthest from that one.
+///
+/// This is exact on trees and usually close on real-world graphs, for two BFS instead of one per
+/// node.
+pub fn estimate_diameter(
+    status_tx: &impl StatusWriterInterface,
+    data: &[impl AbstractNode],
+    start: usize,
+    cancel: &CancelFlag,
+) -> Cancelable<Diameter> {
+    log_progress!(status_tx, 0, 2);
+    let (a, start_eccentricity) = furthest_node(data, start, cancel)?;
+    log_progress!(status_tx, 1, 2);
+    let (b, length) = furthest_node(data, a, cancel)?;
+    Ok(Diameter {
+        length,
+        endpoints: (a, b),
+        start_eccentricity,
+    })
+}
+
+/// Estimates the diameter of the graph, starting from the node with the highest degree, which is
+/// most likely in the largest component. Returns `None` for an empty graph.
+pub fn estimate_graph_diameter(
+    status_tx: &impl StatusWriterInterface,
+    data: &[impl AbstractNode],
+    cancel: &CancelFlag,
+) -> Cancelable<Option<Diameter>> {
+    let Some(start) = (0..data.len()).max_by_key(|&i| data[i].neighbors().len()) else {
+        return Ok(None);
+    };
+    estimate_diameter(status_tx, data, start, cancel).map(Some)
+}
@@ -0,0 +1,68 @@
+//! k-core decomposition, after Batagelj & Zaversnik (2003).
+
+use crate::algorithms::AbstractNode;
+use crate::log_progress;
+use crate::threading::{Cancelable, StatusWriterInterface};
+
+/// Computes the core number of each node, i.e. the largest `k` such that the node belongs to the
+/// k-core, the maximal subgraph in which all nodes have degree at least `k`.
+///
+/// Nodes are peeled by increasing degree using a bucket queue, which runs in linear time.
+pub fn core_numbers(
+    status_tx: &impl StatusWriterInterface,
+    nodes: &[impl AbstractNode],
+) -> Cancelable<Vec<u16>> {
+    let n = nodes.len();
+    let mut degree = nodes
+        .iter()
+        .map(|p| p.neighbors().len())
+        .collect::<Vec<_>>();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+
+    // bin[d] is the index in `order` of the first node of degree d
+    let mut bin = vec![0; max_degree + 2];
+    for &d in &degree {
+        bin[d + 1] += 1;
+    }
+    for d in 1..bin.len() {
+        bin[d] += bin[d - 1];
+    }
+    let mut pos = vec![0; n];
+    let mut order = vec![0; n];
+    {
+        let mut next = bin.clone();
+        for (v, &d) in degree.iter().enumerate() {
+            pos[v] = next[d];
+            order[pos[v]] = v;
+            next[d] += 1;
+        }
+    }
+
+    let how_often = (n / 100).max(1);
+    for i in 0..n {
+        let v = order[i];
+        for &u in nodes[v].neighbors() {
+            if degree[u] > degree[v] {
+                // move u to the start of its bin, then shrink the bin past it
+                let du = degree[u];
+                let (pu, pw) = (pos[u], bin[du]);
+                let w = order[pw];
+                if u != w {
+                    order.swap(pu, pw);
+                    pos[u] = pw;
+                    pos[w] = pu;
+                }
+                bin[du] += 1;
+                degree[u] -= 1;
+            }
+        }
+        if i % how_often == 0 {
+            log_progress!(status_tx, i, n);
+        }
+    }
+
+    Ok(degree
+        .into_iter()
+        .map(|d| d.min(u16::MAX as usize) as u16)
+        .collect())
+}
@@ -0,0 +1,86 @@
+//! ForceAtlas2 layout, for graphs that don't come with node positions.
+
+use crate::algorithms::AbstractGraph;
+use crate::app::Person;
+use crate::log_progress;
+use crate::threading::{Cancelable, StatusWriterInterface};
+use forceatlas2::{Layout, Node, Settings, VecN};
+use graph_format::Point;
+use rand::Rng;
+
+/// Settings used when starting a layout.
+pub fn default_settings() -> Settings<f32> {
+    Settings {
+        theta: 0.5,
+        ka: 0.1,
+        kg: 0.1,
+        kr: 0.02,
+        lin_log: false,
+        speed: 0.01,
+        prevent_overlapping: None,
+        strong_gravity: false,
+    }
+}
+
+/// Average distance between nodes once laid out, in world units. Nodes are drawn 12 to 100 units
+/// wide, so this keeps them from overlapping too much.
+const NODE_SPACING: f32 = 100.0;
+
+/// Lays out the nodes from random positions with the given number of ForceAtlas2 iterations.
+///
+/// The result is scaled so that the graph covers about [`NODE_SPACING`]² per node, whatever the
+/// scale the forces settled on.
+pub fn layout(
+    status_tx: &impl StatusWriterInterface,
+    persons: &mut [Person],
+    iterations: usize,
+) -> Cancelable<()> {
+    let mut rng = rand::thread_rng();
+    let mut layout = Layout::<f32, 2>::from_positioned(
+        default_settings(),
+        persons
+            .iter()
+            .map(|_| Node {
+                pos: VecN([rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)]),
+                ..Default::default()
+            })
+            .collect(),
+        persons.iter().get_edges().map(|e| (e, 1.0)).collect(),
+    );
+
+    let how_often = (iterations / 100).max(1);
+    for i in 0..iterations {
+        layout.iteration();
+        if i % how_often == 0 {
+            log_progress!(status_tx, i, iterations);
+        }
+    }
+
+    let positions = layout
+        .nodes
+        .iter()
+        .map(|node| Point::new(node.pos[0], node.pos[1]))
+        .collect::<Vec<_>>();
+    let (min, max) = positions.iter().fold(
+        (
+            Point::new(f32::INFINITY, f32::INFINITY),
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        ),
+        |(min, max), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    );
+    let extent = (max - min).x.max((max - min).y);
+    let scale = if extent > 0.0 {
+        NODE_SPACING * (persons.len() as f32).sqrt() / extent
+    } else {
+        1.0
+    };
+    for (person, pos) in persons.iter_mut().zip(positions) {
+        person.position = (pos - min) * scale;
+    }
+    Ok(())
+}
@@ -1,222 +1,127 @@
 use crate::algorithms::AbstractNode;
-use ahash::AHashMap;
-use itertools::Itertools;
-use rand::seq::SliceRandom;
-/// Louvain algorithm
-/// Ported from https://github.com/ledyba/cpp-louvain-fast
-/// Licensed under the AGPLv3 license, see https://github.com/ledyba/cpp-louvain-fast/blob/master/LICENSE
-use rand::thread_rng;
-
-pub struct Graph {
-    pub nodes: Vec<Community>,
-    pub total_links: usize,
+use crate::app::{ModularityClass, Person};
+use colourado_iter::{ColorPalette, PaletteType};
+pub use graph_format::louvain::{Graph, Level, ITERATIONS};
+use graph_format::Color3b;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Graph of the nodes, for the Louvain algorithm of [`graph_format::louvain`].
+pub fn graph(persons: &[impl AbstractNode]) -> Graph {
+    Graph::new(persons.iter().map(|p| p.neighbors().iter().copied()))
 }
 
-#[derive(Copy, Clone, Default)]
-pub struct PersonId(pub usize);
-#[derive(Copy, Clone)]
-pub struct CommunityId(pub usize);
+/// Sets the class of each node to the index of its community, and creates a class for each
+/// community, see [`apply_membership`].
+pub fn assign_classes(communities: &Graph, persons: &mut [Person]) -> Vec<ModularityClass> {
+    apply_membership(
+        persons,
+        &communities.membership(persons.len()),
+        communities.nodes.len(),
+    )
+}
 
-const PRECISION: f32 = 0.0;
-const ITERATIONS: usize = 100; // iterations before giving up
+/// Sets the class of each node to the given community, and creates a class for each of the
+/// `count` communities. The colors are random, but the same partition always gets the same ones,
+/// so that runs can be compared.
+pub fn apply_membership(
+    persons: &mut [Person],
+    membership: &[u32],
+    count: usize,
+) -> Vec<ModularityClass> {
+    for (p, &class) in persons.iter_mut().zip(membership) {
+        p.modularity_class = class;
+    }
 
-fn merge(nodes: &[Community], idxs: &[CommunityId]) -> Vec<PersonId> {
-    idxs.iter()
-        .flat_map(|i| nodes[i.0].payload.as_ref().unwrap())
-        .copied()
+    let mut rng = StdRng::seed_from_u64(palette_seed(membership, count));
+    palette_colors(count, &mut rng)
+        .enumerate()
+        .map(|(i, color)| ModularityClass::new(color, (i + 1) as u32))
         .collect()
 }
 
-impl Graph {
-    pub fn new(persons: &[impl AbstractNode]) -> Self {
-        let mut nodes = Vec::with_capacity(persons.len());
-        let mut total_links = 0;
-        for (i, pers) in persons.iter().enumerate() {
-            let mut comm = Community::new(Some(vec![PersonId(i)]));
-            comm.neighbors = pers.neighbors().iter().map(|&x| Edge { other: CommunityId(x), weight: 1 }).collect();
-            nodes.push(comm);
-            total_links += pers.neighbors().len();
-        }
-        Self { nodes, total_links }
+/// Merges the smallest communities into one, so that there are at most `max` of them. The others
+/// are renumbered by decreasing size. Returns the new number of communities, and how many were
+/// merged, if any.
+pub fn merge_smallest(membership: &mut [u32], count: usize, max: usize) -> (usize, Option<usize>) {
+    if count <= max {
+        return (count, None);
     }
-
-    pub fn next(mut self, precision: f32) -> Self {
-        const MAX: usize = 50;
-
-        let n_nodes = self.nodes.len();
-        let mut tmp_comm = (0..n_nodes).collect_vec();
-        {
-            let g_total = self.total_links;
-            let mut order = (0..n_nodes).collect_vec();
-            let mut comm_total = self.nodes.iter().map(|n| n.degree).collect_vec();
-
-            let mut neigh_links = vec![0; n_nodes];
-            let mut neigh_comm = Vec::with_capacity(n_nodes);
-
-            let mut changed = n_nodes;
-            let mut cnt = 0;
-            let change_limit = n_nodes / 100;
-            order.shuffle(&mut thread_rng());
-            while changed > change_limit {
-                if MAX > 0 && cnt >= MAX {
-                    println!("Exceed limit pass");
-                    break;
-                }
-                cnt += 1;
-                changed = 0;
-                for &pos in &order {
-                    let node = &self.nodes[pos];
-                    let node_tmp_comm = tmp_comm[pos];
-                    let node_degree = node.degree;
-                    for &comm in &neigh_comm {
-                        neigh_links[comm] = 0;
-                    }
-                    neigh_comm.clear();
-                    for link in &node.neighbors {
-                        let to = tmp_comm[link.other.0];
-                        let weight = link.weight;
-                        if neigh_links[to] <= 0 {
-                            neigh_comm.push(to);
-                            neigh_links[to] = weight;
-                        } else {
-                            neigh_links[to] += weight;
-                        }
-                    }
-                    let mut best_comm = node_tmp_comm;
-                    let mut best_gain = precision;
-                    for &comm in &neigh_comm {
-                        let gain = if comm == node_tmp_comm {
-                            neigh_links[comm] as f32
-                                - (comm_total[comm] - node_degree) as f32 * node_degree as f32
-                                / g_total as f32
-                        } else {
-                            neigh_links[comm] as f32
-                                - comm_total[comm] as f32 * node_degree as f32 / g_total as f32
-                        };
-                        if gain > best_gain {
-                            best_gain = gain;
-                            best_comm = comm;
-                        }
-                    }
-                    if node_tmp_comm != best_comm {
-                        changed += 1;
-                        tmp_comm[pos] = best_comm;
-                        comm_total[node_tmp_comm] -= node_degree;
-                        comm_total[best_comm] += node_degree;
-                    }
-                }
-            }
-        }
-        let mut old_comm_idx = Vec::with_capacity(self.nodes.len() / 10);
-        let mut c2i = vec![0; n_nodes];
-        let mut communities = Vec::with_capacity(self.nodes.len() / 10);
-        for (i, node_tmp_comm) in tmp_comm.iter().copied().enumerate() {
-            let c = c2i[node_tmp_comm];
-            if c <= 0 {
-                c2i[node_tmp_comm] = communities.len() + 1;
-                old_comm_idx.push(node_tmp_comm);
-                communities.push(Community {
-                    children: vec![CommunityId(i)],
-                    ..Community::new(None)
-                });
-            } else {
-                communities[c - 1].children.push(CommunityId(i));
-            }
-        }
-        for (i, comm) in communities.iter_mut().enumerate() {
-            let old_comm = old_comm_idx[i];
-            let mut links = AHashMap::new();
-            for cidx in &comm.children {
-                let child = &mut self.nodes[cidx.0];
-                child.parent = Some(i);
-                comm.self_loops += child.self_loops;
-                comm.degree += child.self_loops;
-                for link in &child.neighbors {
-                    let link_to_idx = link.other.0;
-                    let weight = link.weight;
-                    let c_link_to_comm_now = tmp_comm[link_to_idx];
-                    comm.degree += weight;
-                    if c_link_to_comm_now == old_comm {
-                        comm.self_loops += weight;
-                    } else {
-                        *links.entry(c2i[c_link_to_comm_now] - 1).or_default() += weight;
-                    }
-                }
-            }
-
-            comm.neighbors = comm
-                .neighbors
-                .splice(
-                    0..0,
-                    links
-                        .iter()
-                        .map(|(&comm, &weight)| Edge { other: CommunityId(comm), weight }),
-                )
-                .collect();
-            comm.payload = Some(merge(&self.nodes, &comm.children));
-        }
-        Self {
-            nodes: communities,
-            total_links: self.total_links,
+    let mut sizes = vec![0usize; count];
+    for &class in membership.iter() {
+        if let Some(size) = sizes.get_mut(class as usize) {
+            *size += 1;
         }
     }
-
-    pub fn stats(&self) -> (usize, usize) {
-        (self.nodes.len(), self.total_links)
+    let mut order = (0..count).collect::<Vec<_>>();
+    order.sort_by_key(|&c| std::cmp::Reverse(sizes[c]));
+    let last = max - 1;
+    let mut new_id = vec![last as u32; count];
+    for (rank, &c) in order.iter().take(last).enumerate() {
+        new_id[c] = rank as u32;
     }
-
-    /*fn modularity(&self) -> f32 {
-        self.nodes
-            .iter()
-            .map(|c| {
-                let x = 0;
-                todo!();
-                0.0
-            })
-            .sum()
-    }*/
-
-    pub fn louvain(mut self) -> Self {
-        for _ in 0..ITERATIONS {
-            let old_stats = self.stats();
-            self = self.next(PRECISION);
-            /*log!(
-                "Louvain iteration {} done : {:?} → {:?}",
-                i,
-                old_stats,
-                self.stats()
-            );*/
-            if old_stats == self.stats() {
-                return self;
-            }
+    for class in membership.iter_mut() {
+        if let Some(&id) = new_id.get(*class as usize) {
+            *class = id;
         }
-        panic!("Graph did not converge after {} iterations", ITERATIONS);
     }
+    (max, Some(count - last))
 }
 
-pub struct Edge {
-    other: CommunityId,
-    weight: usize, // TODO: always 1?
+/// Hash of the size of each community, which doesn't change between runs or versions, unlike
+/// the hashers of std.
+fn palette_seed(membership: &[u32], count: usize) -> u64 {
+    let mut sizes = vec![0u64; count];
+    for &class in membership {
+        if let Some(size) = sizes.get_mut(class as usize) {
+            *size += 1;
+        }
+    }
+    // FNV-1a
+    sizes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &size| {
+        (hash ^ size).wrapping_mul(0x100_0000_01b3)
+    })
 }
 
-pub struct Community {
-    pub payload: Option<Vec<PersonId>>,
-    pub children: Vec<CommunityId>,
-    neighbors: Vec<Edge>,
-    degree: usize,
-    parent: Option<usize>,
-    self_loops: usize,
+/// Picks `count` random colors that stand apart from each other.
+pub fn palette_colors(count: usize, rng: &mut impl Rng) -> impl Iterator<Item = Color3b> {
+    ColorPalette::new(PaletteType::Random, false, rng)
+        .take(count)
+        .map(|color| {
+            let [r, g, b] = color.to_array();
+            Color3b {
+                r: (r * 255.0) as u8,
+                g: (g * 255.0) as u8,
+                b: (b * 255.0) as u8,
+            }
+        })
 }
 
-impl Community {
-    fn new(payload: Option<Vec<PersonId>>) -> Self {
-        Self {
-            payload,
-            children: Vec::new(),
-            neighbors: Vec::new(),
-            degree: 0,
-            parent: None,
-            self_loops: 0,
-        }
+/// Modularity of a partition of the graph: the fraction of the edges that are inside communities,
+/// minus the fraction expected if the edges were placed at random. Ranges from -1/2 to 1, higher
+/// is better.
+pub fn modularity(persons: &[impl AbstractNode], membership: &[u32]) -> f64 {
+    let count = membership.iter().map(|&c| c as usize + 1).max().unwrap_or(0);
+    let mut internal = vec![0usize; count];
+    let mut degrees = vec![0usize; count];
+    let mut total = 0;
+    for (node, &class) in persons.iter().zip(membership) {
+        let class = class as usize;
+        let neighbors = node.neighbors();
+        internal[class] += neighbors
+            .iter()
+            .filter(|&&nb| membership[nb] as usize == class)
+            .count();
+        degrees[class] += neighbors.len();
+        total += neighbors.len();
+    }
+    if total == 0 {
+        return 0.0;
     }
-}
\ No newline at end of file
+    let total = total as f64;
+    internal
+        .iter()
+        .zip(&degrees)
+        .map(|(&inside, &degree)| inside as f64 / total - (degree as f64 / total).powi(2))
+        .sum()
+}
@@ -0,0 +1,82 @@
+use crate::app::Person;
+use crate::threading::StatusWriterInterface;
+use crate::{for_progress, log};
+use ahash::AHashMap;
+use graph_format::Point;
+
+/// Classes of a graph collapsed into the nodes of a meta-graph, see [`collapse_classes`].
+pub struct CollapsedClasses {
+    /// Class each node stands for. Classes without members are left out.
+    pub classes: Vec<u32>,
+    /// Number of members of each node.
+    pub members: Vec<usize>,
+    /// Centroid of the members of each node.
+    pub centroids: Vec<Point>,
+    /// Number of links between the members of two nodes, by the nodes in increasing order.
+    /// Links within a class aren't counted.
+    pub link_counts: AHashMap<(usize, usize), u64>,
+}
+
+/// Groups the nodes of a graph by class, among `class_count` classes. Nodes whose class is past the
+/// end are left out, along with their links.
+pub fn collapse_classes(
+    status_tx: &impl StatusWriterInterface,
+    persons: &[Person],
+    directed: bool,
+    class_count: usize,
+) -> CollapsedClasses {
+    log!(status_tx, t!("Grouping the nodes by class"));
+    let mut members = vec![0usize; class_count];
+    let mut sums = vec![Point::new(0.0, 0.0); class_count];
+    for p in persons.iter() {
+        let class = p.modularity_class as usize;
+        if let Some(count) = members.get_mut(class) {
+            *count += 1;
+            sums[class] = sums[class] + p.position;
+        }
+    }
+
+    let mut meta_ids = vec![None; class_count];
+    let mut classes = Vec::new();
+    for class in 0..class_count {
+        if members[class] > 0 {
+            meta_ids[class] = Some(classes.len());
+            classes.push(class as u32);
+        }
+    }
+    let centroids = classes
+        .iter()
+        .map(|&class| sums[class as usize] / members[class as usize] as f32)
+        .collect();
+    let members = classes
+        .iter()
+        .map(|&class| members[class as usize])
+        .collect();
+
+    log!(status_tx, t!("Counting the links between the classes"));
+    let mut link_counts: AHashMap<(usize, usize), u64> = AHashMap::new();
+    let meta_id = |p: &Person| meta_ids.get(p.modularity_class as usize).copied().flatten();
+    for_progress!(status_tx, (i, p) in persons.iter().enumerate(), {
+        let Some(a) = meta_id(p) else {
+            continue;
+        };
+        for &j in p.out_neighbors() {
+            // undirected edges are listed from both ends
+            if !directed && j < i {
+                continue;
+            }
+            if let Some(b) = meta_id(&persons[j])
+                && a != b
+            {
+                *link_counts.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+    });
+
+    CollapsedClasses {
+        classes,
+        members,
+        centroids,
+        link_counts,
+    }
+}
@@ -1,11 +1,33 @@
 use crate::app::Person;
 
+pub mod closeness;
+pub mod clustering;
+pub mod components;
+pub mod diameter;
+pub mod kcore;
+pub mod layout;
 pub mod louvain;
+pub mod meta_graph;
+pub mod pagerank;
 pub mod pathfinding;
 
 pub trait AbstractNode {
     fn neighbors(&self) -> &Vec<usize>;
     fn display(&self) -> &str;
+
+    /// Neighbors reachable by following edges in their direction.
+    fn out_neighbors(&self) -> &[usize] {
+        self.neighbors()
+    }
+
+    /// Neighbors reachable by following edges backwards.
+    fn in_neighbors(&self) -> &[usize] {
+        self.neighbors()
+    }
+
+    fn modularity_class(&self) -> u32 {
+        0
+    }
 }
 
 impl AbstractNode for Person {
@@ -15,6 +37,15 @@ impl AbstractNode for Person {
     fn display(&self) -> &str {
         self.name
     }
+    fn out_neighbors(&self) -> &[usize] {
+        Person::out_neighbors(self)
+    }
+    fn in_neighbors(&self) -> &[usize] {
+        Person::in_neighbors(self)
+    }
+    fn modularity_class(&self) -> u32 {
+        self.modularity_class
+    }
 }
 
 pub trait AbstractGraph<'a> {
@@ -0,0 +1,67 @@
+use crate::algorithms::AbstractNode;
+use crate::log_progress;
+use crate::threading::{CancelFlag, Cancelable, StatusWriterInterface};
+use rayon::prelude::*;
+
+/// Probability of following an edge rather than jumping to a random node.
+pub const DEFAULT_DAMPING: f32 = 0.85;
+
+/// Iterations after which the scores are returned even if they haven't converged.
+pub const MAX_ITERATIONS: usize = 100;
+
+/// Convergence threshold on the total change of the scores in an iteration, per node.
+const TOLERANCE: f64 = 1e-6;
+
+/// Computes the PageRank of each node by power iteration. The scores sum to 1.
+///
+/// Random walkers follow the edges in their direction, and those stuck on a node without outgoing
+/// edges jump to any node uniformly.
+pub fn pagerank(
+    status_tx: &impl StatusWriterInterface,
+    data: &[impl AbstractNode + Sync],
+    damping: f32,
+    cancel: &CancelFlag,
+) -> Cancelable<Vec<f32>> {
+    let n = data.len();
+    if n == 0 {
+        return Ok(vec![]);
+    }
+    let damping = damping as f64;
+    let out_degree = data
+        .iter()
+        .map(|p| p.out_neighbors().len() as f64)
+        .collect::<Vec<_>>();
+
+    let mut rank = vec![1.0 / n as f64; n];
+    for i in 0..MAX_ITERATIONS {
+        cancel.check()?;
+        log_progress!(status_tx, i, MAX_ITERATIONS);
+
+        let dangling: f64 = rank
+            .iter()
+            .zip(&out_degree)
+            .filter(|&(_, &d)| d == 0.0)
+            .map(|(r, _)| r)
+            .sum();
+        let base = (1.0 - damping + damping * dangling) / n as f64;
+        let next = data
+            .par_iter()
+            .map(|p| {
+                base + damping
+                    * p.in_neighbors()
+                        .iter()
+                        .map(|&u| rank[u] / out_degree[u])
+                        .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+
+        let change: f64 = next.iter().zip(&rank).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if change < TOLERANCE * n as f64 {
+            break;
+        }
+    }
+    log_progress!(status_tx, MAX_ITERATIONS, MAX_ITERATIONS);
+
+    Ok(rank.into_iter().map(|r| r as f32).collect())
+}
@@ -1,126 +1,469 @@
 use crate::algorithms::AbstractNode;
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use bit_set::BitSet;
 use derivative::*;
 use itertools::Itertools;
+use std::collections::hash_map::Entry;
 use std::collections::VecDeque;
 
+/// Number of previous searches whose results are kept by a [`Pathfinder`].
+const CACHE_SIZE: usize = 16;
+
+/// Number of shortest paths enumerated by default.
+pub const DEFAULT_MAX_PATHS: usize = 10;
+
+/// Nodes that are neighbors of both `a` and `b`.
+pub fn mutual_neighbors(data: &[impl AbstractNode], a: usize, b: usize) -> AHashSet<usize> {
+    AHashSet::<_>::from_iter(data[a].neighbors().iter().copied())
+        .intersection(&AHashSet::<_>::from_iter(
+            data[b].neighbors().iter().copied(),
+        ))
+        .copied()
+        .collect()
+}
+
 pub fn do_pathfinding(
     settings: PathSectionSettings,
     data: &[impl AbstractNode],
-) -> Option<PathSectionResults> {
-    let src_id = settings.path_src.unwrap();
-    let dest_id = settings.path_dest.unwrap();
-    let src = &data[src_id];
-    let dest = &data[dest_id];
-
-    let mutual: AHashSet<usize> = if settings.path_no_mutual {
-        AHashSet::<_>::from_iter(src.neighbors().iter().copied())
-            .intersection(&AHashSet::<_>::from_iter(dest.neighbors().iter().copied()))
-            .copied()
-            .collect()
-    } else {
-        AHashSet::new()
+) -> Result<PathSectionResults, PathNotFound> {
+    Pathfinder::default().find(&settings, data)
+}
+
+/// Constraints on the path searched by [`shortest_path`]. They have the same meaning as the
+/// fields of [`PathSectionSettings`].
+#[derive(Clone, Default)]
+pub struct PathOptions {
+    pub exclude_ids: Vec<usize>,
+    pub no_direct: bool,
+    pub no_mutual: bool,
+    pub directed: bool,
+    pub exclude_classes: Vec<u32>,
+}
+
+/// Shortest path from `src` to `dest`, both included, or `None` if they aren't connected under
+/// the given constraints. A node is its own path.
+pub fn shortest_path(
+    data: &[impl AbstractNode],
+    src: usize,
+    dest: usize,
+    options: &PathOptions,
+) -> Option<Vec<usize>> {
+    let settings = PathSectionSettings {
+        path_src: Some(src),
+        path_dest: Some(dest),
+        exclude_ids: options.exclude_ids.clone(),
+        path_no_direct: options.no_direct,
+        path_no_mutual: options.no_mutual,
+        path_directed: options.directed,
+        exclude_classes: options.exclude_classes.clone(),
+        max_paths: 1,
     };
+    let res = do_pathfinding(settings, data).ok()?;
+    res.paths.into_iter().next()
+}
 
-    let exclude_set: AHashSet<usize> = AHashSet::from_iter(settings.exclude_ids.iter().cloned());
-
-    let mut queue_f = VecDeque::new();
-    let mut queue_b = VecDeque::new();
-    let mut visited_f = BitSet::with_capacity(data.len());
-    let mut visited_b = BitSet::with_capacity(data.len());
-    let mut pred_f = vec![None; data.len()];
-    let mut pred_b = vec![None; data.len()];
-
-    visited_f.insert(src_id);
-    visited_b.insert(dest_id);
-    queue_f.push_back(src_id);
-    queue_b.push_back(dest_id);
-
-    let bfs = |current: usize,
-               queue: &mut VecDeque<usize>,
-               visited: &mut BitSet,
-               pred: &mut Vec<Option<usize>>,
-               visited_other: &BitSet| {
-        let person = &data[current];
-        for &nb_id in person.neighbors().iter() {
-            if settings.path_no_direct
-                && ((current, nb_id) == (src_id, dest_id) || (current, nb_id) == (dest_id, src_id))
-            {
-                continue;
+/// Bidirectional BFS whose buffers are kept between searches, so that repeatedly searching for a
+/// path between the same nodes (e.g. while excluding nodes one by one) doesn't have to allocate
+/// and rebuild everything each time.
+///
+/// The search itself is always run in full, which guarantees that the path found is the same as
+/// with a fresh [`do_pathfinding`].
+#[derive(Default)]
+pub struct Pathfinder {
+    queue_f: VecDeque<usize>,
+    queue_b: VecDeque<usize>,
+    visited_f: BitSet,
+    visited_b: BitSet,
+    pred_f: Vec<Option<usize>>,
+    pred_b: Vec<Option<usize>>,
+    /// Mutual neighbors of the source and destination, for the pair they were computed for.
+    mutual: Option<((usize, usize), AHashSet<usize>)>,
+    /// Contents of `exclude_set`, to update it incrementally.
+    exclude_ids: Vec<usize>,
+    exclude_set: BitSet,
+    /// Results of the last searches, most recent first.
+    cache: VecDeque<(
+        PathSectionSettings,
+        Result<PathSectionResults, PathNotFound>,
+    )>,
+}
+
+impl Pathfinder {
+    /// Returns the result of a previous search with the same settings, if there was one.
+    pub fn cached(
+        &self,
+        settings: &PathSectionSettings,
+    ) -> Option<Result<PathSectionResults, PathNotFound>> {
+        self.cache
+            .iter()
+            .find(|(s, _)| s == settings)
+            .map(|(_, res)| res.clone())
+    }
+
+    pub fn find(
+        &mut self,
+        settings: &PathSectionSettings,
+        data: &[impl AbstractNode],
+    ) -> Result<PathSectionResults, PathNotFound> {
+        if let Some(res) = self.cached(settings) {
+            return res;
+        }
+        let res = self.search(settings, data);
+        self.cache.push_front((settings.clone(), res.clone()));
+        self.cache.truncate(CACHE_SIZE);
+        res
+    }
+
+    fn prepare(&mut self, settings: &PathSectionSettings, data: &[impl AbstractNode]) {
+        let src_id = settings.path_src.unwrap();
+        let dest_id = settings.path_dest.unwrap();
+
+        self.queue_f.clear();
+        self.queue_b.clear();
+        self.visited_f.clear();
+        self.visited_b.clear();
+        self.pred_f.clear();
+        self.pred_f.resize(data.len(), None);
+        self.pred_b.clear();
+        self.pred_b.resize(data.len(), None);
+
+        if settings.path_no_mutual
+            && self.mutual.as_ref().map(|(pair, _)| *pair) != Some((src_id, dest_id))
+        {
+            self.mutual = Some(((src_id, dest_id), mutual_neighbors(data, src_id, dest_id)));
+        }
+
+        // excluding nodes one at a time is the common case, so only the difference is applied
+        for &id in &self.exclude_ids {
+            if !settings.exclude_ids.contains(&id) {
+                self.exclude_set.remove(id);
             }
+        }
+        for &id in &settings.exclude_ids {
+            self.exclude_set.insert(id);
+        }
+        self.exclude_ids.clone_from(&settings.exclude_ids);
+    }
+
+    fn search(
+        &mut self,
+        settings: &PathSectionSettings,
+        data: &[impl AbstractNode],
+    ) -> Result<PathSectionResults, PathNotFound> {
+        let src_id = settings.path_src.unwrap();
+        let dest_id = settings.path_dest.unwrap();
+        if src_id == dest_id {
+            // both searches would start from the same node, and meet again at one of its
+            // neighbors
+            return Ok(PathSectionResults {
+                paths: vec![vec![src_id]],
+            });
+        }
+        self.prepare(settings, data);
+
+        let Pathfinder {
+            queue_f,
+            queue_b,
+            visited_f,
+            visited_b,
+            pred_f,
+            pred_b,
+            mutual,
+            exclude_set,
+            ..
+        } = self;
+        let empty = AHashSet::new();
+        let mutual = match mutual {
+            Some((_, mutual)) if settings.path_no_mutual => &*mutual,
+            _ => &empty,
+        };
+        let constraints = Constraints {
+            settings,
+            src: src_id,
+            dest: dest_id,
+            mutual,
+            exclude_set,
+        };
+        // how many times each excluded class stopped the search, to tell which one is to blame
+        // when there is no path
+        let mut blocked = AHashMap::<u32, usize>::new();
 
-            if settings.path_no_mutual && mutual.contains(&nb_id) {
-                continue;
+        visited_f.insert(src_id);
+        visited_b.insert(dest_id);
+        queue_f.push_back(src_id);
+        queue_b.push_back(dest_id);
+
+        let mut bfs = |current: usize,
+                       forward: bool,
+                       queue: &mut VecDeque<usize>,
+                       visited: &mut BitSet,
+                       pred: &mut Vec<Option<usize>>,
+                       visited_other: &BitSet| {
+            for &nb_id in constraints.neighbors(&data[current], forward) {
+                if !constraints.allows(current, nb_id) {
+                    continue;
+                }
+
+                if let Some(class) = constraints.excluded_class(nb_id, &data[nb_id]) {
+                    *blocked.entry(class).or_default() += 1;
+                    continue;
+                }
+
+                if !visited.contains(nb_id) {
+                    pred[nb_id] = Some(current);
+                    if visited_other.contains(nb_id) {
+                        return Some(nb_id);
+                    }
+                    visited.insert(nb_id);
+                    queue.push_back(nb_id);
+                }
             }
+            None
+        };
 
-            if exclude_set.contains(&nb_id) {
-                continue;
+        let intersect = 'main: loop {
+            // Balancing the bidirectional BFS (instead of visiting each k-neighborhood alternatively)
+            // shortens the usual runtime on my machine for long paths (>11) from 500ms to 10ms.
+            // Thanks to https://arxiv.org/pdf/2410.22186
+            if queue_b.is_empty() || queue_f.is_empty() {
+                break None;
             }
 
-            if !visited.contains(nb_id) {
-                pred[nb_id] = Some(current);
-                if visited_other.contains(nb_id) {
-                    return Some(nb_id);
+            let (forward, queue, visited, pred, queue_other) = if visited_b.len() < visited_f.len()
+            {
+                (
+                    false,
+                    &mut *queue_b,
+                    &mut *visited_b,
+                    &mut *pred_b,
+                    &*visited_f,
+                )
+            } else {
+                (
+                    true,
+                    &mut *queue_f,
+                    &mut *visited_f,
+                    &mut *pred_f,
+                    &*visited_b,
+                )
+            };
+            let mut level_count = queue.len();
+            while let Some(id) = queue.pop_front() {
+                if let Some(inter) = bfs(id, forward, queue, visited, pred, queue_other) {
+                    break 'main Some(inter);
                 }
-                visited.insert(nb_id);
-                queue.push_back(nb_id);
+                if level_count == 1 {
+                    continue 'main;
+                }
+                level_count -= 1;
             }
-        }
-        None
-    };
+        };
 
-    let intersect = 'main: loop {
-        // Balancing the bidirectional BFS (instead of visiting each k-neighborhood alternatively)
-        // shortens the usual runtime on my machine for long paths (>11) from 500ms to 10ms.
-        // Thanks to https://arxiv.org/pdf/2410.22186
-        if queue_b.is_empty() || queue_f.is_empty() {
-            return None;
+        let Some(intersect) = intersect else {
+            let blocking_class = blocked
+                .into_iter()
+                .max_by_key(|&(class, count)| (count, std::cmp::Reverse(class)))
+                .map(|(class, _)| class);
+            return Err(PathNotFound { blocking_class });
+        };
+
+        let mut path = vec![intersect];
+        let mut cur = intersect;
+        while let Some(pred) = pred_f[cur] {
+            path.push(pred);
+            cur = pred;
+        }
+        path.reverse();
+        cur = intersect;
+        while let Some(pred) = pred_b[cur] {
+            path.push(pred);
+            cur = pred;
         }
 
-        let (queue, visited, pred, queue_other) = if visited_b.len() < visited_f.len() {
-            (&mut queue_b, &mut visited_b, &mut pred_b, &visited_f)
+        let paths = if settings.max_paths > 1 {
+            all_shortest_paths(&constraints, data, path, settings.max_paths)
         } else {
-            (&mut queue_f, &mut visited_f, &mut pred_f, &visited_b)
+            vec![path]
         };
-        let mut level_count = queue.len();
-        while let Some(id) = queue.pop_front() {
-            if let Some(inter) = bfs(id, queue, visited, pred, queue_other) {
-                break 'main inter;
-            }
-            if level_count == 1 {
-                continue 'main;
+        Ok(PathSectionResults { paths })
+    }
+}
+
+/// Rules restricting which edges a path can go through.
+struct Constraints<'a> {
+    settings: &'a PathSectionSettings,
+    src: usize,
+    dest: usize,
+    mutual: &'a AHashSet<usize>,
+    exclude_set: &'a BitSet,
+}
+
+impl Constraints<'_> {
+    fn neighbors<'n>(&self, node: &'n impl AbstractNode, forward: bool) -> &'n [usize] {
+        // the backward search goes from the destination, so it walks edges in reverse
+        match (self.settings.path_directed, forward) {
+            (false, _) => node.neighbors(),
+            (true, true) => node.out_neighbors(),
+            (true, false) => node.in_neighbors(),
+        }
+    }
+
+    fn allows(&self, current: usize, nb_id: usize) -> bool {
+        if self.settings.path_no_direct
+            && ((current, nb_id) == (self.src, self.dest)
+                || (current, nb_id) == (self.dest, self.src))
+        {
+            return false;
+        }
+
+        if self.settings.path_no_mutual && self.mutual.contains(&nb_id) {
+            return false;
+        }
+
+        !self.exclude_set.contains(nb_id)
+    }
+
+    /// Class of the node if it is one of the excluded classes. The source and destination can
+    /// always be used, whatever their class.
+    fn excluded_class(&self, id: usize, node: &impl AbstractNode) -> Option<u32> {
+        let class = node.modularity_class();
+        (id != self.src && id != self.dest && self.settings.exclude_classes.contains(&class))
+            .then_some(class)
+    }
+
+    /// BFS from `root` up to `depth`, returning for each node reached all its predecessors at the
+    /// previous level.
+    fn predecessors(
+        &self,
+        data: &[impl AbstractNode],
+        root: usize,
+        forward: bool,
+        depth: usize,
+    ) -> AHashMap<usize, (usize, Vec<usize>)> {
+        let mut reached = AHashMap::new();
+        reached.insert(root, (0, vec![]));
+        let mut level = vec![root];
+        for dist in 1..=depth {
+            let mut next = vec![];
+            for &current in &level {
+                for &nb_id in self.neighbors(&data[current], forward) {
+                    if !self.allows(current, nb_id)
+                        || self.excluded_class(nb_id, &data[nb_id]).is_some()
+                    {
+                        continue;
+                    }
+                    match reached.entry(nb_id) {
+                        Entry::Vacant(e) => {
+                            e.insert((dist, vec![current]));
+                            next.push(nb_id);
+                        }
+                        Entry::Occupied(mut e) if e.get().0 == dist => {
+                            e.get_mut().1.push(current);
+                        }
+                        Entry::Occupied(_) => {}
+                    }
+                }
             }
-            level_count -= 1;
+            level = next;
         }
-    };
+        reached
+    }
+}
 
-    let mut path = vec![intersect];
-    let mut cur = intersect;
-    while let Some(pred) = pred_f[cur] {
-        path.push(pred);
-        cur = pred;
+/// Up to `limit` paths from the root of the BFS tree to `node`.
+fn paths_from_root(
+    node: usize,
+    tree: &AHashMap<usize, (usize, Vec<usize>)>,
+    limit: usize,
+) -> Vec<Vec<usize>> {
+    let preds = &tree[&node].1;
+    if preds.is_empty() {
+        return vec![vec![node]];
     }
-    path.reverse();
-    cur = intersect;
-    while let Some(pred) = pred_b[cur] {
-        path.push(pred);
-        cur = pred;
+    let mut paths = vec![];
+    for &pred in preds {
+        // every node of the tree leads back to the root, so each predecessor gives at least one
+        // path and this stops after at most `limit` of them
+        for mut path in paths_from_root(pred, tree, limit - paths.len()) {
+            path.push(node);
+            paths.push(path);
+        }
+        if paths.len() >= limit {
+            break;
+        }
+    }
+    paths
+}
+
+/// Enumerates up to `limit` shortest paths, given one of them. The paths are those of the
+/// shortest-path DAG, which is explored from both ends up to the middle of the path, and the
+/// enumeration stops as soon as enough paths have been found so that it stays cheap even when
+/// there are millions of them.
+fn all_shortest_paths(
+    constraints: &Constraints<'_>,
+    data: &[impl AbstractNode],
+    path: Vec<usize>,
+    limit: usize,
+) -> Vec<Vec<usize>> {
+    let dist = path.len() - 1;
+    let depth_f = dist.div_ceil(2);
+    let depth_b = dist - depth_f;
+    let tree_f = constraints.predecessors(data, constraints.src, true, depth_f);
+    let tree_b = constraints.predecessors(data, constraints.dest, false, depth_b);
+
+    // nodes in the middle of a shortest path
+    let middle = tree_f
+        .iter()
+        .filter(|&(id, &(d, _))| d == depth_f && tree_b.get(id).is_some_and(|&(d, _)| d == depth_b))
+        .map(|(&id, _)| id)
+        .sorted();
+
+    // the path that was found comes first so it doesn't change when asking for more paths
+    let mut paths = vec![path];
+    'outer: for mid in middle {
+        let suffixes = paths_from_root(mid, &tree_b, limit);
+        for prefix in paths_from_root(mid, &tree_f, limit) {
+            for suffix in &suffixes {
+                let mut path = prefix.clone();
+                path.extend(suffix.iter().rev().skip(1));
+                if path != paths[0] {
+                    paths.push(path);
+                }
+                if paths.len() >= limit {
+                    break 'outer;
+                }
+            }
+        }
     }
-    Some(PathSectionResults { path })
+    paths
 }
 
 #[derive(Derivative)]
-#[derivative(Default, Clone)]
+#[derivative(Default, Clone, PartialEq)]
 pub struct PathSectionSettings {
     pub path_src: Option<usize>,
     pub path_dest: Option<usize>,
     pub exclude_ids: Vec<usize>,
     pub path_no_direct: bool,
     pub path_no_mutual: bool,
+    /// Only follow edges in their direction, for directed graphs.
+    pub path_directed: bool,
+    /// Modularity classes whose nodes can't be part of the path.
+    pub exclude_classes: Vec<u32>,
+    /// Maximum number of shortest paths to find, when there are several.
+    #[derivative(Default(value = "DEFAULT_MAX_PATHS"))]
+    pub max_paths: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct PathSectionResults {
-    pub path: Vec<usize>,
+    /// Shortest paths found, all of the same length.
+    pub paths: Vec<Vec<usize>>,
+}
+
+/// Returned when the source and destination aren't connected under the given settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathNotFound {
+    /// Excluded class that the search ran into most often, which likely prevents finding a path.
+    pub blocking_class: Option<u32>,
 }
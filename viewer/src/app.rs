@@ -1,5 +1,10 @@
+use crate::algorithms::clustering::Triangles;
+use crate::algorithms::diameter::Diameter;
+use crate::attributes::Attribute;
 use crate::graph_render::camera::{CamXform, Camera};
-use crate::graph_storage::{load_binary, load_file, ProcessedData};
+use crate::graph_storage::{
+    decompress_brotli, load_binary_pruned, load_edge_list, load_file, GraphSource, ProcessedData,
+};
 use crate::ui::{tabs, UiState};
 use eframe::glow::HasContext;
 use eframe::{egui_glow, glow};
@@ -8,6 +13,7 @@ use egui::{
     RichText, TextFormat, TextStyle, Ui, Vec2, WidgetText,
 };
 use egui_dock::{DockArea, DockState, Style};
+use serde::{Deserialize, Serialize};
 use graph_format::{Color3b, Point};
 
 use std::sync::mpsc::{Receiver, Sender};
@@ -16,10 +22,17 @@ use zearch::{Document, Index, Search};
 
 use crate::graph_render::{GlForwarder, GlMpsc};
 use crate::search::SearchEngine;
+use crate::session;
 use crate::threading;
-use crate::threading::{Cancelable, StatusReader, StatusWriter, StatusWriterInterface};
-use crate::ui::modal::{show_modal, ModalInfo};
+use crate::threading::{
+    Cancelable, CancelableError, StatusReader, StatusWriter, StatusWriterInterface,
+};
+use crate::ui::display::DisplaySection;
+use crate::ui::modal::{show_modal, show_retry_modal, ModalInfo, ModalKind};
+use crate::ui::sections::meta_graph::MetaGraph;
+use crate::ui::sections::path::MainPathRequest;
 use crate::ui::tabs::{GraphTab, GraphTabLoaded, TabViewer};
+use crate::utils::format_count;
 use eframe::emath::Align;
 use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
 #[cfg(not(target_arch = "wasm32"))]
@@ -95,17 +108,24 @@ where
 pub struct Person {
     pub position: Point,
     pub size: f32,
-    pub modularity_class: u16,
+    pub modularity_class: u32,
     pub id: &'static str,
     pub name: &'static str,
     pub neighbors: Vec<usize>,
+    /// Number of outgoing edges, which come first in `neighbors`. In undirected graphs, all
+    /// edges are considered outgoing.
+    pub out_degree: usize,
+    /// Core number from the k-core decomposition, 0 until it has been computed.
+    pub core_number: u16,
+    /// Whether the name contains the text of the name filter, see [`NodeFilter::filter_names`].
+    pub name_match: bool,
 }
 
 impl Person {
     pub fn new(
         position: Point,
         size: f32,
-        modularity_class: u16,
+        modularity_class: u32,
         id: &'static str,
         name: &'static str,
         total_edge_count: usize,
@@ -117,32 +137,76 @@ impl Person {
             id,
             name,
             neighbors: Vec::with_capacity(total_edge_count),
+            out_degree: 0,
+            core_number: 0,
+            name_match: true,
         }
     }
+
+    pub fn out_neighbors(&self) -> &[usize] {
+        &self.neighbors[..self.out_degree]
+    }
+
+    pub fn in_neighbors(&self) -> &[usize] {
+        &self.neighbors[self.out_degree..]
+    }
 }
 
 #[derive(Clone)]
 pub struct ModularityClass {
     pub color: Color3b,
-    pub id: u16,
+    pub id: u32,
     pub name: String,
 }
 
 impl ModularityClass {
-    pub fn new(color: Color3b, id: u16) -> ModularityClass {
+    pub fn new(color: Color3b, id: u32) -> ModularityClass {
         ModularityClass {
             color,
             id,
-            name: format!("Classe {}", id),
+            name: t!("Class %{class}", class = id).to_string(),
         }
     }
 }
 
-//#[derive(Clone)]
+/// Nodes and results of a graph. Cloning it shares the nodes, which are only copied once either
+/// copy changes them.
+#[derive(Clone)]
 pub struct ViewerData {
     pub persons: Arc<Vec<Person>>,
     pub modularity_classes: Vec<ModularityClass>,
     pub engine: Arc<SearchEngine>,
+    /// For subgraphs, the index of each node in the main graph.
+    pub base_ids: Option<Vec<usize>>,
+    /// For subgraphs, the search engine of the main graph, to find the nodes left out.
+    pub base_engine: Option<Arc<SearchEngine>>,
+    pub directed: bool,
+    /// Estimated diameter, computed on demand. Edges never change once loaded, so it stays valid.
+    pub diameter: Option<Diameter>,
+    /// Modularity of the classes, when they were computed by community detection.
+    pub modularity: Option<f64>,
+    /// Number of communities that were merged into the last class by community detection, when
+    /// there were more than the shaders can draw.
+    pub merged_communities: Option<usize>,
+    /// Incremented each time the classes of the nodes are rewritten, so that the results which
+    /// depend on them can tell when they are stale.
+    pub class_generation: u64,
+    /// PageRank of each node, computed on demand. Like the diameter, it only depends on the edges.
+    pub pagerank: Option<Arc<Vec<f32>>>,
+    /// Average clustering coefficient estimated on a sample of nodes, along with the size of the
+    /// sample. Like the diameter, it only depends on the edges.
+    pub avg_clustering: Option<(f32, usize)>,
+    /// Number of triangles and global clustering coefficient, computed on demand.
+    pub triangles: Option<Triangles>,
+    /// Size of the file the graph was read from, compressed if it was. Subgraphs have none.
+    pub file_size: Option<usize>,
+    /// Numeric columns attached from a CSV file, to color the nodes by.
+    pub attributes: Vec<Arc<Attribute>>,
+    /// For meta-graphs, the graph their classes come from and the links between them.
+    pub meta: Option<MetaGraph>,
+    /// Ids and names of the nodes, when they don't point to the string tables of a file, like the
+    /// classes of a meta-graph. Subgraphs keep them, as their nodes point to the same strings.
+    pub strings: Option<Arc<StringTables>>,
 }
 
 impl ViewerData {
@@ -156,8 +220,36 @@ impl ViewerData {
             persons,
             modularity_classes,
             engine,
+            base_ids: None,
+            base_engine: None,
+            directed: false,
+            diameter: None,
+            modularity: None,
+            merged_communities: None,
+            class_generation: 0,
+            pagerank: None,
+            avg_clustering: None,
+            triangles: None,
+            file_size: None,
+            attributes: Vec::new(),
+            meta: None,
+            strings: None,
         })
     }
+
+    /// Number of edges, each of which is listed by both of its ends.
+    pub fn edge_count(&self) -> usize {
+        self.persons
+            .iter()
+            .map(|p| p.neighbors.len())
+            .sum::<usize>()
+            / 2
+    }
+
+    /// Returns the index of the given node in the main graph.
+    pub fn base_id(&self, id: usize) -> usize {
+        self.base_ids.as_ref().map_or(id, |ids| ids[id])
+    }
 }
 
 pub struct StringTables {
@@ -236,12 +328,104 @@ pub struct GraphViewApp {
     modal: (Receiver<ModalInfo>, Sender<ModalInfo>),
     state: AppState,
     md_cache: CommonMarkCache,
+    /// Dock layout saved by the previous run, applied once the graph is loaded.
+    saved_layout: Option<DockState<SavedTab>>,
+    /// Nodes with a lower degree are left out of the graph files opened.
+    open_min_degree: u16,
+    /// Sort the edges of the graphs opened, see [`DisplaySection::sort_edges`].
+    open_sort_edges: bool,
+    /// Whether a tab had something moving on screen during the last frame, in which case the UI
+    /// is repainted continuously. Otherwise, it's only repainted when needed.
+    animating: bool,
+    /// Name and contents of the files picked with [`pick_file`], to open in new tabs.
+    #[cfg(target_arch = "wasm32")]
+    picked_files: (Sender<(String, Vec<u8>)>, Receiver<(String, Vec<u8>)>),
+}
+
+/// Downloads and reads the main graph in the background.
+///
+/// Download errors are sent back to the loading screen instead of ending the loading, so that the
+/// download can be started again with the same channels. The download starts over, as the file is
+/// served compressed and only its decoded bytes can be seen.
+#[derive(Clone)]
+pub struct GraphLoader {
+    status_tx: StatusWriter,
+    file_tx: Sender<ProcessedData>,
+    error_tx: Sender<String>,
+    modal: Sender<ModalInfo>,
+    /// See [`load_binary_pruned`].
+    min_degree: u16,
+}
+
+impl GraphLoader {
+    fn start(&self) {
+        let loader = self.clone();
+
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(async move {
+            match load_file(&loader.status_tx).await {
+                Ok(source) => loader.read(source),
+                Err(e) => loader.fail(e),
+            }
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(move || match load_file(&loader.status_tx) {
+            Ok(source) => loader.read(source),
+            Err(e) => loader.fail(e),
+        });
+    }
+
+    fn read(self, source: GraphSource) {
+        threading::spawn_cancelable(self.modal.clone(), move || {
+            match load_binary_pruned(&self.status_tx, source, self.min_degree) {
+                Ok(res) => self.file_tx.send(res)?,
+                // the file is decoded while it's downloaded, so download errors end up here
+                Err(e @ CancelableError::Other(_)) => self.fail(e),
+                Err(e) => return Err(e),
+            }
+            Ok(())
+        });
+    }
+
+    fn fail(&self, e: CancelableError) {
+        // the other errors only happen when the app is closing
+        if let CancelableError::Other(e) = e {
+            log::error!("Error loading graph file: {:?}", e);
+            let _ = self.error_tx.send(format!("{:#}", e));
+        }
+    }
+}
+
+/// Size of the native window, unless another one was saved.
+pub const DEFAULT_WINDOW_SIZE: [f32; 2] = [1600.0, 900.0];
+
+/// Storage key of the dock layout.
+const LAYOUT_KEY: &str = "dock_layout";
+
+/// Placeholder for a tab in the saved dock layout. Subgraph tabs can't be restored without their
+/// contents, so only the main one is saved.
+#[derive(Serialize, Deserialize)]
+struct SavedTab;
+
+/// Puts the main tab in the saved layout, or in a fresh one if there's none or it's invalid.
+fn restore_layout(saved: Option<DockState<SavedTab>>, main: GraphTab) -> DockState<GraphTab> {
+    match saved {
+        Some(layout) if layout.iter_all_tabs().count() == 1 => {
+            let mut main = Some(main);
+            layout.map_tabs(|_| main.take().expect("the layout has a single tab"))
+        }
+        _ => DockState::new(vec![main]),
+    }
 }
 
 pub enum AppState {
     Loading {
         status_rx: StatusReader,
         file_rx: Receiver<ProcessedData>,
+        loader: GraphLoader,
+        /// Download errors, after which the download can be retried.
+        error_rx: Receiver<String>,
     },
     Loaded {
         tree: DockState<GraphTab>,
@@ -300,40 +484,230 @@ impl GraphViewApp {
             Ok(())
         });
 
-        #[cfg(target_arch = "wasm32")]
-        wasm_bindgen_futures::spawn_local(async move {
-            let Ok(res) = load_file(&status_tx).await else {
-                log::info!("Error loading graph file");
-                return;
-            };
-
-            thread::spawn(move || {
-                let Ok(res) = load_binary(&status_tx, res) else {
-                    log::info!("Error processing graph file");
-                    return;
-                };
-                file_tx.send(res).unwrap();
-            });
-        });
-
-        #[cfg(not(target_arch = "wasm32"))]
-        threading::spawn_cancelable(modal_tx.clone(), move || {
-            let res = load_file(&status_tx)?;
-            let res = load_binary(&status_tx, res)?;
-            file_tx.send(res)?;
-            Ok(())
-        });
+        let (error_tx, error_rx) = mpsc::channel();
+        let loader = GraphLoader {
+            status_tx,
+            file_tx,
+            error_tx,
+            modal: modal_tx.clone(),
+            min_degree: startup_min_degree(),
+        };
+        loader.start();
 
         Self {
             top_bar: true,
             modal: (modal_rx, modal_tx),
             tasks: ctx_rx,
-            state: AppState::Loading { status_rx, file_rx },
+            state: AppState::Loading {
+                status_rx,
+                file_rx,
+                loader,
+                error_rx,
+            },
             md_cache: CommonMarkCache::default(),
+            saved_layout: cc
+                .storage
+                .and_then(|s| eframe::get_value(s, LAYOUT_KEY)),
+            open_min_degree: 0,
+            open_sort_edges: true,
+            animating: false,
+            #[cfg(target_arch = "wasm32")]
+            picked_files: mpsc::channel(),
         }
     }
 }
 
+/// Gathers all the tabs in a single leaf, main tab first, and restores the window size.
+fn reset_layout(tree: &mut DockState<GraphTab>, ctx: &Context) {
+    let mut tabs = Vec::new();
+    while let Some(index) = tree.find_tab_from(|_| true) {
+        tabs.extend(tree.remove_tab(index));
+    }
+    tabs.sort_by_key(|tab| tab.closeable);
+    *tree = DockState::new(tabs);
+    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(DEFAULT_WINDOW_SIZE.into()));
+}
+
+/// Shows the path in the tab of the graph it was asked for, and brings that tab to the front.
+fn open_main_path(tree: &mut DockState<GraphTab>, request: MainPathRequest) {
+    let is_target = |tab: &GraphTab| match &tab.state {
+        GraphTabState::Loaded(loaded) => {
+            Arc::ptr_eq(&loaded.viewer_data.read().engine, &request.engine)
+        }
+        GraphTabState::Loading { .. } => false,
+    };
+    let Some(index) = tree.find_tab_from(is_target) else {
+        log::warn!("The tab of the main graph was closed");
+        return;
+    };
+    if let Some((_, tab)) = tree.iter_all_tabs_mut().find(|(_, tab)| is_target(tab))
+        && let GraphTabState::Loaded(loaded) = &mut tab.state
+    {
+        let path = &mut loaded.ui_state.path;
+        path.path_settings.path_src = request.path_src;
+        path.path_settings.path_dest = request.path_dest;
+        path.path_dirty = true;
+    }
+    tree.set_active_tab(index);
+    tree.set_focused_node_and_surface((index.0, index.1));
+}
+
+/// Creates a camera showing the whole graph.
+pub(crate) fn fit_camera(persons: &[Person]) -> Camera {
+    let mut min = Point::new(f32::INFINITY, f32::INFINITY);
+    let mut max = Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for p in persons {
+        min.x = min.x.min(p.position.x);
+        min.y = min.y.min(p.position.y);
+        max.x = max.x.max(p.position.x);
+        max.y = max.y.max(p.position.y);
+    }
+    let center = (min + max) / 2.0;
+    let mut cam = Camera::new(center);
+    // cam is normalized on the [-1, 1] range
+    // compute x and y scaling to fit the circle, take the best
+    let fig_size = max - min;
+    let scale_x = 1.0 / fig_size.x;
+    let scale_y = 1.0 / fig_size.y;
+    let scale = scale_x.min(scale_y) * 0.98;
+    cam.transf.append_scaling_mut(scale);
+    cam
+}
+
+/// Minimum degree of the nodes kept from the main graph, given by the `min_degree` parameter of
+/// the page URL in the browser, or the `--min-degree` argument on desktop.
+fn startup_min_degree() -> u16 {
+    #[cfg(target_arch = "wasm32")]
+    let value = web_sys::window()
+        .and_then(|w| w.location().search().ok())
+        .and_then(|query| web_sys::UrlSearchParams::new_with_str(&query).ok())
+        .and_then(|params| params.get("min_degree"));
+    #[cfg(not(target_arch = "wasm32"))]
+    let value = std::env::args()
+        .skip_while(|arg| arg != "--min-degree")
+        .nth(1);
+    value.and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+type SourceFn = Box<dyn FnOnce() -> Cancelable<GraphSource> + Send>;
+
+/// Title and contents of a file dropped on the window. In the browser, the contents are given
+/// directly, while on desktop only the path is.
+fn dropped_source(file: egui::DroppedFile) -> Option<(String, SourceFn)> {
+    match (file.bytes, file.path) {
+        (Some(bytes), _) => Some((file.name, Box::new(move || Ok(bytes.to_vec().into())))),
+        #[cfg(not(target_arch = "wasm32"))]
+        (None, Some(path)) => Some((
+            path.file_name()
+                .map_or(file.name, |n| n.to_string_lossy().into()),
+            Box::new(move || Ok(GraphSource::open(path)?)),
+        )),
+        _ => None,
+    }
+}
+
+/// Asks the browser for a file, and sends its name and contents once they have been read.
+#[cfg(target_arch = "wasm32")]
+fn pick_file(tx: Sender<(String, Vec<u8>)>, ctx: Context) -> anyhow::Result<()> {
+    use anyhow::anyhow;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    let input: web_sys::HtmlInputElement = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("No document"))?
+        .create_element("input")
+        .map_err(|e| anyhow!("{:?}", e))?
+        .dyn_into()
+        .map_err(|_| anyhow!("Not an input"))?;
+    input.set_type("file");
+    input.set_accept(".bin,.br,.txt,.csv,.edges");
+    let reader = input.clone();
+    let on_change = Closure::once(move || {
+        let Some(file) = reader.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => {
+                    let _ = tx.send((file.name(), js_sys::Uint8Array::new(&buffer).to_vec()));
+                    ctx.request_repaint();
+                }
+                Err(e) => log::error!("Cannot read {}: {:?}", file.name(), e),
+            }
+        });
+    });
+    input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+    // the input isn't part of the page, so the handler is kept until it runs, if ever
+    on_change.forget();
+    input.click();
+    Ok(())
+}
+
+/// Opens a file in a new tab, named after the file. Graph files (`.bin`, possibly compressed as
+/// `.bin.br`) are read like the main graph, without the nodes of degree below `min_degree`,
+/// anything else as an edge list, see [`load_edge_list`]. Unless `sort_edges` is false, the edges
+/// are sorted before being drawn, see [`DisplaySection::sort_edges`].
+fn open_file(
+    title: String,
+    source: impl FnOnce() -> Cancelable<GraphSource> + Send + 'static,
+    ctx: &Context,
+    modal: &Sender<ModalInfo>,
+    min_degree: u16,
+    sort_edges: bool,
+) -> GraphTab {
+    let name = title.to_lowercase();
+    let compressed = name.ends_with(".br");
+    let binary = name.trim_end_matches(".br").ends_with(".bin");
+
+    let (status_tx, status_rx) = threading::status_pipe(ctx);
+    let (state_tx, state_rx) = mpsc::channel();
+    let (gl_fwd, gl_mpsc) = GlForwarder::new();
+
+    let tab = GraphTab {
+        id: Id::new((&title, chrono::Utc::now())),
+        title,
+        closeable: true,
+        state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
+    };
+
+    threading::spawn_cancelable(modal.clone(), move || {
+        let mut source = source()?;
+        if compressed {
+            log!(status_tx, t!("Decompressing file"));
+            source = decompress_brotli(source);
+        }
+        let file = if binary {
+            load_binary_pruned(&status_tx, source, min_degree)?
+        } else {
+            load_edge_list(&status_tx, source)?
+        };
+        // the nodes point into the string tables, and their copies in subgraphs can outlive the
+        // tab, so the tables are kept for the rest of the program like the main graph's
+        std::mem::forget(file.strings);
+        let cam = fit_camera(&file.viewer.persons);
+        let tab = tabs::create_tab(
+            file.viewer,
+            file.edges,
+            gl_fwd,
+            1,
+            cam,
+            UiState {
+                display: DisplaySection {
+                    sort_edges,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            status_tx,
+        )?;
+        state_tx.send(tab)?;
+        Ok(())
+    });
+
+    tab
+}
+
 pub(crate) fn show_status(ui: &mut Ui, status_rx: &mut StatusReader) {
     ui.vertical_centered(|ui| {
         ui.spinner();
@@ -353,9 +727,19 @@ pub fn show_progress_bar(ui: &mut Ui, status_rx: &StatusReader) {
 }
 
 impl eframe::App for GraphViewApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // while loading, the layout saved by the previous run is left as is
+        if let AppState::Loaded { tree, .. } = &self.state {
+            let layout = tree.filter_map_tabs(|tab| (!tab.closeable).then_some(SavedTab));
+            eframe::set_value(storage, LAYOUT_KEY, &layout);
+        }
+    }
+
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
         let mut new_tab_request = None;
+        let mut path_request = None;
+        self.animating = false;
 
         while let Ok(task) = self.tasks.try_recv() {
             task(ctx);
@@ -369,44 +753,40 @@ impl eframe::App for GraphViewApp {
             .frame(Frame::central_panel(&ctx.style()).inner_margin(0.))
             .show(ctx, |ui| {
                 match &mut self.state {
-                    AppState::Loading { status_rx, file_rx } => {
+                    AppState::Loading {
+                        status_rx,
+                        file_rx,
+                        loader,
+                        error_rx,
+                    } => {
                         show_status(ui, status_rx);
+                        if show_retry_modal(ctx, error_rx, "download_error") {
+                            let _ = loader
+                                .status_tx
+                                .send(t!("Retrying the download").to_string());
+                            loader.start();
+                        }
                         if let Ok(file) = file_rx.try_recv() {
                             let (status_tx, status_rx) = threading::status_pipe(ctx);
                             let (state_tx, state_rx) = mpsc::channel();
                             let (gl_fwd, gl_mpsc) = GlForwarder::new();
+                            let main = GraphTab {
+                                id: Id::new(("main_tab", chrono::Utc::now())),
+                                closeable: false,
+                                title: t!("Graph").to_string(),
+                                state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
+                            };
                             self.state = AppState::Loaded {
-                                tree: DockState::new(vec![GraphTab {
-                                    id: Id::new(("main_tab", chrono::Utc::now())),
-                                    closeable: false,
-                                    title: t!("Graph").to_string(),
-                                    state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
-                                }]),
+                                tree: restore_layout(self.saved_layout.take(), main),
                                 string_tables: file.strings,
                             };
                             threading::spawn_cancelable(self.modal.1.clone(), move || {
-                                let mut min = Point::new(f32::INFINITY, f32::INFINITY);
-                                let mut max = Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
                                 log!(status_tx, t!("Computing graph boundaries..."));
-                                for p in &*file.viewer.persons {
-                                    min.x = min.x.min(p.position.x);
-                                    min.y = min.y.min(p.position.y);
-                                    max.x = max.x.max(p.position.x);
-                                    max.y = max.y.max(p.position.y);
-                                }
-                                let center = (min + max) / 2.0;
-                                let mut cam = Camera::new(center);
-                                // cam is normalized on the [-1, 1] range
-                                // compute x and y scaling to fit the circle, take the best
-                                let fig_size = max - min;
-                                let scale_x = 1.0 / fig_size.x;
-                                let scale_y = 1.0 / fig_size.y;
-                                let scale = scale_x.min(scale_y) * 0.98;
-                                cam.transf.append_scaling_mut(scale);
+                                let cam = fit_camera(&file.viewer.persons);
 
                                 let tab = tabs::create_tab(
                                     file.viewer,
-                                    file.edges.iter(),
+                                    file.edges,
                                     gl_fwd,
                                     if cfg!(target_arch = "wasm32") {
                                         120
@@ -436,14 +816,43 @@ impl eframe::App for GraphViewApp {
                                 ui,
                                 &mut TabViewer {
                                     tab_request: &mut new_tab_request,
+                                    path_request: &mut path_request,
                                     top_bar: &mut self.top_bar,
+                                    animating: &mut self.animating,
                                     frame,
                                     modal: self.modal.1.clone(),
                                 },
                             );
+                        // after the tabs, which may have taken the files for themselves, see
+                        // DisplaySection::show_attributes
+                        let dropped = ctx.input_mut(|i| std::mem::take(&mut i.raw.dropped_files));
+                        for (title, source) in dropped.into_iter().filter_map(dropped_source) {
+                            tree.push_to_focused_leaf(open_file(
+                                title,
+                                source,
+                                ctx,
+                                &self.modal.1,
+                                self.open_min_degree,
+                                self.open_sort_edges,
+                            ));
+                        }
+                        #[cfg(target_arch = "wasm32")]
+                        while let Ok((title, bytes)) = self.picked_files.1.try_recv() {
+                            tree.push_to_focused_leaf(open_file(
+                                title,
+                                move || Ok(bytes.into()),
+                                ctx,
+                                &self.modal.1,
+                                self.open_min_degree,
+                                self.open_sort_edges,
+                            ));
+                        }
                         if let Some(request) = new_tab_request {
                             tree.push_to_focused_leaf(request);
                         }
+                        if let Some(request) = path_request {
+                            open_main_path(tree, request);
+                        }
                     }
                 };
 
@@ -462,10 +871,67 @@ impl eframe::App for GraphViewApp {
                     }
                 }
             });
+
+        if self.animating {
+            ctx.request_repaint();
+        }
     }
 }
 
 impl GraphViewApp {
+    /// Contents of the About dialog: the build of the viewer, and the main graph once loaded.
+    fn about_info(&self) -> ModalInfo {
+        let mut body = t!(
+            "Version %{version}, commit %{commit}, built on %{date}",
+            version = env!("CARGO_PKG_VERSION"),
+            commit = env!("VERGEN_GIT_SHA"),
+            date = env!("VERGEN_BUILD_DATE")
+        )
+        .to_string();
+        body.push_str("\n\n");
+        let main = match &self.state {
+            AppState::Loaded { tree, .. } => {
+                tree.iter_all_tabs().find_map(|(_, tab)| match &tab.state {
+                    GraphTabState::Loaded(loaded) if !tab.closeable => Some(loaded),
+                    _ => None,
+                })
+            }
+            AppState::Loading { .. } => None,
+        };
+        match main {
+            Some(loaded) => {
+                let data = loaded.viewer_data.read();
+                let mut lines = vec![
+                    t!(
+                        "Node count: %{count}",
+                        count = format_count(data.persons.len())
+                    ),
+                    t!(
+                        "Edge count: %{count}",
+                        count = format_count(data.edge_count())
+                    ),
+                    t!(
+                        "Class count: %{count}",
+                        count = data.modularity_classes.len()
+                    ),
+                ];
+                if let Some(size) = data.file_size {
+                    lines.push(t!(
+                        "File size: %{size} MB",
+                        size = format!("{:.1}", size as f64 / 1e6)
+                    ));
+                }
+                body.push_str(&lines.join("\n"));
+            }
+            None => body.push_str(&t!("The graph is still loading")),
+        }
+        ModalInfo {
+            title: t!("About").to_string(),
+            kind: ModalKind::Info,
+            body: body.into(),
+        }
+    }
+
     fn show_top_bar(&mut self, ctx: &Context, shown: bool) {
         egui::TopBottomPanel::top("top_panel").show_animated(ctx, shown, |ui| {
             ui.add_space(10.0);
@@ -480,22 +946,9 @@ impl GraphViewApp {
             ui.horizontal(|ui| {
                 //ui.spacing_mut().item_spacing.x = 50.0;
                 ui.vertical(|ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        ui.spacing_mut().item_spacing.x = 0.0;
-                        let commit = env!("VERGEN_GIT_SHA");
-                        ui.label("Commit ");
-                        ui.add(
-                            Hyperlink::from_label_and_url(
-                                commit,
-                                format!(
-                                    "https://github.com/zdimension/graphrust/commit/{}",
-                                    commit
-                                ),
-                            )
-                                .open_in_new_tab(true),
-                        );
-                        ui.label(format!(" ({})", env!("VERGEN_BUILD_DATE")));
-                    });
+                    if ui.button(t!("ℹ About")).clicked() {
+                        let _ = self.modal.1.send(self.about_info());
+                    }
                     ui.add(
                         Hyperlink::from_label_and_url("zdimension", "https://zdimension.fr")
                             .open_in_new_tab(true),
@@ -512,6 +965,66 @@ impl GraphViewApp {
                                     }
                                 }
                             });
+                            if let AppState::Loaded { tree, .. } = &mut self.state {
+                                ui.horizontal(|ui| {
+                                    if ui.button(t!("💾 Save session")).clicked() {
+                                        session::save_session(tree, &self.modal.1);
+                                    }
+                                    if ui.button(t!("📂 Load session")).clicked() {
+                                        session::load_session(tree, ctx, &self.modal.1);
+                                    }
+                                });
+                                if ui
+                                    .button(t!("Reset layout"))
+                                    .on_hover_text(t!("Put all the tabs back in a single pane, and restore the default window size."))
+                                    .clicked()
+                                {
+                                    reset_layout(tree, ctx);
+                                }
+                                #[cfg(not(target_arch = "wasm32"))]
+                                if ui
+                                    .button(t!("📂 Open file…"))
+                                    .on_hover_text(t!("A graph file, or a text file with one pair of node ids per line. Files can also be dropped on the window."))
+                                    .clicked()
+                                    && let Some(path) = rfd::FileDialog::new()
+                                        .add_filter(t!("Graph"), &["bin", "br"])
+                                        .add_filter(t!("Edge list"), &["txt", "csv", "edges"])
+                                        .pick_file()
+                                {
+                                    let title = path.file_name().map_or_else(
+                                        || t!("Graph").to_string(),
+                                        |n| n.to_string_lossy().into(),
+                                    );
+                                    tree.push_to_focused_leaf(open_file(
+                                        title,
+                                        move || Ok(GraphSource::open(path)?),
+                                        ctx,
+                                        &self.modal.1,
+                                        self.open_min_degree,
+                                        self.open_sort_edges,
+                                    ));
+                                }
+                                #[cfg(target_arch = "wasm32")]
+                                if ui
+                                    .button(t!("📂 Open file…"))
+                                    .on_hover_text(t!("A graph file, or a text file with one pair of node ids per line. Files can also be dropped on the window."))
+                                    .clicked()
+                                    && let Err(e) = pick_file(self.picked_files.0.clone(), ctx.clone())
+                                {
+                                    let _ = self.modal.1.send(ModalInfo {
+                                        title: t!("Error").to_string(),
+                                        kind: ModalKind::Error,
+                                        body: e.to_string().into(),
+                                    });
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.label(t!("Minimum degree of opened graphs:"));
+                                    ui.add(egui::DragValue::new(&mut self.open_min_degree))
+                                        .on_hover_text(t!("Nodes with fewer links are left out when opening a graph file, which saves memory, unlike the filter of the display section."));
+                                });
+                                ui.checkbox(&mut self.open_sort_edges, t!("Sort the edges of opened graphs"))
+                                    .on_hover_text(t!("Draw the longest edges first, for the shorter ones to stay visible on top. Opening large graphs is faster without it, the edges are then drawn in the order of the file."));
+                            }
                             if small_window {
                                 hide_header!(ui);
                             }
@@ -0,0 +1,126 @@
+use crate::app::Person;
+use ahash::AHashMap;
+use anyhow::anyhow;
+use itertools::Itertools;
+
+/// Numeric column of a CSV file attached to the nodes.
+#[derive(PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    /// Value of each node, NaN for the nodes the file doesn't have a value for.
+    pub values: Vec<f32>,
+}
+
+impl Attribute {
+    /// Lowest and highest value, ignoring the missing ones.
+    pub fn range(&self) -> (f32, f32) {
+        self.values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            })
+    }
+}
+
+/// Columns read from a CSV file, see [`parse_csv`].
+pub struct AttachedAttributes {
+    pub columns: Vec<Attribute>,
+    /// Rows whose id matched a node.
+    pub matched: usize,
+    /// Rows whose id didn't match any node, and were left out.
+    pub unmatched: usize,
+}
+
+/// Splits a line on the separator, and removes the quotes around the fields. Separators between
+/// quotes are part of the field, and doubled quotes stand for a quote.
+fn fields(line: &str, separator: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Number of separators in a line, leaving out the quoted ones.
+fn count_separators(line: &str, separator: char) -> usize {
+    line.split('"')
+        .step_by(2)
+        .map(|part| part.matches(separator).count())
+        .sum()
+}
+
+/// Reads the numeric columns of a CSV file with a header row. The rows are matched to the nodes
+/// by the column named `id`, or the first one if there is none. Commas, semicolons and tabs are
+/// accepted as separators, whichever the header has the most of. Fields can be quoted to contain
+/// the separator, but not line breaks.
+///
+/// A column is numeric when all its non-empty values are numbers, the others are left out.
+pub fn parse_csv(text: &str, persons: &[Person]) -> anyhow::Result<AttachedAttributes> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let header = lines
+        .next()
+        .ok_or_else(|| anyhow!(t!("The file is empty")))?;
+    let separator = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|&c| count_separators(header, c))
+        .unwrap();
+    let names = fields(header, separator);
+    let id_column = names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case("id"))
+        .unwrap_or(0);
+
+    let index: AHashMap<&str, usize> = persons.iter().enumerate().map(|(i, p)| (p.id, i)).collect();
+    let mut values = vec![vec![f32::NAN; persons.len()]; names.len()];
+    let mut numeric = vec![true; names.len()];
+    let (mut matched, mut unmatched) = (0, 0);
+    for line in lines {
+        let row = fields(line, separator);
+        let Some(&node) = row.get(id_column).and_then(|id| index.get(id.as_str())) else {
+            unmatched += 1;
+            continue;
+        };
+        matched += 1;
+        for (column, field) in row.into_iter().enumerate().take(names.len()) {
+            if column == id_column || field.is_empty() || !numeric[column] {
+                continue;
+            }
+            match field.parse::<f32>() {
+                Ok(value) => values[column][node] = value,
+                Err(_) => numeric[column] = false,
+            }
+        }
+    }
+
+    let columns = names
+        .into_iter()
+        .zip(values)
+        .enumerate()
+        .filter(|&(column, _)| column != id_column && numeric[column])
+        .map(|(_, (name, values))| Attribute { name, values })
+        .collect_vec();
+    if columns.is_empty() {
+        return Err(anyhow!(t!("The file has no numeric column")));
+    }
+    Ok(AttachedAttributes {
+        columns,
+        matched,
+        unmatched,
+    })
+}
@@ -0,0 +1,176 @@
+//! Spatial buckets of edges, so that only the edges near the visible part of the graph are drawn.
+//!
+//! The edges are sorted into the cells of a loose quadtree over the bounding box of the graph: each
+//! edge goes to the deepest level whose cells are at least as large as the edge, in the cell that
+//! contains its middle. Each cell is a contiguous range of the vertex buffer, and keeps the bounds
+//! of its edges, which are tested against the view when drawing.
+
+use crate::graph_render::PersonVertex;
+use graph_format::Point;
+use rayon::prelude::*;
+use std::ops::Range;
+
+/// Depth of the finest level, which has `2^DEPTH` by `2^DEPTH` cells.
+const DEPTH: u32 = 4;
+
+/// Edges of a cell, and the bounds of their vertices.
+#[derive(Clone, Debug)]
+pub struct Bucket {
+    pub edges: Range<usize>,
+    pub min: Point,
+    pub max: Point,
+}
+
+/// Buckets of the edges of a vertex buffer, in the order of the buffer, coarsest levels first so
+/// that the longest edges are drawn below the others.
+#[derive(Clone, Debug, Default)]
+pub struct EdgeBuckets {
+    pub buckets: Vec<Bucket>,
+}
+
+/// Bounds that contain nothing, which any point extends.
+fn empty() -> (Point, Point) {
+    (
+        Point::new(f32::INFINITY, f32::INFINITY),
+        Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+    )
+}
+
+/// Smallest bounds that contain both bounds. NaN coordinates are ignored.
+fn union((lo, hi): (Point, Point), (min, max): (Point, Point)) -> (Point, Point) {
+    (
+        Point::new(lo.x.min(min.x), lo.y.min(min.y)),
+        Point::new(hi.x.max(max.x), hi.y.max(max.y)),
+    )
+}
+
+/// Bounds of the given points, as the minimum and maximum coordinates.
+pub fn bounds(points: impl IntoIterator<Item = Point>) -> (Point, Point) {
+    points
+        .into_iter()
+        .fold(empty(), |acc, p| union(acc, (p, p)))
+}
+
+/// Index of the cell of an edge with the given bounds, in a graph whose bounding box is the square
+/// of side `size` starting at `origin`. Edges with non-finite bounds go to the root.
+fn cell(min: Point, max: Point, origin: Point, size: f32) -> u32 {
+    let extent = (max.x - min.x).max(max.y - min.y) / size;
+    if !extent.is_finite() {
+        return 0;
+    }
+    let level = if extent > 0.0 {
+        (-extent.log2()).floor().clamp(0.0, DEPTH as f32) as u32
+    } else {
+        DEPTH
+    };
+    let cells = 1 << level;
+    let middle = (min + max) / 2.0 - origin;
+    // the casts saturate, which keeps negative coordinates in the first cell
+    let x = ((middle.x / size * cells as f32) as u32).min(cells - 1);
+    let y = ((middle.y / size * cells as f32) as u32).min(cells - 1);
+    // the cells of the levels above come first
+    ((1 << (2 * level)) - 1) / 3 + y * cells + x
+}
+
+impl EdgeBuckets {
+    /// Sorts edges with the given bounds into buckets. Returns the order the edges must be put in,
+    /// which keeps the original order of the edges within each bucket.
+    pub fn new(bounds: &[(Point, Point)]) -> (Vec<usize>, EdgeBuckets) {
+        let (origin, end) = bounds
+            .iter()
+            .filter(|(min, max)| [min.x, min.y, max.x, max.y].iter().all(|c| c.is_finite()))
+            .fold(empty(), |acc, &b| union(acc, b));
+        let size = (end.x - origin.x)
+            .max(end.y - origin.y)
+            .max(f32::MIN_POSITIVE);
+
+        let cells: Vec<u32> = bounds
+            .par_iter()
+            .map(|&(min, max)| cell(min, max, origin, size))
+            .collect();
+        let mut order: Vec<usize> = (0..bounds.len()).collect();
+        // stable, so that each bucket keeps the order of its edges
+        order.par_sort_by_key(|&e| cells[e]);
+
+        let mut buckets: Vec<Bucket> = Vec::new();
+        for (i, &e) in order.iter().enumerate() {
+            let (min, max) = bounds[e];
+            match buckets.last_mut() {
+                Some(bucket) if cells[order[bucket.edges.start]] == cells[e] => {
+                    bucket.edges.end = i + 1;
+                    (bucket.min, bucket.max) = union((bucket.min, bucket.max), (min, max));
+                }
+                _ => buckets.push(Bucket {
+                    edges: i..i + 1,
+                    min,
+                    max,
+                }),
+            }
+        }
+        (order, EdgeBuckets { buckets })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Ranges of edges to draw for a view with the given bounds, among the first `loaded` edges.
+    /// Only the first `fraction` of each bucket is drawn, see [`crate::lod`]. Adjacent ranges are
+    /// merged, so that a view of the whole graph is drawn in a single call.
+    pub fn visible(
+        &self,
+        min: Point,
+        max: Point,
+        fraction: f32,
+        loaded: usize,
+    ) -> Vec<Range<usize>> {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for bucket in &self.buckets {
+            // written so that buckets with NaN bounds are drawn
+            if bucket.max.x < min.x
+                || bucket.min.x > max.x
+                || bucket.max.y < min.y
+                || bucket.min.y > max.y
+            {
+                continue;
+            }
+            let start = bucket.edges.start;
+            let end = start + (bucket.edges.len() as f32 * fraction).ceil() as usize;
+            let end = end.min(bucket.edges.end).min(loaded);
+            if start >= end {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+}
+
+/// Sorts the edges of a vertex buffer into buckets, see [`EdgeBuckets::new`]. The first
+/// `nodes_count` vertices are the nodes, which stay in place, and each edge is made of
+/// `verts_per_edge` vertices.
+pub fn bucket_edges(
+    vertices: Vec<PersonVertex>,
+    nodes_count: usize,
+    verts_per_edge: usize,
+) -> (Vec<PersonVertex>, EdgeBuckets) {
+    let edges = &vertices[nodes_count..];
+    let edges = &edges[..edges.len() / verts_per_edge * verts_per_edge];
+    let bounds: Vec<(Point, Point)> = edges
+        .par_chunks_exact(verts_per_edge)
+        .map(|verts| bounds(verts.iter().map(|v| v.position)))
+        .collect();
+    let (order, buckets) = EdgeBuckets::new(&bounds);
+    let mut sorted = Vec::with_capacity(nodes_count + edges.len());
+    sorted.extend_from_slice(&vertices[..nodes_count]);
+    sorted.par_extend(order.par_iter().flat_map_iter(|&e| {
+        edges[e * verts_per_edge..(e + 1) * verts_per_edge]
+            .iter()
+            .copied()
+    }));
+    (sorted, buckets)
+}
@@ -0,0 +1,158 @@
+//! Force-directed edge bundling, after Holten & van Wijk (2009).
+//!
+//! Each edge is split into a fixed number of segments, and the control points of compatible
+//! edges (similar angle, length and position) are attracted to each other, while a spring force
+//! keeps each edge in shape.
+
+use crate::app::Person;
+use crate::log_progress;
+use crate::threading::{Cancelable, StatusWriterInterface};
+use ahash::AHashMap;
+use graph_format::Point;
+use itertools::Itertools;
+
+/// Number of segments each bundled edge is split into.
+pub const BUNDLE_SEGMENTS: usize = 8;
+
+const ITERATIONS: usize = 40;
+const INITIAL_STEP: f32 = 0.3;
+const SPRING: f32 = 1.0;
+const MIN_COMPATIBILITY: f32 = 0.6;
+/// Maximum number of edges each edge is attracted to, to keep the cost linear in the edge count.
+const MAX_COMPATIBLE: usize = 24;
+
+pub type BundledEdge = [Point; BUNDLE_SEGMENTS + 1];
+
+struct Segment {
+    p: Point,
+    q: Point,
+}
+
+impl Segment {
+    fn vector(&self) -> Point {
+        self.q - self.p
+    }
+
+    fn midpoint(&self) -> Point {
+        (self.p + self.q) / 2.0
+    }
+
+    /// Product of the angle, scale and position compatibility measures from the paper.
+    fn compatibility(&self, other: &Segment) -> f32 {
+        let (u, v) = (self.vector(), other.vector());
+        let (lu, lv) = (u.norm(), v.norm());
+        if lu == 0.0 || lv == 0.0 {
+            return 0.0;
+        }
+        let angle = ((u.x * v.x + u.y * v.y) / (lu * lv)).abs();
+        let avg = (lu + lv) / 2.0;
+        let scale = 2.0 / (avg / lu.min(lv) + lu.max(lv) / avg);
+        let position = avg / (avg + (self.midpoint() - other.midpoint()).norm());
+        angle * scale * position
+    }
+}
+
+/// Computes the control points of the bundled edges, in the same order as `edges`.
+pub fn bundle_edges(
+    status_tx: &impl StatusWriterInterface,
+    persons: &[Person],
+    edges: &[(usize, usize)],
+) -> Cancelable<Vec<BundledEdge>> {
+    let segments = edges
+        .iter()
+        .map(|&(a, b)| Segment {
+            p: persons[a].position,
+            q: persons[b].position,
+        })
+        .collect_vec();
+
+    let mut points: Vec<BundledEdge> = segments
+        .iter()
+        .map(|s| std::array::from_fn(|k| s.p + s.vector() * (k as f32 / BUNDLE_SEGMENTS as f32)))
+        .collect();
+
+    if segments.is_empty() {
+        return Ok(points);
+    }
+
+    // compatible edges can only be close to each other, so candidates are looked up in a grid of
+    // edge midpoints with cells the size of an average edge
+    let cell = (segments.iter().map(|s| s.vector().norm()).sum::<f32>() / segments.len() as f32)
+        .max(f32::EPSILON);
+    let cell_of = |p: Point| ((p.x / cell).floor() as i32, (p.y / cell).floor() as i32);
+    let mut grid: AHashMap<(i32, i32), Vec<usize>> = AHashMap::new();
+    for (i, s) in segments.iter().enumerate() {
+        grid.entry(cell_of(s.midpoint())).or_default().push(i);
+    }
+
+    let mut compatible = Vec::with_capacity(segments.len());
+    let how_often = (segments.len() / 100).max(1);
+    for (i, s) in segments.iter().enumerate() {
+        let (cx, cy) = cell_of(s.midpoint());
+        let list = (cx - 1..=cx + 1)
+            .cartesian_product(cy - 1..=cy + 1)
+            .filter_map(|c| grid.get(&c))
+            .flatten()
+            .copied()
+            .filter(|&j| j != i)
+            .map(|j| (j, s.compatibility(&segments[j])))
+            .filter(|&(_, c)| c >= MIN_COMPATIBILITY)
+            .k_largest_by(MAX_COMPATIBLE, |(_, a), (_, b)| a.total_cmp(b))
+            .map(|(j, _)| {
+                // edges going the other way have their control points in reverse order
+                let (u, v) = (s.vector(), segments[j].vector());
+                (j, u.x * v.x + u.y * v.y < 0.0)
+            })
+            .collect_vec();
+        compatible.push(list);
+        if i % how_often == 0 {
+            log_progress!(status_tx, i, 2 * segments.len());
+        }
+    }
+
+    // forces are expressed relative to the length of a segment of each edge, so the step size
+    // is too
+    let mut step = INITIAL_STEP;
+    for it in 0..ITERATIONS {
+        let next: Vec<BundledEdge> = points
+            .iter()
+            .zip(&segments)
+            .zip(&compatible)
+            .map(|((cur, s), others)| {
+                let mut new = *cur;
+                let seg_len = s.vector().norm() / BUNDLE_SEGMENTS as f32;
+                if seg_len <= f32::EPSILON || others.is_empty() {
+                    return new;
+                }
+                for i in 1..BUNDLE_SEGMENTS {
+                    let spring = (cur[i - 1] + cur[i + 1] - cur[i] * 2.0) / seg_len * SPRING;
+                    let attraction = others
+                        .iter()
+                        .map(|&(j, flipped)| {
+                            let other = if flipped { BUNDLE_SEGMENTS - i } else { i };
+                            let d = points[j][other] - cur[i];
+                            let n = d.norm();
+                            if n > f32::EPSILON {
+                                d / n
+                            } else {
+                                Point::new(0.0, 0.0)
+                            }
+                        })
+                        .sum::<Point>()
+                        / others.len() as f32;
+                    new[i] = cur[i] + (spring + attraction) * (step * seg_len);
+                }
+                new
+            })
+            .collect();
+        points = next;
+        step *= 0.9;
+        log_progress!(
+            status_tx,
+            segments.len() + (it + 1) * segments.len() / ITERATIONS,
+            2 * segments.len()
+        );
+    }
+
+    Ok(points)
+}
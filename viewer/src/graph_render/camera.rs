@@ -72,6 +72,15 @@ impl Camera {
             ));
     }
 
+    /// Returns the transform that centers the view on the given point, keeping the current zoom
+    /// and rotation.
+    pub fn centered_on(&self, p: Point) -> CamXform {
+        let mut transf = self.transf;
+        transf.isometry.translation.vector =
+            -(transf.isometry.rotation * Vector3::new(p.x, p.y, 0.0)) * transf.scaling();
+        transf
+    }
+
     /// Pans the view.
     pub fn pan(&mut self, dx: f32, dy: f32) {
         self.transf
@@ -0,0 +1,25 @@
+//! Gradient used to color nodes by a numeric value.
+//!
+//! This is a polynomial fit of matplotlib's viridis, the same one is used in `graph.vert`.
+
+use egui::Color32;
+
+const COEFFS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_1],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_5, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_03],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+/// Maps a value in [0, 1] to a color.
+pub fn viridis(t: f32) -> Color32 {
+    let t = t.clamp(0.0, 1.0);
+    let [r, g, b] = std::array::from_fn(|c| {
+        let v = COEFFS.iter().rev().fold(0.0, |acc, k| acc * t + k[c]);
+        (v.clamp(0.0, 1.0) * 255.0) as u8
+    });
+    Color32::from_rgb(r, g, b)
+}
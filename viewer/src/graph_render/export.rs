@@ -0,0 +1,364 @@
+//! Offscreen rendering of the graph view to a PNG image.
+
+use crate::graph_render::camera::Camera;
+use crate::graph_render::{GlTask, NodeSizes, RenderedGraph};
+use crate::threading::{Cancelable, StatusWriterInterface};
+use crate::{log, log_progress};
+use anyhow::anyhow;
+use eframe::glow;
+use egui::{vec2, Color32};
+use graph_format::nalgebra::Matrix4;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Largest width or height of an exported image, in pixels.
+pub const MAX_EXPORT_SIZE: u32 = 8192;
+
+#[derive(Copy, Clone)]
+pub struct ExportSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Each output pixel is the average of `supersampling`² rendered pixels.
+    pub supersampling: u32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            width: 3840,
+            height: 2160,
+            supersampling: 2,
+        }
+    }
+}
+
+/// What the view looks like, captured on the UI thread when the export is requested.
+pub struct ExportView {
+    pub camera: Camera,
+    pub edges: (bool, f32),
+    /// Half-width of the edges in world units, as on screen.
+    pub edge_width: f32,
+    pub nodes: (bool, f32),
+    pub node_sizes: NodeSizes,
+    pub class_colors: Vec<u32>,
+    pub background: Color32,
+}
+
+/// Most pixels in an exported image. The image is rendered a band at a time, but it's kept whole
+/// once scaled down, then encoded.
+pub const MAX_EXPORT_PIXELS: u64 = 40_000_000;
+
+/// Most pixels rendered and read back in a frame, supersampling included.
+const MAX_BAND_PIXELS: u64 = 16_000_000;
+
+/// Rows of the image rendered in a frame, see [`render_task`].
+pub struct Band {
+    /// Number of rows of the image above the band.
+    top: u32,
+    /// Number of rows of the image in the band.
+    rows: u32,
+    /// Supersampling factor actually used, which may be lower than requested if the GPU doesn't
+    /// support renderbuffers that large.
+    supersampling: u32,
+    /// Rendered pixels, bottom row first as returned by OpenGL.
+    rgba: Vec<u8>,
+}
+
+/// Creates a task that draws the graph into an offscreen framebuffer and sends its contents
+/// through `tx`, a band of rows at a time from the top, so that each frame only reads back part
+/// of the image. The task queues itself again for the next band, until the image is complete or
+/// the receiver is gone.
+pub fn render_task(
+    settings: ExportSettings,
+    view: ExportView,
+    tx: Sender<anyhow::Result<Band>>,
+) -> GlTask {
+    band_task(settings, view, tx, 0)
+}
+
+fn band_task(
+    settings: ExportSettings,
+    view: ExportView,
+    tx: Sender<anyhow::Result<Band>>,
+    top: u32,
+) -> GlTask {
+    Box::new(move |graph, gl| {
+        let band = unsafe { render_band(graph, gl, settings, &view, top) };
+        let next = band.as_ref().ok().map(|band| band.top + band.rows);
+        if tx.send(band).is_ok()
+            && let Some(next) = next
+            && next < settings.height
+        {
+            graph.tasks.push_back(band_task(settings, view, tx, next));
+        }
+    })
+}
+
+unsafe fn render_band(
+    graph: &RenderedGraph,
+    gl: &glow::Context,
+    settings: ExportSettings,
+    view: &ExportView,
+    top: u32,
+) -> anyhow::Result<Band> {
+    use glow::HasContext;
+
+    if settings.width as u64 * settings.height as u64 > MAX_EXPORT_PIXELS {
+        return Err(anyhow!(t!(
+            "The image can't have more than %{max} million pixels",
+            max = MAX_EXPORT_PIXELS / 1_000_000
+        )));
+    }
+    let max_size = gl.get_parameter_i32(glow::MAX_RENDERBUFFER_SIZE).max(1) as u32;
+    if settings.width > max_size {
+        return Err(anyhow!(t!(
+            "The image can't be larger than %{max} pixels on this device",
+            max = max_size
+        )));
+    }
+    let supersampling = settings.supersampling.clamp(1, max_size / settings.width);
+    let (width, height) = (
+        settings.width * supersampling,
+        settings.height * supersampling,
+    );
+
+    // same framing as on screen, scaled to the size of the image
+    let mut camera = view.camera;
+    let zoom = camera.transf.scaling();
+    camera.set_window_size(vec2(width as f32, height as f32));
+    // the node size cap is in pixels, so it grows along with the image
+    let mut node_sizes = view.node_sizes;
+    node_sizes.max_pixels *= camera.transf.scaling() / zoom;
+
+    // nodes are culled when their center is out of view, so the band is drawn with a margin for
+    // the ones across its borders
+    let margin = ((node_sizes.max_pixels / 2.0).ceil() as u32 + 1).min(max_size / 4);
+    let max_rows = (MAX_BAND_PIXELS / (width as u64 * supersampling as u64)).max(1) as u32;
+    let fit_rows = (max_size - 2 * margin) / supersampling;
+    let rows = max_rows.min(fit_rows).clamp(1, settings.height - top);
+    let band_height = rows * supersampling;
+    let drawn_height = band_height + 2 * margin;
+    // bottom of the band in the rows of the whole image, which OpenGL counts from the bottom
+    let bottom = height - (top + rows) * supersampling;
+
+    let previous_fbo = gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING);
+    let mut previous_viewport = [0; 4];
+    gl.get_parameter_i32_slice(glow::VIEWPORT, &mut previous_viewport);
+    let scissor = gl.is_enabled(glow::SCISSOR_TEST);
+
+    let fbo = gl.create_framebuffer().map_err(|e| anyhow!(e))?;
+    let rbo = gl.create_renderbuffer().map_err(|e| anyhow!(e))?;
+    gl.bind_renderbuffer(glow::RENDERBUFFER, Some(rbo));
+    gl.renderbuffer_storage(
+        glow::RENDERBUFFER,
+        glow::RGBA8,
+        width as i32,
+        drawn_height as i32,
+    );
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(fbo));
+    gl.framebuffer_renderbuffer(
+        glow::FRAMEBUFFER,
+        glow::COLOR_ATTACHMENT0,
+        glow::RENDERBUFFER,
+        Some(rbo),
+    );
+
+    let result = if gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+        Err(anyhow!(t!("Unable to create the image framebuffer")))
+    } else {
+        gl.viewport(0, 0, width as i32, drawn_height as i32);
+        gl.disable(glow::SCISSOR_TEST);
+        let [r, g, b, _] = view.background.to_normalized_gamma_f32();
+        gl.clear_color(r, g, b, 1.0);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+
+        // stretches the rows of the band and its margins over the framebuffer
+        let scale = height as f32 / drawn_height as f32;
+        let shift = scale - 2.0 * (bottom as f32 - margin as f32) / drawn_height as f32 - 1.0;
+        #[rustfmt::skip]
+        let band = Matrix4::new(
+            1.0, 0.0, 0.0, 0.0,
+            0.0, scale, 0.0, shift,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        );
+        graph.draw(
+            gl,
+            band * camera.get_matrix(),
+            view.edges,
+            view.edge_width,
+            // the image has all the edges, however slow it is to draw them
+            1.0,
+            view.nodes,
+            node_sizes,
+            &view.class_colors,
+        );
+
+        let mut rgba = vec![0; width as usize * band_height as usize * 4];
+        gl.read_pixels(
+            0,
+            margin as i32,
+            width as i32,
+            band_height as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(Some(&mut rgba)),
+        );
+        Ok(Band {
+            top,
+            rows,
+            supersampling,
+            rgba,
+        })
+    };
+
+    gl.bind_framebuffer(glow::FRAMEBUFFER, previous_fbo);
+    gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+    gl.delete_framebuffer(fbo);
+    gl.delete_renderbuffer(rbo);
+    let [x, y, w, h] = previous_viewport;
+    gl.viewport(x, y, w, h);
+    if scissor {
+        gl.enable(glow::SCISSOR_TEST);
+    }
+
+    result
+}
+
+/// Receives the bands of the image from [`render_task`], downsamples them, and encodes the image
+/// as a PNG file.
+pub fn encode_png(
+    status_tx: &impl StatusWriterInterface,
+    settings: ExportSettings,
+    bands: Receiver<anyhow::Result<Band>>,
+) -> Cancelable<Vec<u8>> {
+    let (width, height) = (settings.width as usize, settings.height as usize);
+    let mut rgb = Vec::with_capacity(width * height * 3);
+    while rgb.len() < width * height * 3 {
+        let Band {
+            rows,
+            supersampling: ss,
+            rgba,
+            ..
+        } = bands.recv()??;
+        let (rows, ss) = (rows as usize, ss as usize);
+        let stride = width * ss * 4;
+        let samples = (ss * ss) as u32;
+        for y in 0..rows {
+            // OpenGL rows go from bottom to top
+            let band_rows = (rows - 1 - y) * ss..(rows - y) * ss;
+            for x in 0..width {
+                let mut sum = [0u32; 3];
+                for row in band_rows.clone() {
+                    let start = row * stride + x * ss * 4;
+                    for px in rgba[start..start + ss * 4].chunks_exact(4) {
+                        for (s, &c) in sum.iter_mut().zip(px) {
+                            *s += c as u32;
+                        }
+                    }
+                }
+                rgb.extend(sum.map(|s| ((s + samples / 2) / samples) as u8));
+            }
+        }
+        log_progress!(status_tx, rgb.len() / 3 / width, height);
+    }
+
+    log!(status_tx, t!("Encoding the image"));
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&rgb)?;
+    Ok(png)
+}
+
+/// Kind of file produced by an export.
+#[derive(Copy, Clone)]
+pub struct ExportFormat {
+    pub name: &'static str,
+    pub extension: &'static str,
+    pub mime: &'static str,
+}
+
+pub const PNG: ExportFormat = ExportFormat {
+    name: "PNG",
+    extension: "png",
+    mime: "image/png",
+};
+
+pub const JSON: ExportFormat = ExportFormat {
+    name: "JSON",
+    extension: "json",
+    mime: "application/json",
+};
+
+/// Graph file, which can be opened again.
+pub const GRAPH: ExportFormat = ExportFormat {
+    name: "Graph",
+    extension: "bin",
+    mime: "application/octet-stream",
+};
+
+impl ExportFormat {
+    /// Default name of the exported file.
+    pub fn file_name(&self) -> String {
+        format!("graph.{}", self.extension)
+    }
+}
+
+/// Asks the user where to save the file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn pick_file(format: ExportFormat) -> Option<std::path::PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(format.name, &[format.extension])
+        .set_file_name(format.file_name())
+        .save_file()
+}
+
+/// In the browser, files are downloaded with their default name.
+#[cfg(target_arch = "wasm32")]
+pub fn pick_file(format: ExportFormat) -> Option<std::path::PathBuf> {
+    Some(std::path::PathBuf::from(format.file_name()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(
+    contents: &[u8],
+    path: &std::path::Path,
+    _format: ExportFormat,
+) -> anyhow::Result<()> {
+    std::fs::write(path, contents)?;
+    log::info!("Exported to {}", path.display());
+    Ok(())
+}
+
+/// Triggers a download of the file in the browser. This needs the DOM, so it must be called from
+/// the main thread.
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(
+    contents: &[u8],
+    path: &std::path::Path,
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    use wasm_bindgen::JsCast;
+
+    let js_err = |e: wasm_bindgen::JsValue| anyhow!("{:?}", e);
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(format.mime);
+    let blob =
+        web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(js_err)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_err)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("No document"))?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_err)?
+        .dyn_into()
+        .map_err(|_| anyhow!("Not an anchor"))?;
+    anchor.set_href(&url);
+    anchor.set_download(&path.to_string_lossy());
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_err)?;
+    Ok(())
+}
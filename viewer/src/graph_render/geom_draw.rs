@@ -1,25 +1,140 @@
 use crate::app::Person;
-use crate::graph_render::PersonVertex;
+use crate::graph_render::bundling::{BundledEdge, BUNDLE_SEGMENTS};
+use crate::graph_render::{packing, PersonVertex};
+use graph_format::Point;
+use itertools::Itertools;
 
 pub fn create_node_vertex(p: &Person) -> PersonVertex {
-    PersonVertex::new(
-        p.position,
-        p.neighbors.len() as u16,
-        p.modularity_class,
-    )
+    PersonVertex::new(p.position, p)
 }
 
 pub const VERTS_PER_EDGE: usize = 6;
 
+/// Creates the two triangles of a segment going from `a` to `b`. The vertices are on the segment,
+/// and offset sideways by a unit vector, scaled by the edge width in the vertex shader so that
+/// edges keep the same thickness on screen whatever the zoom level. The side of each vertex lets
+/// the fragment shader smooth the borders.
+fn segment_vertices(
+    a: Point,
+    b: Point,
+    na: &Person,
+    nb: &Person,
+) -> [PersonVertex; VERTS_PER_EDGE] {
+    let ortho = (b - a).ortho().normalized();
+    let x = [
+        (a, 1.0, na),
+        (a, -1.0, na),
+        (b, -1.0, nb),
+        (b, -1.0, nb),
+        (b, 1.0, nb),
+        (a, 1.0, na),
+    ];
+    x.map(|(pos, side, node)| {
+        let vertex = PersonVertex::with_offset(pos, ortho * side, node);
+        PersonVertex {
+            class: packing::pack_side(vertex.class, side),
+            ..vertex
+        }
+    })
+}
+
+/// Creates the vertices of a straight edge. Each end takes the class of its node, and the color is
+/// interpolated along the edge, so that links between classes show a gradient.
 pub fn create_edge_vertices(pa: &Person, pb: &Person) -> [PersonVertex; VERTS_PER_EDGE] {
-    let a = pa.position;
+    segment_vertices(pa.position, pb.position, pa, pb)
+}
+
+pub const VERTS_PER_ARROW: usize = 3;
+
+pub const VERTS_PER_DIRECTED_EDGE: usize = VERTS_PER_EDGE + VERTS_PER_ARROW;
+
+/// Size of the arrowheads, relative to the half-width of the edges.
+const ARROW_LENGTH: f32 = 10.0;
+const ARROW_HALF_WIDTH: f32 = 4.0;
+
+/// Largest distance between an edge vertex and its position once offset, relative to the
+/// half-width of the edges.
+pub const MAX_OFFSET: f32 = ARROW_LENGTH + ARROW_HALF_WIDTH;
+
+/// Creates the arrowhead at the end of a directed edge going from `pa` to `pb`.
+pub fn create_arrow_vertices(pa: &Person, pb: &Person) -> [PersonVertex; VERTS_PER_ARROW] {
+    arrow_vertices(pa.position, pb)
+}
+
+/// Creates the arrowhead of a directed edge ending on `pb`, pointing away from `from`.
+fn arrow_vertices(from: Point, pb: &Person) -> [PersonVertex; VERTS_PER_ARROW] {
     let b = pb.position;
-    const EDGE_HALF_WIDTH: f32 = 0.75;
-    let ortho = (b - a).ortho().normalized() * EDGE_HALF_WIDTH;
-    let v0 = a + ortho;
-    let v1 = a - ortho;
-    let v2 = b - ortho;
-    let v3 = b + ortho;
-    let x = [(v0, pa), (v1, pa), (v2, pb), (v2, pb), (v3, pb), (v0, pa)];
-    x.map(|(pos, node)| PersonVertex::new(pos, node.neighbors.len() as u16, node.modularity_class))
-}
\ No newline at end of file
+    let dir = (b - from).normalized();
+    let base = dir * -ARROW_LENGTH;
+    let ortho = dir.ortho() * ARROW_HALF_WIDTH;
+    [Point::new(0.0, 0.0), base + ortho, base - ortho]
+        .map(|offset| PersonVertex::with_offset(b, offset, pb))
+}
+
+pub const VERTS_PER_BUNDLED_EDGE: usize = VERTS_PER_EDGE * BUNDLE_SEGMENTS;
+
+/// Creates the vertices for an edge made of several segments. The points of the first half of the
+/// edge take their attributes from the first node, and those of the second half from the second
+/// node, so that the colors blend over the middle segment instead of switching abruptly.
+fn polyline_vertices<'a>(
+    pa: &'a Person,
+    pb: &'a Person,
+    points: impl IntoIterator<Item = Point> + 'a,
+    segments: usize,
+) -> impl Iterator<Item = PersonVertex> + 'a {
+    points
+        .into_iter()
+        .tuple_windows()
+        .enumerate()
+        .flat_map(move |(i, (a, b))| {
+            let node = |point| if point < segments / 2 { pa } else { pb };
+            segment_vertices(a, b, node(i), node(i + 1))
+        })
+}
+
+pub fn create_bundled_edge_vertices<'a>(
+    pa: &'a Person,
+    pb: &'a Person,
+    points: &'a BundledEdge,
+) -> impl Iterator<Item = PersonVertex> + 'a {
+    polyline_vertices(pa, pb, points.iter().copied(), BUNDLE_SEGMENTS)
+}
+
+/// Number of segments a curved edge is approximated with.
+pub const CURVE_SEGMENTS: usize = 8;
+
+pub const VERTS_PER_CURVED_EDGE: usize = VERTS_PER_EDGE * CURVE_SEGMENTS;
+
+pub const VERTS_PER_DIRECTED_CURVED_EDGE: usize = VERTS_PER_CURVED_EDGE + VERTS_PER_ARROW;
+
+/// Control point of the quadratic Bézier curve of an edge going from `a` to `b`, offset sideways
+/// from the middle by `curvature` times the length of the edge. The side depends on the direction,
+/// so the two edges between a pair of nodes in a directed graph bend apart.
+fn curve_control(a: Point, b: Point, curvature: f32) -> Point {
+    (a + b) * 0.5 + (b - a).ortho() * curvature
+}
+
+/// Creates the vertices of an edge bent into a quadratic Bézier curve. A curvature of zero gives a
+/// straight edge, but [`create_edge_vertices`] draws those with fewer vertices.
+pub fn create_curved_edge_vertices<'a>(
+    pa: &'a Person,
+    pb: &'a Person,
+    curvature: f32,
+) -> impl Iterator<Item = PersonVertex> + 'a {
+    let (a, b) = (pa.position, pb.position);
+    let c = curve_control(a, b, curvature);
+    let points: [Point; CURVE_SEGMENTS + 1] = std::array::from_fn(|k| {
+        let t = k as f32 / CURVE_SEGMENTS as f32;
+        a * ((1.0 - t) * (1.0 - t)) + c * (2.0 * (1.0 - t) * t) + b * (t * t)
+    });
+    polyline_vertices(pa, pb, points, CURVE_SEGMENTS)
+}
+
+/// Creates the arrowhead of a curved edge, along the tangent of the curve at its end.
+pub fn create_curved_arrow_vertices(
+    pa: &Person,
+    pb: &Person,
+    curvature: f32,
+) -> [PersonVertex; VERTS_PER_ARROW] {
+    arrow_vertices(curve_control(pa.position, pb.position, curvature), pb)
+}
@@ -0,0 +1,139 @@
+//! Level of detail of the edges: when frames get too slow, only the most important edges are
+//! drawn.
+
+use crate::app::Person;
+use derivative::Derivative;
+use graph_format::EdgeStore;
+use itertools::{Either, Itertools};
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+
+/// Average frame time above which fewer edges are drawn, in seconds.
+const SLOW_FRAME: f64 = 0.033;
+
+/// Average frame time below which more edges are drawn again, in seconds.
+const FAST_FRAME: f64 = 0.020;
+
+/// Frames are averaged over this long before adjusting the fraction, in seconds.
+const WINDOW: f64 = 1.0;
+
+/// Fewer frames in a window aren't enough to judge.
+const MIN_FRAMES: usize = 3;
+
+/// Longer pauses between frames are idle time rather than slow frames.
+const MAX_FRAME_TIME: f64 = 1.0;
+
+/// Lowest fraction of the edges drawn.
+pub const MIN_FRACTION: f32 = 0.02;
+
+/// How much the fraction changes after a slow or fast window.
+const STEP_DOWN: f32 = 0.7;
+const STEP_UP: f32 = 1.25;
+
+/// Importance of an edge, higher for edges between well-connected nodes. The edges are sorted by
+/// decreasing importance, so that drawing the first ones leaves out the least important.
+pub fn edge_importance(a: &Person, b: &Person) -> usize {
+    a.neighbors.len().min(b.neighbors.len())
+}
+
+/// Ends of the edges, in the order they're drawn in when the graph is loaded.
+///
+/// With `sorted`, the most important edges come first, to be the ones drawn when only part of them
+/// are, then the longest ones, for the shorter ones to be drawn on top. Sorting takes a while and
+/// a copy of the edge list on large graphs, without it the edges keep the order of the file.
+pub fn edge_draw_order<'a, 'e>(
+    persons: &'a [Person],
+    edges: impl Iterator<Item = &'e EdgeStore>,
+    sorted: bool,
+) -> impl Iterator<Item = (&'a Person, &'a Person)> {
+    let ends = edges.map(move |e| (&persons[e.a as usize], &persons[e.b as usize]));
+    if !sorted {
+        return Either::Left(ends);
+    }
+    Either::Right(
+        ends.map(|(a, b)| (a, b, (a.position - b.position).norm_squared()))
+            .sorted_unstable_by(|(a1, b1, dist1), (a2, b2, dist2)| {
+                edge_importance(a2, b2)
+                    .cmp(&edge_importance(a1, b1))
+                    .then(dist2.total_cmp(dist1))
+            })
+            .map(|(a, b, _)| (a, b)),
+    )
+}
+
+/// Keeps the `max` most important edges, in no particular order. Unsorted edges that don't all fit
+/// in the vertex buffer are cut down with this, which is much cheaper than sorting them.
+pub fn keep_most_important<E: Borrow<EdgeStore>>(
+    persons: &[Person],
+    edges: &mut Vec<E>,
+    max: usize,
+) {
+    if edges.len() > max {
+        edges.select_nth_unstable_by_key(max, |e| {
+            let e = e.borrow();
+            Reverse(edge_importance(
+                &persons[e.a as usize],
+                &persons[e.b as usize],
+            ))
+        });
+        edges.truncate(max);
+    }
+}
+
+/// Fraction of the edges drawn in a tab, adapted to the time taken by the frames.
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct EdgeLod {
+    /// Fraction picked from the frame times.
+    #[derivative(Default(value = "1.0"))]
+    pub auto: f32,
+    /// Fraction chosen by the user, which replaces the automatic one.
+    pub manual: Option<f32>,
+    last_time: Option<f64>,
+    window_start: f64,
+    frames: usize,
+    /// Sum of the frame times in the current window.
+    busy: f64,
+}
+
+impl EdgeLod {
+    /// Fraction of the edges to draw.
+    pub fn fraction(&self) -> f32 {
+        self.manual.unwrap_or(self.auto)
+    }
+
+    /// Records a frame drawn at the given time, from `egui::InputState::time`. Only frames drawn
+    /// continuously are timed, e.g. while the camera moves, as frames drawn in response to
+    /// sparse input events are far apart whatever they cost.
+    pub fn update(&mut self, time: f64, continuous: bool) {
+        if !continuous {
+            self.last_time = None;
+            return;
+        }
+        match self.last_time.replace(time) {
+            Some(last) if time - last <= MAX_FRAME_TIME => {
+                self.frames += 1;
+                self.busy += time - last;
+            }
+            _ => {
+                self.window_start = time;
+                self.frames = 0;
+                self.busy = 0.0;
+            }
+        }
+        if time - self.window_start < WINDOW {
+            return;
+        }
+        if self.frames >= MIN_FRAMES {
+            let average = self.busy / self.frames as f64;
+            if average > SLOW_FRAME {
+                self.auto = (self.auto * STEP_DOWN).max(MIN_FRACTION);
+            } else if average < FAST_FRAME {
+                self.auto = (self.auto * STEP_UP).min(1.0);
+            }
+        }
+        self.window_start = time;
+        self.frames = 0;
+        self.busy = 0.0;
+    }
+}
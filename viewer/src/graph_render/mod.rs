@@ -1,17 +1,28 @@
-use crate::app::ViewerData;
+use crate::app::{Person, ViewerData};
+use crate::attributes::Attribute;
 use crate::threading::{Cancelable, StatusWriter};
 use crate::{for_progress, log};
 use anyhow::anyhow;
 use derivative::Derivative;
 use eframe::glow;
-use graph_format::nalgebra::Matrix4;
+use graph_format::nalgebra::{Matrix4, Vector4};
 use graph_format::{Color3b, Color3f, EdgeStore, Point};
 use std::collections::VecDeque;
+use std::ops::Range;
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
+pub mod buckets;
+pub mod bundling;
 pub mod camera;
+pub mod colormap;
+pub mod export;
 pub mod geom_draw;
+pub mod lod;
+pub mod packing;
+
+use buckets::EdgeBuckets;
 
 pub type GlWorkResult = Box<dyn std::any::Any + Send>;
 
@@ -59,147 +70,578 @@ impl GlForwarder {
 
 pub type GlTask = Box<dyn FnOnce(&mut RenderedGraph, &glow::Context) + Send + Sync + 'static>;
 
-#[derive(Copy, Clone, Derivative)]
+/// Maximum size of the vertex buffer, beyond which edges are dropped.
+pub const MAX_VERTEX_BUFFER_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Copy, Clone, PartialEq, Derivative)]
 #[derivative(Default())]
 pub struct NodeFilter {
     #[derivative(Default(value = "(0, u16::MAX)"))]
     pub degree_filter: (u16, u16),
     pub filter_nodes: bool,
+    /// Only show the k-core for this k, 0 to show all nodes.
+    pub kcore: u16,
+    /// Only show the nodes whose name matches the name filter, see [`Person::name_match`].
+    pub filter_names: bool,
+}
+
+impl NodeFilter {
+    /// Whether the node passes the filter, i.e. is drawn.
+    pub fn shows(&self, p: &Person) -> bool {
+        let deg_ok = if self.filter_nodes {
+            let deg = packing::filter_degree(p.neighbors.len());
+            deg >= self.degree_filter.0 && deg <= self.degree_filter.1
+        } else {
+            true
+        };
+        deg_ok && p.core_number >= self.kcore && (!self.filter_names || p.name_match)
+    }
+}
+
+/// How nodes are colored.
+#[derive(Clone, PartialEq, Default)]
+pub enum ColorMode {
+    /// By modularity class.
+    #[default]
+    Class,
+    /// On a gradient, by degree, in log scale.
+    Degree,
+    /// On a gradient, by PageRank, in log scale.
+    PageRank(Arc<Vec<f32>>),
+    /// On a gradient, by an arbitrary value for each node.
+    Custom(Vec<f32>),
+    /// On a gradient, by a column of the attached attributes, between the given bounds.
+    Attribute(Arc<Attribute>, (f32, f32)),
+}
+
+impl ColorMode {
+    /// Computes the value of each node to map on the gradient, normalized to [0, 1], along with
+    /// the range of the raw values. Returns `None` when nodes are colored by class. Nodes without a
+    /// value get the lowest one.
+    pub fn node_values(&self, persons: &[Person]) -> Option<(Vec<f32>, (f32, f32))> {
+        let raw = match self {
+            ColorMode::Class => return None,
+            ColorMode::Degree => persons.iter().map(|p| p.neighbors.len() as f32).collect(),
+            ColorMode::PageRank(values) => values.to_vec(),
+            ColorMode::Custom(values) => values.clone(),
+            ColorMode::Attribute(attribute, _) => attribute.values.clone(),
+        };
+        let scale = |v: f32| match self {
+            ColorMode::Degree => v.ln_1p(),
+            // scores are never 0, since any node can be reached by a random jump
+            ColorMode::PageRank(_) => v.ln(),
+            _ => v,
+        };
+        let (min, max) = match self {
+            ColorMode::Attribute(_, bounds) => *bounds,
+            _ => raw
+                .iter()
+                .filter(|v| v.is_finite())
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &v| {
+                    (min.min(v), max.max(v))
+                }),
+        };
+        let (low, high) = (scale(min), scale(max));
+        let normalized = raw
+            .iter()
+            .map(|&v| {
+                if high > low && v.is_finite() {
+                    ((scale(v) - low) / (high - low)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        Some((normalized, (min, max)))
+    }
+}
+
+/// How the size of nodes is computed.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub enum NodeSizeMode {
+    /// All nodes have the same size.
+    Uniform,
+    /// From the size stored in the graph file.
+    File,
+    /// By degree, in log scale.
+    #[default]
+    Degree,
+}
+
+/// Diameter of the smallest and largest nodes, in world units, before scaling.
+const NODE_SIZE_RANGE: (f32, f32) = (12.0, 100.0);
+
+/// Diameter past which nodes stop growing when zooming in, in pixels, before scaling. Without it,
+/// hubs cover the whole view when zoomed in.
+pub const MAX_NODE_PIXELS: f32 = 64.0;
+
+/// Parameters of the node sizes, shared by the shader and the node picking.
+#[derive(Copy, Clone)]
+pub struct NodeSizes {
+    pub mode: NodeSizeMode,
+    /// Factor applied to the size of all nodes.
+    pub scale: f32,
+    /// Value mapped to the largest size: the largest file size, or the log of the highest degree.
+    pub max_value: f32,
+    /// See [`MAX_NODE_PIXELS`].
+    pub max_pixels: f32,
+}
+
+impl NodeSizes {
+    /// Diameter of a node on screen, in pixels, at the given zoom level. Kept in sync with
+    /// graph.vert.
+    pub fn pixels(&self, node: &Person, zoom: f32) -> f32 {
+        let value = match self.mode {
+            NodeSizeMode::Uniform => 0.0,
+            NodeSizeMode::File => node.size,
+            NodeSizeMode::Degree => (node.neighbors.len() as f32).ln_1p(),
+        };
+        let t = if self.max_value > 0.0 {
+            (value / self.max_value).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let (min, max) = NODE_SIZE_RANGE;
+        ((min + (max - min) * t) * self.scale * zoom).min(self.max_pixels)
+    }
+
+    /// Radius of a node in world units, at the given zoom level.
+    pub fn radius(&self, node: &Person, zoom: f32) -> f32 {
+        self.pixels(node, zoom) / 2.0 / zoom
+    }
+}
+
+/// Uniform vectors kept for the uniforms of `graph.vert` other than the class colors.
+const RESERVED_UNIFORM_VECTORS: usize = 32;
+
+/// Most classes the shaders can be compiled for: each entry of `u_class_colors` takes a uniform
+/// vector, and the vertex shader only gets so many of them.
+unsafe fn max_classes(gl: &glow::Context) -> usize {
+    use glow::HasContext as _;
+    let vectors = if cfg!(target_arch = "wasm32") {
+        gl.get_parameter_i32(glow::MAX_VERTEX_UNIFORM_VECTORS)
+    } else {
+        gl.get_parameter_i32(glow::MAX_VERTEX_UNIFORM_COMPONENTS) / 4
+    };
+    (vectors as usize)
+        .saturating_sub(RESERVED_UNIFORM_VECTORS)
+        .max(1)
+}
+
+/// Compiles a program from its shader sources, prefixed with the GLSL version and the number of
+/// classes.
+unsafe fn compile_program(
+    gl: &glow::Context,
+    num_classes: usize,
+    shader_sources: [(u32, &str); 2],
+) -> anyhow::Result<glow::Program> {
+    use glow::HasContext as _;
+    let shader_version = if cfg!(target_arch = "wasm32") {
+        "#version 300 es"
+    } else {
+        "#version 330"
+    };
+    let program = gl
+        .create_program()
+        .map_err(|e| anyhow!("Cannot create program: {e}"))?;
+
+    let mut shaders = Vec::with_capacity(shader_sources.len());
+    for (shader_type, shader_source) in shader_sources {
+        let shader = gl
+            .create_shader(shader_type)
+            .map_err(|e| anyhow!("Cannot create shader: {e}"))?;
+        gl.shader_source(
+            shader,
+            &format!("{shader_version}\n#define NUM_CLASSES {num_classes}\n{shader_source}"),
+        );
+        gl.compile_shader(shader);
+        if !gl.get_shader_compile_status(shader) {
+            return Err(anyhow!(
+                "Failed to compile {shader_type}: {}",
+                gl.get_shader_info_log(shader)
+            ));
+        }
+        gl.attach_shader(program, shader);
+        shaders.push(shader);
+    }
+
+    gl.link_program(program);
+    if !gl.get_program_link_status(program) {
+        return Err(anyhow!("{}", gl.get_program_info_log(program)));
+    }
+
+    for shader in shaders {
+        gl.detach_shader(program, shader);
+        gl.delete_shader(shader);
+    }
+
+    Ok(program)
+}
+
+/// Compiles the basic, edge and node programs. Also returns whether the edges are antialiased,
+/// which needs a fragment shader that some drivers refuse, in which case the edges are drawn with
+/// hard borders.
+unsafe fn create_programs(
+    gl: &glow::Context,
+    num_classes: usize,
+) -> anyhow::Result<([glow::Program; 3], bool)> {
+    let basic_frag = (glow::FRAGMENT_SHADER, include_str!("shaders/basic.frag"));
+    let graph_vert = (glow::VERTEX_SHADER, include_str!("shaders/graph.vert"));
+    let program_basic = compile_program(
+        gl,
+        num_classes,
+        [
+            (glow::VERTEX_SHADER, include_str!("shaders/basic.vert")),
+            basic_frag,
+        ],
+    )?;
+    let edge_frag = (
+        glow::FRAGMENT_SHADER,
+        include_str!("shaders/graph_edge.frag"),
+    );
+    let (program_edge, antialiased) =
+        match compile_program(gl, num_classes, [graph_vert, edge_frag]) {
+            Ok(program) => (program, true),
+            Err(e) => {
+                log::warn!("Cannot antialias the edges: {e}");
+                (
+                    compile_program(gl, num_classes, [graph_vert, basic_frag])?,
+                    false,
+                )
+            }
+        };
+    let program_node = compile_program(
+        gl,
+        num_classes,
+        [
+            graph_vert,
+            (
+                glow::FRAGMENT_SHADER,
+                include_str!("shaders/graph_node.frag"),
+            ),
+        ],
+    )?;
+    Ok(([program_basic, program_edge, program_node], antialiased))
+}
+
+/// Creates the vertex array, the vertex buffer with room for `vertices_count` vertices and the
+/// buffer of node values.
+unsafe fn create_buffers(
+    gl: &glow::Context,
+    vertices_count: usize,
+    nodes_count: usize,
+) -> anyhow::Result<(glow::VertexArray, glow::Buffer, glow::Buffer)> {
+    use glow::HasContext as _;
+    let vertices_array = gl
+        .create_vertex_array()
+        .map_err(|e| anyhow!("Cannot create vertex array: {e}"))?;
+    gl.bind_vertex_array(Some(vertices_array));
+    let vertices_buffer = gl
+        .create_buffer()
+        .map_err(|e| anyhow!("Cannot create buffer: {e}"))?;
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertices_buffer));
+    gl.buffer_data_size(
+        glow::ARRAY_BUFFER,
+        (vertices_count * size_of::<PersonVertex>())
+            .try_into()
+            .unwrap(),
+        glow::STATIC_DRAW,
+    );
+    let err = gl.get_error();
+    if err != glow::NO_ERROR {
+        log::error!("Error: {:x}", err);
+    }
+    gl.vertex_attrib_pointer_f32(
+        0,
+        2,
+        glow::FLOAT,
+        false,
+        size_of::<PersonVertex>() as i32,
+        0,
+    );
+    gl.enable_vertex_attrib_array(0);
+    gl.vertex_attrib_pointer_i32(
+        1,
+        1,
+        glow::UNSIGNED_INT,
+        size_of::<PersonVertex>() as i32,
+        size_of::<Point>() as i32,
+    );
+    gl.enable_vertex_attrib_array(1);
+    gl.vertex_attrib_pointer_i32(
+        6,
+        1,
+        glow::UNSIGNED_INT,
+        size_of::<PersonVertex>() as i32,
+        (size_of::<Point>() + size_of::<u32>()) as i32,
+    );
+    gl.enable_vertex_attrib_array(6);
+    gl.vertex_attrib_pointer_i32(
+        3,
+        1,
+        glow::UNSIGNED_INT,
+        size_of::<PersonVertex>() as i32,
+        (size_of::<Point>() + 2 * size_of::<u32>()) as i32,
+    );
+    gl.enable_vertex_attrib_array(3);
+    gl.vertex_attrib_pointer_f32(
+        4,
+        2,
+        glow::FLOAT,
+        false,
+        size_of::<PersonVertex>() as i32,
+        (size_of::<Point>() + 3 * size_of::<u32>()) as i32,
+    );
+    gl.enable_vertex_attrib_array(4);
+
+    // node values live in their own buffer, so they can be changed without touching
+    // the vertices; the attribute is only enabled when drawing nodes on a gradient
+    let values_buffer = gl
+        .create_buffer()
+        .map_err(|e| anyhow!("Cannot create buffer: {e}"))?;
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(values_buffer));
+    gl.buffer_data_size(
+        glow::ARRAY_BUFFER,
+        (nodes_count * size_of::<f32>()).try_into().unwrap(),
+        glow::STATIC_DRAW,
+    );
+    gl.vertex_attrib_pointer_f32(2, 1, glow::FLOAT, false, 0, 0);
+
+    Ok((vertices_array, vertices_buffer, values_buffer))
+}
+
+/// Error returned by `getError` once the WebGL context has been lost.
+#[cfg(target_arch = "wasm32")]
+const CONTEXT_LOST_WEBGL: u32 = 0x9242;
+
+/// Recreates the programs and buffers after the context has been lost, and uploads the vertices
+/// and node values again. While the context is still lost, it's tried again on the next frame.
+pub fn restore_task(
+    vertices: Vec<PersonVertex>,
+    verts_per_edge: usize,
+    buckets: EdgeBuckets,
+    values: Option<(Vec<f32>, (f32, f32))>,
+) -> GlTask {
+    Box::new(move |graph, gl| {
+        let created = unsafe {
+            create_programs(gl, graph.num_classes).and_then(|programs| {
+                Ok((
+                    programs,
+                    create_buffers(gl, vertices.len(), graph.nodes_count)?,
+                ))
+            })
+        };
+        match created {
+            Ok((
+                ([program_basic, program_edge, program_node], antialiased),
+                (array, buffer, values_buffer),
+            )) => {
+                graph.program_basic = program_basic;
+                graph.program_edge = program_edge;
+                graph.program_node = program_node;
+                graph.edges_antialiased = antialiased;
+                graph.nodes_array = array;
+                graph.nodes_buffer = buffer;
+                graph.values_buffer = values_buffer;
+                graph.verts_per_edge = verts_per_edge;
+                // the new buffer is empty until the edges are streamed in again
+                graph.edges_count = 0;
+                graph.set_vertices(gl, Arc::new(vertices), verts_per_edge, buckets);
+                graph.edges_bundled = false;
+                graph.set_color_mode(gl, graph.color_mode.clone(), values);
+                graph.context_lost = false;
+                graph.restoring = false;
+                log::info!("Graphics resources restored");
+            }
+            Err(e) => {
+                log::warn!("Could not restore graphics resources yet: {e}");
+                graph
+                    .tasks
+                    .push_back(restore_task(vertices, verts_per_edge, buckets, values));
+            }
+        }
+    })
+}
+
+/// Number of edge vertices uploaded each frame while the edges are streamed in, so that no
+/// single frame stalls on a large upload.
+const EDGE_BATCH_SIZE: usize = 500_000;
+
+unsafe fn upload_vertices(
+    gl: &glow::Context,
+    buffer: glow::Buffer,
+    vertices: &[PersonVertex],
+    start: usize,
+) {
+    use glow::HasContext as _;
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_sub_data_u8_slice(
+        glow::ARRAY_BUFFER,
+        (start * size_of::<PersonVertex>()).try_into().unwrap(),
+        std::slice::from_raw_parts(vertices.as_ptr() as *const u8, size_of_val(vertices)),
+    );
+    let err = gl.get_error();
+    if err != glow::NO_ERROR {
+        log::error!("Error: {:x}", err);
+    }
+}
+
+/// Uploads a batch of edge vertices starting at `start`, then queues the next batch for the next
+/// frame. Only the edges uploaded so far are drawn, along with the older ones still in the buffer
+/// when it's updated in place. The buckets of the edges are used once they're all uploaded.
+fn upload_edges(
+    vertices: Arc<Vec<PersonVertex>>,
+    start: usize,
+    generation: usize,
+    buckets: EdgeBuckets,
+) -> GlTask {
+    Box::new(move |graph, gl| {
+        if graph.generation != generation {
+            // the vertices have been replaced in the meantime
+            return;
+        }
+        let end = (start + EDGE_BATCH_SIZE).min(vertices.len());
+        unsafe {
+            upload_vertices(gl, graph.nodes_buffer, &vertices[start..end], start);
+        }
+        graph.edges_count = graph
+            .edges_count
+            .max((end - graph.nodes_count) / graph.verts_per_edge);
+        if end < vertices.len() {
+            graph
+                .tasks
+                .push_back(upload_edges(vertices, end, generation, buckets));
+        } else {
+            graph.edge_buckets = buckets;
+        }
+    })
 }
 
 pub struct RenderedGraph {
     pub program_node: glow::Program,
     pub program_basic: glow::Program,
     pub program_edge: glow::Program,
+    /// The edge program smooths the borders of the edges, see [`create_programs`].
+    pub edges_antialiased: bool,
     pub nodes_buffer: glow::Buffer,
     pub nodes_count: usize,
     pub nodes_array: glow::VertexArray,
+    /// Normalized value of each node, used by the gradient color modes.
+    pub values_buffer: glow::Buffer,
+    pub color_mode: ColorMode,
+    /// Range of the values shown on the gradient, if any.
+    pub value_range: Option<(f32, f32)>,
     pub edges_count: usize,
+    /// Spatial buckets of the edges, see [`buckets`]. Empty while the edges are streamed in, in
+    /// which case they're all drawn.
+    pub edge_buckets: EdgeBuckets,
+    /// Number of vertices used by each edge, which depends on whether edges are bundled or
+    /// directed.
+    pub verts_per_edge: usize,
+    pub edges_bundled: bool,
+    /// Curvature the edges were last drawn with, zero for straight edges.
+    pub edge_curvature: f32,
+    /// Whether the edges are sorted before being drawn, see [`lod::edge_draw_order`]. Kept for
+    /// when the vertices are built again.
+    pub sort_edges: bool,
+    /// Incremented each time the vertices are replaced, i.e. when the node positions change.
+    pub generation: usize,
     pub node_filter: NodeFilter,
+    /// Largest node size stored in the file.
+    pub max_file_size: f32,
+    pub max_degree: usize,
     pub destroyed: bool,
+    /// Most classes the shaders can draw, see [`max_classes`].
+    pub num_classes: usize,
+    /// The GL context has been lost, along with the programs and buffers, which need to be
+    /// recreated once it comes back.
+    pub context_lost: bool,
+    /// A [`restore_task`] has been queued.
+    pub restoring: bool,
+    /// Progress of the fade-in of the nodes marked with [`packing::APPEARING`], from 0 to 1.
+    pub appear: f32,
     pub tasks: VecDeque<GlTask>,
 }
 
 impl RenderedGraph {
+    /// Uploads the nodes of the graph, and queues the upload of its edges. Unless `sort_edges` is
+    /// false, the edges are sorted first, see [`lod::edge_draw_order`].
     pub fn new<'a>(
         gl: GlForwarder,
         viewer: &ViewerData,
         edges: impl ExactSizeIterator<Item = &'a EdgeStore>,
+        sort_edges: bool,
         status_tx: StatusWriter,
     ) -> Cancelable<Self> {
-        use eframe::glow::HasContext;
-        use glow::HasContext as _;
-        use graph_format::Point;
-        use itertools::Itertools;
+        use itertools::{Either, Itertools};
         use std::collections::VecDeque;
-        let shader_version = if cfg!(target_arch = "wasm32") {
-            "#version 300 es"
-        } else {
-            "#version 330"
-        };
-
         unsafe {
-            let programs = [
-                [
-                    (glow::VERTEX_SHADER, include_str!("shaders/basic.vert")),
-                    (glow::FRAGMENT_SHADER, include_str!("shaders/basic.frag")),
-                ],
-                [
-                    (glow::VERTEX_SHADER, include_str!("shaders/graph.vert")),
-                    (
-                        glow::FRAGMENT_SHADER,
-                        include_str!("shaders/graph_edge.frag"),
-                    ),
-                ],
-                [
-                    (glow::VERTEX_SHADER, include_str!("shaders/graph.vert")),
-                    (
-                        glow::FRAGMENT_SHADER,
-                        include_str!("shaders/graph_node.frag"),
-                    ),
-                ],
-            ];
-
             log!(status_tx, t!("Compiling shaders"));
-            let num_classes = viewer.modularity_classes.len();
-            let [program_basic, program_edge, program_node] = gl.run(move |gl| {
-                programs.map(|shader_sources| {
-                    let program = gl.create_program().expect("Cannot create program");
-
-                    let shaders: Vec<_> = shader_sources
-                        .iter()
-                        .map(|(shader_type, shader_source)| {
-                            let shader = gl
-                                .create_shader(*shader_type)
-                                .expect("Cannot create shader");
-                            gl.shader_source(
-                                shader,
-                                &format!(
-                                    "{shader_version}\n#define NUM_CLASSES {0}\n{shader_source}",
-                                    num_classes,
-                                ),
-                            );
-                            gl.compile_shader(shader);
-                            assert!(
-                                gl.get_shader_compile_status(shader),
-                                "Failed to compile {shader_type}: {}",
-                                gl.get_shader_info_log(shader)
-                            );
-                            gl.attach_shader(program, shader);
-                            shader
-                        })
-                        .collect();
-
-                    gl.link_program(program);
-                    assert!(
-                        gl.get_program_link_status(program),
-                        "{}",
-                        gl.get_program_info_log(program)
-                    );
-
-                    for shader in shaders {
-                        gl.detach_shader(program, shader);
-                        gl.delete_shader(shader);
-                    }
-
-                    program
-                })
-            })?;
+            let num_classes = gl.run(|gl| max_classes(gl))?;
+            if viewer.modularity_classes.len() > num_classes {
+                log::warn!(
+                    "The graph has {} classes, but only {num_classes} can be drawn, the others \
+                    share the color of the last one",
+                    viewer.modularity_classes.len()
+                );
+            }
+            let ([program_basic, program_edge, program_node], edges_antialiased) =
+                gl.run(move |gl| create_programs(gl, num_classes))??;
 
             #[cfg(target_arch = "wasm32")]
             let edges = edges.take(10_000_000);
 
             let edges_count = edges.len();
             log!(status_tx, t!("Creating vertice list"));
+            let directed = viewer.directed;
+            let verts_per_edge = if directed {
+                geom_draw::VERTS_PER_DIRECTED_EDGE
+            } else {
+                geom_draw::VERTS_PER_EDGE
+            };
             const VERTS_PER_NODE: usize = 1;
             let node_vertices = viewer
                 .persons
                 .iter()
                 .map(|p| geom_draw::create_node_vertex(p));
 
-            let edge_vertices = edges
-                .map(|e| {
-                    let pa = &viewer.persons[e.a as usize];
-                    let pb = &viewer.persons[e.b as usize];
-                    let dist = (pa.position - pb.position).norm_squared();
-                    (pa, pb, dist)
-                })
-                //.sorted_unstable_by_key(|(_, _, dist)| std::cmp::Reverse(*dist))
-                .sorted_unstable_by(|(_, _, dist1), (_, _, dist2)| {
-                    dist2.partial_cmp(dist1).unwrap()
-                })
-                .flat_map(|(pa, pb, _)| geom_draw::create_edge_vertices(pa, pb));
+            // unsorted, the edges that don't fit would be whichever come last in the file
+            let max_edges = (MAX_VERTEX_BUFFER_SIZE / size_of::<PersonVertex>())
+                .saturating_sub(viewer.persons.len())
+                / verts_per_edge;
+            let edges = if !sort_edges && edges_count > max_edges {
+                let mut kept = edges.collect_vec();
+                lod::keep_most_important(&viewer.persons, &mut kept, max_edges);
+                Either::Left(kept.into_iter())
+            } else {
+                Either::Right(edges)
+            };
+
+            let edge_vertices =
+                lod::edge_draw_order(&viewer.persons, edges, sort_edges).flat_map(|(pa, pb)| {
+                    let arrow = directed.then(|| geom_draw::create_arrow_vertices(pa, pb));
+                    geom_draw::create_edge_vertices(pa, pb)
+                        .into_iter()
+                        .chain(arrow.into_iter().flatten())
+                });
 
             let nodes_count = viewer.persons.len();
+            let max_file_size = viewer.persons.iter().map(|p| p.size).fold(0.0, f32::max);
+            let max_degree = viewer
+                .persons
+                .iter()
+                .map(|p| p.neighbors.len())
+                .max()
+                .unwrap_or(0);
             //let nodes_count = 0;
             //let node_vertices = node_vertices.take(nodes_count);
             let vertices = node_vertices.chain(edge_vertices);
 
             let vertices = {
-                const THRESHOLD: usize = 256 * 1024 * 1024;
+                const THRESHOLD: usize = MAX_VERTEX_BUFFER_SIZE;
                 const MAX_VERTS_IN_THRESHOLD: usize = THRESHOLD / size_of::<PersonVertex>();
                 let num_vertices =
-                    nodes_count * VERTS_PER_NODE + edges_count * geom_draw::VERTS_PER_EDGE;
+                    nodes_count * VERTS_PER_NODE + edges_count * verts_per_edge;
                 if num_vertices > MAX_VERTS_IN_THRESHOLD {
                     log!(
                         status_tx,
@@ -223,91 +665,53 @@ impl RenderedGraph {
                 }
             };
 
+            log!(status_tx, t!("Sorting edges by area"));
+            let (vertices, edge_buckets) =
+                buckets::bucket_edges(vertices, nodes_count, verts_per_edge);
+
             let vertices_count = vertices.len();
 
-            let edges_count =
-                (vertices_count - (nodes_count * VERTS_PER_NODE)) / geom_draw::VERTS_PER_EDGE;
+            let total_edges =
+                (vertices_count - (nodes_count * VERTS_PER_NODE)) / verts_per_edge;
 
             log!(
                 status_tx,
                 t!(
                     "New node count: %{num}, edge count: %{edges}",
                     num = nodes_count,
-                    edges = edges_count
+                    edges = total_edges
                 )
             );
 
             log!(status_tx, t!("Allocating vertex buffer"));
-            let (vertices_array, vertices_buffer) = gl.run(move |gl: &glow::Context| {
-                let vertices_array = gl
-                    .create_vertex_array()
-                    .expect("Cannot create vertex array");
-                gl.bind_vertex_array(Some(vertices_array));
-                let vertices_buffer = gl.create_buffer().expect("Cannot create buffer");
-                gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertices_buffer));
-                gl.buffer_data_size(
-                    glow::ARRAY_BUFFER,
-                    (vertices_count * size_of::<PersonVertex>())
-                        .try_into()
-                        .unwrap(),
-                    glow::STATIC_DRAW,
-                );
-                let err = gl.get_error();
-                if err != glow::NO_ERROR {
-                    log::error!("Error: {:x}", err);
-                }
-                gl.vertex_attrib_pointer_f32(
-                    0,
-                    2,
-                    glow::FLOAT,
-                    false,
-                    size_of::<PersonVertex>() as i32,
-                    0,
-                );
-                gl.enable_vertex_attrib_array(0);
-                gl.vertex_attrib_pointer_i32(
-                    1,
-                    1,
-                    glow::UNSIGNED_INT,
-                    size_of::<PersonVertex>() as i32,
-                    size_of::<Point>() as i32,
-                );
-                gl.enable_vertex_attrib_array(1);
-
-                (vertices_array, vertices_buffer)
-            })?;
+            let (vertices_array, vertices_buffer, values_buffer) =
+                gl.run(move |gl| create_buffers(gl, vertices_count, nodes_count))??;
 
             log!(
                 status_tx,
-                t!("Buffering %{num} vertices", num = vertices.len())
+                t!("Buffering %{num} vertices", num = nodes_count)
             );
 
-            let vertices = std::sync::Arc::new(vertices);
+            let vertices = Arc::new(vertices);
 
             const BATCH_SIZE: usize = 1000000;
 
-            for_progress!(status_tx, i in 0..vertices.len().div_ceil(BATCH_SIZE), {
+            // only the nodes are uploaded before the tab opens, the edges are streamed in
+            // afterwards so that the graph can be shown right away
+            for_progress!(status_tx, i in 0..nodes_count.div_ceil(BATCH_SIZE), {
                 let vertices = vertices.clone();
                 gl.run(move |gl: &glow::Context| {
                     let start = i * BATCH_SIZE;
-                    let end = ((i + 1) * BATCH_SIZE).min(vertices.len());
-                    let batch = &vertices[i * BATCH_SIZE..end];
-                    gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertices_buffer));
-                    gl.buffer_sub_data_u8_slice(
-                        glow::ARRAY_BUFFER,
-                        (start * size_of::<PersonVertex>()).try_into().unwrap(),
-                        std::slice::from_raw_parts(
-                            batch.as_ptr() as *const u8,
-                            size_of_val(batch),
-                        ),
-                    );
-                    let err = gl.get_error();
-                    if err != glow::NO_ERROR {
-                        log::error!("Error: {:x}", err);
-                    }
+                    let end = ((i + 1) * BATCH_SIZE).min(nodes_count);
+                    upload_vertices(gl, vertices_buffer, &vertices[start..end], start);
                 })?;
             });
 
+            let mut tasks = VecDeque::new();
+            if total_edges > 0 {
+                tasks.push_back(upload_edges(vertices, nodes_count, 0, edge_buckets));
+            }
+
             log!(
                 status_tx,
                 t!(
@@ -320,17 +724,121 @@ impl RenderedGraph {
                 program_basic,
                 program_edge,
                 program_node,
+                edges_antialiased,
                 nodes_buffer: vertices_buffer,
                 nodes_count,
                 nodes_array: vertices_array,
-                edges_count,
+                values_buffer,
+                color_mode: ColorMode::Class,
+                value_range: None,
+                edges_count: 0,
+                edge_buckets: EdgeBuckets::default(),
+                verts_per_edge,
+                edges_bundled: false,
+                edge_curvature: 0.0,
+                sort_edges,
+                generation: 0,
                 node_filter: NodeFilter::default(),
+                max_file_size,
+                max_degree,
                 destroyed: false,
-                tasks: VecDeque::new(),
+                num_classes,
+                context_lost: false,
+                restoring: false,
+                appear: 1.0,
+                tasks,
             })
         }
     }
 
+    /// Uploads new vertices, nodes first then edges sorted into `buckets`, see
+    /// [`buckets::bucket_edges`]. The nodes are uploaded right away, and the edges are streamed in
+    /// over the next frames by [`upload_edges`]. The buffer is only reallocated when the edge
+    /// geometry changes, otherwise it's updated in place.
+    pub(crate) fn set_vertices(
+        &mut self,
+        gl: &glow::Context,
+        vertices: Arc<Vec<PersonVertex>>,
+        verts_per_edge: usize,
+        buckets: EdgeBuckets,
+    ) {
+        use glow::HasContext as _;
+        unsafe {
+            if verts_per_edge != self.verts_per_edge {
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.nodes_buffer));
+                gl.buffer_data_size(
+                    glow::ARRAY_BUFFER,
+                    size_of_val(&vertices[..]).try_into().unwrap(),
+                    glow::STATIC_DRAW,
+                );
+                self.verts_per_edge = verts_per_edge;
+                self.edges_count = 0;
+            }
+            upload_vertices(gl, self.nodes_buffer, &vertices[..self.nodes_count], 0);
+        }
+        // also stops the edges from the previous vertices, if they were still being streamed in
+        self.generation += 1;
+        self.edge_buckets = EdgeBuckets::default();
+        if vertices.len() > self.nodes_count {
+            self.tasks.push_back(upload_edges(
+                vertices,
+                self.nodes_count,
+                self.generation,
+                buckets,
+            ));
+        }
+    }
+
+    /// Switches the node color mode, uploading the values computed by [`ColorMode::node_values`].
+    pub(crate) fn set_color_mode(
+        &mut self,
+        gl: &glow::Context,
+        mode: ColorMode,
+        values: Option<(Vec<f32>, (f32, f32))>,
+    ) {
+        use glow::HasContext as _;
+        self.value_range = None;
+        if let Some((values, range)) = values {
+            unsafe {
+                gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.values_buffer));
+                gl.buffer_sub_data_u8_slice(
+                    glow::ARRAY_BUFFER,
+                    0,
+                    std::slice::from_raw_parts(
+                        values.as_ptr() as *const u8,
+                        size_of_val(&values[..values.len().min(self.nodes_count)]),
+                    ),
+                );
+            }
+            self.value_range = Some(range);
+        }
+        self.color_mode = mode;
+    }
+
+    /// Number of vertices in the vertex buffer, for the nodes and the edges.
+    pub fn vertex_count(&self) -> usize {
+        self.nodes_count + self.edges_count * self.verts_per_edge
+    }
+
+    /// Estimated GPU memory used by the vertex buffer and the node values, in bytes.
+    pub fn buffer_size(&self) -> usize {
+        self.vertex_count() * size_of::<PersonVertex>() + self.nodes_count * size_of::<f32>()
+    }
+
+    /// Node size parameters for the given mode and scale.
+    pub fn node_sizes(&self, mode: NodeSizeMode, scale: f32) -> NodeSizes {
+        NodeSizes {
+            mode,
+            scale,
+            max_value: match mode {
+                NodeSizeMode::Uniform => 0.0,
+                NodeSizeMode::File => self.max_file_size,
+                NodeSizeMode::Degree => (self.max_degree as f32).ln_1p(),
+            },
+            max_pixels: MAX_NODE_PIXELS * scale,
+        }
+    }
+
     pub(crate) fn destroy(&mut self, gl: &glow::Context) {
         log::info!("Destroying graph");
         self.destroyed = true;
@@ -343,27 +851,99 @@ impl RenderedGraph {
             gl.delete_program(self.program_node);
             log::info!("Deleting buffers");
             gl.delete_buffer(self.nodes_buffer);
+            gl.delete_buffer(self.values_buffer);
             log::info!("Deleting arrays");
             gl.delete_vertex_array(self.nodes_array);
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn paint(
         &mut self,
         gl: &glow::Context,
         cam: Matrix4<f32>,
         edges: (bool, f32),
+        edge_width: f32,
+        edge_fraction: f32,
         nodes: (bool, f32),
+        node_sizes: NodeSizes,
         class_colors: &[u32],
     ) {
         if self.destroyed {
             return;
         }
 
-        while let Some(task) = self.tasks.pop_front() {
+        #[cfg(target_arch = "wasm32")]
+        if !self.context_lost && unsafe { glow::HasContext::get_error(gl) } == CONTEXT_LOST_WEBGL {
+            log::warn!("WebGL context lost");
+            self.context_lost = true;
+        }
+
+        // tasks queued by the tasks themselves wait for the next frame
+        for _ in 0..self.tasks.len() {
+            let task = self.tasks.pop_front().unwrap();
             task(self, gl);
         }
 
+        if self.context_lost {
+            return;
+        }
+
+        self.draw(
+            gl,
+            cam,
+            edges,
+            edge_width,
+            edge_fraction,
+            nodes,
+            node_sizes,
+            class_colors,
+        );
+    }
+
+    /// Ranges of edges to draw with the camera `cam`: those of the buckets that can be seen, or all
+    /// of them if the edges aren't sorted into buckets. Only the first `edge_fraction` of each
+    /// range is drawn.
+    fn visible_edges(
+        &self,
+        cam: Matrix4<f32>,
+        edge_width: f32,
+        edge_fraction: f32,
+    ) -> Vec<Range<usize>> {
+        let inverse = cam.try_inverse().filter(|_| !self.edge_buckets.is_empty());
+        let Some(inverse) = inverse else {
+            let drawn = (self.edges_count as f32 * edge_fraction.clamp(0.0, 1.0)).ceil() as usize;
+            return vec![0..drawn.min(self.edges_count)];
+        };
+        // bounds of the view in world space, grown by the widest edge offset
+        let margin = edge_width * geom_draw::MAX_OFFSET;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, y)| {
+            let p = inverse * Vector4::new(x, y, 0.0, 1.0);
+            Point::new(p.x / p.w, p.y / p.w)
+        });
+        let (min, max) = buckets::bounds(corners);
+        self.edge_buckets.visible(
+            min - Point::new(margin, margin),
+            max + Point::new(margin, margin),
+            edge_fraction,
+            self.edges_count,
+        )
+    }
+
+    /// Draws the graph into the current framebuffer. `edge_width` is the half-width of the edges,
+    /// in world units, and only the first `edge_fraction` of the visible ones are drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw(
+        &self,
+        gl: &glow::Context,
+        cam: Matrix4<f32>,
+        edges: (bool, f32),
+        edge_width: f32,
+        edge_fraction: f32,
+        nodes: (bool, f32),
+        node_sizes: NodeSizes,
+        class_colors: &[u32],
+    ) {
         use eframe::glow::HasContext;
         use glow::HasContext as _;
         unsafe {
@@ -373,6 +953,7 @@ impl RenderedGraph {
             gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.nodes_buffer));
 
             if edges.0 {
+                gl.disable_vertex_attrib_array(2);
                 gl.use_program(Some(self.program_edge));
                 gl.uniform_matrix_4_f32_slice(
                     Some(
@@ -387,8 +968,21 @@ impl RenderedGraph {
                         &gl.get_uniform_location(self.program_edge, "u_degfilter")
                             .unwrap(),
                     ),
-                    ((self.node_filter.degree_filter.1 as u32) << 16)
-                        | (self.node_filter.degree_filter.0 as u32),
+                    packing::pack_degree_filter(self.node_filter.degree_filter),
+                );
+                gl.uniform_1_u32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_kcore")
+                            .unwrap(),
+                    ),
+                    self.node_filter.kcore as u32,
+                );
+                gl.uniform_1_i32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_filter_names")
+                            .unwrap(),
+                    ),
+                    self.node_filter.filter_names as i32,
                 );
                 gl.uniform_1_f32(
                     Some(
@@ -397,19 +991,50 @@ impl RenderedGraph {
                     ),
                     edges.1,
                 );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_appear")
+                            .unwrap(),
+                    ),
+                    self.appear,
+                );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_edge_width")
+                            .unwrap(),
+                    ),
+                    edge_width,
+                );
+                gl.uniform_1_i32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_antialias")
+                            .unwrap(),
+                    ),
+                    self.edges_antialiased as i32,
+                );
 
                 gl.uniform_1_u32_slice(
                     Some(
                         &gl.get_uniform_location(self.program_edge, "u_class_colors")
                             .unwrap(),
                     ),
-                    &class_colors,
+                    &class_colors[..class_colors.len().min(self.num_classes)],
+                );
+                gl.uniform_1_i32(
+                    Some(
+                        &gl.get_uniform_location(self.program_edge, "u_gradient")
+                            .unwrap(),
+                    ),
+                    0,
                 );
-                let verts = 2 * 3 * self.edges_count as i32;
-                // if wasm, clamp verts at 30M, because Firefox refuses to draw anything above that
-                #[cfg(target_arch = "wasm32")]
-                let verts = verts.min(30_000_000);
-                gl.draw_arrays(glow::TRIANGLES, self.nodes_count as i32, verts);
+                for range in self.visible_edges(cam, edge_width, edge_fraction) {
+                    let first = self.nodes_count + self.verts_per_edge * range.start;
+                    let verts = (self.verts_per_edge * range.len()) as i32;
+                    // if wasm, clamp verts at 30M, because Firefox refuses to draw anything above that
+                    #[cfg(target_arch = "wasm32")]
+                    let verts = verts.min(30_000_000);
+                    gl.draw_arrays(glow::TRIANGLES, first as i32, verts);
+                }
             }
             if nodes.0 {
                 gl.use_program(Some(self.program_node));
@@ -427,12 +1052,25 @@ impl RenderedGraph {
                             .unwrap(),
                     ),
                     if self.node_filter.filter_nodes {
-                        ((self.node_filter.degree_filter.1 as u32) << 16)
-                            | (self.node_filter.degree_filter.0 as u32)
+                        packing::pack_degree_filter(self.node_filter.degree_filter)
                     } else {
                         0xffff_0000
                     },
                 );
+                gl.uniform_1_u32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_kcore")
+                            .unwrap(),
+                    ),
+                    self.node_filter.kcore as u32,
+                );
+                gl.uniform_1_i32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_filter_names")
+                            .unwrap(),
+                    ),
+                    self.node_filter.filter_names as i32,
+                );
                 gl.uniform_1_f32(
                     Some(
                         &gl.get_uniform_location(self.program_node, "opacity")
@@ -440,14 +1078,62 @@ impl RenderedGraph {
                     ),
                     nodes.1,
                 );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_appear")
+                            .unwrap(),
+                    ),
+                    self.appear,
+                );
+                gl.uniform_1_u32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_size_mode")
+                            .unwrap(),
+                    ),
+                    node_sizes.mode as u32,
+                );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_size_max")
+                            .unwrap(),
+                    ),
+                    node_sizes.max_value,
+                );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_node_scale")
+                            .unwrap(),
+                    ),
+                    node_sizes.scale,
+                );
+                gl.uniform_1_f32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_max_point_size")
+                            .unwrap(),
+                    ),
+                    node_sizes.max_pixels,
+                );
 
                 gl.uniform_1_u32_slice(
                     Some(
                         &gl.get_uniform_location(self.program_node, "u_class_colors")
                             .unwrap(),
                     ),
-                    &class_colors,
+                    &class_colors[..class_colors.len().min(self.num_classes)],
+                );
+                let gradient = self.value_range.is_some();
+                gl.uniform_1_i32(
+                    Some(
+                        &gl.get_uniform_location(self.program_node, "u_gradient")
+                            .unwrap(),
+                    ),
+                    gradient as i32,
                 );
+                if gradient {
+                    gl.enable_vertex_attrib_array(2);
+                } else {
+                    gl.disable_vertex_attrib_array(2);
+                }
                 gl.draw_arrays(glow::POINTS, 0, self.nodes_count as i32);
             }
         }
@@ -465,14 +1151,30 @@ pub struct Vertex {
 #[derive(Copy, Clone)]
 pub struct PersonVertex {
     pub position: Point,
-    pub degree_and_class: u32,
+    pub degree: u32,
+    /// Class, whether the name matches the name filter, and the side of the edge the vertex is on,
+    /// used to smooth the borders, see [`packing::pack_class`] and [`packing::pack_side`].
+    pub class: u32,
+    /// Core number and node size, see [`packing::pack_core_and_size`].
+    pub core_and_size: u32,
+    /// Added to the position once scaled by the edge width, zero for nodes.
+    pub offset: Point,
 }
 
 impl PersonVertex {
-    pub fn new(position: Point, degree: u16, class: u16) -> PersonVertex {
+    /// Creates a vertex at the given position, with the attributes of the given node.
+    pub fn new(position: Point, node: &Person) -> PersonVertex {
+        PersonVertex::with_offset(position, Point::new(0.0, 0.0), node)
+    }
+
+    /// Creates an edge vertex, see [`PersonVertex::offset`].
+    pub fn with_offset(position: Point, offset: Point, node: &Person) -> PersonVertex {
         PersonVertex {
             position,
-            degree_and_class: ((class as u32) << 16) | (degree as u32),
+            degree: node.neighbors.len().min(u32::MAX as usize) as u32,
+            class: packing::pack_class(node.modularity_class, node.name_match),
+            core_and_size: packing::pack_core_and_size(node.core_number, node.size),
+            offset,
         }
     }
 }
@@ -0,0 +1,68 @@
+//! Packing of node attributes into the 32-bit integers read by `graph.vert`.
+
+/// Degree as seen by the degree filter, which works on 16 bits. Higher degrees are clamped, so
+/// that the largest hubs stay at the top of the range instead of wrapping around.
+pub fn filter_degree(degree: usize) -> u16 {
+    degree.min(u16::MAX as usize) as u16
+}
+
+/// Packs a degree range into the `u_degfilter` uniform, the upper bound in the high 16 bits.
+pub fn pack_degree_filter((low, high): (u16, u16)) -> u32 {
+    ((high as u32) << 16) | low as u32
+}
+
+/// Inverse of [`pack_degree_filter`].
+pub fn unpack_degree_filter(packed: u32) -> (u16, u16) {
+    (packed as u16, (packed >> 16) as u16)
+}
+
+/// Bit of the class attribute set on the nodes whose name doesn't match the name filter. Classes
+/// never get that high, see `graph_format::MAX_CLASSES`.
+pub const NAME_HIDDEN: u32 = 1 << 31;
+
+/// Packs a class along with whether the name of the node matches the name filter.
+pub fn pack_class(class: u32, name_match: bool) -> u32 {
+    if name_match {
+        class
+    } else {
+        class | NAME_HIDDEN
+    }
+}
+
+/// Inverse of [`pack_class`].
+pub fn unpack_class(packed: u32) -> (u32, bool) {
+    (
+        packed & !(NAME_HIDDEN | EDGE_SIDE | APPEARING),
+        packed & NAME_HIDDEN == 0,
+    )
+}
+
+/// Bits of the class attribute holding the side of the edge a vertex is on, right below
+/// [`NAME_HIDDEN`]. They keep the vertices small, as there are several per edge.
+pub const EDGE_SIDE: u32 = 0b11 << 29;
+
+/// Packs the side of the edge a vertex is on, -1 or 1, or 0 for nodes and arrowheads, into a class
+/// packed by [`pack_class`], as a 2-bit signed integer.
+pub fn pack_side(packed: u32, side: f32) -> u32 {
+    (packed & !EDGE_SIDE) | (((side as i32 as u32) << 29) & EDGE_SIDE)
+}
+
+/// Inverse of [`pack_side`].
+pub fn unpack_side(packed: u32) -> f32 {
+    (((packed as i32) << 1) >> 30) as f32
+}
+
+/// Bit of the class attribute set on the nodes of a new tab that fade in, right below
+/// [`EDGE_SIDE`]. The edges take it from their ends, like the class.
+pub const APPEARING: u32 = 1 << 28;
+
+/// Packs a core number in the low 16 bits, and the high 16 bits of the node size as an `f32`,
+/// which keeps its sign, exponent and 7 bits of mantissa.
+pub fn pack_core_and_size(core: u16, size: f32) -> u32 {
+    (size.to_bits() & 0xFFFF_0000) | core as u32
+}
+
+/// Inverse of [`pack_core_and_size`], up to the precision lost on the size.
+pub fn unpack_core_and_size(packed: u32) -> (u16, f32) {
+    (packed as u16, f32::from_bits(packed & 0xFFFF_0000))
+}
@@ -1,12 +1,45 @@
 precision mediump float;
 
 layout (location = 0) in vec2 position;
-layout (location = 1) in uint deg_and_class;
+layout (location = 1) in uint degree;
+// normalized value used when coloring nodes on a gradient
+layout (location = 2) in float value;
+// core number in the low 16 bits, size from the file as a truncated float in the high 16 bits
+layout (location = 3) in uint core_and_size;
+// sideways offset of edge vertices, in units of the edge half-width
+layout (location = 4) in vec2 offset;
+// modularity class, the classes past the end of u_class_colors share its last color. The high
+// bit is set on the nodes whose name doesn't match the name filter, and the next two hold the side
+// of the edge the vertex is on, -1 or 1, 0 for nodes and arrowheads. The one below them is set on
+// the nodes that fade in when a tab is opened
+layout (location = 6) in uint class_;
 out vec4 v_color;
+// distance from the middle of the edge and half-width of the edge, in points
+out float v_edge_dist;
+out float v_edge_half_width;
 uniform mat4 u_projection;
+// degree range in 16 bits each, the upper bound in the high bits
 uniform uint u_degfilter;
+// nodes outside of the k-core for this k are hidden
+uniform uint u_kcore;
+// nodes whose name doesn't match the name filter are hidden
+uniform bool u_filter_names;
 uniform float opacity;
+// progress of the fade-in of the nodes marked as appearing, from 0 to 1
+uniform float u_appear;
+// half-width of the edges in world units, so that they keep the same thickness on screen
+uniform float u_edge_width;
+// edges are widened by a point to fade out their borders
+uniform bool u_antialias;
 uniform uint u_class_colors[NUM_CLASSES];
+uniform bool u_gradient;
+// 0: uniform size, 1: size from the file, 2: size by degree
+uniform uint u_size_mode;
+// value mapped to the largest size
+uniform float u_size_max;
+uniform float u_node_scale;
+// nodes stop growing past this diameter when zooming in, in pixels
+uniform float u_max_point_size;
 const float neg_infinity = uintBitsToFloat(0xFF800000u);
 const float nan = intBitsToFloat(int(0xFFC00000u));
 vec3 unpack_color(uint color) {
@@ -16,26 +49,61 @@ vec3 unpack_color(uint color) {
     float(color & 0xFFu) / 255.0
     );
 }
+// polynomial fit of viridis, kept in sync with colormap.rs
+vec3 viridis(float t) {
+    const vec3 c0 = vec3(0.27772733, 0.0054073445, 0.3340998);
+    const vec3 c1 = vec3(0.10509304, 1.4046135, 1.3845901);
+    const vec3 c2 = vec3(-0.33086183, 0.21484756, 0.09509516);
+    const vec3 c3 = vec3(-4.6342305, -5.799101, -19.332441);
+    const vec3 c4 = vec3(6.22827, 14.179933, 56.69055);
+    const vec3 c5 = vec3(4.776385, -13.745145, -65.35303);
+    const vec3 c6 = vec3(-5.435456, 4.6458526, 26.312435);
+    t = clamp(t, 0.0, 1.0);
+    return clamp(c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6))))), 0.0, 1.0);
+}
+// kept in sync with NodeSizes::pixels
+float node_size(uint deg, float file_size, float zoom) {
+    const float min_size = 12.0;
+    const float max_size = 100.0;
+    float value = 0.0;
+    if (u_size_mode == 1u) {
+        value = file_size;
+    } else if (u_size_mode == 2u) {
+        value = log(1.0 + float(deg));
+    }
+    float t = u_size_max > 0.0 ? clamp(value / u_size_max, 0.0, 1.0) : 0.0;
+    return min((min_size + (max_size - min_size) * t) * u_node_scale * zoom, u_max_point_size);
+}
 void main()
 {
-    uint deg = deg_and_class & 0xFFFFu;
-    uint class_ = deg_and_class >> 16;
+    // the filter works on 16 bits, higher degrees count as the largest one
+    uint deg = min(degree, 0xFFFFu);
     uint low = u_degfilter & 0xFFFFu;
     uint high = u_degfilter >> 16;
-    if (deg < low || deg > high) {
+    uint core_number = core_and_size & 0xFFFFu;
+    // kept in sync with packing::NAME_HIDDEN, packing::EDGE_SIDE and packing::APPEARING
+    bool name_hidden = u_filter_names && (class_ & 0x80000000u) != 0u;
+    uint class_index = class_ & 0x0FFFFFFFu;
+    float appear = (class_ & 0x10000000u) != 0u ? u_appear : 1.0;
+    float side = float(int(class_ << 1) >> 30);
+    if (deg < low || deg > high || core_number < u_kcore || name_hidden) {
         // alpha=-inf so when blended all points have alpha=-inf
         // it's clamped to 0 anyway after the fragment shader
         v_color = vec4(0.0, 0.0, 0.0, neg_infinity);
         // set position to nan so the vertex gets culled out of existence and the whole primitive is scrapped
         gl_Position = vec4(nan, nan, nan, nan);
     } else {
-        gl_Position = u_projection * vec4(position, 0.0, 1.0);
-        float scale = sqrt(float(min(deg, 1000u)) / 1000.0);
+        // the projection scales z like x and y, so this is the zoom level
+        float zoom = -u_projection[2][2];
+        float feather = u_antialias && side != 0.0 ? 1.0 / zoom : 0.0;
+        gl_Position = u_projection * vec4(position + offset * (u_edge_width + feather), 0.0, 1.0);
+        // arrowheads get a half-width large enough for the fragment shader to leave them as they are
+        v_edge_half_width = side != 0.0 ? u_edge_width * zoom : 1.0;
+        v_edge_dist = side * (u_edge_width + feather) * zoom;
+        float scale = sqrt(float(min(degree, 1000u)) / 1000.0);
         //v_color = vec4(color, min(1.0, opacity * (1.0 + 1.2 * scale)));
-        v_color = vec4(unpack_color(u_class_colors[class_]), min(1.0, opacity * (1.0 + 1.2 * scale)));
-        const float min_size = 12.0;
-        const float max_size = 100.0;
-        float size = (max_size - min_size) * scale + min_size;
-        gl_PointSize = size * -u_projection[2][2];
+        vec3 color = u_gradient ? viridis(value) : unpack_color(u_class_colors[min(class_index, uint(NUM_CLASSES) - 1u)]);
+        v_color = vec4(color, min(1.0, opacity * (1.0 + 1.2 * scale)) * appear);
+        gl_PointSize = node_size(degree, uintBitsToFloat(core_and_size & 0xFFFF0000u), zoom);
     }
 }
\ No newline at end of file
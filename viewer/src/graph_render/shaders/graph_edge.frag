@@ -1,9 +1,13 @@
 precision mediump float;
 
 in vec4 v_color;
+in float v_edge_dist;
+in float v_edge_half_width;
 out vec4 color;
 
 void main()
 {
-    color = v_color;
+    // fraction of the point covered by the edge, fading over the point past its border
+    float coverage = clamp(v_edge_half_width + 0.5 - abs(v_edge_dist), 0.0, 1.0);
+    color = vec4(v_color.rgb, v_color.a * coverage);
 }
\ No newline at end of file
@@ -1,15 +1,23 @@
-use crate::app::{iter_progress, ModularityClass, Person, StringTables, ViewerData};
+use crate::algorithms::{layout, louvain};
+use crate::app::{ModularityClass, Person, StringTables, ViewerData};
 
-use graph_format::{EdgeStore, GraphFile};
+use ahash::AHashMap;
+use anyhow::anyhow;
+use graph_format::{
+    check_class_count, EdgeStore, FormatError, GraphFile, GraphFileStream, NodeStore, Point,
+    GRAPH_VERSION,
+};
 use itertools::Itertools;
 use rayon::prelude::*;
+use serde::Serialize;
+use std::io::{BufRead, Read};
 
-use speedy::Readable;
+use crate::http;
+use crate::utils::{format_count, str_from_null_terminated_utf8, SliceExt};
 
-use crate::utils::{str_from_null_terminated_utf8, SliceExt};
-
-use crate::threading::{Cancelable, StatusWriter, StatusWriterInterface};
-use crate::{for_progress, log};
+use crate::threading::{Cancelable, CancelableError, StatusWriter, StatusWriterInterface};
+use crate::ui::modal::{ModalInfo, ModalKind};
+use crate::{log, log_progress};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -17,229 +25,285 @@ use wasm_bindgen::prelude::*;
 const GRAPH_NAME: &str = "graph_n4j.bin";
 //const GRAPH_NAME: &str = "graph_n4j_5.57M_400k.bin";
 
+/// A graph file being read, along with its total size for progress reporting.
+pub struct GraphSource {
+    pub reader: Box<dyn BufRead + Send>,
+    pub size: usize,
+}
+
+impl From<Vec<u8>> for GraphSource {
+    fn from(buf: Vec<u8>) -> Self {
+        GraphSource {
+            size: buf.len(),
+            reader: Box::new(std::io::Cursor::new(buf)),
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
-pub fn load_file(_status_tx: &impl StatusWriterInterface) -> Cancelable<GraphFile> {
-    GraphFile::read_from_file(format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), GRAPH_NAME))
-        .map_err(Into::into)
+impl GraphSource {
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<GraphSource> {
+        let file = std::fs::File::open(path)?;
+        Ok(GraphSource {
+            size: file.metadata()?.len() as usize,
+            reader: Box::new(std::io::BufReader::new(file)),
+        })
+    }
+
+    /// Reads the file at `url` in chunks on another thread, ahead of the decoding, like it is
+    /// downloaded in the browser.
+    pub fn download(url: String, size: usize) -> GraphSource {
+        let (tx, reader) = ChunkReader::new();
+        crate::thread::spawn(move || {
+            let res = http::download_stream(&url, |chunk| {
+                tx.blocking_send(Ok(chunk.to_vec()))
+                    .map_err(|_| anyhow!("Graph decoding stopped early"))
+            });
+            if let Err(e) = res {
+                let _ = tx.blocking_send(Err(std::io::Error::other(e)));
+            }
+        });
+        GraphSource {
+            reader: Box::new(reader),
+            size,
+        }
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-#[wasm_bindgen(
-    inline_js = "export function downloadGraph(filesize, progressHandler) {
-    const DB_NAME = 'graphCacheDB';
-    const DB_VERSION = 2;
-    const STORE_NAME = 'files';
-    const FILE_NAME = 'graph_n4j.bin.br';
-
-    // Open the IndexedDB
-    return openIndexedDB().then(db => {
-        return getFileFromDB(db, filesize)
-            .then(cachedFile => {
-                if (cachedFile) {
-                    // If file is already in the cache and matches the size, return it
-                    return cachedFile;
-                } else {
-                    // If not cached or size mismatch, download and cache the file
-                    return fetchAndCacheFile(db, filesize, progressHandler);
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(_status_tx: &impl StatusWriterInterface) -> Cancelable<GraphSource> {
+    let path = format!("{}/../{}", env!("CARGO_MANIFEST_DIR"), GRAPH_NAME);
+    let size = std::fs::metadata(&path).map_err(|e| CancelableError::Other(e.into()))?;
+    Ok(GraphSource::download(path, size.len() as usize))
+}
+
+/// Sends the chunks of a file to a [`ChunkReader`]. Errors end the file.
+pub type ChunkSender = tokio::sync::mpsc::Sender<std::io::Result<Vec<u8>>>;
+
+/// Number of chunks read ahead of the decoding.
+const QUEUED_CHUNKS: usize = 4;
+
+/// Reads chunks of a file as they are downloaded.
+///
+/// In the browser, the downloaded data arrives on the main thread, while decoding happens in a
+/// worker. Copying it whole into the wasm heap would mean holding both the raw bytes and the
+/// decoded graph at the same time, and the wasm heap never shrinks back, so it's streamed through
+/// a bounded channel instead and each chunk is freed once consumed.
+pub struct ChunkReader {
+    rx: tokio::sync::mpsc::Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChunkReader {
+    pub fn new() -> (ChunkSender, ChunkReader) {
+        let (tx, rx) = tokio::sync::mpsc::channel(QUEUED_CHUNKS);
+        (
+            tx,
+            ChunkReader {
+                rx,
+                chunk: Vec::new(),
+                pos: 0,
+            },
+        )
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let avail = self.fill_buf()?;
+        let n = avail.len().min(buf.len());
+        buf[..n].copy_from_slice(&avail[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for ChunkReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        while self.pos == self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
                 }
-            })
-            .catch(() => {
-                // If any error occurs while checking the cache, fall back to download
-                return fetchAndCacheFile(null, filesize, progressHandler);
-            });
-    }).catch(() => {
-        // If any error occurs when opening the IndexedDB, fall back to download
-        return fetchAndCacheFile(null, filesize, progressHandler);
+                // the sender is dropped once the whole file has been sent
+                None => return Ok(&[]),
+            }
+        }
+        Ok(&self.chunk[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(inline_js = "
+const DB_NAME = 'graphCacheDB';
+const DB_VERSION = 2;
+const STORE_NAME = 'files';
+const FILE_NAME = 'graph_n4j.bin.br';
+// the file is stored in parts, because Firefox limits the size of a single entry
+const CHUNK_SIZE = 200 * 1024 * 1024; // 200MB
+
+// Open IndexedDB and create object store if needed
+function openIndexedDB() {
+    return new Promise((resolve, reject) => {
+        const request = indexedDB.open(DB_NAME, DB_VERSION);
+
+        request.onupgradeneeded = event => {
+            const db = event.target.result;
+            if (!db.objectStoreNames.contains(STORE_NAME)) {
+                db.createObjectStore(STORE_NAME, { keyPath: 'id' });
+            }
+        };
+
+        request.onsuccess = event => {
+            resolve(event.target.result);
+        };
+
+        request.onerror = event => {
+            reject('Error opening IndexedDB: ' + event.target.errorCode);
+        };
     });
+}
 
-    // Open IndexedDB and create object store if needed
-    function openIndexedDB() {
-        return new Promise((resolve, reject) => {
-            const request = indexedDB.open(DB_NAME, DB_VERSION);
+function request(req) {
+    return new Promise((resolve, reject) => {
+        req.onsuccess = event => resolve(event.target.result);
+        req.onerror = event => reject(event.target.error);
+    });
+}
 
-            request.onupgradeneeded = event => {
-                const db = event.target.result;
-                if (!db.objectStoreNames.contains(STORE_NAME)) {
-                    db.createObjectStore(STORE_NAME, { keyPath: 'id' });
-                }
-            };
+function objectStore(db, mode) {
+    return db.transaction([STORE_NAME], mode).objectStore(STORE_NAME);
+}
 
-            request.onsuccess = event => {
-                resolve(event.target.result);
-            };
+function partCount(filesize) {
+    return Math.ceil(filesize / CHUNK_SIZE);
+}
 
-            request.onerror = event => {
-                reject('Error opening IndexedDB: ' + event.target.errorCode);
-            };
-        });
+function partName(i) {
+    return `${FILE_NAME}_part${i}`;
+}
+
+// Returns whether all the parts of the file are stored. A part is only stored once it has been
+// downloaded entirely, and the metadata once the file has.
+async function isStored(db, filesize) {
+    const total = partCount(filesize);
+    const meta = await request(objectStore(db, 'readonly').get(FILE_NAME));
+    if (!meta || meta.size !== filesize || meta.parts !== total) {
+        return false;
+    }
+    const keys = new Set(await request(objectStore(db, 'readonly').getAllKeys()));
+    for (let i = 0; i < total; i++) {
+        if (!keys.has(partName(i))) {
+            return false;
+        }
     }
+    return true;
+}
 
-    // Get file from IndexedDB
-    function getFileFromDB(db, filesize) {
-        return new Promise((resolve, reject) => {
-            if (!db) {
-                return reject('No IndexedDB available');
-            }
-    
-            const transaction = db.transaction([STORE_NAME], 'readonly');
-            const store = transaction.objectStore(STORE_NAME);
-            const metaRequest = store.get(FILE_NAME);
-    
-            metaRequest.onsuccess = event => {
-                const meta = event.target.result;
-                if (meta && meta.size === filesize && meta.parts) {
-                    const parts = new Array(meta.parts).fill(null).map((_, i) => {
-                        return new Promise((resolve, reject) => {
-                            const partRequest = store.get(`${FILE_NAME}_part${i}`);
-                            partRequest.onsuccess = event => {
-                                const data = event.target.result.data;
-                                if (!data || data == {}) {
-                                    console.warn(`Part ${i} not found in IndexedDB`);
-                                    resolve(null);
-                                } else {
-                                    resolve(event.target.result.data);
-                                }
-                            };
-                            partRequest.onerror = event => {
-                                reject(`Error retrieving part ${i} from IndexedDB: ${event.target.errorCode}`);
-                            };
-                        });
-                    });
-    
-                    Promise.all(parts).then(chunks => {
-                        const fileData = new Uint8Array(filesize);
-                        let offset = 0;
-                        for (const chunk of chunks) {
-                            if (!chunk) {
-                                console.log('Part not found');
-                                resolve(null);
-                                return;
-                            }
-                            fileData.set(new Uint8Array(chunk), offset);
-                            offset += chunk.byteLength;
-                        }
-                        resolve(fileData.buffer);
-                    }).catch(reject);
-                } else {
-                    console.log('File not found or size mismatch');
-                    resolve(null); // Return null if file not found or size mismatch
-                }
-            };
-    
-            metaRequest.onerror = event => {
-                reject(`Error retrieving metadata from IndexedDB: ${event.target.errorCode}`);
-            };
-        });
+// Size of the slices the stored parts are handed to Rust in, so that they aren't copied whole into
+// the wasm heap.
+const STREAM_CHUNK_SIZE = 4 * 1024 * 1024;
+
+// Hands the file to chunkHandler in order, reading it from the stored parts if they're all there,
+// or else downloading it and storing the parts as they are completed. chunkHandler returns a
+// promise that is waited for before passing the next chunk.
+//
+// The file is served compressed, and the browser hands out the decoded bytes, so an interrupted
+// download can't be resumed with a range request: it starts over.
+export async function downloadGraph(filesize, chunkHandler) {
+    let db = null;
+    try {
+        db = await openIndexedDB();
+    } catch (error) {
+        // the file can still be downloaded, it just won't be kept
+        console.error(error);
+    }
+    const stored = db ? await isStored(db, filesize).catch(() => false) : false;
+    if (stored) {
+        await emitStoredParts(db, partCount(filesize), chunkHandler);
+        return;
     }
 
-    // Download file and cache it in IndexedDB
-    function fetchAndCacheFile(db, filesize, progressHandler) {
-        return fetch(FILE_NAME + '?size=' + filesize, {
-                cache: 'force-cache',
-                headers: {
-                    'Cache-Control': 'max-age=31536000',
-                    'Accept-Encoding': 'br'
-                }
-            })
-            .then(response => {
-                if (!response.ok) {
-                    throw Error(response.status + ' ' + response.statusText);
-                }
+    const response = await fetch(FILE_NAME + '?size=' + filesize, {
+        cache: 'force-cache',
+        headers: {
+            'Cache-Control': 'max-age=31536000'
+        }
+    });
+    if (!response.ok) {
+        throw Error(response.status + ' ' + response.statusText);
+    }
+    if (db) {
+        // parts left by a download that didn't finish
+        await request(objectStore(db, 'readwrite').clear());
+    }
 
-                const contentLength = response.headers.get('x-file-size');
-                if (contentLength === null) {
-                    throw Error('Response size header unavailable');
-                }
+    let part = 0;
+    let loaded = 0;
+    let partData = null;
+    let partFill = 0;
+    const reader = response.body.getReader();
+    while (true) {
+        const { done, value } = await reader.read();
+        if (done) {
+            break;
+        }
+        if (loaded + value.byteLength > filesize) {
+            throw Error('The file is larger than expected');
+        }
+        await chunkHandler(value);
+        loaded += value.byteLength;
 
-                const total = parseInt(contentLength, 10);
-                let loaded = 0;
-                let progress = 0;
-
-                return new Response(
-                    new ReadableStream({
-                        start(controller) {
-                            const reader = response.body.getReader();
-
-                            read();
-
-                            function read() {
-                                reader.read().then(({ done, value }) => {
-                                    if (done) {
-                                        controller.close();
-                                        return;
-                                    }
-                                    loaded += value.byteLength;
-                                    let newProgress = Math.round(loaded / total * 100);
-                                    if (newProgress > progress) {
-                                        progress = newProgress;
-                                        progressHandler(progress);
-                                    }
-                                    controller.enqueue(value);
-                                    read();
-                                }).catch(error => {
-                                    console.error(error);
-                                    controller.error(error);
-                                });
-                            }
-                        }
-                    })
-                );
-            })
-            .then(a => a.arrayBuffer())
-            .then(arrayBuffer => {
-                if (db) {
-                    const CHUNK_SIZE = 200 * 1024 * 1024; // 200MB, because Firefox has a limit
-                    const totalParts = Math.ceil(arrayBuffer.byteLength / CHUNK_SIZE);
-
-                    try {        
-                        for (let i = 0; i < totalParts; i++) {
-                            const transaction = db.transaction([STORE_NAME], 'readwrite');
-                            const store = transaction.objectStore(STORE_NAME);
-                            const start = i * CHUNK_SIZE;
-                            const end = Math.min(start + CHUNK_SIZE, arrayBuffer.byteLength);
-                            const chunk = arrayBuffer.slice(start, end);
-                
-                            const request = store.put({
-                                id: `${FILE_NAME}_part${i}`,
-                                data: chunk
-                            });
-                
-                            request.onsuccess = () => {
-                                console.log(`Part ${i} cached in IndexedDB`);
-                            };
-                
-                            request.onerror = event => {
-                                console.error(`Error caching part ${i} in IndexedDB: ` + event.target.errorCode);
-                            };
-                        }
-
-                        const transaction = db.transaction([STORE_NAME], 'readwrite');
-                        const store = transaction.objectStore(STORE_NAME);
-                
-                        const metaRequest = store.put({
-                            id: FILE_NAME,
-                            size: arrayBuffer.byteLength,
-                            parts: totalParts
-                        });
-                
-                        metaRequest.onsuccess = () => {
-                            console.log('Metadata cached in IndexedDB');
-                        };
-                
-                        metaRequest.onerror = event => {
-                            console.error('Error caching metadata in IndexedDB: ' + event.target.errorCode);
-                        };
-                    } catch (error) {
-                        console.error('Error caching file in IndexedDB: ' + error);
-                    }
-                }
-                return arrayBuffer;
-            });
+        // a chunk can straddle two parts
+        for (let pos = 0; db && pos < value.byteLength;) {
+            if (!partData) {
+                partData = new Uint8Array(Math.min(CHUNK_SIZE, filesize - part * CHUNK_SIZE));
+                partFill = 0;
+            }
+            const n = Math.min(partData.length - partFill, value.byteLength - pos);
+            partData.set(value.subarray(pos, pos + n), partFill);
+            partFill += n;
+            pos += n;
+            if (partFill === partData.length) {
+                const i = part;
+                request(objectStore(db, 'readwrite').put({ id: partName(i), data: partData.buffer }))
+                    .then(() => console.log(`Part ${i} cached in IndexedDB`))
+                    .catch(error => console.error(`Error caching part ${i} in IndexedDB: ${error}`));
+                part++;
+                partData = null;
+            }
+        }
     }
-}"
-)]
+    if (loaded !== filesize) {
+        throw Error(`The download stopped after ${loaded} of ${filesize} bytes`);
+    }
+    if (db) {
+        await request(objectStore(db, 'readwrite').put({
+            id: FILE_NAME,
+            size: filesize,
+            parts: partCount(filesize)
+        })).catch(error => console.error(`Error caching metadata in IndexedDB: ${error}`));
+    }
+}
+
+async function emitStoredParts(db, count, chunkHandler) {
+    for (let i = 0; i < count; i++) {
+        const record = await request(objectStore(db, 'readonly').get(partName(i)));
+        const data = new Uint8Array(record.data);
+        for (let start = 0; start < data.length; start += STREAM_CHUNK_SIZE) {
+            await chunkHandler(data.subarray(start, start + STREAM_CHUNK_SIZE));
+        }
+    }
+}
+")]
 extern "C" {
-    fn downloadGraph(filesize: u32, progress: &js_sys::Function) -> js_sys::Promise;
+    fn downloadGraph(filesize: u32, chunk_handler: &js_sys::Function) -> js_sys::Promise;
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -252,7 +316,7 @@ extern "C" {
 }
 
 #[cfg(target_arch = "wasm32")]
-pub async fn load_file(status_tx: &StatusWriter) -> Cancelable<GraphFile> {
+pub async fn load_file(status_tx: &StatusWriter) -> Cancelable<GraphSource> {
     /*let url = "https://domino.zdimension.fr/web/network5/graph_n4j.bin.br";
     let xhr = web_sys::XmlHttpRequest::new().unwrap();
     xhr.open("GET", url).unwrap();
@@ -300,33 +364,45 @@ pub async fn load_file(status_tx: &StatusWriter) -> Cancelable<GraphFile> {
     .unwrap()
     .dyn_into::<js_sys::Function>()
     .unwrap();*/
+    let filesize: u32 = include_str!("../file_size").parse().unwrap();
     log!(status_tx, "Downloading file");
-    let status_tx_ = status_tx.clone();
-    use crate::threading::StatusWriterInterface;
-    let progress_handler = Closure::wrap(Box::new(move |progress: usize| {
-        status_tx_
-            .send(crate::threading::Progress {
-                max: 100,
-                val: progress,
-            })
-            .unwrap()
-    }) as Box<dyn FnMut(usize)>);
-    js_console_log("Awaiting JS promise");
-    let result = wasm_bindgen_futures::JsFuture::from(downloadGraph(
-        include_str!("../file_size").parse().unwrap(),
-        progress_handler.as_ref().unchecked_ref(),
-    ))
-    .await
-    .unwrap();
-    js_console_log("Converting to Uint8Array");
-    let array_buffer = js_sys::Uint8Array::new(&result);
-    js_console_log("Converting to Vec");
-    let array_buffer = array_buffer.to_vec();
-    js_console_log("Decoding to GraphFile object");
-    let f = GraphFile::read_from_buffer(&array_buffer).map_err(Into::into);
-    js_console_log("File read end");
-    log!(status_tx, "File read");
-    f
+    let (tx, reader) = ChunkReader::new();
+    wasm_bindgen_futures::spawn_local(async move {
+        let sender = tx.clone();
+        let handler = http::js_chunk_handler(move |chunk| {
+            let tx = sender.clone();
+            async move {
+                tx.send(Ok(chunk))
+                    .await
+                    .map_err(|_| anyhow!("Graph decoding stopped early"))
+            }
+        });
+        let res = wasm_bindgen_futures::JsFuture::from(downloadGraph(
+            filesize,
+            handler.as_ref().unchecked_ref(),
+        ))
+        .await;
+        if let Err(e) = res {
+            let _ = tx.send(Err(std::io::Error::other(http::js_error(e)))).await;
+        }
+        js_console_log("File streaming end");
+    });
+
+    Ok(GraphSource {
+        reader: Box::new(reader),
+        size: filesize as usize,
+    })
+}
+
+/// Decompresses a graph file compressed with brotli, like the one served to the browser, as it is
+/// read. The size stays the compressed one, so it's only a lower bound.
+pub fn decompress_brotli(source: GraphSource) -> GraphSource {
+    GraphSource {
+        reader: Box::new(std::io::BufReader::new(
+            brotli_decompressor::Decompressor::new(source.reader, 1 << 16),
+        )),
+        size: source.size,
+    }
 }
 
 pub struct ProcessedData {
@@ -335,20 +411,96 @@ pub struct ProcessedData {
     pub edges: Vec<EdgeStore>,
 }
 
+/// Modal for files that can't be read as a graph file, shown instead of the decoding error.
+fn invalid_file(details: impl std::fmt::Display) -> CancelableError {
+    CancelableError::Custom(Box::new(ModalInfo {
+        title: t!("Invalid graph file").to_string(),
+        kind: ModalKind::Error,
+        body: t!(
+            "This isn't a graph file, or it is damaged (%{details}).",
+            details = details
+        )
+        .to_string()
+        .into(),
+    }))
+}
+
+/// Turns format errors into a user-facing modal for files produced by a newer version of the
+/// importer, instead of misinterpreting them, and for files that aren't graph files at all.
+fn format_error(e: FormatError) -> CancelableError {
+    match e {
+        FormatError::NewerVersion(version) if version < GRAPH_VERSION => invalid_file(t!(
+            "unknown format version %{version}",
+            version = version
+        )),
+        FormatError::NewerVersion(version) => CancelableError::Custom(Box::new(ModalInfo {
+            title: t!("Unsupported graph file").to_string(),
+            kind: ModalKind::Error,
+            body: t!(
+                "This graph file was produced by a newer version of the application (format version %{version}, this viewer supports up to version %{max}). Please reload the page to get the latest version.",
+                version = version,
+                max = GRAPH_VERSION
+            )
+            .to_string()
+            .into(),
+        })),
+        FormatError::Speedy(e) => invalid_file(e),
+        FormatError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => invalid_file(e),
+        e => CancelableError::Other(e.into()),
+    }
+}
+
+/// Number of offending nodes listed when a file has invalid edges.
+const MAX_REPORTED_NODES: usize = 5;
+
 pub fn load_binary(
     status_tx: &impl StatusWriterInterface,
-    content: GraphFile,
+    source: GraphSource,
 ) -> Cancelable<ProcessedData> {
-    log!(status_tx, t!("Binary content loaded"));
+    load_binary_pruned(status_tx, source, 0)
+}
+
+/// Reads a graph file, leaving out the nodes with fewer than `min_degree` links in the file, and
+/// the links to them. Unlike the degree filter of the viewer, this saves the memory and time they
+/// would take in the layout and the algorithms.
+pub fn load_binary_pruned(
+    status_tx: &impl StatusWriterInterface,
+    source: GraphSource,
+    min_degree: u16,
+) -> Cancelable<ProcessedData> {
+    let file_size = source.size;
+    let mut content = GraphFileStream::new(source.reader).map_err(format_error)?;
+    log!(
+        status_tx,
+        t!("File format version: %{version}", version = content.version)
+    );
     log!(
         status_tx,
         t!("Class count: %{count}", count = content.classes.len())
     );
     log!(
         status_tx,
-        t!("Node count: %{count}", count = content.nodes.len())
+        t!(
+            "Node count: %{count}",
+            count = format_count(content.node_count)
+        )
     );
+    // any node takes more than a byte, so a larger count means the file isn't a graph file, and all
+    // the nodes would be allocated before finding out. for a compressed source, the size is the
+    // compressed one, so this is only a loose bound: it holds for real graphs, whose positions and
+    // edge lists don't compress below a byte per node, but not for arbitrary compressed data
+    if content.node_count > file_size {
+        return Err(invalid_file(t!(
+            "%{count} nodes in %{size} bytes",
+            count = content.node_count,
+            size = file_size
+        )));
+    }
     //log!(status_tx, "Edge count: {}", content.edge_count);
+    let directed = content.directed;
+    if directed {
+        log!(status_tx, t!("The graph is directed"));
+    }
 
     log!(status_tx, t!("Processing modularity classes"));
 
@@ -357,33 +509,174 @@ pub fn load_binary(
         .iter()
         .copied()
         .enumerate()
-        .map(|(id, color)| ModularityClass::new(color, id as u16))
+        .map(|(id, color)| ModularityClass::new(color, id as u32))
         .collect_vec();
 
-    log!(status_tx, t!("Processing nodes"));
+    log!(
+        status_tx,
+        t!(
+            "Loading %{count} nodes…",
+            count = format_count(content.node_count)
+        )
+    );
 
     let start = chrono::Local::now();
-    let mut person_data: Vec<_> = iter_progress(content.nodes.iter(), status_tx)
-        .map(|node| {
-            Person::new(
-                node.position,
-                node.size,
-                node.class,
-                // SAFETY: the strings are null-terminated
-                unsafe {
-                    str_from_null_terminated_utf8(
-                        content.ids.as_ptr().offset(node.offset_id as isize),
-                    )
-                },
-                unsafe {
-                    str_from_null_terminated_utf8(
-                        content.names.as_ptr().offset(node.offset_name as isize),
-                    )
-                },
-                node.total_edge_count as usize,
-            )
-        })
+
+    // names are stored after the nodes, so they're filled in once the string tables are read
+    let mut person_data: Vec<_> = (0..content.node_count)
+        .into_par_iter()
+        .map(|_| Person::new(Point::new(0.0, 0.0), 0.0, 0, "", "", 0))
         .collect();
+    let mut string_offsets = Vec::with_capacity(content.node_count);
+    let mut edges = Vec::new();
+    let mut self_loops = 0;
+    // nodes with edges past the end of the node list
+    let mut out_of_range = Vec::new();
+    // nodes whose class isn't in the class list, which is indexed by class everywhere
+    let mut bad_classes = false;
+
+    let how_often = (content.node_count / 100).max(1);
+    for i in 0..content.node_count {
+        let node = content.read_node().map_err(format_error)?;
+        let person = &mut person_data[i];
+        person.position = node.position;
+        person.size = node.size;
+        person.modularity_class = node.class as u32;
+        if node.class as usize >= content.classes.len() {
+            bad_classes = true;
+        }
+        string_offsets.push((node.offset_id, node.offset_name));
+
+        edges.reserve(node.edge_count as usize);
+        for &e in node.edges.iter() {
+            if e as usize == i {
+                self_loops += 1;
+                continue;
+            }
+            if e as usize >= content.node_count {
+                if out_of_range.last() != Some(&i) {
+                    out_of_range.push(i);
+                }
+                continue;
+            }
+            edges.push(if directed {
+                // the edge goes from e to i
+                EdgeStore { a: e, b: i as u32 }
+            } else {
+                // ordered so that the same edge stored in both lists is caught as a duplicate
+                EdgeStore {
+                    a: e.min(i as u32),
+                    b: e.max(i as u32),
+                }
+            });
+        }
+
+        if i % how_often == 0 {
+            // the size of a file decompressed as it's read isn't known, unlike its node count
+            log_progress!(status_tx, i, content.node_count);
+        }
+    }
+
+    let (ids, names) = content.finish().map_err(format_error)?;
+    // the strings are read up to their null terminator, which has to be in the table
+    let in_table = |table: &[u8], offset: usize| offset < table.len() && table.last() == Some(&0);
+    if string_offsets
+        .par_iter()
+        .any(|&(id, name)| !in_table(&ids, id as usize) || !in_table(&names, name as usize))
+    {
+        return Err(invalid_file(t!("names outside of the string tables")));
+    }
+    if bad_classes {
+        return Err(invalid_file(t!("classes outside of the class list")));
+    }
+
+    if !out_of_range.is_empty() {
+        let shown = out_of_range
+            .iter()
+            .take(MAX_REPORTED_NODES)
+            .map(|&i| {
+                let offset = string_offsets[i].0 as usize;
+                ids.get(offset..)
+                    .and_then(|id| std::ffi::CStr::from_bytes_until_nul(id).ok())
+                    .map_or_else(|| format!("#{}", i), |id| id.to_string_lossy().into_owned())
+            })
+            .join(", ");
+        return Err(CancelableError::Custom(Box::new(ModalInfo {
+            title: t!("Corrupt graph file").to_string(),
+            kind: ModalKind::Error,
+            body: t!(
+                "%{count} nodes have edges to nodes that don't exist (the file has %{nodes} nodes). First ones: %{ids}",
+                count = out_of_range.len(),
+                nodes = person_data.len(),
+                ids = shown
+            )
+            .to_string()
+            .into(),
+        })));
+    }
+    if self_loops > 0 {
+        log!(
+            status_tx,
+            t!("Skipped %{count} self-loops", count = self_loops)
+        );
+    }
+    // e.g. from a layout that failed, which would leave the camera with nothing to fit
+    let misplaced = place_non_finite(&mut person_data);
+    if misplaced > 0 {
+        log!(
+            status_tx,
+            t!(
+                "%{count} nodes have invalid positions, they were placed on a spiral around the others",
+                count = misplaced
+            )
+        );
+    }
+
+    // older importers wrote duplicate relationships as is, which would count them twice in the
+    // degrees and draw them twice
+    edges.par_sort_unstable_by_key(|e| (e.a, e.b));
+    let before = edges.len();
+    edges.dedup();
+    if edges.len() < before {
+        log!(
+            status_tx,
+            t!(
+                "Removed %{count} duplicate edges",
+                count = before - edges.len()
+            )
+        );
+    }
+
+    if min_degree > 0 {
+        prune_nodes(
+            &mut person_data,
+            &mut string_offsets,
+            &mut edges,
+            min_degree,
+        );
+        log!(
+            status_tx,
+            t!(
+                "Kept %{count} nodes with a degree of at least %{min}",
+                count = person_data.len(),
+                min = min_degree
+            )
+        );
+    }
+
+    log!(status_tx, t!("Creating neighbor lists"));
+    build_neighbor_lists(&mut person_data, &edges, directed);
+
+    person_data.par_iter_mut().zip(string_offsets).for_each(
+        |(person, (offset_id, offset_name))| {
+            // SAFETY: the strings are null-terminated
+            person.id =
+                unsafe { str_from_null_terminated_utf8(ids.as_ptr().offset(offset_id as isize)) };
+            person.name = unsafe {
+                str_from_null_terminated_utf8(names.as_ptr().offset(offset_name as isize))
+            };
+        },
+    );
 
     log!(
         status_tx,
@@ -393,23 +686,128 @@ pub fn load_binary(
         )
     );
 
-    log!(status_tx, t!("Generating neighbor lists"));
+    let mut viewer = ViewerData::new(person_data, modularity_classes)?;
+    viewer.directed = directed;
+    viewer.file_size = Some(file_size);
+
+    Ok(ProcessedData {
+        strings: StringTables { ids, names },
+        viewer,
+        edges,
+    })
+}
+
+/// Number of ForceAtlas2 iterations used to lay out edge lists.
+const EDGE_LIST_LAYOUT_ITERATIONS: usize = 300;
 
+/// Reads a plain edge list, with one pair of numeric node ids per line, separated by spaces, tabs
+/// or a comma. Further columns, such as weights, are ignored. Lines starting with `#` or `%` are
+/// comments, and a first line that isn't a pair of ids is taken as a CSV header.
+///
+/// Nodes are named after their id. Since the file has neither positions nor classes, the graph is
+/// laid out with a few ForceAtlas2 iterations, and colored with a single pass of Louvain.
+pub fn load_edge_list(
+    status_tx: &impl StatusWriterInterface,
+    source: GraphSource,
+) -> Cancelable<ProcessedData> {
+    log!(status_tx, t!("Reading edges"));
+
+    let file_size = source.size;
     let start = chrono::Local::now();
 
+    let mut node_ids = AHashMap::new();
+    let mut ids = Vec::new();
     let mut edges = Vec::new();
+    let mut first = true;
+    let mut read = 0;
+    for (i, line) in source.reader.lines().enumerate() {
+        let line = line?;
+        read += line.len() + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', '%']) {
+            continue;
+        }
+        let mut fields = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|f| !f.is_empty())
+            .map(str::parse::<u64>);
+        let (a, b) = match (fields.next(), fields.next()) {
+            (Some(Ok(a)), Some(Ok(b))) => (a, b),
+            _ if first => {
+                first = false;
+                continue;
+            }
+            _ => {
+                return Err(CancelableError::Other(anyhow!(t!(
+                    "Invalid edge on line %{line}",
+                    line = i + 1
+                ))));
+            }
+        };
+        first = false;
+        if a == b {
+            continue;
+        }
+        let [a, b] = [a, b].map(|id| {
+            *node_ids.entry(id).or_insert_with(|| {
+                ids.push(id);
+                ids.len() - 1
+            })
+        });
+        edges.push(EdgeStore {
+            a: a.min(b) as u32,
+            b: a.max(b) as u32,
+        });
 
-    for_progress!(status_tx, (i, n) in content.nodes.iter().enumerate(), {
-        edges.reserve(n.edge_count as usize);
-        for e in n.edges.iter().copied() {
-            person_data[i].neighbors.push(e as usize);
-            person_data[e as usize].neighbors.push(i);
-            edges.push(EdgeStore {
-                a: i as u32,
-                b: e,
-            });
+        if i % 65536 == 0 {
+            log_progress!(status_tx, read.min(source.size), source.size);
         }
-    });
+    }
+    if edges.is_empty() {
+        return Err(CancelableError::Other(anyhow!(t!(
+            "The file doesn't contain any edge"
+        ))));
+    }
+    edges.sort_unstable_by_key(|e| (e.a, e.b));
+    edges.dedup();
+
+    log!(status_tx, t!("Node count: %{count}", count = ids.len()));
+
+    // ids are numeric, so they double as names
+    let mut table = Vec::new();
+    let offsets = ids
+        .iter()
+        .map(|id| {
+            let offset = table.len();
+            table.extend(id.to_string().bytes());
+            table.push(0);
+            offset
+        })
+        .collect_vec();
+    let strings = StringTables {
+        ids: table.clone(),
+        names: table,
+    };
+
+    let mut person_data = offsets
+        .iter()
+        .map(|&offset| {
+            // SAFETY: the strings are null-terminated
+            let id = unsafe { str_from_null_terminated_utf8(strings.ids.as_ptr().add(offset)) };
+            let name = unsafe { str_from_null_terminated_utf8(strings.names.as_ptr().add(offset)) };
+            Person::new(Point::new(0.0, 0.0), 1.0, 0, id, name, 0)
+        })
+        .collect_vec();
+
+    log!(status_tx, t!("Creating neighbor lists"));
+    build_neighbor_lists(&mut person_data, &edges, false);
+
+    log!(status_tx, t!("Computing layout"));
+    layout::layout(status_tx, &mut person_data, EDGE_LIST_LAYOUT_ITERATIONS)?;
+
+    log!(status_tx, t!("Detecting communities"));
+    let communities = louvain::graph(&person_data).next(0.0);
+    let classes = louvain::assign_classes(&communities, &mut person_data);
 
     log!(
         status_tx,
@@ -419,12 +817,236 @@ pub fn load_binary(
         )
     );
 
+    let mut viewer = ViewerData::new(person_data, classes)?;
+    viewer.file_size = Some(file_size);
+
     Ok(ProcessedData {
-        strings: StringTables {
-            ids: content.ids,
-            names: content.names,
-        },
-        viewer: ViewerData::new(person_data, modularity_classes)?,
+        strings,
+        viewer,
         edges,
     })
 }
+
+/// Moves the nodes whose position isn't finite onto a spiral around the other ones, always the
+/// same for a given file. Returns how many were moved.
+fn place_non_finite(persons: &mut [Person]) -> usize {
+    let finite = |p: &Point| p.x.is_finite() && p.y.is_finite();
+    let (min, max, valid) = persons.iter().map(|p| p.position).filter(finite).fold(
+        (
+            Point::new(f32::INFINITY, f32::INFINITY),
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            0,
+        ),
+        |(min, max, n), p| {
+            (
+                Point::new(min.x.min(p.x), min.y.min(p.y)),
+                Point::new(max.x.max(p.x), max.y.max(p.y)),
+                n + 1,
+            )
+        },
+    );
+    let (center, radius, spacing) = if valid > 0 {
+        let extent = max - min;
+        let radius = extent.x.max(extent.y) / 2.0;
+        // about the distance between neighboring nodes
+        let spacing = radius * 2.0 / (valid as f32).sqrt();
+        ((min + max) / 2.0, radius, spacing.max(1.0))
+    } else {
+        (Point::new(0.0, 0.0), 0.0, 1.0)
+    };
+
+    // sunflower spiral, which spreads the nodes evenly
+    let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    let mut moved = 0;
+    for person in persons.iter_mut().filter(|p| !finite(&p.position)) {
+        let r = radius + spacing * (moved as f32 + 1.0).sqrt();
+        let theta = moved as f32 * golden_angle;
+        person.position = center + Point::new(r * theta.cos(), r * theta.sin());
+        moved += 1;
+    }
+    moved
+}
+
+/// Removes the nodes with a degree below `min_degree`, along with their edges, and renumbers the
+/// others. The degrees are those in the whole graph, so nodes can end up below the threshold once
+/// their neighbors are removed, like when the importer applies it.
+fn prune_nodes(
+    persons: &mut Vec<Person>,
+    string_offsets: &mut Vec<(u32, u32)>,
+    edges: &mut Vec<EdgeStore>,
+    min_degree: u16,
+) {
+    let mut degrees = vec![0u32; persons.len()];
+    for e in edges.iter() {
+        degrees[e.a as usize] += 1;
+        degrees[e.b as usize] += 1;
+    }
+    // the kept nodes stay in the same order, so the edges stay sorted
+    let mut kept = 0;
+    let new_index = degrees
+        .iter()
+        .map(|&d| {
+            (d >= min_degree as u32).then(|| {
+                let i = kept;
+                kept += 1;
+                i
+            })
+        })
+        .collect_vec();
+    let mut keep = new_index.iter().map(Option::is_some);
+    persons.retain(|_| keep.next().unwrap());
+    let mut keep = new_index.iter().map(Option::is_some);
+    string_offsets.retain(|_| keep.next().unwrap());
+    edges.retain_mut(|e| {
+        let (Some(a), Some(b)) = (new_index[e.a as usize], new_index[e.b as usize]) else {
+            return false;
+        };
+        *e = EdgeStore { a, b };
+        true
+    });
+}
+
+/// Fills the neighbor lists from the edge list, which must be sorted by `(a, b)`, in parallel.
+///
+/// The edges of a node are two contiguous runs: those where it's `a`, in the edge list, and those
+/// where it's `b`, in a copy of the list sorted by `(b, a)`. Each node finds its own runs by binary
+/// search, so the lists are filled without any synchronization between threads, and are exactly
+/// the same as when adding the edges one at a time. For directed graphs, outgoing edges come
+/// first.
+pub fn build_neighbor_lists(persons: &mut [Person], edges: &[EdgeStore], directed: bool) {
+    debug_assert!(edges.is_sorted_by_key(|e| (e.a, e.b)));
+
+    /// Edges of `edges`, sorted by `end`, whose `end` is `node`.
+    fn run(edges: &[EdgeStore], node: usize, end: fn(&EdgeStore) -> u32) -> &[EdgeStore] {
+        let start = edges.partition_point(|e| (end(e) as usize) < node);
+        let len = edges[start..].partition_point(|e| end(e) as usize == node);
+        &edges[start..start + len]
+    }
+
+    let mut reversed = edges.to_vec();
+    reversed.par_sort_unstable_by_key(|e| (e.b, e.a));
+
+    persons.par_iter_mut().enumerate().for_each(|(i, person)| {
+        let outgoing = run(edges, i, |e| e.a).iter().map(|e| e.b as usize);
+        let incoming = run(&reversed, i, |e| e.b).iter().map(|e| e.a as usize);
+
+        person.neighbors = Vec::with_capacity(outgoing.len() + incoming.len());
+        if directed {
+            person.neighbors.extend(outgoing);
+            person.out_degree = person.neighbors.len();
+            person.neighbors.extend(incoming);
+        } else {
+            // undirected edges have a < b, so those where the node is b come first in the list
+            person.neighbors.extend(incoming);
+            person.neighbors.extend(outgoing);
+            person.out_degree = person.neighbors.len();
+        }
+    });
+}
+
+/// Writes the graph to a graph file, with the current positions, sizes and classes of the nodes,
+/// so that a layout computed in the viewer can be loaded again later. The string tables are
+/// written again from the ids and names of the nodes, in the same order.
+pub fn export_graph_file(data: &ViewerData) -> Result<Vec<u8>, FormatError> {
+    check_class_count(data.modularity_classes.len())?;
+
+    let mut file = GraphFile {
+        directed: data.directed,
+        classes: data.modularity_classes.iter().map(|c| c.color).collect(),
+        ..Default::default()
+    };
+    for p in data.persons.iter() {
+        file.nodes.push(NodeStore {
+            position: p.position,
+            size: p.size,
+            class: p.modularity_class as u16,
+            offset_id: file.ids.len() as u32,
+            offset_name: file.names.len() as u32,
+            total_edge_count: 0,
+            edge_count: 0,
+            edges: Vec::new(),
+        });
+        for (table, text) in [(&mut file.ids, p.id), (&mut file.names, p.name)] {
+            table.extend_from_slice(text.as_bytes());
+            table.push(0);
+        }
+    }
+
+    // each edge is written once, from the node it goes out of in directed graphs
+    let mut edges = data
+        .persons
+        .iter()
+        .enumerate()
+        .flat_map(|(i, p)| {
+            let ends = if data.directed {
+                p.out_neighbors()
+            } else {
+                &p.neighbors[..]
+            };
+            ends.iter()
+                .filter(move |&&j| data.directed || i < j)
+                .map(move |&j| (i, j))
+        })
+        .collect_vec();
+    file.set_edges(&mut edges)?;
+    file.finish_header();
+
+    file.to_bytes()
+}
+
+#[derive(Serialize)]
+struct NodeJson<'a> {
+    id: &'a str,
+    name: &'a str,
+    class: u32,
+    x: f32,
+    y: f32,
+    degree: usize,
+}
+
+#[derive(Serialize)]
+struct ClassJson {
+    id: usize,
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+#[derive(Serialize)]
+struct GraphJson<'a> {
+    nodes: Vec<NodeJson<'a>>,
+    classes: Vec<ClassJson>,
+}
+
+/// Serializes the nodes for which `include` returns true, and the class palette, to JSON. The
+/// `class` of a node is the `id` of its class in the palette.
+pub fn export_json(
+    data: &ViewerData,
+    include: impl Fn(&Person) -> bool,
+) -> serde_json::Result<Vec<u8>> {
+    let nodes = data
+        .persons
+        .iter()
+        .filter(|p| include(p))
+        .map(|p| NodeJson {
+            id: p.id,
+            name: p.name,
+            class: p.modularity_class,
+            x: p.position.x,
+            y: p.position.y,
+            degree: p.neighbors.len(),
+        })
+        .collect();
+    let classes = data
+        .modularity_classes
+        .iter()
+        .enumerate()
+        .map(|(id, class)| ClassJson {
+            id,
+            r: class.color.r,
+            g: class.color.g,
+            b: class.color.b,
+        })
+        .collect();
+    serde_json::to_vec(&GraphJson { nodes, classes })
+}
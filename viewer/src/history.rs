@@ -0,0 +1,171 @@
+use crate::app::{ModularityClass, Person, ViewerData};
+use graph_format::Point;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Number of operations that can be undone. A snapshot of a graph with 900k nodes weighs about
+/// 7 MB.
+const HISTORY_SIZE: usize = 5;
+
+/// The parts of a graph that are overwritten by the layout and community detection algorithms.
+#[derive(Default)]
+pub struct Snapshot {
+    positions: Vec<Point>,
+    classes: Vec<u32>,
+    modularity_classes: Vec<ModularityClass>,
+    modularity: Option<f64>,
+    merged_communities: Option<usize>,
+}
+
+impl Snapshot {
+    /// Overwrites the snapshot with the current state of the graph, reusing its buffers.
+    fn capture(&mut self, data: &ViewerData) {
+        self.positions.clear();
+        self.positions
+            .extend(data.persons.iter().map(|p| p.position));
+        self.classes.clear();
+        self.classes
+            .extend(data.persons.iter().map(|p| p.modularity_class));
+        self.modularity_classes.clone_from(&data.modularity_classes);
+        self.modularity = data.modularity;
+        self.merged_communities = data.merged_communities;
+    }
+
+    /// Returns the nodes with the positions and classes of the snapshot.
+    fn apply(&self, persons: &[Person]) -> Vec<Person> {
+        let mut persons = persons.to_vec();
+        for ((person, &position), &class) in
+            persons.iter_mut().zip(&self.positions).zip(&self.classes)
+        {
+            person.position = position;
+            person.modularity_class = class;
+        }
+        persons
+    }
+
+    /// Puts the snapshot back in the graph, along with the nodes returned by [`Snapshot::apply`].
+    fn restore(&self, data: &mut ViewerData, persons: Vec<Person>) {
+        data.persons = Arc::new(persons);
+        data.modularity_classes.clone_from(&self.modularity_classes);
+        data.modularity = self.modularity;
+        data.merged_communities = self.merged_communities;
+        data.class_generation += 1;
+    }
+}
+
+/// Undo and redo stacks for the operations that modify a graph in place.
+#[derive(Default)]
+pub struct History {
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    /// Snapshot dropped from one of the stacks, kept to reuse its buffers.
+    spare: Option<Snapshot>,
+    /// State of the graph before an operation running in the background, see [`History::begin`].
+    pending: Option<Snapshot>,
+}
+
+/// An undo or a redo, applied away from the UI thread as it copies all the nodes.
+pub struct Restore {
+    snapshot: Snapshot,
+    /// State the snapshot replaces, to go back to it.
+    replaced: Snapshot,
+    undo: bool,
+}
+
+impl Restore {
+    /// Returns the nodes of the restored graph. Only reads the graph, which can be drawn meanwhile.
+    pub fn prepare(&mut self, data: &ViewerData) -> Vec<Person> {
+        self.replaced.capture(data);
+        self.snapshot.apply(&data.persons)
+    }
+
+    /// Swaps the nodes returned by [`Restore::prepare`] into the graph, with the rest of the
+    /// snapshot.
+    pub fn swap(&self, data: &mut ViewerData, persons: Vec<Person>) {
+        self.snapshot.restore(data, persons);
+    }
+}
+
+impl History {
+    fn capture(&mut self, data: &ViewerData) -> Snapshot {
+        let mut snapshot = self.spare.take().unwrap_or_default();
+        snapshot.capture(data);
+        snapshot
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        if self.undo_stack.len() >= HISTORY_SIZE {
+            self.spare = self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(snapshot);
+    }
+
+    /// Saves the state of the graph before an operation that overwrites it.
+    pub fn push(&mut self, data: &ViewerData) {
+        let snapshot = self.capture(data);
+        self.push_snapshot(snapshot);
+    }
+
+    /// Saves the state of the graph before an operation running in the background, which is only
+    /// pushed by [`History::end`] if the operation changes the graph.
+    pub fn begin(&mut self, data: &ViewerData) {
+        self.pending = Some(self.capture(data));
+    }
+
+    /// Pushes the state saved by [`History::begin`] once the operation is `done`, or drops it if
+    /// the operation was canceled and left the graph as it was.
+    pub fn end(&mut self, done: bool) {
+        match self.pending.take() {
+            Some(snapshot) if done => self.push_snapshot(snapshot),
+            Some(snapshot) => self.spare = Some(snapshot),
+            None => {}
+        }
+    }
+
+    fn push_snapshot(&mut self, snapshot: Snapshot) {
+        self.push_undo(snapshot);
+        if let Some(snapshot) = self.redo_stack.pop() {
+            self.spare.get_or_insert(snapshot);
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Takes the state of the graph before the last operation, to put it back with [`Restore`],
+    /// then [`History::finish`].
+    pub fn undo(&mut self) -> Option<Restore> {
+        let snapshot = self.undo_stack.pop_back()?;
+        Some(self.restore(snapshot, true))
+    }
+
+    /// Takes the state of the graph after the last undone operation, like [`History::undo`].
+    pub fn redo(&mut self) -> Option<Restore> {
+        let snapshot = self.redo_stack.pop()?;
+        Some(self.restore(snapshot, false))
+    }
+
+    fn restore(&mut self, snapshot: Snapshot, undo: bool) -> Restore {
+        Restore {
+            snapshot,
+            replaced: self.spare.take().unwrap_or_default(),
+            undo,
+        }
+    }
+
+    /// Records the state replaced by an undo or a redo, so that it can be redone or undone in turn.
+    pub fn finish(&mut self, restore: Restore) {
+        if restore.undo {
+            self.redo_stack.push(restore.replaced);
+        } else {
+            self.push_undo(restore.replaced);
+        }
+        self.spare = Some(restore.snapshot);
+    }
+}
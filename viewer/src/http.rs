@@ -45,6 +45,37 @@ pub fn download_bytes(url: &str) -> anyhow::Result<Vec<u8>> {
     Ok(response.bytes()?.to_vec())
 }
 
+/// Size of the chunks a file is read in when streamed.
+#[cfg(not(target_arch = "wasm32"))]
+const STREAM_CHUNK_SIZE: usize = 1 << 20;
+
+/// Reads a file in chunks, passing each one to `chunk_cb` as soon as it's read, and stops at the
+/// first error it returns. Local files are read from the disk, like in [`download_bytes`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn download_stream(
+    url: &str,
+    mut chunk_cb: impl FnMut(&[u8]) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    use std::io::Read;
+    let mut reader: Box<dyn Read> = if !url.starts_with("http")
+        && let Ok(file) = try_find_local_file(url)
+    {
+        Box::new(file)
+    } else {
+        Box::new(send_reqwest(url)?)
+    };
+    let mut buf = vec![0; STREAM_CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        chunk_cb(&buf[..read])?;
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 fn send_xhr(url: &str, response_type: web_sys::XmlHttpRequestResponseType) -> anyhow::Result<web_sys::XmlHttpRequest> {
     let xhr = web_sys::XmlHttpRequest::new().unwrap();
@@ -71,4 +102,36 @@ pub fn download_bytes(url: &str) -> anyhow::Result<Vec<u8>> {
     Ok(buf)
 }
 
+/// Turns the value a JS promise was rejected with into an error.
+#[cfg(target_arch = "wasm32")]
+pub fn js_error(e: wasm_bindgen::JsValue) -> anyhow::Error {
+    use wasm_bindgen::JsCast;
+    match e.dyn_ref::<js_sys::Error>() {
+        Some(e) => anyhow::anyhow!("{}", String::from(e.message())),
+        None => anyhow::anyhow!("{}", e.as_string().unwrap_or_else(|| format!("{:?}", e))),
+    }
+}
+
+/// Callback handed to JS, which waits for the promise it returns before passing the next chunk.
+#[cfg(target_arch = "wasm32")]
+pub type JsChunkHandler =
+    wasm_bindgen::closure::Closure<dyn FnMut(js_sys::Uint8Array) -> js_sys::Promise>;
 
+/// Wraps `chunk_cb` to be called from JS. The chunks are only copied into the wasm heap once the
+/// future returned for the previous one has completed, so that a slow consumer doesn't let the
+/// whole file pile up in memory.
+#[cfg(target_arch = "wasm32")]
+pub fn js_chunk_handler<F>(mut chunk_cb: impl FnMut(Vec<u8>) -> F + 'static) -> JsChunkHandler
+where
+    F: std::future::Future<Output = anyhow::Result<()>> + 'static,
+{
+    use wasm_bindgen::JsValue;
+    wasm_bindgen::closure::Closure::new(move |chunk: js_sys::Uint8Array| {
+        let done = chunk_cb(chunk.to_vec());
+        wasm_bindgen_futures::future_to_promise(async move {
+            done.await
+                .map(|()| JsValue::UNDEFINED)
+                .map_err(|e| js_sys::Error::new(&e.to_string()).into())
+        })
+    })
+}
@@ -18,6 +18,7 @@ i18n!("locales",
     minify_key_prefix = "tr_",
     minify_key_thresh = 8);
 mod app;
+pub mod attributes;
 pub mod graph_storage;
 mod ui;
 pub mod utils;
@@ -25,8 +26,14 @@ pub mod algorithms;
 pub mod threading;
 mod graph_render;
 mod gfonts;
+mod history;
 mod http;
-mod search;
+pub mod search;
+mod session;
 
 pub use app::thread;
+pub use graph_render::buckets;
+pub use graph_render::lod;
+pub use graph_render::packing;
 pub use app::GraphViewApp;
+pub use app::DEFAULT_WINDOW_SIZE;
@@ -55,7 +55,9 @@ fn main() -> eframe::Result<()> {
     } // only for #[cfg]
 
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([1600.0, 900.0]),
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(viewer::DEFAULT_WINDOW_SIZE)
+            .with_app_id("graphrust"),
         renderer: eframe::Renderer::Glow,
         multisampling: 4,
         ..Default::default()
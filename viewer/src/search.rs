@@ -1,75 +1,280 @@
 use crate::app::{thread, Person};
+use ahash::AHashSet;
+use egui::{Context, Id};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::sync::{Arc, Condvar, Mutex};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 use zearch::{Document, Index, Search};
 
-pub struct SearchIndex {
-    fuzzy: Index<'static>,
-    exact: Vec<(&'static str, u32)>,
-    #[allow(dead_code)]
-    persons: Arc<Vec<Person>>,
+/// Delay after the last keystroke before a search is started, in seconds.
+pub const DEBOUNCE: f64 = 0.3;
+
+/// How the names are matched, shared by the search section and the node pickers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// "Jerome" finds "Jérôme", and the other way around. The fuzzy matches always ignore them.
+    pub ignore_accents: bool,
+    /// Names starting with the query, or with a word starting with it, come before those that
+    /// only contain it, and the nodes with the most neighbors come first among each. The fuzzy
+    /// matches come after both.
+    pub prefix_first: bool,
 }
 
-impl Document<'_, 'static> for Person {
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            ignore_accents: true,
+            prefix_first: true,
+        }
+    }
+}
+
+impl SearchOptions {
+    fn id() -> Id {
+        Id::new("search_options")
+    }
+
+    /// Options picked by the user, kept across sessions.
+    pub fn get(ctx: &Context) -> Self {
+        ctx.data_mut(|d| *d.get_persisted_mut_or_default::<SearchOptions>(Self::id()))
+    }
+
+    pub fn set(ctx: &Context, options: SearchOptions) {
+        ctx.data_mut(|d| d.insert_persisted(Self::id(), options));
+    }
+}
+
+/// Folds a name or a query for matching: lowercased, and with `ignore_accents`, decomposed with
+/// NFKD and stripped of its combining marks.
+pub fn normalize(text: &str, ignore_accents: bool) -> String {
+    if ignore_accents {
+        text.nfkd()
+            .filter(|&c| !is_combining_mark(c))
+            .flat_map(char::to_lowercase)
+            .collect()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+/// Whether `name` starts with `query`, or has a word starting with it.
+fn is_prefix(name: &str, query: &str) -> bool {
+    name.starts_with(query)
+        || name
+            .match_indices(query)
+            .any(|(i, _)| !name[..i].ends_with(char::is_alphanumeric))
+}
+
+/// Field of a node that matched a search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchedField {
+    Id,
+    Name,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub node: usize,
+    pub field: MatchedField,
+}
+
+impl SearchMatch {
+    pub fn name(node: usize) -> Self {
+        SearchMatch {
+            node,
+            field: MatchedField::Name,
+        }
+    }
+
+    /// Text shown for the match: the name of the node, followed by its ID if that is what
+    /// matched.
+    pub fn label(&self, person: &Person) -> String {
+        match self.field {
+            MatchedField::Id => format!("{} ({})", person.name, person.id),
+            MatchedField::Name => person.name.to_string(),
+        }
+    }
+}
+
+/// Names of the nodes folded with [`normalize`], without their accents.
+struct FoldedName(&'static str);
+
+impl Document<'_, 'static> for FoldedName {
     fn name(&'_ self) -> &'static str {
-        self.name
+        self.0
     }
 }
 
+pub struct SearchIndex {
+    /// Fuzzy index of the folded names. It points into `folded`, so it's declared before it to be
+    /// dropped first.
+    fuzzy: Index<'static>,
+    /// Folded names, one after the other.
+    folded: String,
+    /// End of each name in `folded`.
+    folded_ends: Vec<usize>,
+    /// IDs, sorted, to find the exact matches and the IDs starting with the query.
+    ids: Vec<(&'static str, u32)>,
+    persons: Arc<Vec<Person>>,
+}
+
 impl SearchIndex {
     pub fn new(persons: Arc<Vec<Person>>) -> Self {
         log::info!("Initializing search engine");
-        let fuzzy = Index::new_in_memory(&persons);
+        let mut folded = String::new();
+        let mut folded_ends = Vec::with_capacity(persons.len());
+        for p in persons.iter() {
+            folded.push_str(&normalize(p.name, true));
+            folded_ends.push(folded.len());
+        }
+        let names = folded_ends
+            .iter()
+            .scan(0, |start, &end| {
+                // the string isn't changed after this, and outlives the index, see `fuzzy`
+                let name: &'static str = unsafe { &*(&folded[*start..end] as *const str) };
+                *start = end;
+                Some(FoldedName(name))
+            })
+            .collect_vec();
+        let fuzzy = Index::new_in_memory(&names);
         log::info!("Fuzzy index initialized");
-        let mut exact = Vec::with_capacity(persons.len());
+        let mut ids = Vec::with_capacity(persons.len());
         for (i, p) in persons.iter().enumerate() {
-            exact.push((p.id, i as u32));
+            ids.push((p.id, i as u32));
         }
-        exact.sort_unstable_by_key(|(id, _)| *id);
+        ids.sort_unstable_by_key(|(id, _)| *id);
         log::info!("Search engine initialized");
         SearchIndex {
             fuzzy,
-            exact,
+            folded,
+            folded_ends,
+            ids,
             persons,
         }
     }
 
-    pub fn search(&self, query: &str, max_results: usize) -> Vec<u32> {
-        let exact = self
-            .exact
-            .binary_search_by_key(&query, |(name, _)| *name)
-            .ok();
-        let mut fuzzy = self
-            .fuzzy
-            .search(Search::new(query).with_limit(max_results));
-        if let Some(e) = exact {
-            let exact_match = self.exact[e].1;
-            if let Some(i) = fuzzy.iter().position(|&i| i == exact_match) {
-                fuzzy.remove(i);
-            }
-            fuzzy.insert(0, exact_match);
+    fn folded_name(&self, i: usize) -> &str {
+        let start = if i == 0 { 0 } else { self.folded_ends[i - 1] };
+        &self.folded[start..self.folded_ends[i]]
+    }
+
+    /// Nodes whose name starts with or contains the query, see [`SearchOptions::prefix_first`].
+    fn name_matches(&self, query: &str, options: SearchOptions, max_results: usize) -> Vec<u32> {
+        // folding both sides the same way keeps a match a match, so the folded names are enough
+        // to find the candidates, which are then checked with their accents if needed
+        let folded_query = normalize(query, true);
+        if folded_query.is_empty() {
+            return vec![];
+        }
+        let query = &normalize(query, options.ignore_accents);
+        let mut matches = (0..self.persons.len())
+            .filter_map(|i| {
+                let folded = self.folded_name(i);
+                if !folded.contains(&folded_query) {
+                    return None;
+                }
+                let lowercase;
+                let name = if options.ignore_accents {
+                    folded
+                } else {
+                    lowercase = normalize(self.persons[i].name, false);
+                    if !lowercase.contains(query) {
+                        return None;
+                    }
+                    &lowercase
+                };
+                let degree = self.persons[i].neighbors.len();
+                Some((!is_prefix(name, query), Reverse(degree), i as u32))
+            })
+            .collect_vec();
+        if matches.len() > max_results {
+            matches.select_nth_unstable(max_results);
+            matches.truncate(max_results);
         }
-        fuzzy
+        matches.sort_unstable();
+        matches.into_iter().map(|(_, _, i)| i).collect()
+    }
+
+    /// Nodes whose ID starts with the query, the exact match first.
+    fn id_matches(&self, query: &str) -> impl Iterator<Item = u32> + '_ {
+        let start = self.ids.partition_point(|(id, _)| *id < query);
+        self.ids[start..]
+            .iter()
+            .take_while(move |(id, _)| id.starts_with(query))
+            .map(|&(_, i)| i)
+    }
+
+    /// Searches both the names and the IDs. An exact ID match always comes first; for queries
+    /// made only of digits, the IDs starting with the query come before the names.
+    pub fn search(
+        &self,
+        query: &str,
+        max_results: usize,
+        options: SearchOptions,
+    ) -> Vec<SearchMatch> {
+        let id_match = |i: u32| SearchMatch {
+            node: i as usize,
+            field: MatchedField::Id,
+        };
+        let mut results = if !query.is_empty() && query.bytes().all(|b| b.is_ascii_digit()) {
+            self.id_matches(query)
+                .take(max_results)
+                .map(id_match)
+                .collect_vec()
+        } else {
+            self.ids
+                .binary_search_by_key(&query, |(id, _)| *id)
+                .ok()
+                .map(|e| id_match(self.ids[e].1))
+                .into_iter()
+                .collect_vec()
+        };
+        let prefixes = if options.prefix_first {
+            self.name_matches(query, options, max_results)
+        } else {
+            vec![]
+        };
+        // the index only holds the names without their accents
+        let names = self
+            .fuzzy
+            .search(Search::new(&normalize(query, true)).with_limit(max_results));
+        let mut seen: AHashSet<usize> = results.iter().map(|m| m.node).collect();
+        results.extend(
+            prefixes
+                .into_iter()
+                .chain(names)
+                .map(|i| SearchMatch::name(i as usize))
+                .filter(|m| seen.insert(m.node)),
+        );
+        results.truncate(max_results);
+        results
     }
 }
 
 pub struct SearchEngine {
     inner: Arc<(Mutex<Option<SearchIndex>>, Condvar)>,
+    /// Nodes the index was built from, which the results refer to.
+    pub persons: Arc<Vec<Person>>,
 }
 
 impl SearchEngine {
     pub fn new(persons: Arc<Vec<Person>>) -> Self {
         let inner = Arc::new((Mutex::new(None), Condvar::new()));
         let inner_clone = inner.clone();
+        let persons_clone = persons.clone();
 
         thread::spawn(move || {
-            let engine = SearchIndex::new(persons);
+            let engine = SearchIndex::new(persons_clone);
             let (lock, cvar) = &*inner_clone;
             let mut state = lock.lock().unwrap();
             *state = Some(engine);
             cvar.notify_all();
         });
 
-        SearchEngine { inner }
+        SearchEngine { inner, persons }
     }
 
     pub fn get_blocking<T>(&self, op: impl FnOnce(&SearchIndex) -> T) -> T {
@@ -80,4 +285,10 @@ impl SearchEngine {
         }
         op(state.as_ref().unwrap())
     }
+
+    /// Returns the indices of the best `limit` matches for the query, best first. Blocks until the
+    /// index is ready.
+    pub fn search_n(&self, query: &str, limit: usize, options: SearchOptions) -> Vec<SearchMatch> {
+        self.get_blocking(|s| s.search(query, limit, options))
+    }
 }
@@ -0,0 +1,269 @@
+use crate::algorithms::pathfinding::{PathSectionSettings, DEFAULT_MAX_PATHS};
+use crate::app::GraphTabState;
+use crate::graph_render::camera::CamXform;
+use crate::ui::sections::infos::create_subgraph;
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
+use crate::ui::tabs::GraphTab;
+use ahash::AHashSet;
+use egui::Context;
+use egui_dock::DockState;
+use graph_format::nalgebra::{Quaternion, Translation3, UnitQuaternion, Vector4};
+use serde::{Deserialize, Serialize};
+
+/// Camera transform, stored as its components since nalgebra isn't built with serde support.
+#[derive(Serialize, Deserialize)]
+pub struct CameraSession {
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    scaling: f32,
+}
+
+impl From<&CamXform> for CameraSession {
+    fn from(xform: &CamXform) -> Self {
+        CameraSession {
+            translation: xform.isometry.translation.vector.into(),
+            rotation: xform.isometry.rotation.coords.into(),
+            scaling: xform.scaling(),
+        }
+    }
+}
+
+impl CameraSession {
+    fn to_xform(&self) -> CamXform {
+        let [x, y, z] = self.translation;
+        CamXform::from_parts(
+            Translation3::new(x, y, z),
+            UnitQuaternion::from_quaternion(Quaternion::from(Vector4::from(self.rotation))),
+            self.scaling,
+        )
+    }
+}
+
+/// Saved state of a tab. All node indices refer to the main graph.
+#[derive(Serialize, Deserialize)]
+pub struct TabSession {
+    title: String,
+    camera: CameraSession,
+    /// Nodes included in the tab, `None` for the main tab.
+    nodes: Option<Vec<usize>>,
+    infos_current: Option<usize>,
+    path_src: Option<usize>,
+    path_dest: Option<usize>,
+    exclude_ids: Vec<usize>,
+    path_no_direct: bool,
+    path_no_mutual: bool,
+    #[serde(default)]
+    path_directed: bool,
+    #[serde(default = "default_max_paths")]
+    max_paths: usize,
+    #[serde(default)]
+    exclude_classes: Vec<u32>,
+}
+
+fn default_max_paths() -> usize {
+    DEFAULT_MAX_PATHS
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Session {
+    tabs: Vec<TabSession>,
+}
+
+impl Session {
+    pub fn capture(tree: &DockState<GraphTab>) -> Session {
+        let tabs = tree
+            .iter_all_tabs()
+            .filter_map(|(_, tab)| {
+                let GraphTabState::Loaded(loaded) = &tab.state else {
+                    return None;
+                };
+                let data = loaded.viewer_data.read();
+                // graphs opened from another file, such as edge lists, can't be restored
+                if tab.closeable && data.base_ids.is_none() {
+                    return None;
+                }
+                let to_base = |id: usize| data.base_id(id);
+                let infos = &loaded.ui_state.infos;
+                let settings = &loaded.ui_state.path.path_settings;
+                Some(TabSession {
+                    title: tab.title.clone(),
+                    camera: (&loaded.tab_camera.camera.transf).into(),
+                    nodes: data.base_ids.clone(),
+                    infos_current: infos.infos_current.map(to_base),
+                    path_src: settings.path_src.map(to_base),
+                    path_dest: settings.path_dest.map(to_base),
+                    exclude_ids: settings.exclude_ids.iter().copied().map(to_base).collect(),
+                    path_no_direct: settings.path_no_direct,
+                    path_no_mutual: settings.path_no_mutual,
+                    path_directed: settings.path_directed,
+                    max_paths: settings.max_paths,
+                    exclude_classes: settings.exclude_classes.clone(),
+                })
+            })
+            .collect();
+        Session { tabs }
+    }
+
+    /// Restores the main tab's state and reopens the saved subgraph tabs.
+    ///
+    /// Subgraphs are derived again from the main graph; saved nodes that don't exist anymore are
+    /// skipped.
+    pub fn restore(
+        self,
+        tree: &mut DockState<GraphTab>,
+        ctx: &Context,
+        modal: &impl ModalWriter,
+    ) -> anyhow::Result<()> {
+        let main = tree
+            .iter_all_tabs_mut()
+            .find_map(|(_, tab)| {
+                if tab.closeable {
+                    return None;
+                }
+                match &mut tab.state {
+                    GraphTabState::Loaded(loaded) => Some(loaded),
+                    GraphTabState::Loading { .. } => None,
+                }
+            })
+            .ok_or_else(|| anyhow::anyhow!(t!("The graph is still loading")))?;
+
+        let data = main.viewer_data.clone();
+        let node_count = data.read().persons.len();
+        let mut skipped = 0;
+        let mut check = |id: usize| {
+            if id < node_count {
+                Some(id)
+            } else {
+                skipped += 1;
+                None
+            }
+        };
+
+        let mut new_tabs = Vec::new();
+        for tab in self.tabs {
+            let infos_current = tab.infos_current.and_then(&mut check);
+            let settings = PathSectionSettings {
+                path_src: tab.path_src.and_then(&mut check),
+                path_dest: tab.path_dest.and_then(&mut check),
+                exclude_ids: tab.exclude_ids.into_iter().filter_map(&mut check).collect(),
+                path_no_direct: tab.path_no_direct,
+                path_no_mutual: tab.path_no_mutual,
+                path_directed: tab.path_directed,
+                max_paths: tab.max_paths,
+                exclude_classes: tab.exclude_classes,
+            };
+            match tab.nodes {
+                None => {
+                    main.tab_camera.camera.transf = tab.camera.to_xform();
+                    main.ui_state.infos.set_infos_current(infos_current);
+                    main.ui_state.path.path_settings = settings;
+                    main.ui_state.path.path_dirty = true;
+                }
+                Some(nodes) => {
+                    let nodes: AHashSet<usize> = nodes.into_iter().filter_map(&mut check).collect();
+                    let mut camera = main.tab_camera.camera;
+                    camera.transf = tab.camera.to_xform();
+                    new_tabs.push(create_subgraph(
+                        tab.title,
+                        &data,
+                        camera,
+                        infos_current,
+                        settings,
+                        main.ui_state.display.inherited(),
+                        ctx,
+                        modal.clone(),
+                        move |_, _| Ok(nodes),
+                    ));
+                }
+            }
+        }
+
+        for tab in new_tabs {
+            tree.push_to_focused_leaf(tab);
+        }
+
+        if skipped > 0 {
+            modal.send(ModalInfo {
+                title: t!("Warning").to_string(),
+                kind: ModalKind::Error,
+                body: t!(
+                    "%{count} saved nodes don't exist in the current graph and were skipped.",
+                    count = skipped
+                )
+                .to_string()
+                .into(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+const SESSION_FILE: &str = "session.json";
+
+/// Saves a small file next to the executable, or in the local storage of the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write_local(file: &str, json: &str) -> anyhow::Result<()> {
+    std::fs::write(file, json)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn read_local(file: &str) -> anyhow::Result<String> {
+    Ok(std::fs::read_to_string(file)?)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> anyhow::Result<web_sys::Storage> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| anyhow::anyhow!(t!("Local storage is unavailable")))
+}
+
+/// Saves a small file next to the executable, or in the local storage of the browser.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn write_local(file: &str, json: &str) -> anyhow::Result<()> {
+    local_storage()?
+        .set_item(file, json)
+        .map_err(|_| anyhow::anyhow!(t!("Unable to save %{file}", file = file)))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn read_local(file: &str) -> anyhow::Result<String> {
+    local_storage()?
+        .get_item(file)
+        .ok()
+        .flatten()
+        .ok_or_else(|| anyhow::anyhow!(t!("Nothing saved in %{file}", file = file)))
+}
+
+fn report_error(modal: &impl ModalWriter, e: anyhow::Error) {
+    log::error!("Session error: {}", e);
+    modal.send(ModalInfo {
+        title: t!("Error").to_string(),
+        kind: ModalKind::Error,
+        body: e.to_string().into(),
+    });
+}
+
+pub fn save_session(tree: &DockState<GraphTab>, modal: &impl ModalWriter) {
+    let res: anyhow::Result<()> = try {
+        let json = serde_json::to_string_pretty(&Session::capture(tree))?;
+        write_local(SESSION_FILE, &json)?;
+        log::info!("Session saved");
+    };
+    if let Err(e) = res {
+        report_error(modal, e);
+    }
+}
+
+pub fn load_session(tree: &mut DockState<GraphTab>, ctx: &Context, modal: &impl ModalWriter) {
+    let res: anyhow::Result<()> = try {
+        let session: Session = serde_json::from_str(&read_local(SESSION_FILE)?)?;
+        session.restore(tree, ctx, modal)?;
+        log::info!("Session loaded");
+    };
+    if let Err(e) = res {
+        report_error(modal, e);
+    }
+}
@@ -1,17 +1,21 @@
 use crate::app::ContextUpdater;
 use crate::thread;
-use crate::ui::modal::{ModalInfo, ModalWriter};
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
 use eframe::epaint::text::{LayoutJob, TextFormat};
 use eframe::epaint::{FontFamily, FontId};
 use egui::Context;
 use parking_lot::lock_api::{RwLockReadGuard, RwLockWriteGuard};
 use parking_lot::{RawRwLock, RwLock};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Weak};
 
 #[derive(Debug)]
 pub enum CancelableError {
     TabClosed,
+    /// The user stopped the operation.
+    Canceled,
     Other(anyhow::Error),
     Custom(Box<ModalInfo>),
 }
@@ -96,9 +100,23 @@ impl<T> MyRwLock<T> {
     }
 }
 
+/// Log lines kept by a [`StatusReader`], the older ones are dropped.
+pub const MAX_STATUS_LINES: usize = 10;
+
+/// State shared by the two ends of a [`status_pipe`]. Progress updates overwrite each other, so
+/// that a fast loop can't queue more of them than the UI can show.
+struct StatusShared {
+    lines: MyRwLock<VecDeque<String>>,
+    /// Latest progress, see [`pack_progress`].
+    progress: AtomicU64,
+    /// Something changed since the reader last looked. Only the first change requests a repaint.
+    dirty: AtomicBool,
+}
+
 #[derive(Clone)]
 pub struct StatusWriter {
-    tx: Sender<StatusData>,
+    /// Weak, so that sending fails once the reader is dropped, e.g. when the tab is closed.
+    shared: Weak<StatusShared>,
     ctx: ContextUpdater,
 }
 
@@ -108,10 +126,23 @@ pub struct Progress {
     pub val: usize,
 }
 
+/// Packs a progress into 64 bits, `max` in the high half. Zero is no progress.
+fn pack_progress(p: Progress) -> u64 {
+    let max = p.max.clamp(1, u32::MAX as usize);
+    ((max as u64) << 32) | p.val.min(max) as u64
+}
+
+fn unpack_progress(packed: u64) -> Option<Progress> {
+    (packed != 0).then(|| Progress {
+        max: (packed >> 32) as usize,
+        val: packed as u32 as usize,
+    })
+}
+
 pub struct StatusReader {
     status: String,
     pub(crate) progress: Option<Progress>,
-    rx: Receiver<StatusData>,
+    shared: Arc<StatusShared>,
 }
 
 pub enum StatusData {
@@ -145,74 +176,182 @@ impl StatusWriterInterface for NullStatusWriter {
 
 impl StatusWriterInterface for StatusWriter {
     fn send(&self, s: impl Into<StatusData>) -> Result<(), mpsc::SendError<StatusData>> {
-        self.tx.send(s.into())?;
-        self.ctx.update();
+        let s = s.into();
+        let Some(shared) = self.shared.upgrade() else {
+            return Err(mpsc::SendError(s));
+        };
+        match s {
+            StatusData::Message(s) => {
+                let mut lines = shared.lines.write();
+                if lines.len() == MAX_STATUS_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(s);
+                // a new step starts, the progress of the previous one is over
+                shared.progress.store(0, Ordering::Relaxed);
+            }
+            StatusData::Progress(p) => {
+                shared.progress.store(pack_progress(p), Ordering::Relaxed);
+            }
+        }
+        if !shared.dirty.swap(true, Ordering::AcqRel) {
+            self.ctx.update();
+        }
         Ok(())
     }
 }
 
 impl StatusReader {
+    /// Last log lines, one per line. Also updates [`StatusReader::progress`].
     pub fn recv(&mut self) -> &str {
-        if let Ok(s) = self.rx.try_recv() {
-            match s {
-                StatusData::Message(s) => {
-                    self.progress = None;
-                    if !self.status.is_empty() {
-                        self.status.push('\n');
-                    }
-                    self.status.push_str(&s);
-                }
-                StatusData::Progress(p) => {
-                    self.progress = Some(p);
+        if self.shared.dirty.swap(false, Ordering::AcqRel) {
+            let lines = self.shared.lines.read();
+            self.status.clear();
+            for (i, line) in lines.iter().enumerate() {
+                if i > 0 {
+                    self.status.push('\n');
                 }
+                self.status.push_str(line);
             }
         }
+        self.progress = unpack_progress(self.shared.progress.load(Ordering::Relaxed));
         &self.status
     }
+
+    pub fn progress(&self) -> Option<Progress> {
+        self.progress
+    }
 }
 
 pub fn status_pipe(ctx: &Context) -> (StatusWriter, StatusReader) {
-    let (tx, rx) = mpsc::channel();
+    let shared = Arc::new(StatusShared {
+        lines: MyRwLock::new(VecDeque::with_capacity(MAX_STATUS_LINES)),
+        progress: AtomicU64::new(0),
+        dirty: AtomicBool::new(false),
+    });
     (
         StatusWriter {
-            tx,
+            shared: Arc::downgrade(&shared),
             ctx: ContextUpdater::new(ctx),
         },
         StatusReader {
             status: "".to_string(),
             progress: None,
-            rx,
+            shared,
         },
     )
 }
 
+/// Flag set to ask a background operation to stop, polled by its long-running loops.
+#[derive(Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [`CancelableError::Canceled`] once the flag has been set.
+    pub fn check(&self) -> Cancelable<()> {
+        if self.is_canceled() {
+            Err(CancelableError::Canceled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// An algorithm running in the background, which reports its progress and can be canceled.
+pub struct AlgoHandle<T> {
+    thread: thread::JoinHandle<Option<T>>,
+    pub status_rx: StatusReader,
+    cancel: CancelFlag,
+}
+
+impl<T: Send + 'static> AlgoHandle<T> {
+    /// Runs `f` in a new thread. Errors are reported like in [`spawn_cancelable`].
+    pub fn spawn(
+        ctx: &Context,
+        ms: impl ModalWriter,
+        f: impl FnOnce(&StatusWriter, &CancelFlag) -> Cancelable<T> + Send + 'static,
+    ) -> Self {
+        let (status_tx, status_rx) = status_pipe(ctx);
+        let cancel = CancelFlag::default();
+        let cancel_thr = cancel.clone();
+        let thread = thread::spawn(move || {
+            f(&status_tx, &cancel_thr)
+                .map_err(|e| report_error(&ms, e))
+                .ok()
+        });
+        AlgoHandle {
+            thread,
+            status_rx,
+            cancel,
+        }
+    }
+}
+
+impl<T> AlgoHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Asks the algorithm to stop. It does so the next time it polls the flag.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.cancel.is_canceled()
+    }
+
+    /// Waits for the algorithm to finish, and returns its result, or `None` if it failed or was
+    /// canceled.
+    pub fn join(self) -> Option<T> {
+        self.thread.join().ok().flatten()
+    }
+}
+
+fn report_error(ms: &impl ModalWriter, e: CancelableError) {
+    match e {
+        CancelableError::TabClosed => {
+            log::info!("Tab closed; cancelled");
+        }
+        CancelableError::Canceled => {
+            log::info!("Cancelled by the user");
+        }
+        CancelableError::Other(e) => {
+            ms.send(ModalInfo {
+                title: t!("Error").to_string(),
+                kind: ModalKind::Error,
+                body: {
+                    let mut job = LayoutJob::default();
+                    job.append(&t!("An error occurred:\n\n"), 0.0, TextFormat {
+                        font_id: FontId::new(14.0, FontFamily::Proportional),
+                        ..Default::default()
+                    });
+                    job.append(&format!("{:?}", e), 0.0, TextFormat {
+                        font_id: FontId::new(11.0, FontFamily::Monospace),
+                        ..Default::default()
+                    });
+                    job.into()
+                },
+            });
+        }
+        CancelableError::Custom(box info) => {
+            ms.send(info);
+        }
+    }
+}
+
 pub fn spawn_cancelable(ms: impl ModalWriter, f: impl FnOnce() -> Cancelable<()> + Send + 'static) -> thread::JoinHandle<()> {
     thread::spawn(move || {
-        match f() {
-            Err(CancelableError::TabClosed) => {
-                log::info!("Tab closed; cancelled");
-            }
-            Err(CancelableError::Other(e)) => {
-                ms.send(ModalInfo {
-                    title: t!("Error").to_string(),
-                    body: {
-                        let mut job = LayoutJob::default();
-                        job.append(&t!("An error occurred:\n\n"), 0.0, TextFormat {
-                            font_id: FontId::new(14.0, FontFamily::Proportional),
-                            ..Default::default()
-                        });
-                        job.append(&format!("{:?}", e), 0.0, TextFormat {
-                            font_id: FontId::new(11.0, FontFamily::Monospace),
-                            ..Default::default()
-                        });
-                        job.into()
-                    },
-                });
-            }
-            Err(CancelableError::Custom(box info)) => {
-                ms.send(info);
-            }
-            Ok(()) => {}
+        if let Err(e) = f() {
+            report_error(&ms, e);
         }
     })
-}
\ No newline at end of file
+}
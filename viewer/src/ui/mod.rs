@@ -1,11 +1,18 @@
+use crate::algorithms::closeness::DistanceDistribution;
 use crate::algorithms::AbstractGraph;
 use crate::app::{Person, ViewerData};
-use crate::graph_render::{GlTask, NodeFilter, PersonVertex, RenderedGraph};
-use crate::threading::MyRwLock;
+use crate::graph_render::buckets::{bucket_edges, EdgeBuckets};
+use crate::graph_render::{
+    geom_draw, lod, restore_task, ColorMode, GlTask, NodeFilter, PersonVertex, RenderedGraph,
+    MAX_VERTEX_BUFFER_SIZE,
+};
+use crate::history::History;
+use crate::thread::JoinHandle;
+use crate::threading::{CancelFlag, MyRwLock};
 use eframe::glow;
-use eframe::glow::HasContext;
-use egui::{Color32, Id, Ui};
-use itertools::Itertools;
+use egui::{Color32, Id, Response, Ui};
+use graph_format::{EdgeStore, Point};
+use itertools::{Either, Itertools};
 use modal::ModalWriter;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
@@ -13,9 +20,11 @@ use std::sync::Arc;
 pub mod sections;
 pub(crate) mod tabs;
 pub(crate) mod modal;
+pub(crate) mod shortcuts;
 mod widgets;
 
 use sections::*;
+use sections::path::MainPathRequest;
 use tabs::{NewTabRequest, TabCamera};
 
 fn set_bg_color_tinted(base: Color32, ui: &mut Ui) {
@@ -51,33 +60,165 @@ struct ParadoxState {
     max: usize,
 }
 
-fn rerender_graph(persons: &[Person]) -> GlTask {
-    let nodes = persons
-        .iter()
-        .map(|p| {
-            crate::graph_render::geom_draw::create_node_vertex(p)
-        });
+/// Distances from the selected node, which its closeness centrality is computed from.
+#[derive(Default)]
+struct ClosenessState {
+    current: Option<usize>,
+    thread: Option<JoinHandle<Option<DistanceDistribution>>>,
+    /// Stops the computation for the previous node when another one is selected.
+    cancel: CancelFlag,
+    value: Option<DistanceDistribution>,
+}
+
+#[derive(Default)]
+struct ClusteringState {
+    current: Option<usize>,
+    thread: Option<JoinHandle<f32>>,
+    value: Option<f32>,
+}
+
+/// Vertices of the nodes then the edges, along with the number of vertices per edge. Edges are
+/// straight when `curvature` is zero, and bent into curves otherwise.
+///
+/// Edges that don't fit in the vertex buffer are left out, as curved ones take many more vertices.
+/// The least important ones are, see [`lod::edge_importance`]. With `sort_edges`, the others are
+/// drawn in the order of [`lod::edge_draw_order`].
+///
+/// With `shown`, the other nodes are hidden along with their edges. They keep a vertex, so that the
+/// nodes stay at their index in the buffer.
+///
+/// The edges are sorted into spatial buckets, see [`bucket_edges`].
+fn graph_vertices(
+    persons: &[Person],
+    directed: bool,
+    curvature: f32,
+    sort_edges: bool,
+    shown: Option<&[bool]>,
+) -> (Vec<PersonVertex>, usize, EdgeBuckets) {
+    let is_shown = |i: usize| shown.is_none_or(|shown| shown[i]);
+    let nodes = persons.iter().enumerate().map(|(i, p)| {
+        let vertex = crate::graph_render::geom_draw::create_node_vertex(p);
+        if is_shown(i) {
+            vertex
+        } else {
+            // a NaN position culls the vertex, like the filters in the shader
+            PersonVertex {
+                position: Point::new(f32::NAN, f32::NAN),
+                ..vertex
+            }
+        }
+    });
 
-    let edges = persons.iter().get_edges().flat_map(
-        |(a, b)| crate::graph_render::geom_draw::create_edge_vertices(&persons[a], &persons[b])
-    );
+    let straight = curvature == 0.0;
+    let verts_per_edge = match (straight, directed) {
+        (true, false) => geom_draw::VERTS_PER_EDGE,
+        (true, true) => geom_draw::VERTS_PER_DIRECTED_EDGE,
+        (false, false) => geom_draw::VERTS_PER_CURVED_EDGE,
+        (false, true) => geom_draw::VERTS_PER_DIRECTED_CURVED_EDGE,
+    };
+    let max_edges = (MAX_VERTEX_BUFFER_SIZE / size_of::<PersonVertex>())
+        .saturating_sub(persons.len())
+        / verts_per_edge;
+
+    let pairs = if directed {
+        Either::Left(
+            persons
+                .iter()
+                .enumerate()
+                .flat_map(|(a, p)| p.out_neighbors().iter().map(move |&b| (a, b))),
+        )
+    } else {
+        Either::Right(persons.iter().get_edges())
+    };
+    let mut pairs = pairs
+        .filter(|&(a, b)| is_shown(a) && is_shown(b))
+        .map(|(a, b)| EdgeStore {
+            a: a as u32,
+            b: b as u32,
+        })
+        .collect_vec();
+    // the most important edges are the ones kept, either first once sorted or picked out
+    if !sort_edges {
+        lod::keep_most_important(persons, &mut pairs, max_edges);
+    }
+    let ends = lod::edge_draw_order(persons, pairs.iter(), sort_edges).take(max_edges);
+    let edges = ends.flat_map(|(pa, pb)| {
+        if straight {
+            let arrow = directed.then(|| geom_draw::create_arrow_vertices(pa, pb));
+            Either::Left(
+                geom_draw::create_edge_vertices(pa, pb)
+                    .into_iter()
+                    .chain(arrow.into_iter().flatten()),
+            )
+        } else {
+            let arrow =
+                directed.then(|| geom_draw::create_curved_arrow_vertices(pa, pb, curvature));
+            Either::Right(
+                geom_draw::create_curved_edge_vertices(pa, pb, curvature)
+                    .chain(arrow.into_iter().flatten()),
+            )
+        }
+    });
     let vertices = nodes.chain(edges).collect_vec();
+    let (vertices, buckets) = bucket_edges(vertices, persons.len(), verts_per_edge);
+    (vertices, verts_per_edge, buckets)
+}
 
-    let closure = move |graph: &mut RenderedGraph, gl: &glow::Context| unsafe {
-        gl.bind_buffer(glow::ARRAY_BUFFER, Some(graph.nodes_buffer));
-        gl.buffer_sub_data_u8_slice(
-            glow::ARRAY_BUFFER,
-            0,
-            std::slice::from_raw_parts(
-                vertices.as_ptr() as *const u8,
-                vertices.len() * size_of::<PersonVertex>(),
-            ),
-        );
+/// Recreates the graphics resources of the graph after the GL context has been lost.
+fn restore_graph(
+    persons: &[Person],
+    directed: bool,
+    curvature: f32,
+    sort_edges: bool,
+    color_mode: &ColorMode,
+) -> GlTask {
+    let (vertices, verts_per_edge, buckets) =
+        graph_vertices(persons, directed, curvature, sort_edges, None);
+    restore_task(
+        vertices,
+        verts_per_edge,
+        buckets,
+        color_mode.node_values(persons),
+    )
+}
+
+/// Replaces the vertices of the graph after the nodes have changed, with edges bent by
+/// `curvature`. Bundled edges are straightened back.
+fn rerender_graph(persons: &[Person], directed: bool, curvature: f32, sort_edges: bool) -> GlTask {
+    let (vertices, verts_per_edge, buckets) =
+        graph_vertices(persons, directed, curvature, sort_edges, None);
+    let vertices = Arc::new(vertices);
+
+    let closure = move |graph: &mut RenderedGraph, gl: &glow::Context| {
+        graph.set_vertices(gl, vertices, verts_per_edge, buckets);
+        graph.edges_bundled = false;
+        graph.edge_curvature = curvature;
     };
 
     Box::new(closure)
 }
 
+/// Like [`rerender_graph`], but only shows the `shown` nodes and the edges between them.
+fn reveal_graph(
+    persons: &[Person],
+    directed: bool,
+    curvature: f32,
+    sort_edges: bool,
+    shown: &[bool],
+) -> GlTask {
+    let (vertices, verts_per_edge, buckets) =
+        graph_vertices(persons, directed, curvature, sort_edges, Some(shown));
+    let vertices = Arc::new(vertices);
+
+    Box::new(move |graph: &mut RenderedGraph, gl: &glow::Context| {
+        // the buffer is updated in place, the edges of the previous step mustn't be drawn
+        graph.edges_count = 0;
+        graph.set_vertices(gl, vertices, verts_per_edge, buckets);
+        graph.edges_bundled = false;
+        graph.edge_curvature = curvature;
+    })
+}
+
 #[derive(Default, PartialEq, Eq)]
 pub enum SelectedUserField {
     Selected,
@@ -90,22 +231,33 @@ pub enum SelectedUserField {
 pub struct NodeStats {
     node_count: usize,
     node_classes: Vec<(usize, usize)>,
+    degree_histogram: Vec<DegreeBin>,
+    /// Filter the stats were computed with.
+    filter: Option<NodeFilter>,
+}
+
+/// Range of degrees `[start, end)` in the degree histogram.
+pub struct DegreeBin {
+    start: usize,
+    end: usize,
+    count: usize,
 }
 
 impl NodeStats {
     pub fn new(data: &ViewerData, filter: NodeFilter) -> Self {
         let mut count_classes = vec![0; data.modularity_classes.len()];
         let mut node_count = 0;
+        let mut degree_counts = Vec::new();
         for p in &*data.persons {
-            let ok = if filter.filter_nodes {
-                let deg = p.neighbors.len() as u16;
-                deg >= filter.degree_filter.0 && deg <= filter.degree_filter.1
-            } else {
-                true
-            };
-            if ok {
+            if filter.shows(p) {
                 node_count += 1;
                 count_classes[p.modularity_class as usize] += 1;
+                // bin 0 holds isolated nodes, bin k holds degrees in [2^(k-1), 2^k)
+                let bin = (usize::BITS - p.neighbors.len().leading_zeros()) as usize;
+                if degree_counts.len() <= bin {
+                    degree_counts.resize(bin + 1, 0);
+                }
+                degree_counts[bin] += 1;
             }
         }
         let node_classes = count_classes
@@ -115,9 +267,21 @@ impl NodeStats {
             .sorted_by_key(|(_, &c)| std::cmp::Reverse(c))
             .map(|(i, &c)| (i, c))
             .collect_vec();
+        let degree_histogram = degree_counts
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count != 0)
+            .map(|(bin, count)| DegreeBin {
+                start: if bin == 0 { 0 } else { 1 << (bin - 1) },
+                end: 1 << bin,
+                count,
+            })
+            .collect();
         Self {
             node_count,
             node_classes,
+            degree_histogram,
+            filter: Some(filter),
         }
     }
 }
@@ -128,13 +292,37 @@ pub struct UiState {
     pub path: path::PathSection,
     pub classes: class::ClassSection,
     pub infos: infos::InfosSection,
+    pub selection: selection::SelectionSection,
     pub details: details::DetailsSection,
     pub selected_user_field: SelectedUserField,
     pub algorithms: algos::AlgosSection,
+    pub search: search::SearchSection,
+    pub statistics: stats::StatisticsSection,
+    pub components: components::ComponentsSection,
+    pub minimap: widgets::minimap::Minimap,
+    pub ruler: widgets::ruler::Ruler,
+    pub shortcuts: shortcuts::ShortcutsState,
+    /// Action triggered from the keyboard that needs the side panel to run.
+    pub pending_action: Option<shortcuts::Action>,
 
     pub stats: Arc<MyRwLock<NodeStats>>,
 }
 
+/// Interval at which work running in the background is polled in power saving mode, in seconds.
+const POWER_SAVING_POLL: f32 = 0.25;
+
+/// Spinner shown while work runs in the background. A spinner repaints the UI on every frame, so
+/// in power saving mode an hourglass is shown instead, and the work is only polled a few times per
+/// second.
+pub fn spinner(ui: &mut Ui) -> Response {
+    if details::DetailsSection::power_saving(ui.ctx()) {
+        ui.ctx().request_repaint_after_secs(POWER_SAVING_POLL);
+        ui.label("⏳")
+    } else {
+        ui.spinner()
+    }
+}
+
 fn percent_formatter(val: f64, _: RangeInclusive<usize>) -> String {
     format!("{:.1}%", val * 100.0)
 }
@@ -148,54 +336,132 @@ fn percent_parser(s: &str) -> Option<f64> {
 }
 
 impl UiState {
+    /// State of a copy of the tab: the display settings, the selected node and the path settings,
+    /// the path being searched for again.
+    pub(crate) fn duplicated(&self) -> UiState {
+        let mut ui_state = UiState {
+            display: self.display.duplicated(),
+            ..Default::default()
+        };
+        ui_state.infos.set_infos_current(self.infos.infos_current);
+        ui_state.path.path_settings = self.path.path_settings.clone();
+        ui_state.path.path_dirty = true;
+        ui_state
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_ui(
         &mut self,
         ui: &mut Ui,
         data: &Arc<MyRwLock<ViewerData>>,
         graph: &Arc<MyRwLock<RenderedGraph>>,
         tab_request: &mut Option<NewTabRequest>,
+        path_request: &mut Option<MainPathRequest>,
         camera: &mut TabCamera,
         cid: Id,
         modal: &impl ModalWriter,
+        history: &mut History,
     ) {
         ui.spacing_mut().slider_width = 200.0;
+        match self.pending_action.take() {
+            Some(shortcuts::Action::FocusSearch) => self.search.request_focus(),
+            Some(shortcuts::Action::RunLouvain) => {
+                self.algorithms
+                    .run_louvain(data, ui.ctx(), graph, &self.stats, modal, history)
+            }
+            Some(shortcuts::Action::ExportImage) => self.details.export.export_png(
+                data,
+                graph,
+                ui,
+                &self.display,
+                &camera.camera,
+                modal,
+            ),
+            _ => {}
+        }
         egui::ScrollArea::vertical().show(ui, |ui| {
-            self.display.show(graph, ui, &self.stats);
+            self.display.filters_locked = self.details.export.is_exporting();
+            self.display.show(
+                data,
+                graph,
+                ui,
+                &self.stats,
+                modal,
+                tab_request,
+                &camera.camera,
+            );
 
-            if self.display.deg_filter_changed {
-                *self.stats.write() = NodeStats::new(&data.read(), graph.read().node_filter);
+            let filter = graph.read().node_filter;
+            if self.display.deg_filter_changed || self.stats.read().filter != Some(filter) {
+                self.display.deg_filter_changed = false;
+                *self.stats.write() = NodeStats::new(&data.read(), filter);
             }
 
+            self.search.show(data, ui, &mut self.infos, camera, cid);
+
             self.path.show(
                 data,
                 ui,
                 &mut self.infos,
                 &mut self.selected_user_field,
+                path_request,
             );
 
             self.infos.show(
                 data,
                 tab_request,
                 ui,
-                &camera.camera,
+                camera,
+                cid,
                 &self.path,
+                &self.display,
                 &mut self.selected_user_field,
                 modal,
+                graph,
+            );
+
+            self.selection.show(
+                data,
+                graph,
+                ui,
+                &mut self.infos,
+                tab_request,
+                &camera.camera,
+                &self.path,
+                &self.display,
+                modal,
             );
 
             self.classes.show(
                 ui,
                 &self.infos,
-                data, tab_request,
+                data,
+                tab_request,
                 &camera.camera,
                 &self.path,
+                &self.display,
                 modal,
                 &self.stats,
             );
 
-            self.algorithms.show(data, ui, graph, &self.stats, modal);
+            self.statistics.show(data, ui, &self.stats, modal);
+
+            self.components.show(data, ui);
+
+            self.algorithms.show(
+                data,
+                ui,
+                graph,
+                &self.stats,
+                modal,
+                &mut self.infos,
+                &mut self.path,
+                history,
+                camera,
+            );
 
-            self.details.show(ui, camera, cid);
+            self.details
+                .show(data, graph, ui, &self.display, camera, cid, modal);
         });
     }
 }
@@ -7,10 +7,18 @@ use std::sync::mpsc::{Receiver, Sender};
 #[derivative(Debug)]
 pub struct ModalInfo {
     pub title: String,
+    pub kind: ModalKind,
     #[derivative(Debug(format_with = "fmt_modal_body"))]
     pub body: WidgetText,
 }
 
+/// Decides the icon shown next to the body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModalKind {
+    Error,
+    Info,
+}
+
 fn fmt_modal_body(text: &WidgetText, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.write_str(text.text())
 }
@@ -42,7 +50,11 @@ pub fn show_modal(ctx: &Context, recv: &Receiver<ModalInfo>, modal_id: &str) {
         modal.show(|ui| {
             modal.title(ui, data.title);
             modal.frame(ui, |ui| {
-                modal.body_and_icon(ui, data.body, Icon::Error);
+                let icon = match data.kind {
+                    ModalKind::Error => Icon::Error,
+                    ModalKind::Info => Icon::Info,
+                };
+                modal.body_and_icon(ui, data.body, icon);
             });
             modal.buttons(ui, |ui| {
                 ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
@@ -53,4 +65,35 @@ pub fn show_modal(ctx: &Context, recv: &Receiver<ModalInfo>, modal_id: &str) {
     }
 
     modal.show_dialog();
-}
\ No newline at end of file
+}
+
+/// Shows the errors received on `recv` in a modal with a button to try again, and returns whether
+/// it was clicked. The modal can't be dismissed otherwise.
+pub fn show_retry_modal(ctx: &Context, recv: &Receiver<String>, modal_id: &str) -> bool {
+    let modal = Modal::new(ctx, modal_id).with_style(&ModalStyle {
+        default_width: Some(600.0),
+        ..ModalStyle::default()
+    });
+
+    if let Ok(error) = recv.try_recv() {
+        ctx.data_mut(|w| w.insert_temp(Id::new(modal_id).with("data"), error));
+        modal.open();
+    }
+
+    let mut retry = false;
+    if let Some(error) = ctx.data(|w| w.get_temp::<String>(Id::new(modal_id).with("data"))) {
+        modal.show(|ui| {
+            modal.title(ui, t!("Unable to load the graph").to_string());
+            modal.frame(ui, |ui| {
+                modal.body_and_icon(ui, error, Icon::Error);
+            });
+            modal.buttons(ui, |ui| {
+                ui.with_layout(Layout::top_down_justified(Align::Center), |ui| {
+                    retry = modal.button(ui, t!("Retry").to_string()).clicked();
+                });
+            });
+        });
+    }
+
+    retry
+}
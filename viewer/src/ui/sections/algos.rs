@@ -1,16 +1,35 @@
+use crate::algorithms::diameter::{estimate_graph_diameter, Diameter};
+use crate::algorithms::pagerank::{pagerank, DEFAULT_DAMPING};
 use crate::algorithms::AbstractGraph;
-use crate::app::{show_progress_bar, ViewerData};
-use crate::graph_render::RenderedGraph;
+use crate::algorithms::{layout, louvain};
+use crate::app::{fit_camera, show_progress_bar, Person, ViewerData};
+use crate::graph_render::camera::Camera;
+use crate::graph_render::export::{pick_file, save_file, GRAPH};
+use crate::graph_render::{NodeFilter, RenderedGraph};
+use crate::graph_storage::export_graph_file;
+use crate::history::{History, Restore};
 use crate::thread::JoinHandle;
-use crate::threading::{spawn_cancelable, status_pipe, MyRwLock, StatusReader};
+use crate::threading::{AlgoHandle, Cancelable, CancelableError, MyRwLock};
 use crate::ui;
-use crate::ui::modal::ModalWriter;
+use crate::ui::class::ClassSection;
+use crate::ui::infos::InfosSection;
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
+use crate::ui::path::PathSection;
+use crate::ui::tabs::TabCamera;
 use crate::ui::NodeStats;
-use crate::{log_progress, thread};
+use crate::{log, log_progress, thread};
+use ahash::{AHashMap, AHashSet};
+use derivative::Derivative;
 use egui::{CollapsingHeader, Ui};
+use egui_extras::{Column, TableBuilder};
 use forceatlas2::{Layout, Node, Settings, VecN};
 use graph_format::Point;
-use parking_lot::{Mutex, RwLock};
+use itertools::Itertools;
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::{Receiver, RecvError, Sender, TryRecvError};
 use std::sync::{mpsc, Arc};
@@ -18,23 +37,40 @@ use std::time::Duration;
 
 pub struct ForceAtlasRenderDone;
 
-#[derive(Default)]
+#[derive(Derivative)]
+#[derivative(Default)]
 pub struct AlgosSection {
     louvain_precision: f32,
-    louvain_state: Option<LouvainState>,
+    louvain_state: Option<AlgoHandle<()>>,
+    /// Undo or redo being applied.
+    history_thread: Option<AlgoHandle<Restore>>,
     force_atlas_state: ForceAtlasState,
-}
-
-pub struct LouvainState {
-    thread: JoinHandle<()>,
-    status_rx: StatusReader,
+    diameter_thread: Option<AlgoHandle<Option<Diameter>>>,
+    #[derivative(Default(value = "DEFAULT_DAMPING"))]
+    pagerank_damping: f32,
+    pagerank_thread: Option<AlgoHandle<Vec<f32>>>,
+    /// Highest-ranked nodes, in the order of the table.
+    pagerank_top: Vec<usize>,
+    /// Column the table is sorted by, and whether the order is reversed.
+    pagerank_sort: (RankColumn, bool),
+    /// Partitions found by the last sweep over the precision.
+    sweep_results: Arc<Mutex<Vec<SweepResult>>>,
+    /// Communities found by each pass of the last run, from the finest to the coarsest.
+    louvain_levels: Arc<Mutex<Vec<LouvainLevel>>>,
+    /// Level the classes come from, clamped to the last one once a run is over.
+    louvain_level: usize,
+    /// Writes the graph file saved by "Save layout", along with where to save it.
+    save_thread: Option<(AlgoHandle<Vec<u8>>, PathBuf)>,
 }
 
 pub struct ForceAtlasThread {
     status_tx: Sender<bool>,
+    /// Signaled by the layout thread once it has stopped iterating after a pause.
+    paused_rx: Receiver<()>,
 }
 
 impl AlgosSection {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn show(
         &mut self,
         data: &Arc<MyRwLock<ViewerData>>,
@@ -42,7 +78,27 @@ impl AlgosSection {
         graph: &Arc<MyRwLock<RenderedGraph>>,
         stats: &Arc<MyRwLock<NodeStats>>,
         modal: &impl ModalWriter,
+        infos: &mut InfosSection,
+        path: &mut PathSection,
+        history: &mut History,
+        camera: &mut TabCamera,
     ) {
+        let selected = infos.infos_current;
+        if let Some(thr) = self
+            .force_atlas_state
+            .bake_thread
+            .take_if(|thr| thr.is_finished())
+        {
+            if let Ok((layout_thr, mut fit)) = thr.join() {
+                fit.set_window_size(camera.camera_default.size);
+                camera.camera_default = fit;
+                // the layout may have been started over while the positions were being copied
+                if let Some((_, _, thr @ None)) = &mut self.force_atlas_state.data {
+                    *thr = Some(layout_thr);
+                }
+            }
+        }
+
         CollapsingHeader::new(t!("Algorithms"))
             .id_salt("algos")
             .default_open(false)
@@ -51,124 +107,69 @@ impl AlgosSection {
                     ui.label(t!("large_graph_warning"));
                     ui.separator();
                 }
-                if ui
-                    .add_enabled(
-                        self.louvain_state.is_none(),
-                        egui::Button::new("Run Louvain community detection"),
-                    )
-                    .clicked()
-                {
-                    let (status_tx, status_rx) = status_pipe(ui.ctx());
-                    let data = data.clone();
-                    let graph = graph.clone();
-                    const ITERATIONS: usize = 100;
-                    let precision = self.louvain_precision;
-                    let stats = stats.clone();
-                    let thr = spawn_cancelable(modal.clone(), move || {
-                        let mut louvain =
-                            crate::algorithms::louvain::Graph::new(&data.read().persons);
-                        for i in 0..ITERATIONS {
-                            log_progress!(status_tx, i, ITERATIONS);
-                            let old_stats = louvain.stats();
-                            louvain = louvain.next(precision);
-                            let new_stats = louvain.stats();
-                            if old_stats == new_stats {
-                                break;
-                            }
-                        }
-                        log_progress!(status_tx, ITERATIONS, ITERATIONS);
 
-                        let data_ = data.read();
-                        let mut nodes = data_.persons.as_ref().clone();
-                        for n in &mut nodes {
-                            n.modularity_class = u16::MAX;
-                        }
-                        drop(data_);
-
-                        use crate::app::ModularityClass;
-                        use crate::ui;
-                        use colourado_iter::{ColorPalette, PaletteType};
-                        use graph_format::Color3b;
-                        let palette =
-                            ColorPalette::new(PaletteType::Random, false, &mut rand::thread_rng());
-                        let mut classes = Vec::new();
-
-                        for (i, (comm, color)) in louvain.nodes.iter().zip(palette).enumerate() {
-                            for user in comm.payload.as_ref().unwrap() {
-                                nodes[user.0].modularity_class = i as u16;
-                            }
-                            let [r, g, b] = color.to_array();
-                            classes.push(ModularityClass::new(
-                                Color3b {
-                                    r: (r * 255.0) as u8,
-                                    g: (g * 255.0) as u8,
-                                    b: (b * 255.0) as u8,
-                                },
-                                (i + 1) as u16,
-                            ));
-                        }
+                self.show_history(data, ui, graph, stats, modal, history);
 
-                        let task = ui::rerender_graph(&nodes);
+                self.show_louvain(data, ui, graph, stats, modal, history);
 
-                        {
-                            let mut lock = data.write();
-                            lock.persons = Arc::new(nodes);
-                            lock.modularity_classes = classes;
+                ui.separator();
 
-                            let mut graph = graph.write();
-                            *stats.write() = NodeStats::new(&lock, graph.node_filter);
-                            graph.tasks.push_back(task);
-                        }
+                self.show_diameter(data, ui, path, modal);
 
-                        Ok(())
-                    });
-                    self.louvain_state = Some(LouvainState {
-                        thread: thr,
-                        status_rx,
-                    });
-                }
+                ui.separator();
 
-                if let Some(ref mut state) = self.louvain_state {
-                    if state.thread.is_finished() {
-                        self.louvain_state = None;
-                    } else {
-                        state.status_rx.recv();
-                        if ui
-                            .horizontal(|ui| {
-                                ui.spinner();
-                                let cancel = ui.button("✖").clicked();
-                                show_progress_bar(ui, &state.status_rx);
-                                cancel
-                            })
-                            .inner
-                        {
-                            self.louvain_state = None;
-                        };
-                    }
-                } else {
-                    ui.horizontal(|ui| {
-                        ui.label(t!("Precision:"));
-                        ui.add(
-                            egui::Slider::new(&mut self.louvain_precision, 1e-7..=1.0)
-                                .logarithmic(true)
-                                .custom_formatter(|n, _| format!("{:.1e}", n))
-                                .text(""),
-                        )
-                        .changed();
-                    });
-                }
+                self.show_pagerank(data, ui, modal, infos);
 
                 ui.separator();
 
-                if ui
-                    .checkbox(&mut self.force_atlas_state.running, "ForceAtlas2")
-                    .changed()
-                {
-                    if let Some((_, Some(thr))) = &self.force_atlas_state.data {
+                let toggled = ui
+                    .horizontal(|ui| {
+                        let baking = self.force_atlas_state.bake_thread.is_some();
+                        let toggled = ui
+                            .add_enabled(
+                                !baking,
+                                egui::Checkbox::new(
+                                    &mut self.force_atlas_state.running,
+                                    "ForceAtlas2",
+                                ),
+                            )
+                            .changed();
+                        if baking {
+                            ui::spinner(ui);
+                        }
+                        ui.checkbox(
+                            &mut self.force_atlas_state.filtered_only,
+                            t!("Visible nodes only"),
+                        )
+                        .on_hover_text(t!(
+                            "Only lay out the nodes that pass the filters, the others stay in place"
+                        ));
+                        toggled
+                    })
+                    .inner;
+                if toggled {
+                    if self.force_atlas_state.running {
+                        history.push(&data.read());
+                    }
+                    if let Some((_, _, Some(thr))) = &self.force_atlas_state.data {
                         thr.status_tx
                             .send(self.force_atlas_state.running)
                             .expect("Failed to send pause signal");
                     }
+                    if !self.force_atlas_state.running {
+                        self.bake_layout(data, graph, stats);
+                    }
+                }
+
+                // the layout is started over when the set of nodes it covers changes
+                let filter = self
+                    .force_atlas_state
+                    .filtered_only
+                    .then(|| graph.read().node_filter);
+                if filter != self.force_atlas_state.layout_filter {
+                    self.force_atlas_state.layout_filter = filter;
+                    self.force_atlas_state.data = None;
+                    self.force_atlas_state.render_thread = None;
                 }
 
                 egui::Grid::new("#forceatlas").show(ui, |ui| {
@@ -255,30 +256,55 @@ impl AlgosSection {
                     }
                 });
 
+                self.show_pins(&data.read(), ui, selected);
+
+                self.show_save_layout(data, ui, modal);
+
                 if self.force_atlas_state.running {
-                    ui.spinner();
+                    ui::spinner(ui);
 
-                    let layout = self
-                        .force_atlas_state
-                        .data
-                        .get_or_insert_with(|| {
+                    let (layout, subset, _) =
+                        self.force_atlas_state.data.get_or_insert_with(|| {
                             const UPD_PER_SEC: usize = 60;
 
                             let data = data.read();
+                            let subset = Arc::new(LayoutNodes::new(&data.persons, filter));
+                            let node = |p: &Person| Node {
+                                pos: VecN(p.position.to_array()),
+                                ..Default::default()
+                            };
+                            let nodes = match subset.ids {
+                                Some(ref ids) => {
+                                    ids.iter().map(|&i| node(&data.persons[i])).collect()
+                                }
+                                None => data.persons.iter().map(node).collect(),
+                            };
+                            // the links of a meta-graph pull as hard as the links they stand for
+                            let weight = |a, b| {
+                                data.meta
+                                    .as_ref()
+                                    .map_or(1.0, |meta| meta.link_count(a, b) as f32)
+                            };
+                            let edges = data
+                                .persons
+                                .iter()
+                                .get_edges()
+                                .filter_map(|(a, b)| {
+                                    let ends = (subset.layout_index(a)?, subset.layout_index(b)?);
+                                    Some((ends, weight(a, b)))
+                                })
+                                .collect();
                             let layout = Arc::new(RwLock::new(Layout::<f32, 2>::from_positioned(
                                 self.force_atlas_state.settings.clone(),
-                                data.persons
-                                    .iter()
-                                    .map(|p| Node {
-                                        pos: VecN(p.position.to_array()),
-                                        ..Default::default()
-                                    })
-                                    .collect(),
-                                data.persons.iter().get_edges().map(|e| (e, 1.0)).collect(),
+                                nodes,
+                                edges,
                             )));
                             let (status_tx, status_rx) = mpsc::channel();
+                            let (paused_tx, paused_rx) = mpsc::channel();
                             let layout_thr = layout.clone();
+                            let subset_thr = subset.clone();
                             let settings_thr = self.force_atlas_state.new_settings.clone();
+                            let pinned_thr = self.force_atlas_state.pinned.clone();
 
                             thread::spawn(move || {
                                 loop {
@@ -286,8 +312,24 @@ impl AlgosSection {
                                         {
                                             let mut layout = layout_thr.write();
 
+                                            // pinned nodes are put back where they were
+                                            // after each iteration
+                                            let pinned = pinned_thr
+                                                .lock()
+                                                .iter()
+                                                .filter_map(|&i| subset_thr.layout_index(i))
+                                                .map(|i| {
+                                                    let pos = &layout.nodes[i].pos;
+                                                    (i, [pos[0], pos[1]])
+                                                })
+                                                .collect_vec();
+
                                             layout.iteration();
 
+                                            for (i, pos) in pinned {
+                                                layout.nodes[i].pos = VecN(pos);
+                                            }
+
                                             if settings_thr
                                                 .0
                                                 .load(std::sync::atomic::Ordering::Acquire)
@@ -302,9 +344,13 @@ impl AlgosSection {
 
                                         // check if the layout has been paused
                                         match status_rx.try_recv() {
-                                            Ok(true) => {}                             // continue
-                                            Ok(false) => break,                        // pause
-                                            Err(TryRecvError::Empty) => {}             // no change
+                                            Ok(true) => {} // continue
+                                            Ok(false) => {
+                                                // pause
+                                                let _ = paused_tx.send(());
+                                                break;
+                                            }
+                                            Err(TryRecvError::Empty) => {} // no change
                                             Err(TryRecvError::Disconnected) => return, // tab closed
                                         }
 
@@ -315,17 +361,26 @@ impl AlgosSection {
                                     loop {
                                         // wait for resume
                                         match status_rx.recv() {
-                                            Ok(true) => break,        // resume
-                                            Ok(false) => {}           // keep paused
+                                            Ok(true) => break, // resume
+                                            Ok(false) => {
+                                                // keep paused
+                                                let _ = paused_tx.send(());
+                                            }
                                             Err(RecvError) => return, // tab closed
                                         }
                                     }
                                 }
                             });
-                            (layout, Some(ForceAtlasThread { status_tx }))
-                        })
-                        .0
-                        .clone();
+                            (
+                                layout,
+                                subset,
+                                Some(ForceAtlasThread {
+                                    status_tx,
+                                    paused_rx,
+                                }),
+                            )
+                        });
+                    let (layout, subset) = (layout.clone(), subset.clone());
 
                     let (s, r, _t) =
                         self.force_atlas_state.render_thread.get_or_insert_with(|| {
@@ -340,14 +395,20 @@ impl AlgosSection {
                                 result_rx,
                                 thread::spawn(move || {
                                     while let Ok(()) = request_rx.recv() {
-                                        let mut persons = thr_data.read().persons.as_ref().clone();
-                                        for (person, node) in
-                                            persons.iter_mut().zip(layout.read().nodes.iter())
-                                        {
-                                            person.position = Point::new(node.pos[0], node.pos[1]);
+                                        let (mut persons, directed) = {
+                                            let data = thr_data.read();
+                                            (data.persons.as_ref().clone(), data.directed)
+                                        };
+                                        for (i, node) in layout.read().nodes.iter().enumerate() {
+                                            persons[subset.graph_id(i)].position =
+                                                Point::new(node.pos[0], node.pos[1]);
                                         }
 
-                                        let closure = ui::rerender_graph(&persons);
+                                        let curvature = graph.read().edge_curvature;
+                                        let sort_edges = graph.read().sort_edges;
+                                        let closure = ui::rerender_graph(
+                                            &persons, directed, curvature, sort_edges,
+                                        );
 
                                         {
                                             let mut data_w = thr_data.write();
@@ -371,7 +432,11 @@ impl AlgosSection {
                             )
                         });
 
-                    if let Ok(ForceAtlasRenderDone) = r.try_recv() {
+                    // the next copy waits for the edges of the previous one to be streamed in,
+                    // otherwise the last ones would never be updated
+                    if graph.read().tasks.is_empty()
+                        && let Ok(ForceAtlasRenderDone) = r.try_recv()
+                    {
                         s.send(()).unwrap();
                     }
                 }
@@ -379,31 +444,912 @@ impl AlgosSection {
     }
 }
 
+impl AlgosSection {
+    fn show_history(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+        history: &mut History,
+    ) {
+        if let Some(thr) = self.history_thread.take_if(|thr| thr.is_finished()) {
+            if let Some(restore) = thr.join() {
+                history.finish(restore);
+            }
+        }
+
+        // the running algorithms would overwrite the restored state right away
+        let idle = self.louvain_state.is_none()
+            && self.history_thread.is_none()
+            && !self.force_atlas_state.running
+            && self.force_atlas_state.bake_thread.is_none();
+        let (undo, redo) = ui
+            .horizontal(|ui| {
+                (
+                    ui.add_enabled(idle && history.can_undo(), egui::Button::new(t!("⟲ Undo")))
+                        .clicked(),
+                    ui.add_enabled(idle && history.can_redo(), egui::Button::new(t!("⟳ Redo")))
+                        .clicked(),
+                )
+            })
+            .inner;
+        ui.separator();
+        let restore = if undo {
+            history.undo()
+        } else if redo {
+            history.redo()
+        } else {
+            None
+        };
+        let Some(mut restore) = restore else {
+            return;
+        };
+
+        // the paused layout starts again from the restored positions
+        self.force_atlas_state.data = None;
+        self.force_atlas_state.render_thread = None;
+        // the levels of the last detection don't match the restored classes
+        self.louvain_levels.lock().clear();
+
+        let data = data.clone();
+        let graph = graph.clone();
+        let stats = stats.clone();
+        self.history_thread = Some(AlgoHandle::spawn(ui.ctx(), modal.clone(), move |_, _| {
+            let (persons, directed) = {
+                let data = data.read();
+                (restore.prepare(&data), data.directed)
+            };
+            let (curvature, sort_edges) = {
+                let graph = graph.read();
+                (graph.edge_curvature, graph.sort_edges)
+            };
+            let task = ui::rerender_graph(&persons, directed, curvature, sort_edges);
+
+            let mut data = data.write();
+            restore.swap(&mut data, persons);
+            let mut graph = graph.write();
+            *stats.write() = NodeStats::new(&data, graph.node_filter);
+            graph.tasks.push_back(task);
+            Ok(restore)
+        }));
+    }
+
+    /// Writes the final positions of the paused layout back into the graph.
+    ///
+    /// The render thread only copies the positions from time to time, while the layout keeps
+    /// running, so the graph would otherwise be left with whatever copy was made last.
+    fn bake_layout(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        stats: &Arc<MyRwLock<NodeStats>>,
+    ) {
+        let Some((layout, subset, thr @ Some(_))) = &mut self.force_atlas_state.data else {
+            return;
+        };
+        let (layout, subset, layout_thr) = (layout.clone(), subset.clone(), thr.take().unwrap());
+        // dropping the channels stops the render thread after its current copy
+        let render_thr = self
+            .force_atlas_state
+            .render_thread
+            .take()
+            .map(|(_, _, thr)| thr);
+        let data = data.clone();
+        let graph = graph.clone();
+        let stats = stats.clone();
+
+        self.force_atlas_state.bake_thread = Some(thread::spawn(move || {
+            if let Some(thr) = render_thr {
+                let _ = thr.join();
+            }
+            let _ = layout_thr.paused_rx.recv();
+
+            let (persons, directed) = {
+                let mut data = data.write();
+                let persons = Arc::make_mut(&mut data.persons);
+                for (i, node) in layout.read().nodes.iter().enumerate() {
+                    persons[subset.graph_id(i)].position = Point::new(node.pos[0], node.pos[1]);
+                }
+                (data.persons.clone(), data.directed)
+            };
+
+            let (curvature, sort_edges) = {
+                let graph = graph.read();
+                (graph.edge_curvature, graph.sort_edges)
+            };
+            let task = ui::rerender_graph(&persons, directed, curvature, sort_edges);
+            {
+                let data = data.read();
+                let mut graph = graph.write();
+                *stats.write() = NodeStats::new(&data, graph.node_filter);
+                graph.tasks.push_back(task);
+            }
+
+            (layout_thr, fit_camera(&persons))
+        }));
+    }
+
+    /// Nodes kept in place by the layout.
+    pub(crate) fn pinned_nodes(&self) -> MutexGuard<'_, AHashSet<usize>> {
+        self.force_atlas_state.pinned.lock()
+    }
+
+    /// Starts dragging a node, if the move mode is on while the layout runs, or stops dragging.
+    pub(crate) fn grab_node(&mut self, id: Option<usize>) {
+        let state = &mut self.force_atlas_state;
+        state.grabbed = id.filter(|_| state.move_mode && state.running);
+    }
+
+    /// Node being dragged in the view, if the layout is still running.
+    pub(crate) fn grabbed_node(&self) -> Option<usize> {
+        self.force_atlas_state
+            .grabbed
+            .filter(|_| self.force_atlas_state.running)
+    }
+
+    /// Moves a node of the running layout to `pos`, and pins it there.
+    pub(crate) fn move_node(&self, data: &mut ViewerData, id: usize, pos: Point) {
+        self.force_atlas_state.pinned.lock().insert(id);
+        // the layout thread puts the pinned nodes back where they are before each iteration
+        if let Some((layout, subset, _)) = &self.force_atlas_state.data
+            && let Some(i) = subset.layout_index(id)
+        {
+            layout.write().nodes[i].pos = VecN(pos.to_array());
+        }
+        Arc::make_mut(&mut data.persons)[id].position = pos;
+    }
+
+    /// Whether ForceAtlas2 is moving the nodes.
+    pub(crate) fn layout_running(&self) -> bool {
+        self.force_atlas_state.running
+    }
+
+    /// Runs the Louvain community detection in the background, unless it's already running.
+    pub(crate) fn run_louvain(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ctx: &egui::Context,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+        history: &mut History,
+    ) {
+        if self.louvain_state.is_some() || self.history_thread.is_some() {
+            return;
+        }
+        history.begin(&data.read());
+        let data = data.clone();
+        let graph = graph.clone();
+        let precision = self.louvain_precision;
+        let stats = stats.clone();
+        let levels = self.louvain_levels.clone();
+        levels.lock().clear();
+        self.louvain_level = usize::MAX;
+        let thr = AlgoHandle::spawn(ctx, modal.clone(), move |status_tx, cancel| {
+            let persons = data.read().persons.clone();
+            let found = louvain::graph(&persons[..]).run_levels(
+                precision,
+                persons.len(),
+                |i| -> Cancelable<()> {
+                    cancel.check()?;
+                    log_progress!(status_tx, i, louvain::ITERATIONS);
+                    Ok(())
+                },
+            )?;
+            log_progress!(status_tx, louvain::ITERATIONS, louvain::ITERATIONS);
+
+            let found = found
+                .into_iter()
+                .map(|level| LouvainLevel {
+                    modularity: louvain::modularity(&persons[..], &level.membership),
+                    level,
+                })
+                .collect_vec();
+            let last = found.last().unwrap();
+            apply_partition(
+                &data,
+                &graph,
+                &stats,
+                last.level.membership.clone(),
+                last.level.count,
+                last.modularity,
+            );
+            *levels.lock() = found;
+
+            Ok(())
+        });
+        self.louvain_state = Some(thr);
+    }
+
+    fn show_louvain(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+        history: &mut History,
+    ) {
+        let (run, sweep) = ui
+            .horizontal(|ui| {
+                let idle = self.louvain_state.is_none() && self.history_thread.is_none();
+                (
+                    ui.add_enabled(
+                        idle,
+                        egui::Button::new(t!("Run Louvain community detection")),
+                    )
+                    .clicked(),
+                    ui.add_enabled(idle, egui::Button::new(t!("Sweep")))
+                        .on_hover_text(t!(
+                            "Run it with %{count} precisions spread over the range of the slider, to compare the results",
+                            count = SWEEP_STEPS
+                        ))
+                        .clicked(),
+                )
+            })
+            .inner;
+
+        if run {
+            self.run_louvain(data, ui.ctx(), graph, stats, modal, history);
+        }
+
+        if sweep {
+            let data = data.clone();
+            let results = self.sweep_results.clone();
+            results.lock().clear();
+            let thr = AlgoHandle::spawn(ui.ctx(), modal.clone(), move |status_tx, cancel| {
+                let persons = data.read().persons.clone();
+                for (step, precision) in sweep_precisions().enumerate() {
+                    let communities =
+                        louvain::graph(&persons[..]).run(precision, |i| -> Cancelable<()> {
+                            cancel.check()?;
+                            log_progress!(
+                                status_tx,
+                                step * louvain::ITERATIONS + i,
+                                SWEEP_STEPS * louvain::ITERATIONS
+                            );
+                            Ok(())
+                        })?;
+                    let membership = communities.membership(persons.len());
+                    results.lock().push(SweepResult {
+                        precision,
+                        modularity: louvain::modularity(&persons[..], &membership),
+                        class_count: communities.nodes.len(),
+                        membership,
+                    });
+                }
+                Ok(())
+            });
+            self.louvain_state = Some(thr);
+        }
+
+        if let Some(thr) = self.louvain_state.take_if(|thr| thr.is_finished()) {
+            // a canceled run leaves the graph as it was, with nothing to undo
+            history.end(thr.join().is_some());
+        }
+        if let Some(ref mut state) = self.louvain_state {
+            show_running(ui, state);
+        } else {
+            ui.horizontal(|ui| {
+                ui.label(t!("Precision:"));
+                ui.add(
+                    egui::Slider::new(&mut self.louvain_precision, PRECISION_RANGE)
+                        .logarithmic(true)
+                        .custom_formatter(|n, _| format!("{:.1e}", n))
+                        .text(""),
+                )
+                .changed();
+            });
+            self.show_louvain_levels(data, ui, graph, stats, modal, history);
+        }
+
+        let modularity = data.read().modularity;
+        if let Some(modularity) = modularity {
+            ui.horizontal(|ui| {
+                ui.label(t!("Modularity: %{q}", q = format!("{:.3}", modularity)));
+                if ui
+                    .button(t!("Re-shuffle colors"))
+                    .on_hover_text(t!(
+                        "The same communities otherwise always get the same colors."
+                    ))
+                    .clicked()
+                {
+                    let mut data = data.write();
+                    let count = data.modularity_classes.len();
+                    let colors = louvain::palette_colors(count, &mut rand::thread_rng());
+                    for (class, color) in data.modularity_classes.iter_mut().zip(colors) {
+                        class.color = color;
+                    }
+                }
+            });
+        }
+        let merged = data.read().merged_communities;
+        if let Some(merged) = merged {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                t!(
+                    "⚠ The %{count} smallest communities were merged into the last class, as there are more than can be drawn",
+                    count = merged
+                ),
+            );
+        }
+
+        let results = self.sweep_results.lock();
+        if results.is_empty() {
+            return;
+        }
+        let mut apply = None;
+        egui::Grid::new("#louvain_sweep")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label(t!("Precision"));
+                ui.label(t!("Modularity"));
+                ui.label(t!("Classes"));
+                ui.end_row();
+                for (i, result) in results.iter().enumerate() {
+                    ui.label(format!("{:.1e}", result.precision));
+                    ui.label(format!("{:.3}", result.modularity));
+                    ui.label(format!("{}", result.class_count));
+                    if ui
+                        .add_enabled(
+                            self.louvain_state.is_none() && self.history_thread.is_none(),
+                            egui::Button::new(t!("Apply")),
+                        )
+                        .clicked()
+                    {
+                        apply = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(i) = apply {
+            // the classes no longer come from the levels of the last run
+            self.louvain_levels.lock().clear();
+            history.begin(&data.read());
+            let data = data.clone();
+            let graph = graph.clone();
+            let stats = stats.clone();
+            let result = &results[i];
+            let (membership, count, modularity) = (
+                result.membership.clone(),
+                result.class_count,
+                result.modularity,
+            );
+            let thr = AlgoHandle::spawn(ui.ctx(), modal.clone(), move |_, _| {
+                apply_partition(&data, &graph, &stats, membership, count, modularity);
+                Ok(())
+            });
+            self.louvain_state = Some(thr);
+        }
+    }
+
+    /// Slider to switch between the levels of the last run, which are already computed.
+    fn show_louvain_levels(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+        history: &mut History,
+    ) {
+        let levels = self.louvain_levels.lock();
+        if levels.len() < 2 {
+            return;
+        }
+        self.louvain_level = self.louvain_level.min(levels.len() - 1);
+        let changed = ui
+            .horizontal(|ui| {
+                ui.label(t!("Level:"));
+                ui.add_enabled(
+                    self.history_thread.is_none(),
+                    egui::Slider::new(&mut self.louvain_level, 0..=levels.len() - 1)
+                        .custom_formatter(|n, _| format!("{}", n as usize + 1))
+                        .text(t!(
+                            "%{count} communities",
+                            count = levels[self.louvain_level].level.count
+                        )),
+                )
+                .on_hover_text(t!(
+                    "Each pass of the algorithm merges the communities of the previous one."
+                ))
+                .changed()
+            })
+            .inner;
+        ui.label(t!(
+            "Communities per level: %{counts}",
+            counts = levels.iter().map(|l| l.level.count).join(" → ")
+        ));
+
+        if changed {
+            history.begin(&data.read());
+            let data = data.clone();
+            let graph = graph.clone();
+            let stats = stats.clone();
+            let level = &levels[self.louvain_level];
+            let (membership, count, modularity) = (
+                level.level.membership.clone(),
+                level.level.count,
+                level.modularity,
+            );
+            let thr = AlgoHandle::spawn(ui.ctx(), modal.clone(), move |_, _| {
+                apply_partition(&data, &graph, &stats, membership, count, modularity);
+                Ok(())
+            });
+            self.louvain_state = Some(thr);
+        }
+    }
+
+    fn show_diameter(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        path: &mut PathSection,
+        modal: &impl ModalWriter,
+    ) {
+        if let Some(thr) = self.diameter_thread.take_if(|thr| thr.is_finished()) {
+            data.write().diameter = thr.join().flatten();
+        }
+
+        let data_r = data.read();
+        let Some(diameter) = data_r.diameter else {
+            if ui
+                .add_enabled(
+                    self.diameter_thread.is_none(),
+                    egui::Button::new(t!("Estimate diameter")),
+                )
+                .on_hover_text(t!("Double-sweep approximation: two breadth-first searches from the node with the most friends. The result is a lower bound of the actual diameter."))
+                .clicked()
+            {
+                let data = data.clone();
+                self.diameter_thread = Some(AlgoHandle::spawn(
+                    ui.ctx(),
+                    modal.clone(),
+                    move |status_tx, cancel| {
+                        estimate_graph_diameter(status_tx, &data.read().persons[..], cancel)
+                    },
+                ));
+            }
+            if let Some(ref mut thr) = self.diameter_thread {
+                show_running(ui, thr);
+            }
+            return;
+        };
+
+        let (a, b) = diameter.endpoints;
+        ui.label(t!(
+            "Diameter: %{length} (at most %{upper})",
+            length = diameter.length,
+            upper = diameter.upper_bound()
+        ));
+        ui.label(t!(
+            "Between %{a} and %{b}",
+            a = data_r.persons[a].name,
+            b = data_r.persons[b].name
+        ));
+        if ui
+            .add_enabled(diameter.length > 0, egui::Button::new(t!("Show this path")))
+            .clicked()
+        {
+            path.path_settings.path_src = Some(a);
+            path.path_settings.path_dest = Some(b);
+            path.path_dirty = true;
+        }
+    }
+
+    fn show_pagerank(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+        infos: &mut InfosSection,
+    ) {
+        if let Some(thr) = self.pagerank_thread.take_if(|thr| thr.is_finished())
+            && let Some(scores) = thr.join()
+        {
+            self.pagerank_top = (0..scores.len())
+                .k_largest_by(TOP_RANKED, |&a, &b| scores[a].total_cmp(&scores[b]))
+                .collect();
+            data.write().pagerank = Some(Arc::new(scores));
+            self.sort_pagerank(&data.read());
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.pagerank_thread.is_none(),
+                    egui::Button::new(t!("Compute PageRank")),
+                )
+                .on_hover_text(t!("Importance of each node, from how likely a random walk along the links is to end on it. Once computed, it can be used to color the nodes."))
+                .clicked()
+            {
+                let data = data.clone();
+                let damping = self.pagerank_damping;
+                self.pagerank_thread = Some(AlgoHandle::spawn(
+                    ui.ctx(),
+                    modal.clone(),
+                    move |status_tx, cancel| {
+                        pagerank(status_tx, &data.read().persons[..], damping, cancel)
+                    },
+                ));
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.pagerank_damping)
+                    .range(0.0..=0.99)
+                    .speed(0.01)
+                    .prefix(t!("Damping: ")),
+            )
+            .on_hover_text(t!(
+                "Probability of following a link rather than jumping to a random node"
+            ));
+        });
+        if let Some(ref mut thr) = self.pagerank_thread {
+            show_running(ui, thr);
+        }
+
+        let data_r = data.read();
+        let Some(scores) = data_r.pagerank.as_deref() else {
+            return;
+        };
+        let (sorted_by, reversed) = self.pagerank_sort;
+        let mut sort = None;
+        let mut clicked = None;
+        let mut heading = |ui: &mut Ui, column: RankColumn, label: Cow<'_, str>| {
+            let label = match (sorted_by == column, reversed) {
+                (false, _) => label.into_owned(),
+                (true, false) => format!("{label} ⏷"),
+                (true, true) => format!("{label} ⏶"),
+            };
+            if ui.selectable_label(sorted_by == column, label).clicked() {
+                sort = Some(column);
+            }
+        };
+        TableBuilder::new(ui)
+            .striped(true)
+            .max_scroll_height(240.0)
+            .column(Column::exact(160.0).clip(true))
+            .column(Column::exact(60.0))
+            .column(Column::exact(80.0))
+            .header(18.0, |mut header| {
+                header.col(|ui| heading(ui, RankColumn::Name, t!("Name")));
+                header.col(|ui| heading(ui, RankColumn::Degree, t!("Degree")));
+                header.col(|ui| heading(ui, RankColumn::Score, t!("PageRank")));
+            })
+            .body(|body| {
+                body.rows(16.0, self.pagerank_top.len(), |mut row| {
+                    let id = self.pagerank_top[row.index()];
+                    let person = &data_r.persons[id];
+                    row.col(|ui| {
+                        if ui
+                            .selectable_label(infos.infos_current == Some(id), person.name)
+                            .clicked()
+                        {
+                            clicked = Some(id);
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(person.neighbors.len().to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.2e}", scores[id]));
+                    });
+                });
+            });
+        drop(data_r);
+
+        if let Some(column) = sort {
+            self.pagerank_sort = (column, column == sorted_by && !reversed);
+            self.sort_pagerank(&data.read());
+        }
+        if let Some(id) = clicked {
+            infos.set_infos_current(Some(id));
+        }
+    }
+
+    fn sort_pagerank(&mut self, data: &ViewerData) {
+        let Some(scores) = data.pagerank.as_deref() else {
+            return;
+        };
+        let persons = &data.persons;
+        let top = &mut self.pagerank_top;
+        match self.pagerank_sort.0 {
+            RankColumn::Score => top.sort_by(|&a, &b| scores[b].total_cmp(&scores[a])),
+            RankColumn::Name => top.sort_by_key(|&i| persons[i].name),
+            RankColumn::Degree => top.sort_by_key(|&i| Reverse(persons[i].neighbors.len())),
+        }
+        if self.pagerank_sort.1 {
+            top.reverse();
+        }
+    }
+
+    /// Saves the graph with its current layout to a graph file, so that it can be loaded again.
+    fn show_save_layout(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        // the file can only be downloaded from the main thread in the browser
+        if let Some((thr, path)) = self.save_thread.take_if(|(thr, _)| thr.is_finished())
+            && let Some(file) = thr.join()
+            && let Err(e) = save_file(&file, &path, GRAPH)
+        {
+            modal.send(ModalInfo {
+                title: t!("Error").to_string(),
+                kind: ModalKind::Error,
+                body: e.to_string().into(),
+            });
+        }
+
+        if let Some((thr, _)) = &mut self.save_thread {
+            show_running(ui, thr);
+            return;
+        }
+
+        // the positions are only copied into the graph once the layout is paused
+        let idle = !self.force_atlas_state.running && self.force_atlas_state.bake_thread.is_none();
+        if ui
+            .add_enabled(idle, egui::Button::new(t!("Save layout")))
+            .on_hover_text(t!(
+                "Save the graph with the current positions and classes of the nodes, to open it again later"
+            ))
+            .on_disabled_hover_text(t!("Pause the layout to save it"))
+            .clicked()
+            && let Some(path) = pick_file(GRAPH)
+        {
+            let data = data.clone();
+            let thr = AlgoHandle::spawn(ui.ctx(), modal.clone(), move |status_tx, _| {
+                log!(status_tx, t!("Writing the graph file"));
+                export_graph_file(&data.read()).map_err(|e| CancelableError::Other(e.into()))
+            });
+            self.save_thread = Some((thr, path));
+        }
+    }
+
+    /// Controls for the nodes kept in place by the layout.
+    fn show_pins(&mut self, data: &ViewerData, ui: &mut Ui, selected: Option<usize>) {
+        let mut pinned = self.force_atlas_state.pinned.lock();
+        ui.horizontal(|ui| {
+            ui.label(t!("Pinned nodes: %{count}", count = pinned.len()));
+            if ui
+                .add_enabled(!pinned.is_empty(), egui::Button::new(t!("Unpin all")))
+                .clicked()
+            {
+                pinned.clear();
+            }
+        });
+
+        ui.checkbox(
+            &mut self.force_atlas_state.move_mode,
+            t!("Move the selected node"),
+        )
+        .on_hover_text(t!(
+            "While the layout runs, drag the selected node to move it, which pins it"
+        ));
+
+        if let Some(id) = selected {
+            if pinned.contains(&id) {
+                if ui.button(t!("Unpin the selected node")).clicked() {
+                    pinned.remove(&id);
+                }
+            } else if ui.button(t!("Pin the selected node")).clicked() {
+                pinned.insert(id);
+            }
+        }
+
+        let class_members = |class: usize| {
+            data.persons
+                .iter()
+                .enumerate()
+                .filter(move |(_, p)| p.modularity_class as usize == class)
+                .map(|(i, _)| i)
+        };
+        ui.horizontal(|ui| {
+            for (pin, label) in [(true, t!("Pin a class")), (false, t!("Unpin a class"))] {
+                egui::ComboBox::from_id_salt(("#pin_class", pin))
+                    .selected_text(label)
+                    .show_ui(ui, |ui| {
+                        for (id, cl) in data.modularity_classes.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                ClassSection::class_circle(ui, cl);
+                                if ui.selectable_label(false, format!("{}", id)).clicked() {
+                                    if pin {
+                                        pinned.extend(class_members(id));
+                                    } else {
+                                        for i in class_members(id) {
+                                            pinned.remove(&i);
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    });
+            }
+        });
+    }
+}
+
+/// Spinner, cancel button and progress bar of an algorithm running in the background.
+pub(crate) fn show_running<T>(ui: &mut Ui, handle: &mut AlgoHandle<T>) {
+    handle.status_rx.recv();
+    ui.horizontal(|ui| {
+        ui::spinner(ui);
+        if ui
+            .add_enabled(!handle.is_canceled(), egui::Button::new("✖"))
+            .on_hover_text(t!("Cancel"))
+            .clicked()
+        {
+            handle.cancel();
+        }
+        show_progress_bar(ui, &handle.status_rx);
+    });
+}
+
+/// Number of nodes listed in the PageRank table.
+const TOP_RANKED: usize = 100;
+
+/// Column the PageRank table is sorted by. Scores and degrees are sorted in decreasing order, and
+/// names alphabetically.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum RankColumn {
+    #[default]
+    Score,
+    Name,
+    Degree,
+}
+
+/// Range of the precision slider of the Louvain algorithm.
+const PRECISION_RANGE: RangeInclusive<f32> = 1e-7..=1.0;
+
+/// Number of precisions tried by a sweep.
+const SWEEP_STEPS: usize = 5;
+
+/// Precisions tried by a sweep, evenly spread over the logarithmic slider.
+fn sweep_precisions() -> impl Iterator<Item = f32> {
+    let (start, end) = (PRECISION_RANGE.start().ln(), PRECISION_RANGE.end().ln());
+    (0..SWEEP_STEPS)
+        .map(move |i| (start + (end - start) * i as f32 / (SWEEP_STEPS - 1) as f32).exp())
+}
+
+/// Partition found by one of the runs of a sweep, kept to be applied without running it again.
+struct SweepResult {
+    precision: f32,
+    modularity: f64,
+    class_count: usize,
+    /// Community of each node.
+    membership: Vec<u32>,
+}
+
+/// Communities found by one pass of a run, kept to switch between levels without running it again.
+struct LouvainLevel {
+    modularity: f64,
+    level: louvain::Level,
+}
+
+/// Replaces the classes of the graph with the given communities, and redraws it. Communities past
+/// what the shaders can draw are merged, see [`louvain::merge_smallest`], in which case the
+/// `modularity` of the communities is computed again for the merged ones.
+fn apply_partition(
+    data: &MyRwLock<ViewerData>,
+    graph: &MyRwLock<RenderedGraph>,
+    stats: &MyRwLock<NodeStats>,
+    mut membership: Vec<u32>,
+    count: usize,
+    modularity: f64,
+) {
+    let data_ = data.read();
+    let directed = data_.directed;
+    let mut nodes = data_.persons.as_ref().clone();
+    drop(data_);
+
+    let (count, merged) = louvain::merge_smallest(&mut membership, count, graph.read().num_classes);
+    // merging changes the partition, and so its modularity
+    let modularity = if merged.is_some() {
+        louvain::modularity(&nodes[..], &membership)
+    } else {
+        modularity
+    };
+    let classes = louvain::apply_membership(&mut nodes, &membership, count);
+
+    let (curvature, sort_edges) = {
+        let graph = graph.read();
+        (graph.edge_curvature, graph.sort_edges)
+    };
+    let task = ui::rerender_graph(&nodes, directed, curvature, sort_edges);
+
+    let mut lock = data.write();
+    lock.persons = Arc::new(nodes);
+    lock.modularity_classes = classes;
+    lock.modularity = Some(modularity);
+    lock.merged_communities = merged;
+    lock.class_generation += 1;
+
+    let mut graph = graph.write();
+    *stats.write() = NodeStats::new(&lock, graph.node_filter);
+    graph.tasks.push_back(task);
+}
+
+/// Nodes of the graph covered by the layout.
+struct LayoutNodes {
+    /// Node of the graph for each node of the layout, `None` when it covers the whole graph.
+    ids: Option<Vec<usize>>,
+    /// Index in the layout of the nodes of the graph it covers.
+    index: AHashMap<usize, usize>,
+}
+
+impl LayoutNodes {
+    /// Covers the nodes passing `filter`, or all of them if there is none.
+    fn new(persons: &[Person], filter: Option<NodeFilter>) -> Self {
+        let Some(filter) = filter else {
+            return LayoutNodes {
+                ids: None,
+                index: AHashMap::new(),
+            };
+        };
+        let ids = (0..persons.len())
+            .filter(|&i| filter.shows(&persons[i]))
+            .collect_vec();
+        let index = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        LayoutNodes {
+            ids: Some(ids),
+            index,
+        }
+    }
+
+    fn layout_index(&self, id: usize) -> Option<usize> {
+        match self.ids {
+            Some(_) => self.index.get(&id).copied(),
+            None => Some(id),
+        }
+    }
+
+    fn graph_id(&self, i: usize) -> usize {
+        self.ids.as_ref().map_or(i, |ids| ids[i])
+    }
+}
+
 pub struct ForceAtlasState {
     running: bool,
-    data: Option<(Arc<RwLock<Layout<f32, 2>>>, Option<ForceAtlasThread>)>,
+    /// Only lay out the nodes passing the filter.
+    filtered_only: bool,
+    /// Filter the current layout was built with, if it only covers the nodes passing it.
+    layout_filter: Option<NodeFilter>,
+    data: Option<(
+        Arc<RwLock<Layout<f32, 2>>>,
+        Arc<LayoutNodes>,
+        Option<ForceAtlasThread>,
+    )>,
     settings: Settings<f32>,
     new_settings: Arc<(AtomicBool, Mutex<Settings<f32>>)>,
+    /// Nodes that keep their position while the layout runs.
+    pinned: Arc<Mutex<AHashSet<usize>>>,
+    /// Dragging the selected node in the view moves it and pins it.
+    move_mode: bool,
+    /// Node being dragged in the view.
+    grabbed: Option<usize>,
     render_thread: Option<(Sender<()>, Receiver<ForceAtlasRenderDone>, JoinHandle<()>)>,
+    /// Copies the positions of the paused layout into the graph, and returns the camera fitting
+    /// them.
+    bake_thread: Option<JoinHandle<(ForceAtlasThread, Camera)>>,
 }
 
 impl Default for ForceAtlasState {
     fn default() -> Self {
         Self {
             running: false,
+            filtered_only: false,
+            layout_filter: None,
             data: None,
-            settings: Settings {
-                theta: 0.5,
-                ka: 0.1,
-                kg: 0.1,
-                kr: 0.02,
-                lin_log: false,
-                speed: 0.01,
-                prevent_overlapping: None,
-                strong_gravity: false,
-            },
+            settings: layout::default_settings(),
             new_settings: Default::default(),
+            pinned: Default::default(),
+            move_mode: false,
+            grabbed: None,
             render_thread: None,
+            bake_thread: None,
         }
     }
 }
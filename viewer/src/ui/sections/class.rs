@@ -1,20 +1,54 @@
 use crate::app::{ModularityClass, ViewerData};
 use crate::graph_render::camera::Camera;
-use crate::threading::MyRwLock;
-use crate::ui::infos::InfosSection;
-use crate::ui::modal::ModalWriter;
+use crate::session::{read_local, write_local};
+use crate::threading::{AlgoHandle, MyRwLock};
+use crate::ui::algos::show_running;
+use crate::ui::class_links::ClassLinks;
+use crate::ui::display::DisplaySection;
+use crate::ui::infos::{class_members, InfosSection};
+use crate::ui::meta_graph::create_meta_graph;
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
+use crate::ui::mutual_matrix::{MutualMatrix, MAX_MATRIX_MEMBERS};
 use crate::ui::path::PathSection;
 use crate::ui::tabs::NewTabRequest;
 use crate::ui::NodeStats;
+use crate::utils::format_count;
+use derivative::Derivative;
 use eframe::emath::Vec2;
 use eframe::epaint::Color32;
-use egui::{CollapsingHeader, Sense, Ui};
+use egui::{CollapsingHeader, Context, Id, Sense, Theme, Ui};
 use egui_extras::{Column, TableBuilder};
 use graph_format::Color3b;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::sync::Arc;
 
-#[derive(Default)]
-pub struct ClassSection {}
+/// Perceptual lightness below which class colors are brightened on a dark background.
+const MIN_DARK_LIGHTNESS: f32 = 0.65;
+
+const PALETTE_FILE: &str = "palette.json";
+
+/// Colors of the classes, saved to be reused in another session.
+#[derive(Serialize, Deserialize)]
+struct Palette {
+    /// Color of each class, as `#rrggbb`.
+    colors: Vec<String>,
+}
+
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct ClassSection {
+    /// Largest class the matrix of mutual friends can be shown for.
+    #[derivative(Default(value = "30"))]
+    matrix_limit: usize,
+    matrix: Option<Result<MutualMatrix, (u32, usize)>>,
+    links_thread: Option<AlgoHandle<ClassLinks>>,
+    links: Option<ClassLinks>,
+    links_open: bool,
+    /// Classes of the cell last clicked in the matrix of links.
+    links_pair: Option<(u32, u32)>,
+}
 
 impl ClassSection {
     pub(crate) fn show(
@@ -25,6 +59,7 @@ impl ClassSection {
         tab_request: &mut Option<NewTabRequest>,
         camera: &Camera,
         path_section: &PathSection,
+        display: &DisplaySection,
         modal: &impl ModalWriter,
         stats: &Arc<MyRwLock<NodeStats>>,
     ) {
@@ -32,17 +67,50 @@ impl ClassSection {
             .id_salt("classes")
             .default_open(false)
             .show(ui, |ui| {
+                Self::show_palette(ui, data_rw, modal);
+                self.show_links_button(ui, data_rw, modal);
+                if ui
+                    .button(t!("Collapse classes"))
+                    .on_hover_text(t!("Open a tab with a node for each class, sized by its number of members and linked to the classes its members have links to. Double-click a node there to open its class."))
+                    .clicked()
+                {
+                    *tab_request = Some(create_meta_graph(
+                        data_rw,
+                        *camera,
+                        display.inherited(),
+                        ui.ctx(),
+                        modal.clone(),
+                    ));
+                }
+                ui.add(
+                    egui::DragValue::new(&mut self.matrix_limit)
+                        .range(2..=MAX_MATRIX_MEMBERS)
+                        .prefix(t!("Matrix limit: ")),
+                )
+                .on_hover_text(t!("Largest class for which the mutual friends of each pair of members can be shown."));
+
+                let mut edited = None;
+                let mut matrix_class = None;
                 TableBuilder::new(ui)
                     .column(Column::exact(20.0))
                     .column(Column::exact(40.0))
                     .column(Column::exact(70.0))
+                    .column(Column::exact(20.0))
                     .body(|mut body| {
                         let data = data_rw.read();
                         for &(clid, count) in &stats.read().node_classes {
                             body.row(15.0, |mut row| {
                                 let cl = &data.modularity_classes[clid];
                                 row.col(|ui| {
-                                    Self::class_circle(ui, cl);
+                                    let Color3b { r, g, b } = cl.color;
+                                    let mut rgb = [r, g, b];
+                                    ui.spacing_mut().interact_size = Vec2::splat(13.0);
+                                    if egui::color_picker::color_edit_button_srgb(ui, &mut rgb)
+                                        .changed()
+                                    {
+                                        let [r, g, b] = rgb;
+                                        edited = Some((clid, Color3b::new(r, g, b)));
+                                    }
                                 });
                                 row.col(|ui| {
                                     // ui.label(format!("{}", cl.id));
@@ -52,29 +120,322 @@ impl ClassSection {
                                         tab_request,
                                         camera,
                                         path_section,
+                                        display,
                                         modal,
                                         clid.try_into().unwrap(),
                                         ui,
                                     );
                                 });
                                 row.col(|ui| {
-                                    ui.label(format!("{}", count));
+                                    ui.label(format_count(count));
+                                });
+                                row.col(|ui| {
+                                    if ui
+                                        .add_enabled(
+                                            count <= self.matrix_limit,
+                                            egui::Button::new("▦").small(),
+                                        )
+                                        .on_hover_text(t!("Mutual friends between the members"))
+                                        .on_disabled_hover_text(t!(
+                                            "Only for classes of at most %{max} nodes, see the matrix limit",
+                                            max = self.matrix_limit
+                                        ))
+                                        .clicked()
+                                    {
+                                        matrix_class = Some(clid.try_into().unwrap());
+                                    }
                                 });
                             });
                         }
                     });
+                // the colors are sent to the shaders each frame, so the change shows right away
+                if let Some((clid, color)) = edited {
+                    data_rw.write().modularity_classes[clid].color = color;
+                }
+
+                if let Some(class) = matrix_class {
+                    // the table only counts the visible nodes, the class may be larger
+                    self.matrix = Some(
+                        MutualMatrix::compute(&data_rw.read().persons, class, self.matrix_limit)
+                            .map_err(|size| (class, size)),
+                    );
+                }
+                self.show_matrix(ui, data_rw);
+            });
+        self.show_links(
+            ui,
+            infos_section,
+            data_rw,
+            tab_request,
+            camera,
+            path_section,
+            display,
+            modal,
+        );
+    }
+
+    /// Button to count the links between the classes, and the progress while they're counted.
+    fn show_links_button(
+        &mut self,
+        ui: &mut Ui,
+        data_rw: &Arc<MyRwLock<ViewerData>>,
+        modal: &impl ModalWriter,
+    ) {
+        if self
+            .links_thread
+            .as_ref()
+            .is_some_and(AlgoHandle::is_finished)
+        {
+            let thr = self.links_thread.take().unwrap();
+            if let Some(links) = thr.join() {
+                self.links = Some(links);
+                self.links_open = true;
+            }
+        }
+        if ui
+            .add_enabled(
+                self.links_thread.is_none(),
+                egui::Button::new(t!("Links between classes")),
+            )
+            .on_hover_text(t!(
+                "Count the links between each pair of classes, and show them as a matrix."
+            ))
+            .clicked()
+        {
+            let data = data_rw.clone();
+            self.links_thread = Some(AlgoHandle::spawn(
+                ui.ctx(),
+                modal.clone(),
+                move |status_tx, cancel| {
+                    let data = data.read();
+                    ClassLinks::compute(
+                        status_tx,
+                        &data.persons,
+                        data.modularity_classes.len(),
+                        data.directed,
+                        cancel,
+                    )
+                },
+            ));
+        }
+        if let Some(ref mut thr) = self.links_thread {
+            show_running(ui, thr);
+        }
+    }
+
+    /// Window with the matrix of links between the classes, from which a subgraph of the two
+    /// classes of a cell can be opened.
+    #[allow(clippy::too_many_arguments)]
+    fn show_links(
+        &mut self,
+        ui: &mut Ui,
+        infos_section: &InfosSection,
+        data_rw: &Arc<MyRwLock<ViewerData>>,
+        tab_request: &mut Option<NewTabRequest>,
+        camera: &Camera,
+        path_section: &PathSection,
+        display: &DisplaySection,
+        modal: &impl ModalWriter,
+    ) {
+        let Some(ref links) = self.links else {
+            return;
+        };
+        let mut open = self.links_open;
+        let ctx = ui.ctx().clone();
+        egui::Window::new(t!("Links between classes"))
+            .id(ui.id().with("class_links"))
+            .open(&mut open)
+            .resizable(false)
+            .show(&ctx, |ui| {
+                ui.label(t!("Classes by decreasing size. Click a cell to open its two classes."));
+                if let Some(pair) = links.show(ui) {
+                    self.links_pair = Some(pair);
+                }
+                let Some((a, b)) = self.links_pair else {
+                    return;
+                };
+                let title = if a == b {
+                    t!("Class %{class}", class = a).to_string()
+                } else {
+                    t!("Classes %{a} and %{b}", a = a, b = b).to_string()
+                };
+                ui.horizontal(|ui| {
+                    ui.strong(&title);
+                    if ui
+                        .button(t!("Create subgraph"))
+                        .on_hover_text(t!("Open a tab with the nodes of these classes and the links between them."))
+                        .clicked()
+                    {
+                        infos_section.create_subgraph(
+                            title.clone(),
+                            data_rw,
+                            tab_request,
+                            camera,
+                            path_section,
+                            display,
+                            ui,
+                            modal.clone(),
+                            move |_, data| {
+                                Ok(class_members(&data.persons, a)
+                                    .chain(class_members(&data.persons, b))
+                                    .collect())
+                            },
+                        );
+                    }
+                });
             });
+        self.links_open = open;
+    }
+
+    fn show_matrix(&mut self, ui: &mut Ui, data_rw: &Arc<MyRwLock<ViewerData>>) {
+        let Some(ref matrix) = self.matrix else {
+            return;
+        };
+        let mut close = false;
+        ui.separator();
+        ui.horizontal(|ui| {
+            let class = match matrix {
+                Ok(matrix) => matrix.class,
+                Err((class, _)) => *class,
+            };
+            ui.strong(t!("Mutual friends in class %{class}", class = class));
+            close = ui.small_button("🗙").clicked();
+        });
+        match matrix {
+            Ok(matrix) => matrix.show(ui, &data_rw.read().persons),
+            Err((_, size)) => {
+                ui.label(t!(
+                    "This class has %{count} nodes, more than the limit of %{max}.",
+                    count = size,
+                    max = self.matrix_limit
+                ));
+            }
+        }
+        if close {
+            self.matrix = None;
+        }
+    }
+
+    /// Buttons to give new colors to the classes, and to save and load them.
+    fn show_palette(ui: &mut Ui, data_rw: &Arc<MyRwLock<ViewerData>>, modal: &impl ModalWriter) {
+        ui.horizontal(|ui| {
+            if ui
+                .button(t!("Regenerate palette"))
+                .on_hover_text(t!("Give the classes perceptually distinct colors, the largest classes getting the most distinct ones."))
+                .clicked()
+            {
+                regenerate_palette(&mut data_rw.write());
+            }
+            let res: anyhow::Result<()> = try {
+                if ui.button(t!("Save palette")).clicked() {
+                    let palette = Palette {
+                        colors: data_rw
+                            .read()
+                            .modularity_classes
+                            .iter()
+                            .map(|c| c.color.to_hex())
+                            .collect(),
+                    };
+                    write_local(PALETTE_FILE, &serde_json::to_string_pretty(&palette)?)?;
+                    log::info!("Palette saved");
+                }
+                if ui.button(t!("Load palette")).clicked() {
+                    let palette: Palette = serde_json::from_str(&read_local(PALETTE_FILE)?)?;
+                    let colors = palette
+                        .colors
+                        .iter()
+                        .map(|hex| {
+                            Color3b::from_hex(hex).ok_or_else(|| {
+                                anyhow::anyhow!(t!("Invalid color: %{color}", color = hex))
+                            })
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    // extra colors are ignored, and classes without one keep theirs
+                    for (class, color) in data_rw.write().modularity_classes.iter_mut().zip(colors)
+                    {
+                        class.color = color;
+                    }
+                    log::info!("Palette loaded");
+                }
+            };
+            if let Err(e) = res {
+                modal.send(ModalInfo {
+                    title: t!("Error").to_string(),
+                    kind: ModalKind::Error,
+                    body: e.to_string().into(),
+                });
+            }
+        });
     }
 
     pub(crate) fn class_circle(ui: &mut Ui, cl: &ModularityClass) {
         let rad = 5.0;
         let size = Vec2::splat(2.0 * rad + 5.0);
         let (rect, _) = ui.allocate_at_least(size, Sense::hover());
-        let Color3b { r, g, b } = cl.color;
-        ui.painter().circle_filled(
-            rect.center(),
-            rad,
-            Color32::from_rgb(r / 2, g / 2, b / 2),
-        );
+        let color = if Self::adjusts_colors(ui.ctx()) {
+            let Color3b { r, g, b } = Self::display_color(ui.ctx(), cl.color);
+            Color32::from_rgb(r, g, b)
+        } else {
+            let Color3b { r, g, b } = cl.color;
+            Color32::from_rgb(r / 2, g / 2, b / 2)
+        };
+        ui.painter().circle_filled(rect.center(), rad, color);
+    }
+
+    fn raw_colors_id() -> Id {
+        Id::new("raw_class_colors")
+    }
+
+    /// Whether class colors are brightened on a dark background, which is the case unless the
+    /// user asked for the colors from the file.
+    pub(crate) fn adjusts_colors(ctx: &Context) -> bool {
+        ctx.theme() == Theme::Dark
+            && !ctx.data_mut(|d| *d.get_persisted_mut_or_default::<bool>(Self::raw_colors_id()))
+    }
+
+    /// Checkbox to use the colors from the file as they are.
+    pub(crate) fn show_raw_colors(ui: &mut Ui) {
+        let mut raw = ui
+            .ctx()
+            .data_mut(|d| *d.get_persisted_mut_or_default::<bool>(Self::raw_colors_id()));
+        if ui
+            .checkbox(&mut raw, t!("Raw class colors"))
+            .on_hover_text(t!("Dark class colors are brightened on a dark background to stay distinguishable. Check this to draw them exactly as they are in the file."))
+            .changed()
+        {
+            ui.ctx()
+                .data_mut(|d| d.insert_persisted(Self::raw_colors_id(), raw));
+        }
+    }
+
+    /// Color of a class, as drawn with the current theme.
+    pub(crate) fn display_color(ctx: &Context, color: Color3b) -> Color3b {
+        if Self::adjusts_colors(ctx) {
+            color.with_min_lightness(MIN_DARK_LIGHTNESS)
+        } else {
+            color
+        }
+    }
+
+    /// Colors of the classes, as drawn with the current theme, packed for the shaders.
+    pub(crate) fn display_colors(ctx: &Context, classes: &[ModularityClass]) -> Vec<u32> {
+        classes
+            .iter()
+            .map(|c| Self::display_color(ctx, c.color).to_u32())
+            .collect()
+    }
+}
+
+/// Gives the classes colors from [`Color3b::palette`], by decreasing number of nodes.
+fn regenerate_palette(data: &mut ViewerData) {
+    let mut counts = vec![0usize; data.modularity_classes.len()];
+    for p in data.persons.iter() {
+        counts[p.modularity_class as usize] += 1;
+    }
+    let order = (0..counts.len())
+        .sorted_by_key(|&i| Reverse(counts[i]))
+        .collect_vec();
+    for (&class, color) in order.iter().zip(Color3b::palette(order.len())) {
+        data.modularity_classes[class].color = color;
     }
-}
\ No newline at end of file
+}
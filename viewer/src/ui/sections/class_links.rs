@@ -0,0 +1,139 @@
+use crate::app::Person;
+use crate::graph_render::colormap::viridis;
+use crate::log_progress;
+use crate::threading::{CancelFlag, Cancelable, StatusWriterInterface};
+use egui::{vec2, Rect, Sense, Ui};
+use itertools::Itertools;
+use std::cmp::Reverse;
+
+/// Number of classes shown separately in the matrix. The smaller ones share the last row.
+pub const MAX_MATRIX_CLASSES: usize = 40;
+
+/// Side of a cell of the matrix, in points.
+const CELL_SIZE: f32 = 10.0;
+
+/// Number of links between each pair of classes.
+pub struct ClassLinks {
+    /// Class of each row, by decreasing size, `None` for the row of the remaining classes.
+    rows: Vec<Option<u32>>,
+    /// `counts[i * n + j]` is the number of links between the classes of rows `i` and `j`.
+    counts: Vec<u64>,
+    /// Highest count, which gets the brightest color.
+    max: u64,
+}
+
+impl ClassLinks {
+    /// Counts the links in a single pass over the edges. In directed graphs, two nodes following
+    /// each other make two links.
+    pub fn compute(
+        status_tx: &impl StatusWriterInterface,
+        persons: &[Person],
+        class_count: usize,
+        directed: bool,
+        cancel: &CancelFlag,
+    ) -> Cancelable<ClassLinks> {
+        let mut sizes = vec![0usize; class_count];
+        for p in persons {
+            if let Some(size) = sizes.get_mut(p.modularity_class as usize) {
+                *size += 1;
+            }
+        }
+        let mut rows = (0..class_count)
+            .filter(|&c| sizes[c] > 0)
+            .sorted_by_key(|&c| Reverse(sizes[c]))
+            .map(|c| Some(c as u32))
+            .collect_vec();
+        if rows.len() > MAX_MATRIX_CLASSES {
+            rows.truncate(MAX_MATRIX_CLASSES - 1);
+            rows.push(None);
+        }
+        let n = rows.len();
+        // row of each class, the classes left out going to the last one
+        let mut row_of = vec![n.saturating_sub(1); class_count];
+        for (row, class) in rows.iter().enumerate() {
+            if let Some(class) = class {
+                row_of[*class as usize] = row;
+            }
+        }
+        let row = |p: &Person| row_of.get(p.modularity_class as usize).copied();
+
+        let mut counts = vec![0u64; n * n];
+        let how_often = (persons.len() / 100).max(1);
+        for (i, p) in persons.iter().enumerate() {
+            if i % how_often == 0 {
+                cancel.check()?;
+                log_progress!(status_tx, i, persons.len());
+            }
+            let Some(a) = row(p) else {
+                continue;
+            };
+            for &j in &p.neighbors[..p.out_degree] {
+                // undirected edges are listed from both ends
+                if !directed && j < i {
+                    continue;
+                }
+                let Some(b) = row(&persons[j]) else {
+                    continue;
+                };
+                counts[a * n + b] += 1;
+                if a != b {
+                    counts[b * n + a] += 1;
+                }
+            }
+        }
+        let max = counts.iter().copied().max().unwrap_or(0);
+        Ok(ClassLinks { rows, counts, max })
+    }
+
+    /// Draws the matrix as a heatmap on a log scale, with the count of the pair under the pointer.
+    /// Returns the pair of classes whose cell was clicked.
+    pub fn show(&self, ui: &mut Ui) -> Option<(u32, u32)> {
+        let n = self.rows.len();
+        if n == 0 {
+            ui.label(t!("The graph has no classes."));
+            return None;
+        }
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(n as f32, n as f32) * CELL_SIZE, Sense::click());
+        let painter = ui.painter_at(rect);
+        // counts span several orders of magnitude, like the degrees
+        let max = (self.max as f32).ln_1p().max(f32::EPSILON);
+        for i in 0..n {
+            for j in 0..n {
+                let cell = Rect::from_min_size(
+                    rect.min + vec2(j as f32, i as f32) * CELL_SIZE,
+                    vec2(CELL_SIZE, CELL_SIZE),
+                );
+                let count = self.counts[i * n + j];
+                let color = if count == 0 {
+                    ui.visuals().extreme_bg_color
+                } else {
+                    viridis((count as f32).ln_1p() / max)
+                };
+                painter.rect_filled(cell, 0.0, color);
+            }
+        }
+
+        let pos = response.hover_pos()?;
+        let cell = ((pos - rect.min) / CELL_SIZE).floor();
+        let (i, j) = (cell.y as usize, cell.x as usize);
+        if i >= n || j >= n {
+            return None;
+        }
+        let name = |row: Option<u32>| match row {
+            Some(class) => class.to_string(),
+            None => t!("others").to_string(),
+        };
+        let clicked = response.clicked();
+        response.on_hover_text_at_pointer(t!(
+            "Class %{a} and class %{b}: %{count} links",
+            a = name(self.rows[i]),
+            b = name(self.rows[j]),
+            count = self.counts[i * n + j]
+        ));
+        match (self.rows[i], self.rows[j]) {
+            (Some(a), Some(b)) if clicked => Some((a, b)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::algorithms::components::{connected_components, Components};
+use crate::app::ViewerData;
+use crate::thread;
+use crate::thread::JoinHandle;
+use crate::threading::MyRwLock;
+use crate::ui;
+use egui::{CollapsingHeader, Ui};
+use std::sync::Arc;
+
+/// Number of rows shown in the component size distribution.
+const MAX_SIZES: usize = 10;
+
+#[derive(Default)]
+pub struct ComponentsSection {
+    thread: Option<JoinHandle<Components>>,
+    components: Option<Components>,
+}
+
+impl ComponentsSection {
+    pub(crate) fn show(&mut self, data: &Arc<MyRwLock<ViewerData>>, ui: &mut Ui) {
+        CollapsingHeader::new(t!("Connected components"))
+            .id_salt("components")
+            .default_open(false)
+            .show(ui, |ui| {
+                // the edges of a tab never change, so this only has to be computed once
+                if self.components.is_none() && self.thread.is_none() {
+                    let data = data.clone();
+                    self.thread = Some(thread::spawn(move || {
+                        connected_components(&data.read().persons)
+                    }));
+                }
+                if let Some(thr) = self.thread.take_if(|thr| thr.is_finished()) {
+                    self.components = thr.join().ok();
+                }
+                let Some(ref components) = self.components else {
+                    ui::spinner(ui);
+                    return;
+                };
+
+                let total = components.ids.len().max(1);
+                egui::Grid::new("#components").show(ui, |ui| {
+                    ui.label(t!("Components:"));
+                    ui.label(format!("{}", components.count()));
+                    ui.end_row();
+                    ui.label(t!("Largest component:"));
+                    ui.label(t!(
+                        "%{size} nodes (%{percent}%)",
+                        size = components.largest(),
+                        percent =
+                            format!("{:.1}", components.largest() as f32 * 100.0 / total as f32)
+                    ));
+                    ui.end_row();
+                });
+
+                if components.count() > 1 {
+                    ui.separator();
+                    egui::Grid::new("#component_sizes")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong(t!("Size"));
+                            ui.strong(t!("Components"));
+                            ui.end_row();
+                            for (size, count) in
+                                components.size_distribution().into_iter().take(MAX_SIZES)
+                            {
+                                ui.label(format!("{}", size));
+                                ui.label(format!("{}", count));
+                                ui.end_row();
+                            }
+                        });
+                }
+            });
+    }
+}
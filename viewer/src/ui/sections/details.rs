@@ -1,18 +1,124 @@
+use crate::app::ViewerData;
+use crate::graph_render::RenderedGraph;
+use crate::threading::MyRwLock;
+use crate::ui::modal::ModalWriter;
+use crate::ui::sections::display::DisplaySection;
+use crate::ui::sections::export::ExportSection;
 use crate::ui::tabs::{CamAnimating, TabCamera};
 use derivative::Derivative;
 use eframe::emath::Pos2;
-use egui::{CollapsingHeader, Id, Ui};
+use egui::{CollapsingHeader, Context, Id, Ui};
 use graph_format::nalgebra::Vector2;
+use std::sync::Arc;
+
+/// Weight of the last frame in the average frame time.
+const FRAME_SMOOTHING: f32 = 0.05;
+
+/// Longer pauses between frames are idle time rather than slow frames, and aren't averaged.
+const MAX_FRAME_TIME: f32 = 1.0;
+
+/// Running average of the time between frames.
+#[derive(Default)]
+pub struct FrameStats {
+    last_time: Option<f64>,
+    /// Average frame time, in seconds.
+    frame_time: f32,
+}
+
+impl FrameStats {
+    /// Records a frame drawn at the given time, from `egui::InputState::time`.
+    pub fn update(&mut self, time: f64) {
+        if let Some(last) = self.last_time.replace(time) {
+            let dt = (time - last) as f32;
+            if dt > MAX_FRAME_TIME {
+                return;
+            }
+            if self.frame_time == 0.0 {
+                self.frame_time = dt;
+            } else {
+                self.frame_time += (dt - self.frame_time) * FRAME_SMOOTHING;
+            }
+        }
+    }
+
+    pub fn frame_time_ms(&self) -> f32 {
+        self.frame_time * 1000.0
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_time > 0.0 {
+            1.0 / self.frame_time
+        } else {
+            0.0
+        }
+    }
+}
 
 #[derive(Derivative)]
 #[derivative(Default)]
 pub struct DetailsSection {
     pub mouse_pos: Option<Pos2>,
     pub mouse_pos_world: Option<Vector2<f32>>,
+    pub export: ExportSection,
+    pub frame_stats: FrameStats,
+    /// Shows the performance figures over the graph.
+    pub show_perf_overlay: bool,
+    /// Set by "Duplicate tab", for the tab to open a copy of itself.
+    pub duplicate_requested: bool,
+}
+
+/// Performance figures, as shown in the details and in the overlay.
+pub fn perf_grid(ui: &mut Ui, stats: &FrameStats, graph: &RenderedGraph) {
+    egui::Grid::new("#perf").show(ui, |ui| {
+        ui.label(t!("Frame time:"));
+        ui.label(format!("{:.1} ms", stats.frame_time_ms()));
+        ui.end_row();
+        ui.label("FPS:");
+        ui.label(format!("{:.0}", stats.fps()));
+        ui.end_row();
+        ui.label(t!("Vertices:"));
+        ui.label(format!("{}", graph.vertex_count()));
+        ui.end_row();
+        ui.label(t!("Vertex buffer:"));
+        ui.label(format!("{:.1} MB", graph.buffer_size() as f32 / 1e6));
+        ui.end_row();
+    });
 }
 
 impl DetailsSection {
-    pub(crate) fn show(&mut self, ui: &mut Ui, camera: &mut TabCamera, cid: Id) {
+    fn power_saving_id() -> Id {
+        Id::new("power_saving")
+    }
+
+    /// Whether the UI is only repainted continuously while something is animated, like the
+    /// camera or a layout, and not while waiting for work running in the background.
+    pub(crate) fn power_saving(ctx: &Context) -> bool {
+        ctx.data_mut(|d| *d.get_persisted_mut_or_default::<bool>(Self::power_saving_id()))
+    }
+
+    fn show_power_saving(ui: &mut Ui) {
+        let mut enabled = Self::power_saving(ui.ctx());
+        if ui
+            .checkbox(&mut enabled, t!("Power saving"))
+            .on_hover_text(t!("Only redraw continuously while the camera or the layout is moving. Progress of the other algorithms is then updated a few times per second."))
+            .changed()
+        {
+            ui.ctx()
+                .data_mut(|d| d.insert_persisted(Self::power_saving_id(), enabled));
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn show(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        display: &DisplaySection,
+        camera: &mut TabCamera,
+        cid: Id,
+        modal: &impl ModalWriter,
+    ) {
         CollapsingHeader::new(t!("Details"))
             .id_salt("details")
             .default_open(false)
@@ -49,6 +155,13 @@ impl DetailsSection {
                         to: camera.camera_default.transf,
                     });
                 }
+                if ui
+                    .button(t!("Duplicate tab"))
+                    .on_hover_text(t!("Open a copy of this tab, with the same view, filters, selection and path, to try something else without losing them"))
+                    .clicked()
+                {
+                    self.duplicate_requested = true;
+                }
 
                 let matrix = camera.camera.get_matrix();
                 egui::Grid::new("#cammatrix").show(ui, move |ui| {
@@ -60,6 +173,15 @@ impl DetailsSection {
                         ui.end_row();
                     }
                 });
+
+                ui.separator();
+                perf_grid(ui, &self.frame_stats, &graph.read());
+                ui.checkbox(&mut self.show_perf_overlay, t!("Show over the graph"));
+                Self::show_power_saving(ui);
+
+                ui.separator();
+                self.export
+                    .show(data, graph, ui, display, &camera.camera, modal);
             });
     }
 }
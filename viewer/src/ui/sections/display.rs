@@ -1,9 +1,41 @@
-use crate::graph_render::RenderedGraph;
-use crate::threading::MyRwLock;
+use crate::algorithms::kcore::core_numbers;
+use crate::algorithms::pathfinding::PathSectionSettings;
+use crate::algorithms::AbstractGraph;
+use crate::app::{show_progress_bar, ViewerData};
+use crate::attributes::parse_csv;
+use crate::graph_render::buckets::bucket_edges;
+use crate::graph_render::bundling::bundle_edges;
+use crate::graph_render::camera::Camera;
+use crate::graph_render::colormap::viridis;
+use crate::graph_render::geom_draw::{
+    create_bundled_edge_vertices, create_node_vertex, VERTS_PER_BUNDLED_EDGE,
+};
+use crate::graph_render::lod::{self, EdgeLod};
+use crate::graph_render::{
+    ColorMode, GlTask, NodeSizeMode, NodeSizes, PersonVertex, RenderedGraph,
+    MAX_VERTEX_BUFFER_SIZE,
+};
+use crate::log;
+use crate::search::{self, SearchOptions, DEBOUNCE};
+use crate::thread::JoinHandle;
+use crate::threading::{spawn_cancelable, status_pipe, MyRwLock, StatusReader};
 use crate::ui;
+use crate::ui::class::ClassSection;
+use crate::ui::infos::create_subgraph;
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
+use crate::ui::tabs::NewTabRequest;
+use crate::ui::widgets::degree_range::degree_range;
 use crate::ui::NodeStats;
+use crate::utils::format_count;
 use derivative::Derivative;
-use egui::{CollapsingHeader, SliderClamping, Ui};
+use eframe::glow;
+use egui::{
+    pos2, vec2, CollapsingHeader, Color32, Rect, Sense, SliderClamping, TextEdit, Theme, Ui,
+};
+use itertools::Itertools;
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::mem::discriminant;
 use std::sync::Arc;
 
 #[derive(Derivative)]
@@ -14,19 +46,117 @@ pub struct DisplaySection {
     //#[derivative(Default(value = "cfg!(not(target_arch = \"wasm32\"))"))]
     #[derivative(Default(value = "true"))]
     pub g_show_edges: bool,
+    #[derivative(Default(value = "true"))]
+    pub g_show_minimap: bool,
     pub g_opac_nodes: f32,
     pub g_opac_edges: f32,
+    pub g_node_size: NodeSizeMode,
+    /// Factor applied to the size of all nodes.
+    #[derivative(Default(value = "1.0"))]
+    pub g_node_scale: f32,
+    /// Draws the names of the highest-ranked nodes in view.
+    pub g_show_labels: bool,
+    /// Order in which the nodes get a label.
+    pub g_label_rank: LabelRank,
+    /// Maximum number of labels drawn at once.
+    #[derivative(Default(value = "20"))]
+    pub g_label_count: usize,
+    /// Zoom level, relative to the default view, below which labels are hidden.
+    #[derivative(Default(value = "1.0"))]
+    pub g_label_min_zoom: f32,
+    /// Width of the edges on screen, in points.
+    #[derivative(Default(value = "1.5"))]
+    pub g_edge_width: f32,
+    /// How much the edges are bent, relative to their length. Zero draws them straight.
+    pub g_edge_curvature: f32,
+    /// Draws the links of the selected node, and more faintly those of the hovered one, over the
+    /// other links.
+    #[derivative(Default(value = "true"))]
+    pub g_highlight_edges: bool,
+    /// Fraction of the links drawn, lowered when the frames get slow.
+    pub edge_lod: EdgeLod,
+    /// Sorts the edges when the graph is loaded, see [`lod::edge_draw_order`]. Chosen when opening
+    /// a file, and kept by the subgraphs.
+    #[derivative(Default(value = "true"))]
+    pub sort_edges: bool,
+    /// Fill color of the graph view.
+    #[derivative(Default(value = "background_for(Theme::Dark)"))]
+    pub g_background: Color32,
+    /// Theme the background and opacities were last adapted to.
+    theme: Option<Theme>,
     pub deg_filter_changed: bool,
+    /// Disables the filter controls, while an image is being exported.
+    pub filters_locked: bool,
     pub max_degree: u16,
+    /// Number of nodes of each degree, for the histogram of the degree filter.
+    pub degree_counts: Vec<u32>,
+    bundle_state: Option<BundleState>,
+    /// Rebuilds the vertices after the curvature has changed.
+    curve_thread: Option<JoinHandle<()>>,
+    /// k chosen on the slider, applied to the filter once the slider is released.
+    kcore: u16,
+    /// Highest core number, once the decomposition has been computed.
+    max_core: Option<u16>,
+    kcore_state: Option<KCoreState>,
+    /// Text the node names are filtered by, see [`Self::show_name_filter`].
+    name_filter: String,
+    /// Time of the last edit of the name filter that hasn't been applied yet.
+    name_filter_edit: Option<f64>,
+    /// Marks the nodes whose name matches the filter.
+    name_filter_thread: Option<JoinHandle<()>>,
+    /// Values mapped to the ends of the gradient when coloring the nodes by an attribute.
+    attribute_bounds: (f32, f32),
+    /// Files dropped on the window are attached as attributes instead of opened, see
+    /// [`Self::show_attributes`].
+    #[cfg(target_arch = "wasm32")]
+    awaiting_attributes: bool,
+}
+
+pub struct KCoreState {
+    thread: JoinHandle<()>,
+    status_rx: StatusReader,
+}
+
+/// Highest number of labels that can be drawn at once.
+pub const MAX_LABELS: usize = 200;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelRank {
+    #[default]
+    Degree,
+    /// Falls back to the degree until PageRank has been computed.
+    PageRank,
+}
+
+/// How much more opaque nodes and edges are on a light background, to stay as visible as on a dark
+/// one.
+const LIGHT_OPACITY_FACTOR: f32 = 1.8;
+
+fn background_for(theme: Theme) -> Color32 {
+    match theme {
+        Theme::Dark => Color32::from_gray(27),
+        Theme::Light => Color32::WHITE,
+    }
+}
+
+pub struct BundleState {
+    thread: JoinHandle<()>,
+    status_rx: StatusReader,
 }
 
 impl DisplaySection {
     pub(crate) fn show(
         &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
         graph: &Arc<MyRwLock<RenderedGraph>>,
         ui: &mut Ui,
         stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+        tab_request: &mut Option<NewTabRequest>,
+        camera: &Camera,
     ) {
+        self.follow_theme(ui.ctx().theme());
+
         CollapsingHeader::new(t!("Display"))
             .id_salt("display")
             .default_open(true)
@@ -40,6 +170,10 @@ impl DisplaySection {
                             .custom_parser(ui::percent_parser)
                             .clamping(SliderClamping::Always),
                     );
+                    self.show_color_mode(data, graph, ui);
+                    self.show_attributes(data, ui, modal);
+                    self.show_node_size(ui);
+                    self.show_labels(ui, data.read().pagerank.is_some());
                 }
                 ui.checkbox(&mut self.g_show_edges, t!("Show links"));
                 if self.g_show_edges {
@@ -50,41 +184,734 @@ impl DisplaySection {
                             .custom_parser(ui::percent_parser)
                             .clamping(SliderClamping::Always),
                     );
+                    ui.add(
+                        egui::Slider::new(&mut self.g_edge_width, 0.2..=8.0)
+                            .text(t!("Thickness"))
+                            .logarithmic(true)
+                            .clamping(SliderClamping::Always),
+                    );
+                    self.show_curvature(data, graph, ui, modal);
+                    self.show_bundling(data, graph, ui, modal);
+                    self.show_edge_lod(ui);
                 }
+                ui.checkbox(
+                    &mut self.g_highlight_edges,
+                    t!("Highlight links of the selected node"),
+                );
+                ui.checkbox(&mut self.g_show_minimap, t!("Show minimap"));
 
-                ui.horizontal(|ui| {
-                    let mut graph_lock = graph.write();
-                    let graph = &mut *graph_lock;
-                    ui.vertical(|ui| {
-                        let start = ui
-                            .add(
-                                egui::DragValue::new(&mut graph.node_filter.degree_filter.0)
-                                    .speed(1)
-                                    .range(1..=graph.node_filter.degree_filter.1)
-                                    .prefix(t!("Minimum degree: ")),
-                            )
-                            .changed();
-                        let end = ui
-                            .add(
-                                egui::DragValue::new(&mut graph.node_filter.degree_filter.1)
-                                    .speed(1)
-                                    .range(graph.node_filter.degree_filter.0..=self.max_degree)
-                                    .prefix(t!("Maximum degree: ")),
-                            )
-                            .changed();
-                        if start || end {
-                            self.deg_filter_changed = true;
-                        }
-                    });
-                    ui.vertical(|ui| {
-                        ui.checkbox(&mut graph.node_filter.filter_nodes, t!("Filter nodes"));
-                    });
+                ui.add_enabled_ui(!self.filters_locked, |ui| {
+                    self.show_filters(data, graph, ui, modal, tab_request, camera);
                 });
 
                 ui.horizontal(|ui| {
                     ui.label(t!("Visible nodes: "));
-                    ui.label(format!("{}", stats.read().node_count));
+                    ui.label(format_count(stats.read().node_count));
                 });
+
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_srgba(&mut self.g_background);
+                    ui.label(t!("Background"));
+                });
+                ClassSection::show_raw_colors(ui);
+            });
+    }
+
+    /// Half-width of the edges in world units, for them to be [`Self::g_edge_width`] wide on
+    /// screen with the given camera.
+    pub fn edge_half_width(&self, camera: &Camera) -> f32 {
+        self.g_edge_width / 2.0 / camera.transf.scaling()
+    }
+
+    /// Node size parameters for the current settings.
+    pub fn node_sizes(&self, graph: &RenderedGraph) -> NodeSizes {
+        graph.node_sizes(self.g_node_size, self.g_node_scale)
+    }
+
+    fn show_edge_lod(&mut self, ui: &mut Ui) {
+        let mut fraction = self.edge_lod.fraction();
+        ui.horizontal(|ui| {
+            let mut auto = self.edge_lod.manual.is_none();
+            if ui
+                .checkbox(&mut auto, t!("Automatic"))
+                .on_hover_text(t!(
+                    "Draw fewer links when the frames get slow, starting with those between the least connected nodes."
+                ))
+                .changed()
+            {
+                self.edge_lod.manual = (!auto).then_some(fraction);
+            }
+            if ui
+                .add_enabled(
+                    !auto,
+                    egui::Slider::new(&mut fraction, lod::MIN_FRACTION..=1.0)
+                        .text(t!("Links drawn"))
+                        .logarithmic(true)
+                        .custom_formatter(ui::percent_formatter)
+                        .custom_parser(ui::percent_parser)
+                        .clamping(SliderClamping::Always),
+                )
+                .changed()
+            {
+                self.edge_lod.manual = Some(fraction);
+            }
+        });
+    }
+
+    fn show_node_size(&mut self, ui: &mut Ui) {
+        fn label(mode: NodeSizeMode) -> Cow<'static, str> {
+            match mode {
+                NodeSizeMode::Uniform => t!("Uniform"),
+                NodeSizeMode::File => t!("From file"),
+                NodeSizeMode::Degree => t!("Degree"),
+            }
+        }
+
+        egui::ComboBox::from_label(t!("Node sizes"))
+            .selected_text(label(self.g_node_size))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    NodeSizeMode::Uniform,
+                    NodeSizeMode::File,
+                    NodeSizeMode::Degree,
+                ] {
+                    ui.selectable_value(&mut self.g_node_size, mode, label(mode));
+                }
             });
+        ui.add(
+            egui::Slider::new(&mut self.g_node_scale, 0.1..=10.0)
+                .text(t!("Size"))
+                .logarithmic(true)
+                .clamping(SliderClamping::Always),
+        );
+    }
+
+    fn show_labels(&mut self, ui: &mut Ui, has_pagerank: bool) {
+        ui.checkbox(&mut self.g_show_labels, t!("Show labels"));
+        if self.g_show_labels {
+            if has_pagerank {
+                let label = |rank| match rank {
+                    LabelRank::Degree => t!("Degree"),
+                    LabelRank::PageRank => t!("PageRank"),
+                };
+                egui::ComboBox::from_label(t!("Ranked by"))
+                    .selected_text(label(self.g_label_rank))
+                    .show_ui(ui, |ui| {
+                        for rank in [LabelRank::Degree, LabelRank::PageRank] {
+                            ui.selectable_value(&mut self.g_label_rank, rank, label(rank));
+                        }
+                    });
+            }
+            ui.add(
+                egui::Slider::new(&mut self.g_label_count, 1..=MAX_LABELS)
+                    .text(t!("Labels"))
+                    .clamping(SliderClamping::Always),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.g_label_min_zoom, 0.1..=100.0)
+                    .text(t!("Minimum zoom"))
+                    .logarithmic(true)
+                    .clamping(SliderClamping::Always),
+            )
+            .on_hover_text(t!(
+                "Labels are hidden when zoomed out further than this, relative to the default view."
+            ));
+        }
     }
+
+    /// Degree and k-core filters.
+    fn show_filters(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+        tab_request: &mut Option<NewTabRequest>,
+        camera: &Camera,
+    ) {
+        ui.horizontal(|ui| {
+            let mut graph_lock = graph.write();
+            let graph = &mut *graph_lock;
+            ui.vertical(|ui| {
+                let histogram = degree_range(
+                    ui,
+                    &mut graph.node_filter.degree_filter,
+                    &self.degree_counts,
+                )
+                .changed();
+                let start = ui
+                    .add(
+                        egui::DragValue::new(&mut graph.node_filter.degree_filter.0)
+                            .speed(1)
+                            .range(1..=graph.node_filter.degree_filter.1)
+                            .prefix(t!("Minimum degree: ")),
+                    )
+                    .changed();
+                let end = ui
+                    .add(
+                        egui::DragValue::new(&mut graph.node_filter.degree_filter.1)
+                            .speed(1)
+                            .range(graph.node_filter.degree_filter.0..=self.max_degree)
+                            .prefix(t!("Maximum degree: ")),
+                    )
+                    .changed();
+                if histogram || start || end {
+                    self.deg_filter_changed = true;
+                }
+            });
+            ui.vertical(|ui| {
+                ui.checkbox(&mut graph.node_filter.filter_nodes, t!("Filter nodes"));
+            });
+        });
+
+        self.show_name_filter(data, graph, ui, modal);
+        self.show_kcore(data, graph, ui, modal, tab_request, camera);
+    }
+
+    /// Hides the nodes whose name doesn't contain the given text, along with their links, on top of
+    /// the other filters. The nodes are marked once the text stops changing, like in the search.
+    fn show_name_filter(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        if self
+            .name_filter_thread
+            .as_ref()
+            .is_some_and(|t| t.is_finished())
+        {
+            self.name_filter_thread = None;
+            // the visible node count changes without the filter itself changing
+            self.deg_filter_changed = true;
+        }
+
+        let now = ui.input(|i| i.time);
+        ui.horizontal(|ui| {
+            let field =
+                ui.add(TextEdit::singleline(&mut self.name_filter).hint_text(t!("Filter by name")));
+            if field.changed() {
+                self.name_filter_edit = Some(now);
+            }
+            if self.name_filter_thread.is_some() {
+                ui::spinner(ui);
+            }
+        });
+
+        let Some(last_edit) = self.name_filter_edit else {
+            return;
+        };
+        if now - last_edit < DEBOUNCE {
+            ui.ctx()
+                .request_repaint_after_secs((DEBOUNCE - (now - last_edit)) as f32);
+            return;
+        }
+        if self.name_filter_thread.is_some() {
+            // applied once the previous text has been
+            return;
+        }
+        self.name_filter_edit = None;
+        let ignore_accents = SearchOptions::get(ui.ctx()).ignore_accents;
+        let pattern = search::normalize(self.name_filter.trim(), ignore_accents);
+        let data = data.clone();
+        let graph = graph.clone();
+        self.name_filter_thread = Some(spawn_cancelable(modal.clone(), move || {
+            let (mut persons, directed) = {
+                let data = data.read();
+                (data.persons.as_ref().clone(), data.directed)
+            };
+            persons.par_iter_mut().for_each(|p| {
+                p.name_match = search::normalize(p.name, ignore_accents).contains(&pattern);
+            });
+            let curvature = graph.read().edge_curvature;
+            let sort_edges = graph.read().sort_edges;
+            let task = ui::rerender_graph(&persons, directed, curvature, sort_edges);
+            data.write().persons = Arc::new(persons);
+            let mut graph = graph.write();
+            graph.tasks.push_back(task);
+            graph.node_filter.filter_names = !pattern.is_empty();
+            Ok(())
+        }));
+    }
+
+    /// Settings for a subgraph of this tab, which can then be changed independently. The opacities
+    /// and the degree filter depend on the size of the graph, so the subgraph gets its own.
+    pub(crate) fn inherited(&self) -> DisplaySection {
+        DisplaySection {
+            g_show_nodes: self.g_show_nodes,
+            g_show_edges: self.g_show_edges,
+            g_show_minimap: self.g_show_minimap,
+            g_node_size: self.g_node_size,
+            g_node_scale: self.g_node_scale,
+            g_show_labels: self.g_show_labels,
+            g_label_rank: self.g_label_rank,
+            g_label_count: self.g_label_count,
+            g_label_min_zoom: self.g_label_min_zoom,
+            g_edge_width: self.g_edge_width,
+            g_highlight_edges: self.g_highlight_edges,
+            sort_edges: self.sort_edges,
+            // the nodes of the subgraph keep whether their name matches
+            name_filter: self.name_filter.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Display settings of a copy of the tab, which also keeps the opacities and background.
+    pub(crate) fn duplicated(&self) -> DisplaySection {
+        DisplaySection {
+            g_opac_nodes: self.g_opac_nodes,
+            g_opac_edges: self.g_opac_edges,
+            g_background: self.g_background,
+            theme: self.theme,
+            ..self.inherited()
+        }
+    }
+
+    /// Drops the name filter, for a graph whose nodes weren't matched against it.
+    pub(crate) fn clear_name_filter(&mut self) {
+        self.name_filter.clear();
+    }
+
+    /// Whether the nodes are filtered by name.
+    pub(crate) fn filters_names(&self) -> bool {
+        !self.name_filter.is_empty()
+    }
+
+    /// Resets the background to the default of the current egui theme when it changes, and scales
+    /// the opacities so the graph stays readable.
+    fn follow_theme(&mut self, theme: Theme) {
+        let previous = self.theme.replace(theme);
+        if previous == Some(theme) {
+            return;
+        }
+        self.g_background = background_for(theme);
+        // opacity defaults are tuned for the dark theme
+        let factor = match (previous.unwrap_or(Theme::Dark), theme) {
+            (Theme::Dark, Theme::Light) => LIGHT_OPACITY_FACTOR,
+            (Theme::Light, Theme::Dark) => 1.0 / LIGHT_OPACITY_FACTOR,
+            _ => 1.0,
+        };
+        self.g_opac_nodes = (self.g_opac_nodes * factor).min(1.0);
+        self.g_opac_edges = (self.g_opac_edges * factor).min(1.0);
+    }
+
+    fn show_kcore(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+        tab_request: &mut Option<NewTabRequest>,
+        camera: &Camera,
+    ) {
+        if let Some(ref state) = self.kcore_state {
+            if state.thread.is_finished() {
+                self.kcore_state = None;
+                self.max_core = data.read().persons.iter().map(|p| p.core_number).max();
+            }
+        }
+
+        // core numbers can't be higher than the degree
+        let max = self.max_core.unwrap_or(self.max_degree);
+        let resp = ui.add_enabled(
+            self.kcore_state.is_none(),
+            egui::Slider::new(&mut self.kcore, 0..=max).text(t!("k-core")),
+        )
+        .on_hover_text(t!("Only show nodes that keep at least k neighbors when repeatedly removing nodes with fewer than k neighbors."));
+        if resp.drag_stopped() || (resp.changed() && !resp.dragged()) {
+            let k = self.kcore;
+            if self.max_core.is_some() {
+                graph.write().node_filter.kcore = k;
+            } else {
+                let (status_tx, status_rx) = status_pipe(ui.ctx());
+                let data = data.clone();
+                let graph = graph.clone();
+                let thread = spawn_cancelable(modal.clone(), move || {
+                    log!(status_tx, t!("Computing core numbers"));
+                    let (mut persons, directed) = {
+                        let data = data.read();
+                        (data.persons.as_ref().clone(), data.directed)
+                    };
+                    let cores = core_numbers(&status_tx, &persons)?;
+                    for (person, core) in persons.iter_mut().zip(cores) {
+                        person.core_number = core;
+                    }
+                    let curvature = graph.read().edge_curvature;
+                    let sort_edges = graph.read().sort_edges;
+                    let task = ui::rerender_graph(&persons, directed, curvature, sort_edges);
+                    data.write().persons = Arc::new(persons);
+                    let mut graph = graph.write();
+                    graph.tasks.push_back(task);
+                    graph.node_filter.kcore = k;
+                    Ok(())
+                });
+                self.kcore_state = Some(KCoreState { thread, status_rx });
+            }
+        }
+
+        if let Some(ref mut state) = self.kcore_state {
+            state.status_rx.recv();
+            ui.horizontal(|ui| {
+                ui::spinner(ui);
+                show_progress_bar(ui, &state.status_rx);
+            });
+        }
+
+        let k = graph.read().node_filter.kcore;
+        if k > 0
+            && ui
+                .button(t!("Open the %{k}-core in a new tab", k = k))
+                .clicked()
+        {
+            *tab_request = Some(create_subgraph(
+                t!("%{k}-core", k = k).to_string(),
+                data,
+                *camera,
+                None,
+                PathSectionSettings::default(),
+                self.inherited(),
+                ui.ctx(),
+                modal.clone(),
+                move |_, data| {
+                    Ok(data
+                        .persons
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, p)| p.core_number >= k)
+                        .map(|(i, _)| i)
+                        .collect())
+                },
+            ));
+        }
+    }
+
+    fn show_color_mode(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+    ) {
+        fn label(mode: &ColorMode) -> Cow<'static, str> {
+            match mode {
+                ColorMode::Class => t!("Class"),
+                ColorMode::Degree => t!("Degree"),
+                ColorMode::PageRank(_) => t!("PageRank"),
+                ColorMode::Custom(_) => t!("Custom"),
+                ColorMode::Attribute(attribute, _) => attribute.name.clone().into(),
+            }
+        }
+
+        // custom values can't be recomputed from here, so they're only shown while selected
+        let (current, current_attribute, value_range) = {
+            let graph = graph.read();
+            let attribute = match &graph.color_mode {
+                ColorMode::Attribute(attribute, _) => Some(attribute.clone()),
+                _ => None,
+            };
+            (
+                (discriminant(&graph.color_mode), label(&graph.color_mode)),
+                attribute,
+                graph.value_range,
+            )
+        };
+        let (pagerank, attributes) = {
+            let data = data.read();
+            (data.pagerank.clone(), data.attributes.clone())
+        };
+        let mut choice = None;
+        egui::ComboBox::from_label(t!("Node colors"))
+            .selected_text(current.1)
+            .show_ui(ui, |ui| {
+                let modes = [ColorMode::Class, ColorMode::Degree]
+                    .into_iter()
+                    .chain(pagerank.map(ColorMode::PageRank))
+                    .chain(attributes.into_iter().map(|attribute| {
+                        let range = attribute.range();
+                        ColorMode::Attribute(attribute, range)
+                    }));
+                for mode in modes {
+                    let selected = match &mode {
+                        ColorMode::Attribute(attribute, _) => current_attribute
+                            .as_ref()
+                            .is_some_and(|current| Arc::ptr_eq(attribute, current)),
+                        _ => current.0 == discriminant(&mode),
+                    };
+                    if ui.selectable_label(selected, label(&mode)).clicked() && !selected {
+                        choice = Some(mode);
+                    }
+                }
+            });
+        if let Some(mode) = choice {
+            if let ColorMode::Attribute(_, bounds) = mode {
+                self.attribute_bounds = bounds;
+            }
+            set_color_mode(data, graph, mode);
+        }
+
+        if let Some(attribute) = current_attribute {
+            let (min, max) = &mut self.attribute_bounds;
+            let speed = ((*max - *min).abs() / 200.0).max(1e-3);
+            let changed = ui
+                .horizontal(|ui| {
+                    ui.label(t!("From"));
+                    let min = ui.add(egui::DragValue::new(min).speed(speed)).changed();
+                    ui.label(t!("to"));
+                    let max = ui.add(egui::DragValue::new(max).speed(speed)).changed();
+                    min | max
+                })
+                .inner;
+            if changed {
+                set_color_mode(
+                    data,
+                    graph,
+                    ColorMode::Attribute(attribute, self.attribute_bounds),
+                );
+            }
+        }
+
+        if let Some((min, max)) = value_range {
+            let format = |v: f32| {
+                if v.fract() == 0.0 {
+                    format!("{}", v)
+                } else if v.abs() < 0.01 {
+                    format!("{:.2e}", v)
+                } else {
+                    format!("{:.3}", v)
+                }
+            };
+            let resp = ui.horizontal(|ui| {
+                ui.label(format(min));
+                const STEPS: usize = 32;
+                let (rect, _) = ui.allocate_exact_size(vec2(128.0, 12.0), Sense::hover());
+                let step = rect.width() / STEPS as f32;
+                for i in 0..STEPS {
+                    let x = rect.left() + i as f32 * step;
+                    ui.painter().rect_filled(
+                        Rect::from_min_max(pos2(x, rect.top()), pos2(x + step, rect.bottom())),
+                        0.0,
+                        viridis((i as f32 + 0.5) / STEPS as f32),
+                    );
+                }
+                ui.label(format(max));
+            });
+            if matches!(
+                graph.read().color_mode,
+                ColorMode::Degree | ColorMode::PageRank(_)
+            ) {
+                resp.response.on_hover_text(t!("Logarithmic scale"));
+            }
+        }
+    }
+
+    /// Button to attach the numeric columns of a CSV file to the nodes, to color them by. In the
+    /// browser, the file is dropped on the window instead.
+    fn show_attributes(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        let hint = t!("A CSV file with a header row, and a column named id with the IDs of the nodes. Its numeric columns are then listed in the node colors.");
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui
+            .button(t!("Attach attributes…"))
+            .on_hover_text(hint)
+            .clicked()
+            && let Some(path) = rfd::FileDialog::new()
+                .add_filter("CSV", &["csv", "tsv", "txt"])
+                .pick_file()
+        {
+            attach_attributes(
+                data,
+                std::fs::read_to_string(path).map_err(Into::into),
+                modal,
+            );
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            ui.toggle_value(&mut self.awaiting_attributes, t!("Attach attributes…"))
+                .on_hover_text(hint);
+            if self.awaiting_attributes {
+                ui.label(t!("Drop the file on the window."));
+                let dropped = ui
+                    .ctx()
+                    .input_mut(|i| std::mem::take(&mut i.raw.dropped_files));
+                if let Some(file) = dropped.into_iter().next() {
+                    self.awaiting_attributes = false;
+                    let text = match file.bytes {
+                        Some(bytes) => String::from_utf8(bytes.to_vec()).map_err(Into::into),
+                        None => Err(anyhow::anyhow!(t!("The file could not be read"))),
+                    };
+                    attach_attributes(data, text, modal);
+                }
+            }
+        }
+    }
+
+    fn show_curvature(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        if let Some(ref thread) = self.curve_thread
+            && thread.is_finished()
+        {
+            self.curve_thread = None;
+        }
+
+        let bundled = graph.read().edges_bundled;
+        let resp = ui.add_enabled(
+            !bundled && self.curve_thread.is_none(),
+            egui::Slider::new(&mut self.g_edge_curvature, 0.0..=0.5)
+                .text(t!("Curvature"))
+                .clamping(SliderClamping::Always),
+        )
+        .on_hover_text(t!("Bend the edges, so that those between dense clusters overlap less. Curved edges take more memory, and some may be left out on large graphs."));
+        // the vertices are rebuilt once the slider is released
+        if (resp.drag_stopped() || (resp.changed() && !resp.dragged()))
+            && self.g_edge_curvature != graph.read().edge_curvature
+        {
+            let curvature = self.g_edge_curvature;
+            let sort_edges = graph.read().sort_edges;
+            let data = data.clone();
+            let graph = graph.clone();
+            self.curve_thread = Some(spawn_cancelable(modal.clone(), move || {
+                let task = {
+                    let data = data.read();
+                    ui::rerender_graph(&data.persons, data.directed, curvature, sort_edges)
+                };
+                graph.write().tasks.push_back(task);
+                Ok(())
+            }));
+        }
+    }
+
+    fn show_bundling(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        if let Some(ref state) = self.bundle_state {
+            if state.thread.is_finished() {
+                self.bundle_state = None;
+            }
+        }
+
+        let bundled = graph.read().edges_bundled;
+        let mut bundle = bundled;
+        ui.add_enabled(
+            self.bundle_state.is_none(),
+            egui::Checkbox::new(&mut bundle, t!("Bundle edges")),
+        )
+        .on_hover_text(t!("Group similar edges together to make the overall structure more readable. This can take a while on large graphs."));
+
+        if let Some(ref mut state) = self.bundle_state {
+            state.status_rx.recv();
+            ui.horizontal(|ui| {
+                ui::spinner(ui);
+                show_progress_bar(ui, &state.status_rx);
+            });
+        }
+
+        if bundle == bundled {
+            return;
+        }
+
+        let (status_tx, status_rx) = status_pipe(ui.ctx());
+        let curvature = self.g_edge_curvature;
+        let sort_edges = graph.read().sort_edges;
+        let data = data.clone();
+        let graph = graph.clone();
+        let thread = spawn_cancelable(modal.clone(), move || {
+            let data = data.read();
+            let persons = &data.persons;
+            let task: GlTask = if bundle {
+                let max_edges = (MAX_VERTEX_BUFFER_SIZE / size_of::<PersonVertex>())
+                    .saturating_sub(persons.len())
+                    / VERTS_PER_BUNDLED_EDGE;
+                // same order as the initial buffer, longest edges first so they're drawn below
+                let edges = persons
+                    .iter()
+                    .get_edges()
+                    .sorted_unstable_by(|&(a1, b1), &(a2, b2)| {
+                        let d1 = (persons[a1].position - persons[b1].position).norm_squared();
+                        let d2 = (persons[a2].position - persons[b2].position).norm_squared();
+                        d2.total_cmp(&d1)
+                    })
+                    .take(max_edges)
+                    .collect_vec();
+                log!(status_tx, t!("Bundling %{num} edges", num = edges.len()));
+                let points = bundle_edges(&status_tx, persons, &edges)?;
+                let vertices = persons
+                    .iter()
+                    .map(create_node_vertex)
+                    .chain(
+                        edges
+                            .iter()
+                            .zip(points.iter())
+                            .flat_map(|(&(a, b), points)| {
+                                create_bundled_edge_vertices(&persons[a], &persons[b], points)
+                            }),
+                    )
+                    .collect_vec();
+                let (vertices, buckets) =
+                    bucket_edges(vertices, persons.len(), VERTS_PER_BUNDLED_EDGE);
+                let vertices = Arc::new(vertices);
+                Box::new(move |graph: &mut RenderedGraph, gl: &glow::Context| {
+                    graph.set_vertices(gl, vertices, VERTS_PER_BUNDLED_EDGE, buckets);
+                    graph.edges_bundled = true;
+                })
+            } else {
+                ui::rerender_graph(persons, data.directed, curvature, sort_edges)
+            };
+            graph.write().tasks.push_back(task);
+            Ok(())
+        });
+        self.bundle_state = Some(BundleState { thread, status_rx });
+    }
+}
+
+/// Switches the node color mode, once the values of the nodes have been computed.
+fn set_color_mode(data: &MyRwLock<ViewerData>, graph: &MyRwLock<RenderedGraph>, mode: ColorMode) {
+    let values = mode.node_values(&data.read().persons);
+    graph.write().tasks.push_back(Box::new(
+        move |graph: &mut RenderedGraph, gl: &glow::Context| {
+            graph.set_color_mode(gl, mode, values);
+        },
+    ));
+}
+
+/// Attaches the numeric columns of a CSV file, and reports how many of its rows matched a node.
+fn attach_attributes(
+    data: &MyRwLock<ViewerData>,
+    text: anyhow::Result<String>,
+    modal: &impl ModalWriter,
+) {
+    let info = match text.and_then(|text| parse_csv(&text, &data.read().persons)) {
+        Ok(attached) => {
+            let body = t!(
+                "%{columns} columns attached to %{matched} nodes. %{unmatched} rows have an ID that isn't in the graph, and were left out.",
+                columns = attached.columns.len(),
+                matched = attached.matched,
+                unmatched = attached.unmatched
+            );
+            data.write().attributes = attached.columns.into_iter().map(Arc::new).collect();
+            ModalInfo {
+                title: t!("Attributes").to_string(),
+                kind: ModalKind::Info,
+                body: body.to_string().into(),
+            }
+        }
+        Err(e) => ModalInfo {
+            title: t!("Error").to_string(),
+            kind: ModalKind::Error,
+            body: e.to_string().into(),
+        },
+    };
+    modal.send(info);
 }
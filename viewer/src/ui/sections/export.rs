@@ -0,0 +1,215 @@
+use crate::app::{show_progress_bar, ViewerData};
+use crate::graph_render::camera::Camera;
+use crate::graph_render::export::{
+    encode_png, pick_file, render_task, save_file, ExportFormat, ExportSettings, ExportView, JSON,
+    MAX_EXPORT_PIXELS, MAX_EXPORT_SIZE, PNG,
+};
+use crate::graph_render::RenderedGraph;
+use crate::graph_storage::export_json;
+use crate::log;
+use crate::thread::JoinHandle;
+use crate::threading::{spawn_cancelable, status_pipe, MyRwLock, StatusReader};
+use crate::ui;
+use crate::ui::class::ClassSection;
+use crate::ui::modal::{ModalInfo, ModalKind, ModalWriter};
+use crate::ui::sections::display::DisplaySection;
+use egui::Ui;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct ExportSection {
+    settings: ExportSettings,
+    /// Only export the nodes passing the filters to JSON.
+    json_visible_only: bool,
+    state: Option<ExportState>,
+}
+
+pub struct ExportState {
+    thread: JoinHandle<()>,
+    status_rx: StatusReader,
+    file_rx: Receiver<Vec<u8>>,
+    path: PathBuf,
+    format: ExportFormat,
+}
+
+impl ExportSection {
+    pub(crate) fn show(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        display: &DisplaySection,
+        camera: &Camera,
+        modal: &impl ModalWriter,
+    ) {
+        ui.strong(t!("Export image"));
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.settings.width)
+                    .range(1..=MAX_EXPORT_SIZE)
+                    .suffix(" px"),
+            );
+            ui.label("×");
+            ui.add(
+                egui::DragValue::new(&mut self.settings.height)
+                    .range(1..=MAX_EXPORT_SIZE)
+                    .suffix(" px"),
+            );
+        });
+        ui.add(
+            egui::Slider::new(&mut self.settings.supersampling, 1..=4)
+                .text(t!("Supersampling")),
+        )
+        .on_hover_text(t!("Render the image at a higher resolution and scale it down, for smoother nodes and edges."));
+
+        if let Some(ref mut state) = self.state {
+            // checked first so that a PNG sent just before the thread ended isn't lost
+            let finished = state.thread.is_finished();
+            // the file can only be downloaded from the main thread in the browser, so
+            // the worker hands it back instead of saving it itself
+            if let Ok(file) = state.file_rx.try_recv()
+                && let Err(e) = save_file(&file, &state.path, state.format)
+            {
+                modal.send(ModalInfo {
+                    title: t!("Error").to_string(),
+                    kind: ModalKind::Error,
+                    body: e.to_string().into(),
+                });
+            }
+            if finished {
+                self.state = None;
+            }
+        }
+
+        if let Some(ref mut state) = self.state {
+            state.status_rx.recv();
+            ui.horizontal(|ui| {
+                ui::spinner(ui);
+                show_progress_bar(ui, &state.status_rx);
+            });
+        } else {
+            let pixels = self.settings.width as u64 * self.settings.height as u64;
+            let too_large = pixels > MAX_EXPORT_PIXELS;
+            if too_large {
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    t!(
+                        "The image can't have more than %{max} million pixels",
+                        max = MAX_EXPORT_PIXELS / 1_000_000
+                    ),
+                );
+            }
+            if ui
+                .add_enabled(!too_large, egui::Button::new(t!("Export as PNG")))
+                .clicked()
+            {
+                self.export_png(data, graph, ui, display, camera, modal);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button(t!("Export nodes as JSON")).clicked()
+                    && let Some(path) = pick_file(JSON)
+                {
+                    self.start_json(data, graph, ui, modal, path);
+                }
+                ui.checkbox(&mut self.json_visible_only, t!("Only visible nodes"));
+            });
+        }
+    }
+
+    /// Whether an export is in progress, during which the filters must not change.
+    pub fn is_exporting(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Asks where to save the image, and starts exporting it unless an export is in progress.
+    pub(crate) fn export_png(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &Ui,
+        display: &DisplaySection,
+        camera: &Camera,
+        modal: &impl ModalWriter,
+    ) {
+        if self.state.is_none()
+            && let Some(path) = pick_file(PNG)
+        {
+            self.start(data, graph, ui, display, camera, modal, path);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &Ui,
+        display: &DisplaySection,
+        camera: &Camera,
+        modal: &impl ModalWriter,
+        path: PathBuf,
+    ) {
+        let view = ExportView {
+            camera: *camera,
+            edges: (display.g_show_edges, display.g_opac_edges),
+            edge_width: display.edge_half_width(camera),
+            nodes: (display.g_show_nodes, display.g_opac_nodes),
+            node_sizes: display.node_sizes(&graph.read()),
+            class_colors: ClassSection::display_colors(ui.ctx(), &data.read().modularity_classes),
+            background: display.g_background,
+        };
+        let (band_tx, band_rx) = mpsc::channel();
+        let settings = self.settings;
+        graph
+            .write()
+            .tasks
+            .push_back(render_task(settings, view, band_tx));
+        ui.ctx().request_repaint();
+
+        let (status_tx, status_rx) = status_pipe(ui.ctx());
+        let (png_tx, png_rx) = mpsc::channel();
+        let thread = spawn_cancelable(modal.clone(), move || {
+            log!(status_tx, t!("Rendering the image"));
+            png_tx.send(encode_png(&status_tx, settings, band_rx)?)?;
+            Ok(())
+        });
+        self.state = Some(ExportState {
+            thread,
+            status_rx,
+            file_rx: png_rx,
+            path,
+            format: PNG,
+        });
+    }
+
+    fn start_json(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &Ui,
+        modal: &impl ModalWriter,
+        path: PathBuf,
+    ) {
+        let filter = self.json_visible_only.then(|| graph.read().node_filter);
+        let data = data.clone();
+
+        let (status_tx, status_rx) = status_pipe(ui.ctx());
+        let (json_tx, json_rx) = mpsc::channel();
+        let thread = spawn_cancelable(modal.clone(), move || {
+            log!(status_tx, t!("Exporting the nodes"));
+            let json = export_json(&data.read(), |p| filter.is_none_or(|f| f.shows(p)))?;
+            json_tx.send(json)?;
+            Ok(())
+        });
+        self.state = Some(ExportState {
+            thread,
+            status_rx,
+            file_rx: json_rx,
+            path,
+            format: JSON,
+        });
+    }
+}
@@ -1,21 +1,33 @@
+use crate::algorithms::closeness::{distance_distribution, DistanceDistribution};
+use crate::algorithms::clustering::local_clustering;
+use crate::algorithms::components::connected_components;
+use crate::algorithms::pathfinding::PathSectionSettings;
 use crate::app::{GraphTabState, Person, ViewerData};
 use crate::graph_render::camera::Camera;
-use crate::graph_render::GlForwarder;
-use crate::threading::{spawn_cancelable, status_pipe, Cancelable, MyRwLock, StatusWriter};
+use crate::graph_render::{packing, GlForwarder, RenderedGraph};
+use crate::threading::{
+    spawn_cancelable, status_pipe, AlgoHandle, CancelFlag, Cancelable, MyRwLock, StatusWriter,
+    StatusWriterInterface,
+};
+use crate::ui::algos::show_running;
 use crate::ui::class::ClassSection;
+use crate::ui::display::DisplaySection;
 use crate::ui::modal::ModalWriter;
 use crate::ui::path::PathSection;
-use crate::ui::tabs::{create_tab, NewTabRequest};
-use crate::ui::widgets::combo_filter::{combo_with_filter, COMBO_WIDTH};
-use crate::ui::{ParadoxState, SelectedUserField, UiState};
-use crate::{for_progress, log, ui};
+use crate::ui::tabs::{create_tab, Appear, CamAnimating, NewTabRequest, TabCamera};
+use crate::ui::widgets::combo_filter::{combo_with_filter, COMBO_WIDTH, DEFAULT_RESULTS};
+use crate::ui::{ClosenessState, ClusteringState, ParadoxState, SelectedUserField, UiState};
+use crate::utils::format_count;
+use crate::{for_progress, log, thread, ui};
 use ahash::{AHashMap, AHashSet};
 use derivative::Derivative;
 use eframe::emath::vec2;
 use eframe::epaint::Color32;
-use egui::{CollapsingHeader, Hyperlink, Id, SliderClamping, Ui};
-use graph_format::EdgeStore;
+use egui::{CollapsingHeader, Context, Hyperlink, Id, SliderClamping, Ui};
+use egui_plot::{Bar, BarChart, Plot};
+use graph_format::{EdgeStore, Point};
 use itertools::Itertools;
+use rand::seq::IteratorRandom;
 use std::sync::{mpsc, Arc};
 
 #[derive(Derivative)]
@@ -25,7 +37,103 @@ pub struct InfosSection {
     pub infos_open: bool,
     #[derivative(Default(value = "1"))]
     pub neighborhood_degree: usize,
+    /// Maximum number of nodes in a neighborhood subgraph.
+    #[derivative(Default(value = "100_000"))]
+    pub neighborhood_budget: usize,
     pub paradox: ParadoxState,
+    pub closeness: ClosenessState,
+    pub clustering: ClusteringState,
+    /// Neighborhood being revealed in the graph, one ring at a time.
+    expansion: Option<Expansion>,
+    expansion_thread: Option<AlgoHandle<Vec<Vec<usize>>>>,
+    /// Builds the vertices of the rings revealed, or of the whole graph once the expansion stops.
+    reveal_thread: Option<AlgoHandle<()>>,
+    /// Whether the graph doesn't show the current state of the expansion yet.
+    reveal_dirty: bool,
+}
+
+/// Number of bars of the distance distribution, the last one counting the nodes at this distance
+/// or further.
+const DISTANCE_BARS: usize = 10;
+
+/// Seconds between two rings of the neighborhood being revealed.
+const EXPANSION_STEP: f64 = 1.0;
+
+/// Neighborhood revealed one ring at a time by "Expand interactively".
+struct Expansion {
+    /// Nodes at each distance from the center, the first ring being the center itself.
+    rings: Arc<Vec<Vec<usize>>>,
+    /// Number of rings revealed.
+    shown: usize,
+    playing: bool,
+    /// Time the last ring was revealed at, in seconds.
+    last_step: f64,
+}
+
+impl Expansion {
+    /// Shows the controls, and moves to the next ring when it's due. Returns whether the rings
+    /// shown changed, and whether the expansion was stopped.
+    fn show_controls(&mut self, ui: &mut Ui, now: f64) -> (bool, bool) {
+        let mut changed = false;
+        let total = self.rings.len();
+        if self.playing {
+            if self.shown >= total {
+                self.playing = false;
+            } else if now - self.last_step >= EXPANSION_STEP {
+                self.shown += 1;
+                self.last_step = now;
+                changed = true;
+            } else {
+                ui.ctx()
+                    .request_repaint_after_secs((self.last_step + EXPANSION_STEP - now) as f32);
+            }
+        }
+
+        let mut stop = false;
+        ui.horizontal(|ui| {
+            ui.label(t!(
+                "Degree %{deg} of %{max}",
+                deg = self.shown - 1,
+                max = total - 1
+            ));
+            let (icon, hint) = if self.playing {
+                ("⏸", t!("Pause"))
+            } else {
+                ("▶", t!("Play"))
+            };
+            if ui
+                .add_enabled(self.shown < total, egui::Button::new(icon))
+                .on_hover_text(hint)
+                .clicked()
+            {
+                self.playing = !self.playing;
+                self.last_step = now;
+            }
+            if ui
+                .add_enabled(self.shown < total, egui::Button::new("⏭"))
+                .on_hover_text(t!("Next degree"))
+                .clicked()
+            {
+                self.shown += 1;
+                self.last_step = now;
+                changed = true;
+            }
+            if ui
+                .add_enabled(self.shown > 1, egui::Button::new("⏮"))
+                .on_hover_text(t!("Back to the center"))
+                .clicked()
+            {
+                self.shown = 1;
+                self.playing = false;
+                changed = true;
+            }
+            stop = ui
+                .button("🗙")
+                .on_hover_text(t!("Show the whole graph again"))
+                .clicked();
+        });
+        (changed, stop)
+    }
 }
 
 impl InfosSection {
@@ -39,10 +147,13 @@ impl InfosSection {
         data_rw: &Arc<MyRwLock<ViewerData>>,
         tab_request: &mut Option<NewTabRequest>,
         ui: &mut Ui,
-        camera: &Camera,
+        tab_camera: &mut TabCamera,
+        cid: Id,
         path_section: &PathSection,
+        display: &DisplaySection,
         sel_field: &mut SelectedUserField,
         modal: &impl ModalWriter,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
     ) {
         CollapsingHeader::new(t!("Infos"))
             .id_salt("infos")
@@ -51,8 +162,22 @@ impl InfosSection {
                 ui.horizontal(|ui| {
                     ui::set_bg_color_tinted(Color32::GREEN, ui);
                     ui.radio_value(sel_field, SelectedUserField::Selected, "");
-                    combo_with_filter(ui, "#infos_user", &mut self.infos_current, data_rw);
+                    combo_with_filter(
+                        ui,
+                        "#infos_user",
+                        &mut self.infos_current,
+                        data_rw,
+                        DEFAULT_RESULTS,
+                        None,
+                    );
+                    if let Some(id) = self.infos_current {
+                        if ui.button("⌖").on_hover_text(t!("Center on person")).clicked() {
+                            let pos = data_rw.read().persons[id].position;
+                            fly_to(tab_camera, cid, pos, ui);
+                        }
+                    }
                 });
+                let camera = &tab_camera.camera;
                 if let Some(id) = self.infos_current {
                     let data = &*data_rw.read();
                     let person = &data.persons[id];
@@ -82,14 +207,32 @@ impl InfosSection {
                         ui.label(t!("Friends:"));
                         ui.label(format!("{}", person.neighbors.len()));
                         ui.end_row();
+                        if data.directed {
+                            ui.label(t!("Out-degree:"));
+                            ui.label(format!("{}", person.out_degree));
+                            ui.end_row();
+                            ui.label(t!("In-degree:"));
+                            ui.label(format!("{}", person.in_neighbors().len()));
+                            ui.end_row();
+                        }
                         ui.label(t!("Class:"));
                         ui.horizontal(|ui| {
                             ClassSection::class_circle(ui, &data.modularity_classes[class as usize]);
-                            self.create_class_subgraph(data_rw, tab_request, camera, path_section, modal, class, ui);
+                            self.create_class_subgraph(data_rw, tab_request, camera, path_section, display, modal, class, ui);
                         });
                         ui.end_row();
                     });
 
+                    if let Some(meta) = &data.meta {
+                        if ui
+                            .button(t!("Open the members of the class"))
+                            .on_hover_text(t!("Open a tab with the members of this class in the graph it comes from."))
+                            .clicked()
+                        {
+                            *tab_request = Some(meta.open_class(class, *camera, display.inherited(), ui.ctx(), modal.clone()));
+                        }
+                    }
+
                     CollapsingHeader::new(t!("Friends"))
                         .id_salt("friends")
                         .default_open(false)
@@ -97,14 +240,25 @@ impl InfosSection {
                             egui::ScrollArea::vertical().max_height(200.0).show(
                                 ui,
                                 |ui| {
-                                    for (neighb, name) in person
+                                    for (neighb, name, out) in person
                                         .neighbors
                                         .iter()
-                                        .map(|&i| (i, data.persons[i].name))
-                                        .sorted_unstable_by(|(_, a), (_, b)| a.cmp(b))
+                                        .enumerate()
+                                        .map(|(n, &i)| (i, data.persons[i].name, n < person.out_degree))
+                                        .sorted_unstable_by(|(_, a, _), (_, b, _)| a.cmp(b))
                                     {
+                                        let label = match (data.directed, out) {
+                                            (false, _) => name.to_string(),
+                                            (true, true) => format!("→ {}", name),
+                                            (true, false) => format!("← {}", name),
+                                        };
+                                        // the links between the classes of a meta-graph
+                                        let label = match &data.meta {
+                                            Some(meta) => format!("{} ({})", label, meta.link_count(id, neighb)),
+                                            None => label,
+                                        };
                                         if ui
-                                            .add(egui::Button::new(name).min_size(
+                                            .add(egui::Button::new(label).min_size(
                                                 vec2(COMBO_WIDTH - 18.0, 0.0),
                                             ))
                                             .clicked()
@@ -135,6 +289,41 @@ impl InfosSection {
                                 self.paradox = ParadoxState { current: Some(id), sum, min, max };
                             }
 
+                            if self.closeness.current != self.infos_current {
+                                self.closeness.cancel.cancel();
+                                let data = data_rw.clone();
+                                let cancel = CancelFlag::default();
+                                let cancel_thr = cancel.clone();
+                                self.closeness = ClosenessState {
+                                    current: Some(id),
+                                    thread: Some(thread::spawn(move || {
+                                        distance_distribution(&data.read().persons, id, &cancel_thr)
+                                            .ok()
+                                    })),
+                                    cancel,
+                                    value: None,
+                                };
+                            }
+
+                            if let Some(thr) = self.closeness.thread.take_if(|thr| thr.is_finished()) {
+                                self.closeness.value = thr.join().ok().flatten();
+                            }
+
+                            if self.clustering.current != self.infos_current {
+                                let data = data_rw.clone();
+                                self.clustering = ClusteringState {
+                                    current: Some(id),
+                                    thread: Some(thread::spawn(move || {
+                                        local_clustering(&data.read().persons, id)
+                                    })),
+                                    value: None,
+                                };
+                            }
+
+                            if let Some(thr) = self.clustering.thread.take_if(|thr| thr.is_finished()) {
+                                self.clustering.value = thr.join().ok();
+                            }
+
                             let state = &self.paradox;
 
                             egui::Grid::new("#paradox").show(ui, |ui| {
@@ -150,9 +339,39 @@ impl InfosSection {
                                 ui.label(t!("Friends of friends (max):"));
                                 ui.label(format!("{}", state.max));
                                 ui.end_row();
+                                ui.label(t!("Closeness centrality:"));
+                                match (&self.closeness.thread, &self.closeness.value) {
+                                    (_, Some(dist)) => ui.label(format!("{:.4}", dist.closeness())),
+                                    (Some(_), None) => ui::spinner(ui),
+                                    (None, None) => ui.label("?"),
+                                };
+                                ui.end_row();
+                                ui.label(t!("Clustering coefficient:"))
+                                    .on_hover_text(t!("Fraction of the pairs of friends of the person who are friends with each other"));
+                                match (&self.clustering.thread, self.clustering.value) {
+                                    (_, Some(value)) => ui.label(format!("{:.4}", value)),
+                                    (Some(_), None) => ui::spinner(ui),
+                                    (None, None) => ui.label("?"),
+                                };
+                                ui.end_row();
                             });
+
+                            if let Some(dist) = &self.closeness.value {
+                                show_distance_distribution(ui, dist);
+                            }
                         });
 
+                    ui.horizontal(|ui| {
+                        ui.style_mut().spacing.slider_width = 100.0;
+                        ui.add(
+                            egui::Slider::new(&mut self.neighborhood_budget, 1000..=1_000_000)
+                                .text(t!("Max nodes"))
+                                .logarithmic(true)
+                                .clamping(SliderClamping::Always),
+                        )
+                        .on_hover_text(t!("When the next degree would go over this number of nodes, a random sample of its nodes is taken and the neighborhood stops there."));
+                    });
+
                     ui.horizontal(|ui| {
                         ui.style_mut().spacing.slider_width = 100.0;
                         ui.add(
@@ -165,52 +384,139 @@ impl InfosSection {
                             .on_hover_text(t!("Show friends up to a certain distance from the person. Degree 1 will show direct friends, degree 2 friends of friends, etc."))
                             .clicked() {
                             let neighborhood_degree = self.neighborhood_degree;
+                            let budget = self.neighborhood_budget;
                             self.create_subgraph(
                                 t!("%{deg}-neighborhood of %{name}", deg = neighborhood_degree, name = person.name).to_string(),
-                                data_rw, tab_request, camera, path_section, ui, modal.clone(),
+                                data_rw, tab_request, camera, path_section, display, ui, modal.clone(),
                                 move |status_tx, data| {
-                                    let mut new_included = AHashSet::from([id]);
-                                    let mut last_batch = AHashSet::from([id]);
-                                    for i in 0..neighborhood_degree {
-                                        let mut new_friends = AHashSet::new();
-                                        for person in last_batch.iter() {
-                                            new_friends.extend(
-                                                data.persons[*person]
-                                                    .neighbors
-                                                    .iter()
-                                                    .copied()
-                                                    .filter(|&i| !new_included.contains(&i)),
-                                            );
-                                        }
-                                        if new_friends.is_empty() {
-                                            log!(status_tx, t!("No new friends at degree %{deg}", deg = i + 1));
-                                            if last_batch.len() < 50 {
-                                                log!(status_tx, "{}: {:?}", t!("At %{deg}", deg = i), last_batch.iter().map(|i| data.persons[*i].name).collect::<Vec<_>>());
-                                            }
-                                            break;
-                                        }
-                                        new_included.extend(new_friends.iter().copied());
-                                        log!(status_tx, t!("%{num} new friends at degree %{deg}", num = new_friends.len(), deg = i + 1));
-                                        last_batch = new_friends;
-                                    }
-
+                                    let rings = neighborhood_rings(status_tx, &CancelFlag::default(), &data.persons, id, neighborhood_degree, budget)?;
+                                    let new_included: AHashSet<usize> = rings.into_iter().flatten().collect();
                                     log!(status_tx, t!("Got %{len} friends", len = new_included.len()));
                                     Ok(new_included)
                                 });
                         }
                     });
+
+                    if ui
+                        .add_enabled(
+                            self.expansion_thread.is_none(),
+                            egui::Button::new(t!("Expand interactively")),
+                        )
+                        .on_hover_text(t!("Reveal the neighborhood in this tab one degree at a time, to watch it grow."))
+                        .clicked()
+                    {
+                        let data = data_rw.clone();
+                        let degree = self.neighborhood_degree;
+                        let budget = self.neighborhood_budget;
+                        self.expansion_thread = Some(AlgoHandle::spawn(
+                            ui.ctx(),
+                            modal.clone(),
+                            move |status_tx, cancel| {
+                                neighborhood_rings(status_tx, cancel, &data.read().persons, id, degree, budget)
+                            },
+                        ));
+                    }
                 }
+                self.show_expansion(ui, data_rw, graph, modal);
             });
     }
 
+    /// Controls of the neighborhood being revealed, which also reveal the next ring when it's due.
+    fn show_expansion(
+        &mut self,
+        ui: &mut Ui,
+        data_rw: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        modal: &impl ModalWriter,
+    ) {
+        let now = ui.input(|i| i.time);
+        if let Some(thr) = self.expansion_thread.take_if(|thr| thr.is_finished()) {
+            if let Some(rings) = thr.join() {
+                self.expansion = Some(Expansion {
+                    rings: Arc::new(rings),
+                    shown: 1,
+                    playing: true,
+                    last_step: now,
+                });
+                self.reveal_dirty = true;
+            }
+        }
+        if let Some(ref mut thr) = self.expansion_thread {
+            show_running(ui, thr);
+        }
+        if let Some(ref mut exp) = self.expansion {
+            let (changed, stop) = exp.show_controls(ui, now);
+            if stop {
+                self.expansion = None;
+            }
+            self.reveal_dirty |= changed || stop;
+        }
+
+        if let Some(thr) = self.reveal_thread.take_if(|thr| thr.is_finished()) {
+            thr.join();
+        }
+        if !self.reveal_dirty {
+            return;
+        }
+        if let Some(ref thr) = self.reveal_thread {
+            // the vertices being built are already out of date, the next ones are started once
+            // it has stopped
+            thr.cancel();
+            ui.ctx().request_repaint();
+            return;
+        }
+        self.reveal_dirty = false;
+        let data = data_rw.clone();
+        let graph = graph.clone();
+        let rings = self
+            .expansion
+            .as_ref()
+            .map(|exp| (exp.rings.clone(), exp.shown));
+        let ctx = ui.ctx().clone();
+        self.reveal_thread = Some(AlgoHandle::spawn(
+            ui.ctx(),
+            modal.clone(),
+            move |_, cancel| {
+                let curvature = graph.read().edge_curvature;
+                let sort_edges = graph.read().sort_edges;
+                let task = {
+                    let data = data.read();
+                    match rings {
+                        Some((rings, shown)) => {
+                            let mut mask = vec![false; data.persons.len()];
+                            for &node in rings[..shown].iter().flatten() {
+                                mask[node] = true;
+                            }
+                            ui::reveal_graph(
+                                &data.persons,
+                                data.directed,
+                                curvature,
+                                sort_edges,
+                                &mask,
+                            )
+                        }
+                        None => {
+                            ui::rerender_graph(&data.persons, data.directed, curvature, sort_edges)
+                        }
+                    }
+                };
+                cancel.check()?;
+                graph.write().tasks.push_back(task);
+                ctx.request_repaint();
+                Ok(())
+            },
+        ));
+    }
+
     pub(crate) fn create_class_subgraph(
         &self,
         data_rw: &Arc<MyRwLock<ViewerData>>,
         tab_request: &mut Option<NewTabRequest>,
         camera: &Camera,
         path_section: &PathSection,
+        display: &DisplaySection,
         modal: &impl ModalWriter,
-        class: u16,
+        class: u32,
         ui: &mut Ui,
     ) {
         if ui.button(format!("{}", class)).clicked() {
@@ -220,84 +526,223 @@ impl InfosSection {
                 tab_request,
                 camera,
                 path_section,
+                display,
                 ui,
                 modal.clone(),
-                move |_, data| {
-                    Ok(data
-                        .persons
-                        .iter()
-                        .enumerate()
-                        .filter(|(_, p)| p.modularity_class == class)
-                        .map(|(i, _)| i)
-                        .collect())
-                },
+                move |_, data| Ok(class_members(&data.persons, class).collect()),
             );
         }
     }
 
-    fn create_subgraph(
+    pub(crate) fn create_subgraph(
         &self,
         title: String,
         data: &Arc<MyRwLock<ViewerData>>,
         tab_request: &mut Option<NewTabRequest>,
         camera: &Camera,
         path_section: &PathSection,
+        display: &DisplaySection,
         ui: &mut Ui,
         modal_tx: impl ModalWriter,
         x: impl FnOnce(&StatusWriter, &ViewerData) -> Cancelable<AHashSet<usize>> + Send + 'static,
     ) {
-        let (status_tx, status_rx) = status_pipe(ui.ctx());
-        let (state_tx, state_rx) = mpsc::channel();
-        let (gl_fwd, gl_mpsc) = GlForwarder::new();
-
-        *tab_request = Some(NewTabRequest {
-            id: Id::new((&title, chrono::Utc::now())),
+        *tab_request = Some(create_subgraph(
             title,
-            closeable: true,
-            state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
-        });
+            data,
+            *camera,
+            self.infos_current,
+            PathSectionSettings {
+                path_src: path_section.path_settings.path_src,
+                path_dest: path_section.path_settings.path_dest,
+                exclude_classes: path_section.path_settings.exclude_classes.clone(),
+                ..Default::default()
+            },
+            display.inherited(),
+            ui.ctx(),
+            modal_tx,
+            x,
+        ));
+    }
+}
+
+/// Minimum zoom level when centering on a node, relative to the default camera.
+const MIN_FOCUS_ZOOM: f32 = 8.0;
+
+/// Nodes of the given class, in increasing order.
+pub(crate) fn class_members(persons: &[Person], class: u32) -> impl Iterator<Item = usize> + '_ {
+    persons
+        .iter()
+        .enumerate()
+        .filter(move |(_, p)| p.modularity_class == class)
+        .map(|(i, _)| i)
+}
+
+/// Starts a camera animation towards the given point, keeping the current zoom level unless the
+/// view is too far out to see anything around the point.
+pub(crate) fn fly_to(camera: &mut TabCamera, cid: Id, pos: Point, ui: &Ui) {
+    let mut target = camera.camera;
+    let min_scaling = camera.camera_default.transf.scaling() * MIN_FOCUS_ZOOM;
+    if target.transf.scaling() < min_scaling {
+        target.transf.set_scaling(min_scaling);
+    }
+    ui.ctx().animate_bool_with_time(cid, true, 0.0);
+    camera.cam_animating = Some(CamAnimating::PanTo {
+        from: camera.camera.transf,
+        to: target.centered_on(pos),
+    });
+}
 
-        let infos_current = self.infos_current;
-        let path_src = path_section.path_settings.path_src;
-        let path_dest = path_section.path_settings.path_dest;
-        let camera = *camera;
+/// Nodes at each distance from `id`, up to `degree`, the first ring being `id` itself. When a ring
+/// would take the neighborhood over `budget` nodes, a random sample of it is kept and the search
+/// stops there.
+fn neighborhood_rings(
+    status_tx: &impl StatusWriterInterface,
+    cancel: &CancelFlag,
+    persons: &[Person],
+    id: usize,
+    degree: usize,
+    budget: usize,
+) -> Cancelable<Vec<Vec<usize>>> {
+    let mut included = AHashSet::from([id]);
+    let mut rings = vec![vec![id]];
+    for i in 0..degree {
+        cancel.check()?;
+        let last_batch = rings.last().unwrap();
+        let mut new_friends = AHashSet::new();
+        for person in last_batch.iter() {
+            new_friends.extend(
+                persons[*person]
+                    .neighbors
+                    .iter()
+                    .copied()
+                    .filter(|&i| !included.contains(&i)),
+            );
+        }
+        if new_friends.is_empty() {
+            log!(
+                status_tx,
+                t!("No new friends at degree %{deg}", deg = i + 1)
+            );
+            if last_batch.len() < 50 {
+                log!(
+                    status_tx,
+                    "{}: {:?}",
+                    t!("At %{deg}", deg = i),
+                    last_batch
+                        .iter()
+                        .map(|i| persons[*i].name)
+                        .collect::<Vec<_>>()
+                );
+            }
+            break;
+        }
+        let total = new_friends.len();
+        let room = budget.saturating_sub(included.len());
+        let truncated = total > room;
+        if truncated {
+            new_friends = new_friends
+                .into_iter()
+                .choose_multiple(&mut rand::thread_rng(), room)
+                .into_iter()
+                .collect();
+        }
+        included.extend(new_friends.iter().copied());
+        log!(
+            status_tx,
+            t!(
+                "%{num} new friends at degree %{deg}",
+                num = new_friends.len(),
+                deg = i + 1
+            )
+        );
+        if truncated {
+            log!(status_tx, t!("Neighborhood truncated at degree %{deg}: the limit of %{budget} nodes was reached, only %{num} of the %{total} friends at this degree were kept, picked at random", deg = i + 1, budget = budget, num = new_friends.len(), total = total));
+        }
+        rings.push(new_friends.into_iter().collect());
+        if truncated {
+            break;
+        }
+    }
+    Ok(rings)
+}
 
-        let data = data.clone();
-        spawn_cancelable(modal_tx, move || {
-            let new_included = x(&status_tx, &data.read())?;
+/// Creates a new tab containing the nodes selected by `x` in the given graph.
+///
+/// The selection and path settings are expressed in the source graph's indices, and are mapped
+/// to the new graph when the nodes are present in it.
+///
+/// The new tab starts with the given display settings, see [`DisplaySection::inherited`].
+pub(crate) fn create_subgraph(
+    title: String,
+    data: &Arc<MyRwLock<ViewerData>>,
+    camera: Camera,
+    infos_current: Option<usize>,
+    path_settings: PathSectionSettings,
+    display: DisplaySection,
+    ctx: &Context,
+    modal_tx: impl ModalWriter,
+    x: impl FnOnce(&StatusWriter, &ViewerData) -> Cancelable<AHashSet<usize>> + Send + 'static,
+) -> NewTabRequest {
+    let (status_tx, status_rx) = status_pipe(ctx);
+    let (state_tx, state_rx) = mpsc::channel();
+    let (gl_fwd, gl_mpsc) = GlForwarder::new();
 
-            let mut new_persons = Vec::with_capacity(new_included.len());
+    let request = NewTabRequest {
+        id: Id::new((&title, chrono::Utc::now())),
+        title,
+        closeable: true,
+        state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
+    };
 
-            let mut id_map = AHashMap::new();
-            let mut class_list = AHashSet::new();
+    let data = data.clone();
+    spawn_cancelable(modal_tx, move || {
+        let new_included = x(&status_tx, &data.read())?;
 
-            log!(status_tx, t!("Processing person list and creating ID map"));
-            {
-                let data = data.read();
-                for &id in new_included.iter() {
-                    let pers = &data.persons[id];
-                    id_map.insert(id, new_persons.len());
-                    class_list.insert(pers.modularity_class);
-                    new_persons.push(Person {
-                        neighbors: vec![],
-                        ..*pers
-                    });
-                }
+        let mut new_persons = Vec::with_capacity(new_included.len());
+        let mut base_ids = Vec::with_capacity(new_included.len());
+
+        let mut id_map = AHashMap::new();
+        let mut class_list = AHashSet::new();
+
+        log!(status_tx, t!("Processing person list and creating ID map"));
+        {
+            let data = data.read();
+            for &id in new_included.iter() {
+                let pers = &data.persons[id];
+                id_map.insert(id, new_persons.len());
+                class_list.insert(pers.modularity_class);
+                new_persons.push(Person {
+                    neighbors: vec![],
+                    // the subgraph has its own decomposition, but inherits the name filter
+                    core_number: 0,
+                    ..*pers
+                });
+                base_ids.push(data.base_id(id));
             }
+        }
 
-            let mut edges = Vec::new();
+        let mut edges = Vec::new();
 
-            log!(status_tx, t!("Creating new neighbor lists and edge list"));
-            {
-                let data = data.read();
-                for_progress!(status_tx, (&old_id, &new_id) in id_map.iter(), {
-                    new_persons[new_id].neighbors.extend(
-                        data.persons[old_id]
-                            .neighbors
-                            .iter()
-                            .filter_map(|&i| id_map.get(&i)),
-                    );
-                    for &nb in new_persons[new_id].neighbors.iter() {
+        log!(status_tx, t!("Creating new neighbor lists and edge list"));
+        let directed = data.read().directed;
+        {
+            let data = data.read();
+            for_progress!(status_tx, (&old_id, &new_id) in id_map.iter(), {
+                let old = &data.persons[old_id];
+                let new = &mut new_persons[new_id];
+                new.neighbors
+                    .extend(old.out_neighbors().iter().filter_map(|&i| id_map.get(&i)));
+                new.out_degree = new.neighbors.len();
+                new.neighbors
+                    .extend(old.in_neighbors().iter().filter_map(|&i| id_map.get(&i)));
+                if directed {
+                    // each edge is added once, by its source
+                    edges.extend(new.out_neighbors().iter().map(|&nb| EdgeStore {
+                        a: new_id as u32,
+                        b: nb as u32,
+                    }));
+                } else {
+                    for &nb in new.neighbors.iter() {
                         if new_id < nb {
                             edges.push(EdgeStore {
                                 a: new_id as u32,
@@ -307,55 +752,138 @@ impl InfosSection {
                             // we do nothing since we'll get it eventually
                         }
                     }
-                });
-            }
+                }
+            });
+        }
 
-            log!(status_tx, t!("Computing min edge filter"));
-
-            let mut filter = 1;
-            const MAX: usize = 10000;
-            while new_persons
-                .iter()
-                .filter(|p| p.neighbors.len() as u16 >= filter)
-                .enumerate()
-                .skip(MAX)
-                .next()
-                .is_some()
-            {
-                // count() would iterate all the nodes
-                filter += 1;
-            }
+        let components = connected_components(&new_persons);
+        if components.count() > 1 {
+            log::warn!("Subgraph has {} components", components.count());
+            log!(
+                status_tx,
+                t!(
+                    "Warning: the subgraph is not connected (%{count} components, the largest has %{largest} nodes)",
+                    count = components.count(),
+                    largest = components.largest()
+                )
+            );
+        }
 
-            let viewer = ViewerData::new(new_persons, data.read().modularity_classes.clone())?;
+        log!(status_tx, t!("Computing min edge filter"));
 
-            let mut new_ui = UiState::default();
+        let mut filter = 1;
+        const MAX: usize = 10000;
+        while new_persons
+            .iter()
+            .filter(|p| packing::filter_degree(p.neighbors.len()) >= filter)
+            .enumerate()
+            .skip(MAX)
+            .next()
+            .is_some()
+        {
+            // count() would iterate all the nodes
+            filter += 1;
+        }
 
-            // match path and selection
-            macro_rules! match_id {
-                ($field:expr, $self_expr:expr) => {
-                    if let Some(current) = $self_expr {
-                        if let Some(new_id) = id_map.get(&current) {
-                            $field = Some(*new_id);
-                        }
+        let mut viewer = ViewerData::new(new_persons, data.read().modularity_classes.clone())?;
+        viewer.base_ids = Some(base_ids);
+        viewer.base_engine = {
+            let data = data.read();
+            Some(
+                data.base_engine
+                    .clone()
+                    .unwrap_or_else(|| data.engine.clone()),
+            )
+        };
+        viewer.directed = directed;
+        viewer.strings = data.read().strings.clone();
+
+        let mut new_ui = UiState {
+            display,
+            ..Default::default()
+        };
+
+        // match path and selection
+        macro_rules! match_id {
+            ($field:expr, $self_expr:expr) => {
+                if let Some(current) = $self_expr {
+                    if let Some(new_id) = id_map.get(&current) {
+                        $field = Some(*new_id);
                     }
-                };
-            }
-            match_id!(new_ui.infos.infos_current, infos_current);
-            match_id!(new_ui.path.path_settings.path_src, path_src);
-            match_id!(new_ui.path.path_settings.path_dest, path_dest);
-            new_ui.path.path_dirty = true;
-
-            state_tx.send(create_tab(
-                viewer,
-                edges.iter(),
-                gl_fwd,
-                filter,
-                camera,
-                new_ui,
-                status_tx,
-            )?)?;
+                }
+            };
+        }
+        match_id!(new_ui.infos.infos_current, infos_current);
+        match_id!(new_ui.path.path_settings.path_src, path_settings.path_src);
+        match_id!(new_ui.path.path_settings.path_dest, path_settings.path_dest);
+        new_ui.path.path_settings.exclude_ids = path_settings
+            .exclude_ids
+            .iter()
+            .filter_map(|i| id_map.get(i))
+            .copied()
+            .collect();
+        new_ui.path.path_settings.path_no_direct = path_settings.path_no_direct;
+        new_ui.path.path_settings.path_no_mutual = path_settings.path_no_mutual;
+        new_ui.path.path_settings.path_directed = path_settings.path_directed;
+        new_ui.path.path_settings.max_paths = path_settings.max_paths;
+        // the subgraph keeps the classes of its parent, only those still present are relevant
+        new_ui.path.path_settings.exclude_classes = path_settings
+            .exclude_classes
+            .iter()
+            .copied()
+            .filter(|&class| viewer.persons.iter().any(|p| p.modularity_class == class))
+            .collect();
+        new_ui.path.path_dirty = true;
 
-            Ok(())
-        });
+        let mut tab = create_tab(viewer, edges, gl_fwd, filter, camera, new_ui, status_tx)?;
+        Appear::start(&mut tab, Some(&data.read()));
+        state_tx.send(tab)?;
+
+        Ok(())
+    });
+
+    request
+}
+
+/// Bar chart of the number of nodes at each distance from the selected node.
+fn show_distance_distribution(ui: &mut Ui, dist: &DistanceDistribution) {
+    let shown = dist.counts.len().min(DISTANCE_BARS - 1);
+    let mut buckets = (1..=shown)
+        .map(|d| (d.to_string(), dist.counts[d - 1]))
+        .collect_vec();
+    if dist.counts.len() > shown {
+        buckets.push((
+            format!("{}+", DISTANCE_BARS),
+            dist.counts[shown..].iter().sum(),
+        ));
+    }
+
+    let bars = buckets
+        .into_iter()
+        .zip(1..)
+        .map(|((label, count), x)| {
+            Bar::new(x as f64, count as f64).name(t!(
+                "Distance %{dist}: %{count} nodes",
+                dist = label,
+                count = format_count(count as usize)
+            ))
+        })
+        .collect();
+    let chart = BarChart::new(bars).element_formatter(Box::new(|bar, _| bar.name.clone()));
+
+    Plot::new("distance_distribution")
+        .height(100.0)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .allow_drag(false)
+        .x_axis_label(t!("Distance"))
+        .y_axis_label(t!("Nodes"))
+        .show(ui, |plot_ui| plot_ui.bar_chart(chart));
+
+    if dist.unreachable > 0 {
+        ui.label(t!(
+            "%{count} nodes can't be reached",
+            count = format_count(dist.unreachable as usize)
+        ));
     }
 }
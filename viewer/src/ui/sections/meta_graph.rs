@@ -0,0 +1,181 @@
+use crate::algorithms::meta_graph::collapse_classes;
+use crate::algorithms::pathfinding::PathSectionSettings;
+use crate::app::{GraphTabState, Person, StringTables, ViewerData};
+use crate::graph_render::camera::Camera;
+use crate::graph_render::{GlForwarder, NodeSizeMode};
+use crate::threading::{spawn_cancelable, status_pipe, MyRwLock};
+use crate::ui::display::DisplaySection;
+use crate::ui::infos::{class_members, create_subgraph};
+use crate::ui::modal::ModalWriter;
+use crate::ui::tabs::{create_tab, Appear, NewTabRequest};
+use crate::ui::UiState;
+use crate::utils::str_from_null_terminated_utf8;
+use ahash::AHashMap;
+use egui::{Context, Id};
+use graph_format::EdgeStore;
+use itertools::Itertools;
+use std::sync::{mpsc, Arc};
+
+/// Graph with a node for each class of another graph, linked to the classes its members have
+/// links to. Each node has the class it stands for, and its number of members as its size.
+#[derive(Clone)]
+pub struct MetaGraph {
+    /// Graph the classes come from.
+    pub base: Arc<MyRwLock<ViewerData>>,
+    /// Number of links between the classes of each edge, by the ends of the edge in increasing
+    /// order.
+    pub link_counts: AHashMap<(usize, usize), u64>,
+}
+
+impl MetaGraph {
+    /// Number of links between the classes of two linked nodes of the meta-graph.
+    pub fn link_count(&self, a: usize, b: usize) -> u64 {
+        self.link_counts
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Creates a new tab with the members of a class in the graph the classes come from.
+    pub fn open_class(
+        &self,
+        class: u32,
+        camera: Camera,
+        mut display: DisplaySection,
+        ctx: &Context,
+        modal_tx: impl ModalWriter,
+    ) -> NewTabRequest {
+        // the filter was applied to the names of the classes
+        display.clear_name_filter();
+        create_subgraph(
+            t!("Class %{class}", class = class).to_string(),
+            &self.base,
+            camera,
+            None,
+            PathSectionSettings::default(),
+            display,
+            ctx,
+            modal_tx,
+            move |_, data| Ok(class_members(&data.persons, class).collect()),
+        )
+    }
+}
+
+/// Creates a new tab with the meta-graph of the classes of the given graph. Each class is placed
+/// at the centroid of its members, and classes without members are left out.
+pub(crate) fn create_meta_graph(
+    data: &Arc<MyRwLock<ViewerData>>,
+    camera: Camera,
+    mut display: DisplaySection,
+    ctx: &Context,
+    modal_tx: impl ModalWriter,
+) -> NewTabRequest {
+    // the names of the classes aren't matched against the filter
+    display.clear_name_filter();
+    let (status_tx, status_rx) = status_pipe(ctx);
+    let (state_tx, state_rx) = mpsc::channel();
+    let (gl_fwd, gl_mpsc) = GlForwarder::new();
+
+    let title = t!("Classes").to_string();
+    let request = NewTabRequest {
+        id: Id::new((&title, chrono::Utc::now())),
+        title,
+        closeable: true,
+        state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
+    };
+
+    let base = data.clone();
+    spawn_cancelable(modal_tx, move || {
+        let (viewer, edges) = {
+            let data = base.read();
+            let classes = &data.modularity_classes;
+            let collapsed =
+                collapse_classes(&status_tx, &data.persons, data.directed, classes.len());
+
+            // the nodes only borrow their strings, which are kept along with the graph
+            let mut ids = Vec::new();
+            let mut names = Vec::new();
+            let offsets = collapsed
+                .classes
+                .iter()
+                .map(|&class| {
+                    let cl = &classes[class as usize];
+                    let offsets = (ids.len(), names.len());
+                    ids.extend(cl.id.to_string().bytes());
+                    ids.push(0);
+                    names.extend(cl.name.bytes().filter(|&b| b != 0));
+                    names.push(0);
+                    offsets
+                })
+                .collect_vec();
+            let strings = Arc::new(StringTables { ids, names });
+
+            let mut persons = offsets
+                .iter()
+                .enumerate()
+                .map(|(i, &(id, name))| {
+                    // SAFETY: the strings are null-terminated, and the tables are never modified
+                    let (id, name) = unsafe {
+                        (
+                            str_from_null_terminated_utf8(strings.ids.as_ptr().add(id)),
+                            str_from_null_terminated_utf8(strings.names.as_ptr().add(name)),
+                        )
+                    };
+                    Person::new(
+                        collapsed.centroids[i],
+                        collapsed.members[i] as f32,
+                        collapsed.classes[i],
+                        id,
+                        name,
+                        0,
+                    )
+                })
+                .collect_vec();
+
+            let link_counts = collapsed.link_counts;
+            let edges = link_counts
+                .keys()
+                .sorted()
+                .map(|&(a, b)| {
+                    persons[a].neighbors.push(b);
+                    persons[b].neighbors.push(a);
+                    EdgeStore {
+                        a: a as u32,
+                        b: b as u32,
+                    }
+                })
+                .collect_vec();
+            for p in persons.iter_mut() {
+                p.out_degree = p.neighbors.len();
+            }
+
+            let mut viewer = ViewerData::new(persons, classes.clone())?;
+            viewer.meta = Some(MetaGraph {
+                base: base.clone(),
+                link_counts,
+            });
+            viewer.strings = Some(strings);
+            (viewer, edges)
+        };
+
+        let mut tab = create_tab(
+            viewer,
+            edges,
+            gl_fwd,
+            0,
+            camera,
+            UiState {
+                display,
+                ..Default::default()
+            },
+            status_tx,
+        )?;
+        tab.ui_state.display.g_node_size = NodeSizeMode::File;
+        Appear::start(&mut tab, None);
+        state_tx.send(tab)?;
+
+        Ok(())
+    });
+
+    request
+}
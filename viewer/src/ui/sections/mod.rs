@@ -1,6 +1,14 @@
 pub mod display;
 pub mod path;
 pub mod class;
+pub mod components;
 pub mod infos;
 pub mod details;
-pub mod algos;
\ No newline at end of file
+pub mod algos;
+pub mod search;
+pub mod stats;
+pub mod export;
+pub mod selection;
+pub mod mutual_matrix;
+pub mod class_links;
+pub mod meta_graph;
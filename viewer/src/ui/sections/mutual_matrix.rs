@@ -0,0 +1,97 @@
+use crate::app::Person;
+use crate::graph_render::colormap::viridis;
+use crate::ui::infos::class_members;
+use ahash::AHashSet;
+use egui::{vec2, Rect, Sense, Ui};
+
+/// Highest class size the matrix can be computed for, whatever the limit chosen.
+pub const MAX_MATRIX_MEMBERS: usize = 100;
+
+/// Side of a cell of the matrix, in points.
+const CELL_SIZE: f32 = 8.0;
+
+/// Number of mutual friends of each pair of members of a class.
+pub struct MutualMatrix {
+    pub class: u32,
+    members: Vec<usize>,
+    /// `counts[i * n + j]` is the number of mutual friends of members `i` and `j`.
+    counts: Vec<u32>,
+    /// Highest count between two different members, which gets the brightest color.
+    max: u32,
+}
+
+impl MutualMatrix {
+    /// Computes the matrix of the class, unless it has more than `limit` members, in which case
+    /// the size of the class is returned.
+    pub fn compute(persons: &[Person], class: u32, limit: usize) -> Result<MutualMatrix, usize> {
+        let members: Vec<_> = class_members(persons, class).collect();
+        if members.len() > limit {
+            return Err(members.len());
+        }
+        let n = members.len();
+        let neighbors: Vec<AHashSet<usize>> = members
+            .iter()
+            .map(|&m| persons[m].neighbors.iter().copied().collect())
+            .collect();
+        let mut counts = vec![0; n * n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let (small, large) = if neighbors[i].len() < neighbors[j].len() {
+                    (&neighbors[i], &neighbors[j])
+                } else {
+                    (&neighbors[j], &neighbors[i])
+                };
+                let count = small.iter().filter(|n| large.contains(n)).count() as u32;
+                counts[i * n + j] = count;
+                counts[j * n + i] = count;
+            }
+        }
+        let max = counts.iter().copied().max().unwrap_or(0);
+        Ok(MutualMatrix {
+            class,
+            members,
+            counts,
+            max,
+        })
+    }
+
+    /// Draws the matrix as a heatmap, with the counts and names of the pair under the pointer.
+    pub fn show(&self, ui: &mut Ui, persons: &[Person]) {
+        let n = self.members.len();
+        if n < 2 {
+            ui.label(t!("The class needs at least two members."));
+            return;
+        }
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(n as f32, n as f32) * CELL_SIZE, Sense::hover());
+        let painter = ui.painter_at(rect);
+        let max = self.max.max(1) as f32;
+        for i in 0..n {
+            for j in 0..n {
+                let cell = Rect::from_min_size(
+                    rect.min + vec2(j as f32, i as f32) * CELL_SIZE,
+                    vec2(CELL_SIZE, CELL_SIZE),
+                );
+                let color = if i == j {
+                    ui.visuals().extreme_bg_color
+                } else {
+                    viridis(self.counts[i * n + j] as f32 / max)
+                };
+                painter.rect_filled(cell, 0.0, color);
+            }
+        }
+
+        if let Some(pos) = response.hover_pos() {
+            let cell = ((pos - rect.min) / CELL_SIZE).floor();
+            let (i, j) = (cell.y as usize, cell.x as usize);
+            if i < n && j < n && i != j {
+                response.on_hover_text_at_pointer(t!(
+                    "%{a} and %{b}: %{count} mutual friends",
+                    a = persons[self.members[i]].name,
+                    b = persons[self.members[j]].name,
+                    count = self.counts[i * n + j]
+                ));
+            }
+        }
+    }
+}
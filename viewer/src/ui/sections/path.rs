@@ -1,18 +1,23 @@
-use crate::algorithms::pathfinding::{do_pathfinding, PathSectionResults, PathSectionSettings};
+use crate::algorithms::pathfinding::{
+    mutual_neighbors, PathNotFound, PathSectionResults, PathSectionSettings, Pathfinder,
+};
 use crate::algorithms::AbstractNode;
-use crate::app::ViewerData;
+use crate::app::{Person, ViewerData};
+use crate::search::SearchEngine;
 use crate::thread;
 use crate::thread::JoinHandle;
 use crate::threading::MyRwLock;
+use crate::ui;
+use crate::ui::class::ClassSection;
 use crate::ui::infos::InfosSection;
 use crate::ui::sections::path::PathStatus::{NoPath, SameSrcDest};
-use crate::ui::widgets::combo_filter::{combo_with_filter, COMBO_WIDTH};
+use crate::ui::widgets::combo_filter::{combo_with_filter, COMBO_WIDTH, DEFAULT_RESULTS};
 use crate::ui::SelectedUserField;
-use ahash::AHashSet;
 use derivative::Derivative;
 use eframe::emath::vec2;
-use egui::{CollapsingHeader, Sense, Spinner, TextStyle, Ui};
+use egui::{CollapsingHeader, Color32, Key, KeyboardShortcut, Modifiers, Sense, Ui};
 use itertools::Itertools;
+use std::cmp::Reverse;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
@@ -23,15 +28,72 @@ pub struct PathSection {
     pub path_dirty: bool,
     pub path_loading: bool,
     pub path_status: Option<PathStatus>,
-    pub path_thread: Option<JoinHandle<Option<PathSectionResults>>>,
+    /// Path whose entry in the list is hovered, to be highlighted in the graph view.
+    pub hovered_path: Option<usize>,
+    pub path_thread: Option<JoinHandle<(Pathfinder, Result<PathSectionResults, PathNotFound>)>>,
+    /// Kept between searches to reuse its buffers and cached results. It is moved to the
+    /// pathfinding thread while a search is running.
+    pathfinder: Option<Pathfinder>,
+    /// [`ViewerData::class_generation`] of the graph the pathfinder last searched.
+    pathfinder_classes: u64,
+    /// Previous settings, most recent last.
+    undo_stack: VecDeque<PathSectionSettings>,
+    redo_stack: Vec<PathSectionSettings>,
+    /// Settings at the end of the last frame, to detect changes made since then, wherever they
+    /// come from (this section, or clicks on the graph).
+    last_settings: Option<PathSectionSettings>,
+    /// Highlight the mutual friends of the source and destination.
+    pub show_mutual: bool,
+    /// Mutual friends of the source and destination, for the pair they were computed for, by
+    /// decreasing degree.
+    mutual_friends: Option<((usize, usize), Vec<usize>)>,
+    /// Computes the mutual friends of two hubs.
+    mutual_thread: Option<((usize, usize), JoinHandle<Vec<usize>>)>,
+    /// Number of mutual friends listed.
+    #[derivative(Default(value = "MUTUAL_PAGE"))]
+    mutual_shown: usize,
+    /// Path picked in a subgraph with an end it doesn't contain, waiting for confirmation to be
+    /// opened in the main graph, along with the name of that end.
+    main_prompt: Option<(&'static str, MainPathRequest)>,
+}
+
+/// Path to show in the tab of the main graph, asked from a subgraph that lacks one of its ends.
+/// The ends are indices in the main graph.
+pub struct MainPathRequest {
+    /// Search engine of the main graph, which identifies its tab.
+    pub engine: Arc<SearchEngine>,
+    pub path_src: Option<usize>,
+    pub path_dest: Option<usize>,
+}
+
+/// Maximum number of undo steps.
+const HISTORY_SIZE: usize = 100;
+
+/// Highest number of shortest paths that can be asked for.
+const MAX_PATHS: usize = 100;
+
+/// Number of mutual friends listed at first, and added by each click on "Show more".
+const MUTUAL_PAGE: usize = 200;
+
+/// Degree above which, for both nodes, the mutual friends are computed on another thread.
+const MUTUAL_THREAD_DEGREE: usize = 10_000;
+
+/// Mutual friends of two nodes, by decreasing degree.
+fn sorted_mutual(persons: &[Person], (a, b): (usize, usize)) -> Vec<usize> {
+    mutual_neighbors(persons, a, b)
+        .into_iter()
+        .sorted_unstable_by_key(|&id| (Reverse(persons[id].neighbors.len()), persons[id].name))
+        .collect()
 }
 
 #[derive(Default)]
 pub enum PathStatus {
     #[default]
     SameSrcDest,
-    NoPath,
-    PathFound(Vec<usize>),
+    /// No path, possibly because of the given excluded class.
+    NoPath(Option<u32>),
+    /// Shortest paths, all of the same length.
+    PathFound(Vec<Vec<usize>>),
 }
 
 impl PathSection {
@@ -50,29 +112,355 @@ impl PathSection {
         }
     }
 
+    fn show_path(
+        &self,
+        data: &ViewerData,
+        ui: &mut Ui,
+        path: &[usize],
+        cur_path: &mut Option<usize>,
+        del_path: &mut Option<usize>,
+    ) {
+        for (i, id) in path.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui::set_bg_color_tinted(Color32::RED, ui);
+                self.person_button(data, ui, id, cur_path);
+                if i != 0
+                    && i != path.len() - 1
+                    && ui
+                        .button("✖")
+                        .on_hover_text(t!("Exclude this person from the path"))
+                        .clicked()
+                {
+                    *del_path = Some(*id);
+                }
+            });
+        }
+    }
+
+    /// Mutual friends to highlight in the graph view.
+    pub fn mutual_friends(&self) -> &[usize] {
+        let pair = self
+            .path_settings
+            .path_src
+            .zip(self.path_settings.path_dest);
+        match &self.mutual_friends {
+            Some((p, mutual)) if self.show_mutual && Some(*p) == pair => mutual,
+            _ => &[],
+        }
+    }
+
+    /// Starts computing the mutual friends of the pair, unless they already are.
+    fn update_mutual(&mut self, data: &Arc<MyRwLock<ViewerData>>, pair: (usize, usize)) {
+        if let Some((computed, thr)) = self.mutual_thread.take_if(|(_, thr)| thr.is_finished())
+            && let Ok(mutual) = thr.join()
+        {
+            self.mutual_friends = Some((computed, mutual));
+        }
+        if self
+            .mutual_friends
+            .as_ref()
+            .is_some_and(|(p, _)| *p == pair)
+            || self.mutual_thread.as_ref().is_some_and(|(p, _)| *p == pair)
+        {
+            return;
+        }
+        self.mutual_shown = MUTUAL_PAGE;
+        let data_r = data.read();
+        let persons = &data_r.persons;
+        if persons[pair.0].neighbors.len() > MUTUAL_THREAD_DEGREE
+            && persons[pair.1].neighbors.len() > MUTUAL_THREAD_DEGREE
+        {
+            let data = data.clone();
+            self.mutual_thread = Some((
+                pair,
+                thread::spawn(move || sorted_mutual(&data.read().persons, pair)),
+            ));
+        } else {
+            self.mutual_friends = Some((pair, sorted_mutual(persons, pair)));
+        }
+    }
+
+    /// Lists the mutual friends of the source and destination, which are only computed while the
+    /// list is open or they are highlighted.
+    fn show_mutual_friends(
+        &mut self,
+        data_rw: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        infos: &mut InfosSection,
+    ) {
+        let (Some(src), Some(dest)) = (self.path_settings.path_src, self.path_settings.path_dest)
+        else {
+            return;
+        };
+        let pair = (src, dest);
+        ui.checkbox(&mut self.show_mutual, t!("Highlight mutual friends"));
+        if self.show_mutual {
+            self.update_mutual(data_rw, pair);
+        }
+        if self.mutual_thread.is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        let title = match &self.mutual_friends {
+            Some((p, mutual)) if *p == pair => {
+                t!("Mutual friends (%{count})", count = mutual.len())
+            }
+            _ => t!("Mutual friends"),
+        };
+        let mut selected = None;
+        CollapsingHeader::new(title)
+            .id_salt("mutual_friends")
+            .default_open(false)
+            .show(ui, |ui| {
+                self.update_mutual(data_rw, pair);
+                let Some((_, mutual)) = self.mutual_friends.as_ref().filter(|(p, _)| *p == pair)
+                else {
+                    ui::spinner(ui);
+                    return;
+                };
+                let data = data_rw.read();
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for id in mutual.iter().take(self.mutual_shown) {
+                            self.person_button(&data, ui, id, &mut selected);
+                        }
+                    });
+                if mutual.len() > self.mutual_shown {
+                    let left = mutual.len() - self.mutual_shown;
+                    if ui
+                        .button(t!("Show more (%{count} left)", count = left))
+                        .clicked()
+                    {
+                        self.mutual_shown += MUTUAL_PAGE;
+                    }
+                }
+            });
+        if let Some(id) = selected {
+            infos.set_infos_current(Some(id));
+        }
+    }
+
+    fn status(res: Result<PathSectionResults, PathNotFound>) -> PathStatus {
+        match res {
+            Ok(res) => PathStatus::PathFound(res.paths),
+            Err(e) => NoPath(e.blocking_class),
+        }
+    }
+
+    /// Lets the user pick modularity classes that paths must avoid.
+    fn show_excluded_classes(&mut self, data: &ViewerData, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(t!("Avoided classes:"));
+            egui::ComboBox::from_id_salt("#path_exclude_class")
+                .selected_text(t!("Add"))
+                .show_ui(ui, |ui| {
+                    for (id, cl) in data.modularity_classes.iter().enumerate() {
+                        let id = id as u32;
+                        if self.path_settings.exclude_classes.contains(&id) {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ClassSection::class_circle(ui, cl);
+                            if ui.selectable_label(false, format!("{}", id)).clicked() {
+                                self.path_settings.exclude_classes.push(id);
+                                changed = true;
+                            }
+                        });
+                    }
+                });
+        });
+
+        let mut del_class = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, &id) in self.path_settings.exclude_classes.iter().enumerate() {
+                // the classes may have been recomputed since
+                if let Some(cl) = data.modularity_classes.get(id as usize) {
+                    ClassSection::class_circle(ui, cl);
+                }
+                ui.label(format!("{}", id));
+                if ui
+                    .button("✖")
+                    .on_hover_text(t!("Remove from the avoided classes"))
+                    .clicked()
+                {
+                    del_class = Some(i);
+                }
+            }
+        });
+        if let Some(i) = del_class {
+            self.path_settings.exclude_classes.remove(i);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Pushes the previous settings to the undo stack if they have changed.
+    fn record_history(&mut self) {
+        match &self.last_settings {
+            Some(last) if *last == self.path_settings => {}
+            last => {
+                if let Some(last) = last {
+                    self.undo_stack.push_back(last.clone());
+                    if self.undo_stack.len() > HISTORY_SIZE {
+                        self.undo_stack.pop_front();
+                    }
+                    self.redo_stack.clear();
+                }
+                self.last_settings = Some(self.path_settings.clone());
+            }
+        }
+    }
+
+    fn restore(&mut self, settings: PathSectionSettings) {
+        self.path_settings = settings.clone();
+        self.last_settings = Some(settings);
+        self.path_dirty = true;
+    }
+
+    fn undo(&mut self) {
+        if let Some(prev) = self.undo_stack.pop_back() {
+            self.redo_stack.push(self.path_settings.clone());
+            self.restore(prev);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(next) = self.redo_stack.pop() {
+            self.undo_stack.push_back(self.path_settings.clone());
+            self.restore(next);
+        }
+    }
+
+    fn show_history(&mut self, ui: &mut Ui) {
+        // text fields have their own undo
+        if !ui.ctx().wants_keyboard_input() {
+            let (undo, redo) = ui.input_mut(|is| {
+                (
+                    is.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, Key::Z)),
+                    is.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, Key::Y))
+                        || is.consume_shortcut(&KeyboardShortcut::new(
+                            Modifiers::COMMAND | Modifiers::SHIFT,
+                            Key::Z,
+                        )),
+                )
+            });
+            if undo {
+                self.undo();
+            }
+            if redo {
+                self.redo();
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!self.undo_stack.is_empty(), egui::Button::new("⟲"))
+                .on_hover_text(t!("Undo (Ctrl+Z)"))
+                .clicked()
+            {
+                self.undo();
+            }
+            if ui
+                .add_enabled(!self.redo_stack.is_empty(), egui::Button::new("⟳"))
+                .on_hover_text(t!("Redo (Ctrl+Y)"))
+                .clicked()
+            {
+                self.redo();
+            }
+        });
+    }
+
+    /// Asks whether to open the path in the main graph, once a node only the main graph has was
+    /// picked as one of its ends.
+    fn show_main_prompt(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        main_src: Option<usize>,
+        main_dest: Option<usize>,
+        main_request: &mut Option<MainPathRequest>,
+    ) {
+        let data = data.read();
+        if let Some(engine) = &data.base_engine
+            && let Some(picked) = main_src.or(main_dest)
+        {
+            let base = |id: Option<usize>| id.map(|i| data.base_id(i));
+            let (path_src, path_dest) = if main_src.is_some() {
+                (main_src, base(self.path_settings.path_dest))
+            } else {
+                (base(self.path_settings.path_src), main_dest)
+            };
+            self.main_prompt = Some((
+                engine.persons[picked].name,
+                MainPathRequest {
+                    engine: engine.clone(),
+                    path_src,
+                    path_dest,
+                },
+            ));
+        }
+
+        let Some(&(name, _)) = self.main_prompt.as_ref() else {
+            return;
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.label(t!(
+                "%{name} isn't in this subgraph. Open the path in the main graph?",
+                name = name
+            ));
+            if ui.button(t!("Open")).clicked() {
+                *main_request = self.main_prompt.take().map(|(_, request)| request);
+            }
+            if ui.button("✖").clicked() {
+                self.main_prompt = None;
+            }
+        });
+    }
+
     pub(crate) fn show(
         &mut self,
         data: &Arc<MyRwLock<ViewerData>>,
         ui: &mut Ui,
         infos: &mut InfosSection,
         sel_field: &mut SelectedUserField,
+        main_request: &mut Option<MainPathRequest>,
     ) {
         use PathStatus::*;
+        self.record_history();
         if let Some(thr) = self.path_thread.take_if(|thr| thr.is_finished()) {
             let res = thr.join();
             self.path_thread = None;
             self.path_loading = false;
-            if let Ok(Some(res)) = res {
-                self.path_status = Some(PathFound(res.path));
-            } else {
-                self.path_status = Some(NoPath);
-            }
+            let res = match res {
+                Ok((pathfinder, res)) => {
+                    self.pathfinder = Some(pathfinder);
+                    res
+                }
+                Err(_) => Err(PathNotFound {
+                    blocking_class: None,
+                }),
+            };
+            self.path_status = Some(Self::status(res));
+        }
+        let classes = data.read().class_generation;
+        if self.path_thread.is_none() && classes != self.pathfinder_classes {
+            // the cached paths may go through classes that are excluded now, and the other way
+            // around
+            self.pathfinder = None;
+            self.pathfinder_classes = classes;
+            self.path_dirty = true;
         }
 
         CollapsingHeader::new(t!("Shortest path"))
             .id_salt("path")
             .default_open(true)
             .show(ui, |ui| {
+                self.show_history(ui);
+
+                let mut main_src = None;
+                let mut main_dest = None;
                 let c1 = ui
                     .horizontal(|ui| {
                         ui.radio_value(sel_field, SelectedUserField::PathSource, "");
@@ -81,6 +469,8 @@ impl PathSection {
                             "#path_src",
                             &mut self.path_settings.path_src,
                             data,
+                            DEFAULT_RESULTS,
+                            Some(&mut main_src),
                         );
                         if c.changed() {
                             infos.set_infos_current(self.path_settings.path_src);
@@ -100,6 +490,8 @@ impl PathSection {
                             "#path_dest",
                             &mut self.path_settings.path_dest,
                             data,
+                            DEFAULT_RESULTS,
+                            Some(&mut main_dest),
                         );
                         if c.changed() {
                             infos.set_infos_current(self.path_settings.path_dest);
@@ -111,6 +503,8 @@ impl PathSection {
                     })
                     .inner;
 
+                self.show_main_prompt(data, ui, main_src, main_dest, main_request);
+
                 if (self.path_dirty || c1.changed() || c2.changed())
                     | ui.checkbox(
                         &mut self.path_settings.path_no_direct,
@@ -122,6 +516,20 @@ impl PathSection {
                         t!("Avoid mutual friends"),
                     )
                     .changed()
+                    | (data.read().directed
+                        && ui
+                            .checkbox(
+                                &mut self.path_settings.path_directed,
+                                t!("Follow link direction"),
+                            )
+                            .changed())
+                    | ui.add(
+                        egui::DragValue::new(&mut self.path_settings.max_paths)
+                            .range(1..=MAX_PATHS)
+                            .prefix(t!("Maximum number of paths: ")),
+                    )
+                    .changed()
+                    | self.show_excluded_classes(&data.read(), ui)
                 {
                     self.path_dirty = false;
                     match (self.path_settings.path_src, self.path_settings.path_dest) {
@@ -134,34 +542,55 @@ impl PathSection {
                             self.path_loading = false;
                         }
                         _ => {
-                            log::info!("Starting pathfinding");
-                            let settings = self.path_settings.clone();
-                            let data = data.clone();
-                            self.path_thread = Some(thread::spawn(move || {
-                                let start = chrono::Utc::now();
-                                let data = data.read().persons.clone();
-                                let res = do_pathfinding(settings, &data);
-                                log::info!(
-                                    "Pathfinding took {}ms",
-                                    (chrono::Utc::now() - start).num_milliseconds()
-                                );
-                                res
-                            }));
-                            self.path_loading = true;
+                            let cached = self
+                                .pathfinder
+                                .as_ref()
+                                .and_then(|p| p.cached(&self.path_settings));
+                            if let Some(res) = cached {
+                                log::info!("Reusing cached path");
+                                self.path_status = Some(Self::status(res));
+                                self.path_loading = false;
+                            } else {
+                                log::info!("Starting pathfinding");
+                                let settings = self.path_settings.clone();
+                                let data = data.clone();
+                                let mut pathfinder = self.pathfinder.take().unwrap_or_default();
+                                self.pathfinder_classes = data.read().class_generation;
+                                self.path_thread = Some(thread::spawn(move || {
+                                    let start = chrono::Utc::now();
+                                    let data = data.read().persons.clone();
+                                    let res = pathfinder.find(&settings, &data);
+                                    log::info!(
+                                        "Pathfinding took {}ms",
+                                        (chrono::Utc::now() - start).num_milliseconds()
+                                    );
+                                    (pathfinder, res)
+                                }));
+                                self.path_loading = true;
+                            }
                         }
                     }
                 }
 
                 ui.horizontal(|ui| {
                     if self.path_loading {
-                        ui.add(Spinner::new()); //.size(ui.text_style_height(&TextStyle::Body) * 0.75));
+                        ui::spinner(ui);
                         ui.label(t!("Loading..."));
                     } else {
                         ui.label(match &self.path_status {
                             Some(SameSrcDest) => t!("🚫 Source and destination are the same"),
-                            Some(NoPath) => t!("🗙 No path found between the two nodes"),
-                            Some(PathFound(path)) => {
-                                t!("✔ Path found, distance %{dist}", dist = path.len() - 1)
+                            Some(NoPath(None)) => t!("🗙 No path found between the two nodes"),
+                            Some(NoPath(Some(class))) => t!(
+                                "🗙 No path found between the two nodes, class %{class} is in the way",
+                                class = class
+                            ),
+                            Some(PathFound(paths)) if paths.len() > 1 => t!(
+                                "✔ %{count} paths found, distance %{dist}",
+                                count = paths.len(),
+                                dist = paths[0].len() - 1
+                            ),
+                            Some(PathFound(paths)) => {
+                                t!("✔ Path found, distance %{dist}", dist = paths[0].len() - 1)
                             }
                             None => t!("🔍 Choose two nodes to find the shortest path"),
                         });
@@ -172,29 +601,28 @@ impl PathSection {
                     );
                 });
 
-                if let Some(PathFound(path)) = &self.path_status {
-                    use crate::ui;
-                    use eframe::epaint::Color32;
+                self.hovered_path = None;
+                if let Some(PathFound(paths)) = &self.path_status {
                     let mut del_path = None;
                     let mut cur_path = None;
                     let data = data.read();
-                    ui.add_enabled_ui(true, |ui| {
-                        for (i, id) in path.iter().enumerate() {
-                            ui.horizontal(|ui| {
-                                ui::set_bg_color_tinted(Color32::RED, ui);
-                                self.person_button(&data, ui, id, &mut cur_path);
-                                if i != 0
-                                    && i != path.len() - 1
-                                    && ui
-                                        .button("✖")
-                                        .on_hover_text(t!("Exclude this person from the path"))
-                                        .clicked()
-                                {
-                                    del_path = Some(*id);
-                                }
-                            });
+                    if let [path] = &paths[..] {
+                        self.show_path(&data, ui, path, &mut cur_path, &mut del_path);
+                    } else {
+                        for (i, path) in paths.iter().enumerate() {
+                            let resp = CollapsingHeader::new(t!("Path %{n}", n = i + 1))
+                                .id_salt(("path", i))
+                                .default_open(i == 0)
+                                .show(ui, |ui| {
+                                    self.show_path(&data, ui, path, &mut cur_path, &mut del_path);
+                                });
+                            if resp.header_response.contains_pointer()
+                                || resp.body_response.is_some_and(|r| r.contains_pointer())
+                            {
+                                self.hovered_path = Some(i);
+                            }
                         }
-                    });
+                    }
                     if let Some(id) = cur_path {
                         infos.set_infos_current(Some(id));
                     }
@@ -242,6 +670,10 @@ impl PathSection {
                         self.path_settings.exclude_ids.remove(i);
                     }
                 }
+
+                self.show_mutual_friends(data, ui, infos);
             });
+
+        self.record_history();
     }
 }
@@ -0,0 +1,166 @@
+use crate::app::{thread, ContextUpdater, ViewerData};
+use crate::search::{SearchMatch, SearchOptions, DEBOUNCE};
+use crate::threading::MyRwLock;
+use crate::ui;
+use crate::ui::class::ClassSection;
+use crate::ui::infos::{fly_to, InfosSection};
+use crate::ui::tabs::TabCamera;
+use derivative::Derivative;
+use egui::{CollapsingHeader, Id, SelectableLabel, TextEdit, Ui};
+use std::sync::Arc;
+
+const MAX_RESULTS: usize = 50;
+
+#[derive(Default)]
+struct SearchResults {
+    pattern: String,
+    items: Vec<SearchMatch>,
+    loading: bool,
+}
+
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct SearchSection {
+    pattern: String,
+    /// Time of the last edit of the pattern that hasn't been searched for yet.
+    last_edit: Option<f64>,
+    results: Arc<MyRwLock<SearchResults>>,
+    #[derivative(Default(value = "true"))]
+    pan_to_result: bool,
+    /// Set by the keyboard shortcut, opens the section and focuses the text field.
+    focus: bool,
+}
+
+impl SearchSection {
+    pub(crate) fn request_focus(&mut self) {
+        self.focus = true;
+    }
+
+    pub(crate) fn show(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        infos: &mut InfosSection,
+        camera: &mut TabCamera,
+        cid: Id,
+    ) {
+        CollapsingHeader::new(t!("Search"))
+            .id_salt("search")
+            .default_open(false)
+            .open(self.focus.then_some(true))
+            .show(ui, |ui| {
+                let now = ui.input(|is| is.time);
+                let field =
+                    ui.add(TextEdit::singleline(&mut self.pattern).hint_text(t!("Name or ID")));
+                if std::mem::take(&mut self.focus) {
+                    field.request_focus();
+                    field.scroll_to_me(None);
+                }
+                if field.changed() {
+                    self.last_edit = Some(now);
+                }
+
+                if let Some(last_edit) = self.last_edit {
+                    if now - last_edit >= DEBOUNCE {
+                        self.last_edit = None;
+                        self.start_search(data, ui);
+                    } else {
+                        ui.ctx()
+                            .request_repaint_after_secs((DEBOUNCE - (now - last_edit)) as f32);
+                    }
+                }
+
+                ui.checkbox(
+                    &mut self.pan_to_result,
+                    t!("Move the camera to the selected result"),
+                );
+                let mut options = SearchOptions::get(ui.ctx());
+                let accents = ui.checkbox(&mut options.ignore_accents, t!("Ignore accents"));
+                let prefix = ui
+                    .checkbox(&mut options.prefix_first, t!("Names starting with the query first"))
+                    .on_hover_text(t!("Among them, the nodes with the most links come first. The approximate matches come after."));
+                if accents.changed() || prefix.changed() {
+                    SearchOptions::set(ui.ctx(), options);
+                    self.start_search(data, ui);
+                }
+
+                let results = self.results.read();
+                if results.loading || self.last_edit.is_some() {
+                    ui::spinner(ui);
+                    return;
+                }
+                if results.pattern.is_empty() {
+                    return;
+                }
+                if results.items.is_empty() {
+                    ui.label(t!("No results found"));
+                    return;
+                }
+
+                let mut clicked = None;
+                let data = data.read();
+                egui::ScrollArea::vertical()
+                    .id_salt("search_results")
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("#search_results")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for item in &results.items {
+                                    let id = item.node;
+                                    let person = &data.persons[id];
+                                    ClassSection::class_circle(
+                                        ui,
+                                        &data.modularity_classes[person.modularity_class as usize],
+                                    );
+                                    if ui
+                                        .add(SelectableLabel::new(
+                                            infos.infos_current == Some(id),
+                                            item.label(person),
+                                        ))
+                                        .clicked()
+                                    {
+                                        clicked = Some(id);
+                                    }
+                                    ui.label(format!("{}", person.neighbors.len()));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if let Some(id) = clicked {
+                    infos.set_infos_current(Some(id));
+                    if self.pan_to_result {
+                        fly_to(camera, cid, data.persons[id].position, ui);
+                    }
+                }
+            });
+    }
+
+    fn start_search(&self, data: &Arc<MyRwLock<ViewerData>>, ui: &Ui) {
+        let mut results = self.results.write();
+        results.pattern = self.pattern.clone();
+        if self.pattern.is_empty() {
+            results.items.clear();
+            results.loading = false;
+            return;
+        }
+        results.loading = true;
+
+        let pattern = self.pattern.clone();
+        let engine = data.read().engine.clone();
+        let results = self.results.clone();
+        let options = SearchOptions::get(ui.ctx());
+        let ctx = ContextUpdater::new(ui.ctx());
+        thread::spawn(move || {
+            let res = engine.search_n(&pattern, MAX_RESULTS, options);
+            let mut results = results.write();
+            // a newer search may have been started in the meantime
+            if results.pattern == pattern {
+                results.items = res;
+                results.loading = false;
+                ctx.update();
+            }
+        });
+    }
+}
@@ -0,0 +1,188 @@
+use crate::app::{Person, ViewerData};
+use crate::graph_render::camera::Camera;
+use crate::graph_render::{ColorMode, NodeFilter, RenderedGraph};
+use crate::threading::MyRwLock;
+use crate::ui::display::DisplaySection;
+use crate::ui::infos::InfosSection;
+use crate::ui::modal::ModalWriter;
+use crate::ui::path::PathSection;
+use crate::ui::tabs::NewTabRequest;
+use ahash::AHashSet;
+use eframe::glow;
+use egui::{CollapsingHeader, Pos2, Ui};
+use graph_format::Point;
+use itertools::Itertools;
+use std::sync::Arc;
+
+/// Nodes picked by hand, with ctrl+click in the graph view.
+#[derive(Default)]
+pub struct SelectionSection {
+    /// In the order they were picked.
+    pub nodes: Vec<usize>,
+    /// Dragging in the graph view traces a lasso instead of moving the camera.
+    pub lasso_mode: bool,
+    /// Points of the lasso being traced, on screen.
+    pub lasso: Vec<Pos2>,
+}
+
+/// Minimum distance between two points of the lasso, in points, to keep the polygon small.
+const LASSO_STEP: f32 = 3.0;
+
+/// Whether the point is inside the polygon, by the even-odd rule.
+fn polygon_contains(polygon: &[Pos2], p: Pos2) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon.iter().circular_tuple_windows() {
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+impl SelectionSection {
+    /// Adds the node to the selection, or removes it if it's already in it.
+    pub fn toggle(&mut self, id: usize) {
+        match self.nodes.iter().position(|&n| n == id) {
+            Some(i) => {
+                self.nodes.remove(i);
+            }
+            None => self.nodes.push(id),
+        }
+    }
+
+    /// Adds a point to the lasso being traced.
+    pub fn extend_lasso(&mut self, pos: Pos2) {
+        if self
+            .lasso
+            .last()
+            .is_none_or(|last| last.distance(pos) >= LASSO_STEP)
+        {
+            self.lasso.push(pos);
+        }
+    }
+
+    /// Closes the lasso, and selects the visible nodes inside it. With `add`, they are added to
+    /// the selection instead of replacing it.
+    pub fn finish_lasso(
+        &mut self,
+        persons: &[Person],
+        filter: NodeFilter,
+        to_screen: impl Fn(Point) -> Pos2,
+        add: bool,
+    ) {
+        let lasso = std::mem::take(&mut self.lasso);
+        if lasso.len() < 3 {
+            return;
+        }
+        if !add {
+            self.nodes.clear();
+        }
+        let picked: AHashSet<usize> = self.nodes.iter().copied().collect();
+        self.nodes.extend((0..persons.len()).filter(|&i| {
+            !picked.contains(&i)
+                && filter.shows(&persons[i])
+                && polygon_contains(&lasso, to_screen(persons[i].position))
+        }));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn show(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+        ui: &mut Ui,
+        infos: &mut InfosSection,
+        tab_request: &mut Option<NewTabRequest>,
+        camera: &Camera,
+        path_section: &PathSection,
+        display: &DisplaySection,
+        modal: &impl ModalWriter,
+    ) {
+        CollapsingHeader::new(t!("Selection"))
+            .id_salt("selection")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.toggle_value(&mut self.lasso_mode, t!("Lasso"))
+                    .on_hover_text(t!("Drag in the graph to select the nodes inside the traced shape. Hold Ctrl to add them to the selection."));
+                if self.nodes.is_empty() {
+                    ui.label(t!("Ctrl+click nodes to add them to the selection."));
+                    return;
+                }
+
+                let mut removed = None;
+                egui::ScrollArea::vertical()
+                    .id_salt("selection_list")
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        let data = data.read();
+                        for (i, &id) in self.nodes.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .small_button("🗙")
+                                    .on_hover_text(t!("Remove from the selection"))
+                                    .clicked()
+                                {
+                                    removed = Some(i);
+                                }
+                                if ui.link(data.persons[id].name).clicked() {
+                                    infos.set_infos_current(Some(id));
+                                }
+                            });
+                        }
+                    });
+                if let Some(i) = removed {
+                    self.nodes.remove(i);
+                }
+
+                ui.horizontal_wrapped(|ui| {
+                    if ui
+                        .button(t!("Create subgraph"))
+                        .on_hover_text(t!("Open a tab with the selected nodes and the links between them."))
+                        .clicked()
+                    {
+                        let nodes: AHashSet<usize> = self.nodes.iter().copied().collect();
+                        infos.create_subgraph(
+                            t!("Selection (%{count} nodes)", count = nodes.len()).to_string(),
+                            data,
+                            tab_request,
+                            camera,
+                            path_section,
+                            display,
+                            ui,
+                            modal.clone(),
+                            move |_, _| Ok(nodes),
+                        );
+                    }
+                    if ui
+                        .button(t!("Copy IDs"))
+                        .on_hover_text(t!("Copy the IDs of the selected nodes, one per line."))
+                        .clicked()
+                    {
+                        let data = data.read();
+                        let text = self.nodes.iter().map(|&id| data.persons[id].id).join("\n");
+                        ui.output_mut(|out| out.copied_text = text);
+                    }
+                    if ui
+                        .button(t!("Highlight"))
+                        .on_hover_text(t!("Color the selected nodes apart from the others. The usual colors can be restored in the display section."))
+                        .clicked()
+                    {
+                        let nodes: AHashSet<usize> = self.nodes.iter().copied().collect();
+                        let values = (0..data.read().persons.len())
+                            .map(|i| if nodes.contains(&i) { 1.0 } else { 0.0 })
+                            .collect();
+                        let mode = ColorMode::Custom(values);
+                        let values = mode.node_values(&data.read().persons);
+                        graph.write().tasks.push_back(Box::new(
+                            move |graph: &mut RenderedGraph, gl: &glow::Context| {
+                                graph.set_color_mode(gl, mode, values);
+                            },
+                        ));
+                    }
+                    if ui.button(t!("Clear")).clicked() {
+                        self.nodes.clear();
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,234 @@
+use crate::algorithms::clustering::{average_clustering, count_triangles, Triangles};
+use crate::algorithms::diameter::{estimate_graph_diameter, Diameter};
+use crate::app::ViewerData;
+use crate::threading::{AlgoHandle, MyRwLock};
+use crate::ui::algos::show_running;
+use crate::ui::modal::ModalWriter;
+use crate::ui::NodeStats;
+use crate::utils::{format_count, format_decimal};
+use derivative::Derivative;
+use egui::{CollapsingHeader, Ui};
+use egui_plot::{Bar, BarChart, Plot};
+use std::sync::Arc;
+
+#[derive(Derivative)]
+#[derivative(Default)]
+pub struct StatisticsSection {
+    log_log: bool,
+    /// Number of nodes the clustering coefficient is averaged over.
+    #[derivative(Default(value = "1000"))]
+    clustering_sample: usize,
+    /// Estimates the average clustering coefficient, and the diameter unless it's known already.
+    metrics_thread: Option<AlgoHandle<(f32, usize, Option<Diameter>)>>,
+    triangles_thread: Option<AlgoHandle<Triangles>>,
+}
+
+impl StatisticsSection {
+    pub(crate) fn show(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        stats: &Arc<MyRwLock<NodeStats>>,
+        modal: &impl ModalWriter,
+    ) {
+        CollapsingHeader::new(t!("Statistics"))
+            .id_salt("statistics")
+            .default_open(false)
+            .show(ui, |ui| {
+                self.show_graph_stats(data, ui, modal);
+                ui.separator();
+
+                ui.checkbox(&mut self.log_log, t!("Log-log axes"));
+                let log_log = self.log_log;
+
+                let stats = stats.read();
+                let bars = stats
+                    .degree_histogram
+                    .iter()
+                    // isolated nodes have no place on a logarithmic axis
+                    .filter(|bin| !log_log || bin.start > 0)
+                    .map(|bin| {
+                        let (start, end) = (bin.start as f64, bin.end as f64);
+                        let bar = if log_log {
+                            Bar::new((start * end).sqrt().log10(), (bin.count as f64).log10())
+                                .width((end / start).log10())
+                        } else {
+                            Bar::new((start + end) / 2.0, bin.count as f64).width(end - start)
+                        };
+                        bar.name(t!(
+                            "Degree %{start}–%{end}: %{count} nodes",
+                            start = bin.start,
+                            end = bin.end - 1,
+                            count = format_count(bin.count)
+                        ))
+                    })
+                    .collect();
+                let chart =
+                    BarChart::new(bars).element_formatter(Box::new(|bar, _| bar.name.clone()));
+
+                let axis = move |value: f64| {
+                    if log_log {
+                        format!("{}", 10f64.powf(value).round())
+                    } else {
+                        format!("{}", value)
+                    }
+                };
+                Plot::new("degree_histogram")
+                    .height(150.0)
+                    .allow_scroll(false)
+                    .allow_zoom(false)
+                    .allow_drag(false)
+                    .x_axis_label(t!("Degree"))
+                    .y_axis_label(t!("Nodes"))
+                    .x_axis_formatter(move |mark, _| axis(mark.value))
+                    .y_axis_formatter(move |mark, _| axis(mark.value))
+                    .show(ui, |plot_ui| plot_ui.bar_chart(chart));
+            });
+    }
+
+    /// Size, density, clustering and diameter of the whole graph of the tab, regardless of the
+    /// filters.
+    fn show_graph_stats(
+        &mut self,
+        data: &Arc<MyRwLock<ViewerData>>,
+        ui: &mut Ui,
+        modal: &impl ModalWriter,
+    ) {
+        if let Some(thr) = self.metrics_thread.take_if(|thr| thr.is_finished())
+            && let Some((clustering, sample, diameter)) = thr.join()
+        {
+            let mut data = data.write();
+            data.avg_clustering = Some((clustering, sample));
+            if data.diameter.is_none() {
+                data.diameter = diameter;
+            }
+        }
+        if let Some(thr) = self.triangles_thread.take_if(|thr| thr.is_finished())
+            && let Some(triangles) = thr.join()
+        {
+            data.write().triangles = Some(triangles);
+        }
+
+        let data_r = data.read();
+        let nodes = data_r.persons.len();
+        let edges = data_r.edge_count();
+        // each pair of nodes can have an edge in both directions in a directed graph
+        let pairs = nodes as f64 * nodes.saturating_sub(1) as f64;
+        let pairs = if data_r.directed { pairs } else { pairs / 2.0 };
+        let density = if pairs > 0.0 {
+            edges as f64 / pairs
+        } else {
+            0.0
+        };
+
+        egui::Grid::new("graph_stats")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label(t!("Nodes"));
+                ui.label(format_count(nodes));
+                ui.end_row();
+                ui.label(t!("Links"));
+                ui.label(format_count(edges));
+                ui.end_row();
+                ui.label(t!("Density"));
+                ui.label(format!("{:.3e}", density));
+                ui.end_row();
+                ui.label(t!("Clustering coefficient"));
+                match data_r.avg_clustering {
+                    Some((value, sample)) => ui
+                        .label(format_decimal(value as f64, 4))
+                        .on_hover_text(t!("Average over %{count} nodes", count = sample)),
+                    None => ui.label("—"),
+                };
+                ui.end_row();
+                ui.label(t!("Triangles"));
+                match data_r.triangles {
+                    Some(triangles) => ui.label(format_count(triangles.count as usize)),
+                    None => ui.label("—"),
+                };
+                ui.end_row();
+                ui.label(t!("Global clustering coefficient"))
+                    .on_hover_text(t!(
+                    "Fraction of the pairs of links sharing a node that are closed by a third link"
+                ));
+                match data_r.triangles {
+                    Some(triangles) => ui.label(format_decimal(triangles.global_clustering, 4)),
+                    None => ui.label("—"),
+                };
+                ui.end_row();
+                ui.label(t!("Diameter"));
+                match data_r.diameter {
+                    Some(diameter) => ui.label(t!(
+                        "%{length} (at most %{upper})",
+                        length = diameter.length,
+                        upper = diameter.upper_bound()
+                    )),
+                    None => ui.label("—"),
+                };
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.clustering_sample)
+                    .range(1..=nodes.max(1))
+                    .prefix(t!("Sample: ")),
+            )
+            .on_hover_text(t!("Number of nodes picked at random to average the clustering coefficient over."));
+            if ui
+                .add_enabled(
+                    self.metrics_thread.is_none(),
+                    egui::Button::new(t!("Compute")),
+                )
+                .on_hover_text(t!("Estimate the clustering coefficient, and the diameter with a double sweep, like in the algorithms section."))
+                .clicked()
+            {
+                let data = data.clone();
+                let sample = self.clustering_sample;
+                let need_diameter = data_r.diameter.is_none();
+                self.metrics_thread = Some(AlgoHandle::spawn(
+                    ui.ctx(),
+                    modal.clone(),
+                    move |status_tx, cancel| {
+                        let persons = data.read().persons.clone();
+                        let clustering =
+                            average_clustering(status_tx, &persons[..], sample, cancel)?;
+                        let diameter = if need_diameter {
+                            estimate_graph_diameter(status_tx, &persons[..], cancel)?
+                        } else {
+                            None
+                        };
+                        Ok((clustering, sample.min(persons.len()), diameter))
+                    },
+                ));
+            }
+        });
+        if let Some(ref mut thr) = self.metrics_thread {
+            show_running(ui, thr);
+        }
+
+        if data_r.triangles.is_none() {
+            if ui
+                .add_enabled(
+                    self.triangles_thread.is_none(),
+                    egui::Button::new(t!("Count triangles")),
+                )
+                .on_hover_text(t!("Count the triangles of the whole graph, which gives the exact global clustering coefficient. This can take a while on graphs with nodes of high degree."))
+                .clicked()
+            {
+                let data = data.clone();
+                self.triangles_thread = Some(AlgoHandle::spawn(
+                    ui.ctx(),
+                    modal.clone(),
+                    move |status_tx, cancel| {
+                        let persons = data.read().persons.clone();
+                        count_triangles(status_tx, &persons[..], cancel)
+                    },
+                ));
+            }
+            if let Some(ref mut thr) = self.triangles_thread {
+                show_running(ui, thr);
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! Keyboard shortcuts, and the command palette listing the same actions.
+
+use egui::{
+    vec2, Align2, Context, Id, Key, KeyboardShortcut, Modifiers, SelectableLabel, TextEdit, Ui,
+    Vec2,
+};
+use itertools::Itertools;
+
+/// Something the user can do from the keyboard or the command palette.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    CommandPalette,
+    FocusSearch,
+    ResetCamera,
+    CenterSelected,
+    ZoomIn,
+    ZoomOut,
+    ToggleNodes,
+    ToggleEdges,
+    ToggleMinimap,
+    RunLouvain,
+    ExportImage,
+    ShowHelp,
+}
+
+impl Action {
+    /// All the actions, in the order they're listed. Shortcuts with modifiers come before the
+    /// ones using the same key without, so that they're matched first.
+    pub const ALL: [Action; 12] = [
+        Action::CommandPalette,
+        Action::FocusSearch,
+        Action::ResetCamera,
+        Action::CenterSelected,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ToggleNodes,
+        Action::ToggleEdges,
+        Action::ToggleMinimap,
+        Action::RunLouvain,
+        Action::ExportImage,
+        Action::ShowHelp,
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            Action::CommandPalette => t!("Command palette"),
+            Action::FocusSearch => t!("Search a node"),
+            Action::ResetCamera => t!("Center camera"),
+            Action::CenterSelected => t!("Go to the selected node"),
+            Action::ZoomIn => t!("Zoom in"),
+            Action::ZoomOut => t!("Zoom out"),
+            Action::ToggleNodes => t!("Show or hide the nodes"),
+            Action::ToggleEdges => t!("Show or hide the links"),
+            Action::ToggleMinimap => t!("Show or hide the minimap"),
+            Action::RunLouvain => t!("Run Louvain community detection"),
+            Action::ExportImage => t!("Export as PNG"),
+            Action::ShowHelp => t!("Keyboard shortcuts"),
+        }
+        .to_string()
+    }
+
+    pub fn shortcut(self) -> Option<KeyboardShortcut> {
+        let (modifiers, key) = match self {
+            Action::CommandPalette => (Modifiers::COMMAND, Key::K),
+            Action::FocusSearch => (Modifiers::COMMAND, Key::F),
+            Action::ResetCamera => (Modifiers::NONE, Key::R),
+            Action::CenterSelected => (Modifiers::NONE, Key::F),
+            Action::ZoomIn => (Modifiers::NONE, Key::Plus),
+            Action::ZoomOut => (Modifiers::NONE, Key::Minus),
+            Action::ToggleNodes => (Modifiers::NONE, Key::Num1),
+            Action::ToggleEdges => (Modifiers::NONE, Key::Num2),
+            Action::ShowHelp => (Modifiers::NONE, Key::F1),
+            Action::ToggleMinimap | Action::RunLouvain | Action::ExportImage => return None,
+        };
+        Some(KeyboardShortcut::new(modifiers, key))
+    }
+}
+
+/// Keys panning the camera while they're held, along with the direction the graph moves in.
+const PAN_KEYS: [(Key, Key, [f32; 2]); 4] = [
+    (Key::ArrowLeft, Key::A, [1.0, 0.0]),
+    (Key::ArrowRight, Key::D, [-1.0, 0.0]),
+    (Key::ArrowUp, Key::W, [0.0, 1.0]),
+    (Key::ArrowDown, Key::S, [0.0, -1.0]),
+];
+
+/// Panning speed, in pixels per second.
+const PAN_SPEED: f32 = 600.0;
+
+/// Returns the actions whose shortcut was pressed this frame, and how far the camera should be
+/// panned by the keys being held. Nothing is read while a text field has focus.
+pub fn read_keys(ctx: &Context) -> (Vec<Action>, Vec2) {
+    if ctx.wants_keyboard_input() {
+        return (vec![], Vec2::ZERO);
+    }
+    ctx.input_mut(|is| {
+        let actions = Action::ALL
+            .into_iter()
+            .filter(|a| a.shortcut().is_some_and(|s| is.consume_shortcut(&s)))
+            .collect();
+        let pan = PAN_KEYS
+            .iter()
+            .filter(|(arrow, letter, _)| {
+                is.modifiers.is_none() && (is.key_down(*arrow) || is.key_down(*letter))
+            })
+            .fold(Vec2::ZERO, |pan, &(_, _, [x, y])| pan + vec2(x, y))
+            * PAN_SPEED
+            * is.stable_dt;
+        (actions, pan)
+    })
+}
+
+#[derive(Default)]
+pub struct ShortcutsState {
+    palette_open: bool,
+    help_open: bool,
+    query: String,
+    /// Index of the highlighted command among the ones matching the query.
+    selected: usize,
+}
+
+impl ShortcutsState {
+    pub fn open_palette(&mut self) {
+        self.palette_open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn open_help(&mut self) {
+        self.help_open = true;
+    }
+
+    /// Shows the command palette and the list of shortcuts if they're open, and returns the
+    /// command picked in the palette.
+    pub fn show(&mut self, ctx: &Context, id: Id) -> Option<Action> {
+        let mut picked = None;
+
+        let mut open = self.palette_open;
+        egui::Window::new(t!("Command palette"))
+            .id(id.with("palette"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                picked = self.show_palette(ui);
+            });
+        self.palette_open = open && picked.is_none();
+
+        egui::Window::new(t!("Keyboard shortcuts"))
+            .id(id.with("help"))
+            .open(&mut self.help_open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, show_help);
+
+        picked
+    }
+
+    fn show_palette(&mut self, ui: &mut Ui) -> Option<Action> {
+        // read before the text field gets them
+        let (up, down, enter, escape) = ui.input_mut(|is| {
+            (
+                is.consume_key(Modifiers::NONE, Key::ArrowUp),
+                is.consume_key(Modifiers::NONE, Key::ArrowDown),
+                is.consume_key(Modifiers::NONE, Key::Enter),
+                is.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.palette_open = false;
+            return None;
+        }
+
+        let query = ui.add(
+            TextEdit::singleline(&mut self.query)
+                .hint_text(t!("Type a command"))
+                .desired_width(300.0),
+        );
+        query.request_focus();
+        if query.changed() {
+            self.selected = 0;
+        }
+
+        let pattern = self.query.to_lowercase();
+        let matches = Action::ALL
+            .into_iter()
+            .filter(|&a| a != Action::CommandPalette)
+            .filter(|a| a.label().to_lowercase().contains(&pattern))
+            .collect_vec();
+        if matches.is_empty() {
+            ui.label(t!("No results found"));
+            return None;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if down {
+            self.selected += 1;
+        }
+        self.selected = self.selected.min(matches.len() - 1);
+
+        let mut picked = enter.then(|| matches[self.selected]);
+        for (i, &action) in matches.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add(SelectableLabel::new(i == self.selected, action.label()))
+                    .clicked()
+                {
+                    picked = Some(action);
+                }
+                if let Some(shortcut) = action.shortcut() {
+                    ui.weak(ui.ctx().format_shortcut(&shortcut));
+                }
+            });
+        }
+        picked
+    }
+}
+
+/// Lists the keyboard shortcuts.
+fn show_help(ui: &mut Ui) {
+    ui.label(t!(
+        "Shortcuts apply to the tab under the mouse, when no text field is focused."
+    ));
+    egui::Grid::new("#shortcuts").striped(true).show(ui, |ui| {
+        ui.weak(t!("Arrows or WASD"));
+        ui.label(t!("Move the camera"));
+        ui.end_row();
+        ui.weak(t!("Double-click on empty space"));
+        ui.label(Action::ResetCamera.label());
+        ui.end_row();
+        ui.weak(t!("Ctrl+click"));
+        ui.label(t!("Add a node to the selection, or remove it"));
+        ui.end_row();
+        for action in Action::ALL {
+            if let Some(shortcut) = action.shortcut() {
+                ui.weak(ui.ctx().format_shortcut(&shortcut));
+                ui.label(action.label());
+                ui.end_row();
+            }
+        }
+    });
+}
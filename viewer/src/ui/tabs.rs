@@ -1,23 +1,36 @@
 use crate::app::{GraphTabState, Person, ViewerData};
 use crate::graph_render::camera::{CamXform, Camera};
-use crate::graph_render::{GlForwarder, RenderedGraph};
-use crate::threading::{Cancelable, MyRwLock, StatusWriter};
-use crate::ui::modal::ModalInfo;
-use crate::ui::sections::display;
-use crate::ui::sections::path::PathStatus;
+use crate::graph_render::{packing, GlForwarder, NodeFilter, NodeSizes, RenderedGraph};
+use crate::history::History;
+use crate::thread::{self, JoinHandle};
+use crate::threading::{spawn_cancelable, status_pipe, Cancelable, MyRwLock, StatusWriter};
+use crate::ui::class::ClassSection;
+use crate::ui::modal::{ModalInfo, ModalWriter};
+use crate::ui::sections::display::LabelRank;
+use crate::ui::sections::infos::fly_to;
+use crate::ui::sections::path::{MainPathRequest, PathStatus};
+use crate::ui::sections::{details, display};
+use crate::ui::shortcuts::{self, Action};
 use crate::ui::{SelectedUserField, UiState};
-use crate::{app, log};
+use crate::utils::format_count;
+use crate::{app, log, ui};
+use ahash::AHashMap;
 use eframe::egui_glow;
-use eframe::emath::{vec2, Align, Vec2};
+use eframe::emath::{vec2, Align, Align2, Vec2};
 use eframe::epaint::text::TextWrapMode;
 use eframe::epaint::Shape::LineSegment;
-use eframe::epaint::{CircleShape, Color32, PathStroke, TextShape};
-use egui::{emath, pos2, Id, Layout, Rect, RichText, TextStyle, Ui, WidgetText};
-use graph_format::nalgebra::{Similarity3, Vector4};
-use graph_format::EdgeStore;
+use eframe::epaint::{CircleShape, Color32, PathShape, PathStroke, Stroke, TextShape};
+use egui::{
+    emath, pos2, Context, CursorIcon, Id, Layout, Painter, Rect, RichText, Spinner, TextStyle, Ui,
+    WidgetText,
+};
+use graph_format::nalgebra::{Matrix4, Similarity3, Vector2, Vector4};
+use graph_format::{EdgeStore, Point};
 use itertools::Itertools;
+use rayon::prelude::*;
+use std::cmp::Reverse;
 use std::ops::Deref;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{self, Sender};
 use std::sync::Arc;
 
 #[derive(Copy, Clone)]
@@ -33,11 +46,147 @@ pub struct TabCamera {
     pub cam_animating: Option<CamAnimating>,
 }
 
+/// Node under the mouse, shown in a tooltip.
+#[derive(Default)]
+pub struct HoverState {
+    /// Position of the mouse in the graph when the node was looked up.
+    pos_world: Option<Vector2<f32>>,
+    node: Option<usize>,
+    /// Time of the last zoom, which moves the point under the mouse without moving the mouse.
+    zoomed_at: f64,
+}
+
 pub struct GraphTabLoaded {
     pub ui_state: UiState,
     pub viewer_data: Arc<MyRwLock<ViewerData>>,
     pub rendered_graph: Arc<MyRwLock<RenderedGraph>>,
     pub tab_camera: TabCamera,
+    pub hover: HoverState,
+    /// Candidates for labels: the nodes with the highest degrees, in decreasing order.
+    pub hubs: Vec<usize>,
+    /// Same, by PageRank, along with the scores they were ranked by.
+    pub pagerank_hubs: Option<(Arc<Vec<f32>>, Vec<usize>)>,
+    /// Snapshots taken before the algorithms overwrite positions or classes.
+    pub history: History,
+    /// Edges the graph was rendered from, to render it again in a copy of the tab.
+    pub edges: Arc<Vec<EdgeStore>>,
+    /// Transition from the tab this one was opened from, played once it's loaded.
+    pub appear: Option<Appear>,
+}
+
+/// Transition of a new tab from the tab it was opened from: the nodes they share move from their
+/// position in the parent to theirs, and the others fade in.
+pub struct Appear {
+    /// Copy of the nodes moved along the transition, the new ones marked with
+    /// [`packing::APPEARING`]. Handed to the thread rendering each step, and only kept if any node
+    /// moves.
+    persons: Option<Vec<Person>>,
+    /// Position of each node in the parent, if it had it.
+    from: Arc<Vec<Option<Point>>>,
+    /// Whether any shared node moves, otherwise the vertices don't need to be built again.
+    moves: bool,
+    step: Option<JoinHandle<Vec<Person>>>,
+    /// Progress of the last step rendered.
+    rendered: f32,
+}
+
+impl Appear {
+    /// Starts the transition of a new tab, by queuing its first step. The nodes of subgraphs are
+    /// matched with those of `parent` through their index in the main graph, those of other tabs
+    /// are all new.
+    pub(crate) fn start(tab: &mut GraphTabLoaded, parent: Option<&ViewerData>) {
+        let data = tab.viewer_data.read();
+        let from = match (parent, &data.base_ids) {
+            (Some(parent), Some(base_ids)) => {
+                let positions: AHashMap<usize, Point> = parent
+                    .persons
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| (parent.base_id(i), p.position))
+                    .collect();
+                base_ids
+                    .iter()
+                    .map(|id| positions.get(id).copied())
+                    .collect()
+            }
+            _ => vec![None; data.persons.len()],
+        };
+        let mut persons = data.persons.as_ref().clone();
+        let mut moves = false;
+        for (p, from) in persons.iter_mut().zip(from.iter()) {
+            match from {
+                Some(from) => {
+                    moves |= (from.x, from.y) != (p.position.x, p.position.y);
+                    p.position = *from;
+                }
+                None => p.modularity_class |= packing::APPEARING,
+            }
+        }
+        let mut graph = tab.rendered_graph.write();
+        let task = ui::rerender_graph(
+            &persons,
+            data.directed,
+            graph.edge_curvature,
+            graph.sort_edges,
+        );
+        graph.tasks.push_back(task);
+        graph.appear = 0.0;
+        tab.appear = Some(Appear {
+            persons: moves.then_some(persons),
+            from: Arc::new(from),
+            moves,
+            step: None,
+            rendered: 0.0,
+        });
+    }
+
+    /// Renders the step of the transition at progress `t` in the background, once the previous
+    /// one has been drawn. Returns whether the transition is over.
+    fn update(
+        &mut self,
+        t: f32,
+        data: &Arc<MyRwLock<ViewerData>>,
+        graph: &Arc<MyRwLock<RenderedGraph>>,
+    ) -> bool {
+        if let Some(step) = self.step.take_if(|step| step.is_finished()) {
+            self.persons = step.join().ok();
+        }
+        if !self.moves || self.rendered >= 1.0 {
+            return t >= 1.0 && self.step.is_none();
+        }
+        if !graph.read().tasks.is_empty() {
+            return false;
+        }
+        let Some(mut persons) = self.persons.take() else {
+            // the step is still running, or failed, in which case the nodes stay where they are
+            return self.step.is_none();
+        };
+        self.rendered = t;
+        let from = self.from.clone();
+        let data = data.clone();
+        let graph = graph.clone();
+        self.step = Some(thread::spawn(move || {
+            let task = {
+                let data = data.read();
+                let ends = data.persons.iter().zip(from.iter());
+                for (p, (to, from)) in persons.iter_mut().zip(ends) {
+                    if let Some(from) = from {
+                        p.position = *from + (to.position - *from) * t;
+                    }
+                }
+                let graph = graph.read();
+                ui::rerender_graph(
+                    &persons,
+                    data.directed,
+                    graph.edge_curvature,
+                    graph.sort_edges,
+                )
+            };
+            graph.write().tasks.push_back(task);
+            persons
+        }));
+        false
+    }
 }
 
 pub struct GraphTab {
@@ -47,31 +196,46 @@ pub struct GraphTab {
     pub state: GraphTabState,
 }
 
-pub fn create_tab<'a>(
+/// Loads a graph into a new tab. The display settings of `ui_state` are kept, apart from the
+/// opacities and the degree histogram, which depend on the graph.
+pub fn create_tab(
     viewer: ViewerData,
-    edges: impl ExactSizeIterator<Item = &'a EdgeStore>,
+    edges: impl Into<Arc<Vec<EdgeStore>>>,
     gl: GlForwarder,
     default_filter: u16,
     camera: Camera,
     ui_state: UiState,
     status_tx: StatusWriter,
 ) -> Cancelable<GraphTabLoaded> {
+    let edges = edges.into();
     log!(
         status_tx,
         t!(
             "Creating tab with %{n} nodes and %{m} edges",
-            n = viewer.persons.len(),
-            m = edges.len()
+            n = format_count(viewer.persons.len()),
+            m = format_count(edges.len())
         )
     );
     log!(status_tx, t!("Computing maximum degree..."));
-    let max_degree = viewer
-        .persons
-        .iter()
-        .map(|p| p.neighbors.len())
-        .max()
-        .unwrap() as u16;
+    let max_degree = packing::filter_degree(
+        viewer
+            .persons
+            .iter()
+            .map(|p| p.neighbors.len())
+            .max()
+            .unwrap_or(0),
+    );
     log!(status_tx, t!("Maximum degree is %{d}", d = max_degree));
+    let mut degree_counts = vec![0; max_degree as usize + 1];
+    for p in viewer.persons.iter() {
+        degree_counts[p.neighbors.len().min(max_degree as usize)] += 1;
+    }
+    let filter_names = ui_state.display.filters_names();
+    let sort_edges = ui_state.display.sort_edges;
+    // degrees never change, so this stays valid when the nodes are moved or recolored
+    let mut hubs = (0..viewer.persons.len()).collect_vec();
+    hubs.par_sort_unstable_by_key(|&i| Reverse(viewer.persons[i].neighbors.len()));
+    hubs.truncate(MAX_LABEL_CANDIDATES);
     Ok(GraphTabLoaded {
         tab_camera: TabCamera {
             camera,
@@ -83,22 +247,261 @@ pub fn create_tab<'a>(
                 g_opac_edges: (400000.0 / edges.len() as f32).min(0.22),
                 g_opac_nodes: ((70000.0 / viewer.persons.len() as f32) * 2.0).min(0.58),
                 max_degree,
-                ..Default::default()
+                degree_counts,
+                ..ui_state.display
             },
             ..ui_state
         },
         rendered_graph: Arc::new(MyRwLock::new({
-            let mut graph = RenderedGraph::new(gl, &viewer, edges, status_tx)?;
+            let mut graph = RenderedGraph::new(gl, &viewer, edges.iter(), sort_edges, status_tx)?;
             graph.node_filter.degree_filter = (default_filter, u16::MAX);
+            graph.node_filter.filter_names = filter_names;
             graph
         })),
         viewer_data: Arc::from(MyRwLock::new(viewer)),
+        hover: HoverState::default(),
+        hubs,
+        pagerank_hubs: None,
+        history: History::default(),
+        edges,
+        appear: None,
     })
 }
 
+/// Opens a copy of a tab, with the same camera, display settings, filters, selection and path.
+///
+/// The copy shares the nodes of the original until either of them changes them, see
+/// [`ViewerData`], and its graph is rendered again from the edges of the original.
+pub(crate) fn duplicate_tab(
+    title: &str,
+    tab: &GraphTabLoaded,
+    ctx: &Context,
+    modal_tx: impl ModalWriter,
+) -> NewTabRequest {
+    let (status_tx, status_rx) = status_pipe(ctx);
+    let (state_tx, state_rx) = mpsc::channel();
+    let (gl_fwd, gl_mpsc) = GlForwarder::new();
+
+    let title = t!("%{title} (copy)", title = title).to_string();
+    let request = NewTabRequest {
+        id: Id::new((&title, chrono::Utc::now())),
+        title,
+        closeable: true,
+        state: GraphTabState::loading(status_rx, state_rx, gl_mpsc),
+    };
+
+    let viewer = tab.viewer_data.read().clone();
+    let edges = tab.edges.clone();
+    let filter = tab.rendered_graph.read().node_filter;
+    let ui_state = tab.ui_state.duplicated();
+    let (opac_nodes, opac_edges) = (ui_state.display.g_opac_nodes, ui_state.display.g_opac_edges);
+    let (camera, camera_default) = (tab.tab_camera.camera, tab.tab_camera.camera_default);
+    spawn_cancelable(modal_tx, move || {
+        let mut copy = create_tab(
+            viewer,
+            edges,
+            gl_fwd,
+            filter.degree_filter.0,
+            camera,
+            ui_state,
+            status_tx,
+        )?;
+        copy.rendered_graph.write().node_filter = filter;
+        copy.tab_camera.camera_default = camera_default;
+        let display = &mut copy.ui_state.display;
+        (display.g_opac_nodes, display.g_opac_edges) = (opac_nodes, opac_edges);
+        state_tx.send(copy)?;
+        Ok(())
+    });
+
+    request
+}
+
+/// Number of nodes looked at when placing labels, which bounds the time spent on them each frame.
+/// Hubs further down the list are only labeled when zoomed in enough for the ones before to be
+/// out of view.
+const MAX_LABEL_CANDIDATES: usize = 20_000;
+
+/// Draws the names of the highest-degree visible nodes in view, skipping those whose label would
+/// overlap one already placed.
+fn draw_labels(
+    painter: &Painter,
+    rect: Rect,
+    cam: Matrix4<f32>,
+    persons: &[Person],
+    hubs: &[usize],
+    display: &display::DisplaySection,
+    filter: NodeFilter,
+) {
+    let bg = display.g_background;
+    let color = if bg.r() as u32 + bg.g() as u32 + bg.b() as u32 > 3 * 128 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    };
+    let count = display.g_label_count;
+    let mut placed: Vec<Rect> = Vec::with_capacity(count);
+    for &id in hubs {
+        if placed.len() >= count {
+            break;
+        }
+        let person = &persons[id];
+        if !filter.shows(person) {
+            continue;
+        }
+        let pos_scr = (cam * Vector4::from(person.position)).xy();
+        if pos_scr.x.abs() > 1.0 || pos_scr.y.abs() > 1.0 {
+            continue;
+        }
+        let center = rect.center() + vec2(pos_scr.x, -pos_scr.y) * rect.size() * 0.5;
+        let galley = painter.layout_no_wrap(
+            person.name.to_string(),
+            TextStyle::Body.resolve(&painter.ctx().style()),
+            color,
+        );
+        // above the node, so that it stays visible
+        let label = Rect::from_center_size(center - vec2(0.0, galley.size().y), galley.size());
+        if placed.iter().any(|r| r.intersects(label)) {
+            continue;
+        }
+        painter.galley(label.min, galley, color);
+        placed.push(label);
+    }
+}
+
+/// Maximum number of links drawn around a highlighted node, which bounds the time spent on hubs.
+const MAX_HIGHLIGHTED_EDGES: usize = 5_000;
+
+/// Color of the links of the selected node. Those of the hovered node are fainter.
+const HIGHLIGHT_COLOR: Color32 = Color32::from_rgba_premultiplied(40, 180, 40, 220);
+
+/// Color of the ring around the nodes pinned in place for the layout.
+const PIN_COLOR: Color32 = Color32::from_rgb(200, 40, 120);
+
+/// Draws the links between `id` and its visible neighbors, on top of the other links. Past
+/// [`MAX_HIGHLIGHTED_EDGES`], the number of links left out is written next to the node.
+fn draw_incident_edges(
+    painter: &Painter,
+    rect: Rect,
+    cam: Matrix4<f32>,
+    persons: &[Person],
+    id: usize,
+    filter: NodeFilter,
+    color: Color32,
+) {
+    let to_screen = |p: &Person| {
+        let pos = (cam * Vector4::from(p.position)).xy();
+        rect.center() + vec2(pos.x, -pos.y) * rect.size() * 0.5
+    };
+    let center = to_screen(&persons[id]);
+    let stroke = PathStroke::new(1.5, color);
+    let mut drawn = 0;
+    let mut left_out = 0;
+    for &j in &persons[id].neighbors {
+        let other = &persons[j];
+        if !filter.shows(other) {
+            continue;
+        }
+        if drawn == MAX_HIGHLIGHTED_EDGES {
+            left_out += 1;
+            continue;
+        }
+        painter.add(LineSegment {
+            points: [center, to_screen(other)],
+            stroke: stroke.clone(),
+        });
+        drawn += 1;
+    }
+    if left_out > 0 {
+        painter.text(
+            center + vec2(10.0, -10.0),
+            Align2::LEFT_BOTTOM,
+            t!("+%{count} links not drawn", count = left_out),
+            TextStyle::Body.resolve(&painter.ctx().style()),
+            color,
+        );
+    }
+}
+
+/// Time the mouse has to stay still before the tooltip of the node under it is shown, in seconds.
+const HOVER_DELAY: f32 = 0.3;
+
+/// Distance from the edge of a node within which it is considered hovered, in pixels.
+const HOVER_RADIUS: f32 = 6.0;
+
+/// Returns the node closest to `pos`, along with its distance in pixels.
+///
+/// The distance used for the comparison is relative to the radius of each node as drawn, so that
+/// pointing inside a small node picks it even when a larger one is behind it.
+fn nearest_node(
+    persons: &[Person],
+    pos: Vector2<f32>,
+    sizes: NodeSizes,
+    zoom: f32,
+) -> Option<(usize, f32)> {
+    persons
+        .iter()
+        .map(|p| (p.position - pos.into()).norm())
+        .enumerate()
+        .min_by(|&(i, a), &(j, b)| {
+            (a / sizes.radius(&persons[i], zoom)).total_cmp(&(b / sizes.radius(&persons[j], zoom)))
+        })
+        .map(|(i, dist)| (i, dist * zoom))
+}
+
+/// Returns the node drawn under `pos`, within [`HOVER_RADIUS`] of its edge.
+fn node_under(persons: &[Person], pos: Vector2<f32>, sizes: NodeSizes, zoom: f32) -> Option<usize> {
+    nearest_node(persons, pos, sizes, zoom)
+        .filter(|&(i, dist)| {
+            // distance in pixels from the edge of the node
+            dist - sizes.pixels(&persons[i], zoom) / 2.0 <= HOVER_RADIUS
+        })
+        .map(|(i, _)| i)
+}
+
+fn show_node_tooltip(ui: &mut Ui, data: &ViewerData, id: usize) {
+    let person = &data.persons[id];
+    ui.strong(person.name);
+    egui::Grid::new("#hover").show(ui, |ui| {
+        ui.label(t!("Facebook ID:"));
+        ui.label(person.id);
+        ui.end_row();
+        ui.label(t!("Friends:"));
+        ui.label(format!("{}", person.neighbors.len()));
+        ui.end_row();
+        ui.label(t!("Class:"));
+        ui.horizontal(|ui| {
+            let class = person.modularity_class;
+            ClassSection::class_circle(ui, &data.modularity_classes[class as usize]);
+            ui.label(format!("{}", class));
+        });
+        ui.end_row();
+    });
+}
+
+/// Starts a camera animation back to the default view.
+fn reset_camera(ui: &Ui, cid: Id, camera: &mut TabCamera) {
+    ui.ctx().animate_bool_with_time(cid, true, 0.0);
+    camera.cam_animating = Some(CamAnimating::PanTo {
+        from: camera.camera.transf,
+        to: camera.camera_default.transf,
+    });
+}
+
+/// Duration of the transition of new tabs from the one they were opened from, see [`Appear`], in
+/// seconds.
+const APPEAR_DURATION: f32 = 0.5;
+
+fn appear_id(tab: Id) -> Id {
+    Id::new("appear").with(tab)
+}
+
 pub struct TabViewer<'tab_request, 'frame> {
     pub tab_request: &'tab_request mut Option<NewTabRequest>,
+    pub path_request: &'tab_request mut Option<MainPathRequest>,
     pub top_bar: &'tab_request mut bool,
+    /// Set when something moves on screen, for the app to keep repainting.
+    pub animating: &'tab_request mut bool,
     pub frame: &'frame mut eframe::Frame,
     pub modal: Sender<ModalInfo>,
 }
@@ -111,6 +514,8 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
     }
 
     fn ui(&mut self, ui: &mut Ui, tab: &mut Self::Tab) {
+        let tab_id = tab.id;
+        let tab_title = &tab.title;
         match &mut tab.state {
             GraphTabState::Loading {
                 status_rx,
@@ -122,12 +527,18 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                 }
                 app::show_status(ui, status_rx);
                 if let Ok(state) = state_rx.try_recv() {
+                    if state.appear.is_some() {
+                        ui.ctx()
+                            .animate_bool_with_time(appear_id(tab.id), false, 0.0);
+                    }
                     tab.state = GraphTabState::Loaded(state);
                     ui.ctx().request_repaint();
                 }
             }
             GraphTabState::Loaded(tab) => {
                 let cid = Id::from("camera").with(ui.id());
+                // when the dock is split, the keys go to the tab under the mouse
+                let hovered = ui.ui_contains_pointer();
 
                 ui.spacing_mut().scroll.floating_allocated_width = 18.0;
                 egui::SidePanel::left("settings")
@@ -138,18 +549,28 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                             &tab.viewer_data,
                             &tab.rendered_graph,
                             self.tab_request,
+                            self.path_request,
                             &mut tab.tab_camera,
                             cid,
                             &self.modal,
+                            &mut tab.history,
                         );
                     });
+                if std::mem::take(&mut tab.ui_state.details.duplicate_requested) {
+                    *self.tab_request =
+                        Some(duplicate_tab(tab_title, tab, ui.ctx(), self.modal.clone()));
+                }
                 egui::CentralPanel::default()
                     .frame(egui::Frame {
-                        fill: Color32::from_rgba_unmultiplied(255, 255, 255, 0),
+                        fill: tab.ui_state.display.g_background,
                         ..Default::default()
                     })
                     .show_inside(ui, |ui| {
                         let (id, rect) = ui.allocate_space(ui.available_size());
+                        tab.ui_state
+                            .details
+                            .frame_stats
+                            .update(ui.input(|i| i.time));
 
                         let sz = rect.size();
                         if sz != tab.tab_camera.camera.size {
@@ -160,6 +581,57 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                         let response =
                             ui.interact(rect, id, egui::Sense::click().union(egui::Sense::drag()));
 
+                        let (actions, pan) = if hovered {
+                            shortcuts::read_keys(ui.ctx())
+                        } else {
+                            (vec![], Vec2::ZERO)
+                        };
+                        if pan != Vec2::ZERO {
+                            tab.tab_camera.camera.pan(pan.x, pan.y);
+                            tab.tab_camera.cam_animating = None;
+                            ui.ctx().request_repaint();
+                        }
+                        let picked = tab.ui_state.shortcuts.show(ui.ctx(), cid);
+                        for action in actions.into_iter().chain(picked) {
+                            match action {
+                                Action::CommandPalette => tab.ui_state.shortcuts.open_palette(),
+                                Action::ShowHelp => tab.ui_state.shortcuts.open_help(),
+                                Action::ResetCamera => {
+                                    reset_camera(ui, cid, &mut tab.tab_camera);
+                                }
+                                Action::CenterSelected => {
+                                    if let Some(sel) = tab.ui_state.infos.infos_current {
+                                        let pos = tab.viewer_data.read().persons[sel].position;
+                                        fly_to(&mut tab.tab_camera, cid, pos, ui);
+                                    }
+                                }
+                                Action::ZoomIn | Action::ZoomOut => {
+                                    const ZOOM_STEP: f32 = 1.25;
+                                    let s = if action == Action::ZoomIn {
+                                        ZOOM_STEP
+                                    } else {
+                                        1.0 / ZOOM_STEP
+                                    };
+                                    let pos = response.hover_pos().unwrap_or(rect.center());
+                                    tab.tab_camera.camera.zoom(s, (pos - rect.min).to_pos2());
+                                }
+                                Action::ToggleNodes => {
+                                    tab.ui_state.display.g_show_nodes ^= true;
+                                }
+                                Action::ToggleEdges => {
+                                    tab.ui_state.display.g_show_edges ^= true;
+                                }
+                                Action::ToggleMinimap => {
+                                    tab.ui_state.display.g_show_minimap ^= true;
+                                }
+                                // handled by the side panel, which is drawn before
+                                Action::FocusSearch | Action::RunLouvain | Action::ExportImage => {
+                                    tab.ui_state.pending_action = Some(action);
+                                    ui.ctx().request_repaint();
+                                }
+                            }
+                        }
+
                         if !response.is_pointer_button_down_on() {
                             if let Some(v) = tab.tab_camera.cam_animating {
                                 const DUR: f32 = 0.5;
@@ -222,7 +694,45 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                             let centered_pos_raw = pos - rect.center();
                             let centered_pos = 2.0 * centered_pos_raw / rect.size();
 
-                            if response.dragged_by(egui::PointerButton::Primary) {
+                            if tab.ui_state.selection.lasso_mode || tab.ui_state.ruler.active {
+                                ui.ctx().set_cursor_icon(CursorIcon::Crosshair);
+                            }
+                            let zero_pos = (pos - rect.min).to_pos2();
+
+                            tab.ui_state.details.mouse_pos = Some(centered_pos.to_pos2());
+                            let pos_world = (tab.tab_camera.camera.get_inverse_matrix()
+                                * Vector4::new(centered_pos.x, -centered_pos.y, 0.0, 1.0))
+                            .xy();
+                            tab.ui_state.details.mouse_pos_world = Some(pos_world);
+
+                            let zoom = tab.tab_camera.camera.transf.scaling();
+                            let sizes = tab.ui_state.display.node_sizes(&tab.rendered_graph.read());
+
+                            if response.drag_started_by(egui::PointerButton::Primary) {
+                                // dragging the selected node moves it instead of the camera
+                                let under = node_under(
+                                    &tab.viewer_data.read().persons,
+                                    pos_world,
+                                    sizes,
+                                    zoom,
+                                );
+                                tab.ui_state.algorithms.grab_node(
+                                    under.filter(|&n| Some(n) == tab.ui_state.infos.infos_current),
+                                );
+                            }
+                            if tab.ui_state.selection.lasso_mode
+                                && response.dragged_by(egui::PointerButton::Primary)
+                            {
+                                tab.ui_state.selection.extend_lasso(pos);
+                            } else if let Some(node) = tab.ui_state.algorithms.grabbed_node()
+                                && response.dragged_by(egui::PointerButton::Primary)
+                            {
+                                tab.ui_state.algorithms.move_node(
+                                    &mut tab.viewer_data.write(),
+                                    node,
+                                    pos_world.into(),
+                                );
+                            } else if response.dragged_by(egui::PointerButton::Primary) {
                                 tab.tab_camera
                                     .camera
                                     .pan(response.drag_delta().x, response.drag_delta().y);
@@ -239,28 +749,25 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                                 tab.tab_camera.cam_animating = Some(CamAnimating::Rot(rot));
                             }
 
-                            let zero_pos = (pos - rect.min).to_pos2();
-
-                            tab.ui_state.details.mouse_pos = Some(centered_pos.to_pos2());
-                            let pos_world = (tab.tab_camera.camera.get_inverse_matrix()
-                                * Vector4::new(centered_pos.x, -centered_pos.y, 0.0, 1.0))
-                            .xy();
-                            tab.ui_state.details.mouse_pos_world = Some(pos_world);
-
-                            if response.clicked() {
-                                let closest = tab
-                                    .viewer_data
-                                    .read()
-                                    .persons
-                                    .iter()
-                                    .map(|p| {
-                                        let diff = p.position - pos_world.into();
-
-                                        diff.norm_squared()
-                                    })
-                                    .enumerate()
-                                    .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                                    .map(|(i, _)| i);
+                            if response.clicked() && tab.ui_state.ruler.active {
+                                tab.ui_state.ruler.click(pos_world);
+                            } else if response.clicked() && ui.input(|i| i.modifiers.command) {
+                                if let Some((closest, _)) = nearest_node(
+                                    &tab.viewer_data.read().persons,
+                                    pos_world,
+                                    sizes,
+                                    zoom,
+                                ) {
+                                    tab.ui_state.selection.toggle(closest);
+                                }
+                            } else if response.clicked() {
+                                let closest = nearest_node(
+                                    &tab.viewer_data.read().persons,
+                                    pos_world,
+                                    sizes,
+                                    zoom,
+                                )
+                                .map(|(i, _)| i);
                                 if let Some(closest) = closest {
                                     log::info!(
                                         "Selected person {}: {:?} (mouse: {:?})",
@@ -292,10 +799,72 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                                 }
                             }
 
+                            // egui only counts presses where the pointer barely moved as clicks,
+                            // but the release of a drag can still end up close to the previous one
+                            if response.hovered()
+                                && !response.drag_stopped()
+                                && !tab.ui_state.ruler.active
+                                && ui.input(|i| {
+                                    i.pointer
+                                        .button_double_clicked(egui::PointerButton::Primary)
+                                })
+                            {
+                                let data = tab.viewer_data.read();
+                                match node_under(&data.persons, pos_world, sizes, zoom) {
+                                    None => reset_camera(ui, cid, &mut tab.tab_camera),
+                                    // the nodes of a meta-graph open the members of their class
+                                    Some(node) => {
+                                        if let Some(meta) = &data.meta {
+                                            *self.tab_request = Some(meta.open_class(
+                                                data.persons[node].modularity_class,
+                                                tab.tab_camera.camera,
+                                                tab.ui_state.display.inherited(),
+                                                ui.ctx(),
+                                                self.modal.clone(),
+                                            ));
+                                        }
+                                    }
+                                }
+                            }
+
                             let (scroll_delta, zoom_delta, multi_touch) = ui.input(|is| {
                                 (is.raw_scroll_delta, is.zoom_delta(), is.multi_touch())
                             });
 
+                            // the tooltip waits for the mouse and the camera to stay still, and
+                            // is only looked up again when the point under it changes
+                            let now = ui.input(|i| i.time);
+                            if scroll_delta.y != 0.0 || zoom_delta != 1.0 || multi_touch.is_some() {
+                                tab.hover.zoomed_at = now;
+                            }
+                            let moving = response.dragged()
+                                || tab.tab_camera.cam_animating.is_some()
+                                || now - tab.hover.zoomed_at < HOVER_DELAY as f64
+                                || ui.input(|i| i.pointer.time_since_last_movement()) < HOVER_DELAY;
+                            if moving {
+                                tab.hover = HoverState {
+                                    zoomed_at: tab.hover.zoomed_at,
+                                    ..HoverState::default()
+                                };
+                                ui.ctx().request_repaint_after_secs(HOVER_DELAY);
+                            } else if tab.hover.pos_world != Some(pos_world) {
+                                let data = tab.viewer_data.read();
+                                tab.hover = HoverState {
+                                    pos_world: Some(pos_world),
+                                    node: node_under(&data.persons, pos_world, sizes, zoom),
+                                    zoomed_at: tab.hover.zoomed_at,
+                                };
+                            }
+                            if let Some(node) = tab.hover.node {
+                                let data = tab.viewer_data.read();
+                                egui::show_tooltip_at_pointer(
+                                    ui.ctx(),
+                                    ui.layer_id(),
+                                    id.with("hover"),
+                                    |ui| show_node_tooltip(ui, &data, node),
+                                );
+                            }
+
                             if scroll_delta.y != 0.0 {
                                 let zoom_speed = 1.1;
                                 let s = if scroll_delta.y > 0.0 {
@@ -315,37 +884,124 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                         } else {
                             tab.ui_state.details.mouse_pos = None;
                             tab.ui_state.details.mouse_pos_world = None;
+                            tab.hover = HoverState::default();
+                        }
+
+                        if response.drag_stopped() {
+                            tab.ui_state.algorithms.grab_node(None);
+                        }
+                        if response.drag_stopped() && !tab.ui_state.selection.lasso.is_empty() {
+                            let cam = tab.tab_camera.camera.get_matrix();
+                            let add = ui.input(|i| i.modifiers.command);
+                            tab.ui_state.selection.finish_lasso(
+                                &tab.viewer_data.read().persons,
+                                tab.rendered_graph.read().node_filter,
+                                |pos| {
+                                    let pos = (cam * Vector4::from(pos)).xy();
+                                    rect.center() + vec2(pos.x, -pos.y) * rect.size() * 0.5
+                                },
+                                add,
+                            );
                         }
 
+                        let context_lost = {
+                            let mut graph = tab.rendered_graph.write();
+                            if graph.context_lost && !graph.restoring {
+                                graph.restoring = true;
+                                let color_mode = graph.color_mode.clone();
+                                let curvature = graph.edge_curvature;
+                                let sort_edges = graph.sort_edges;
+                                let data = tab.viewer_data.clone();
+                                let rendered_graph = tab.rendered_graph.clone();
+                                thread::spawn(move || {
+                                    let task = {
+                                        let data = data.read();
+                                        ui::restore_graph(
+                                            &data.persons,
+                                            data.directed,
+                                            curvature,
+                                            sort_edges,
+                                            &color_mode,
+                                        )
+                                    };
+                                    rendered_graph.write().tasks.push_back(task);
+                                });
+                            }
+                            graph.context_lost
+                        };
+
                         let graph = tab.rendered_graph.clone();
                         let edges = tab.ui_state.display.g_show_edges;
                         let nodes = tab.ui_state.display.g_show_nodes;
+                        let appear = ui.ctx().animate_bool_with_time_and_easing(
+                            appear_id(tab_id),
+                            true,
+                            APPEAR_DURATION,
+                            emath::easing::circular_out,
+                        );
+                        if let Some(transition) = &mut tab.appear
+                            && transition.update(appear, &tab.viewer_data, &tab.rendered_graph)
+                        {
+                            tab.appear = None;
+                        }
+                        let appearing = tab.appear.is_some();
                         let opac_edges = tab.ui_state.display.g_opac_edges;
                         let opac_nodes = tab.ui_state.display.g_opac_nodes;
 
                         let cam = tab.tab_camera.camera.get_matrix();
-                        let class_colors = tab
-                            .viewer_data
-                            .read()
-                            .modularity_classes
-                            .iter()
-                            .map(|c| c.color.to_u32())
-                            .collect_vec();
+                        let edge_width =
+                            tab.ui_state.display.edge_half_width(&tab.tab_camera.camera);
+                        let edge_fraction = tab.ui_state.display.edge_lod.fraction();
+                        let node_sizes = tab.ui_state.display.node_sizes(&graph.read());
+                        let class_colors = ClassSection::display_colors(
+                            ui.ctx(),
+                            &tab.viewer_data.read().modularity_classes,
+                        );
                         let callback = egui::PaintCallback {
                             rect,
                             callback: Arc::new(egui_glow::CallbackFn::new(
                                 move |_info, painter| {
-                                    graph.write().paint(
+                                    let mut graph = graph.write();
+                                    graph.appear = appear;
+                                    graph.paint(
                                         painter.gl(),
                                         cam,
                                         (edges, opac_edges),
+                                        edge_width,
+                                        edge_fraction,
                                         (nodes, opac_nodes),
+                                        node_sizes,
                                         &class_colors,
                                     );
                                 },
                             )),
                         };
                         ui.painter().add(callback);
+                        if context_lost {
+                            ui.put(
+                                Rect::from_center_size(rect.center(), vec2(rect.width(), 60.0)),
+                                |ui: &mut Ui| {
+                                    ui.vertical_centered(|ui| {
+                                        ui.add(Spinner::new());
+                                        ui.label(t!("Restoring graphics resources…"));
+                                    })
+                                    .response
+                                },
+                            );
+                            ui.ctx().request_repaint();
+                        }
+                        let moving = tab.tab_camera.cam_animating.is_some()
+                            || tab.ui_state.algorithms.layout_running();
+                        // edges are still being streamed in, or other work is waiting for the GL
+                        // context
+                        *self.animating |=
+                            moving || !tab.rendered_graph.read().tasks.is_empty() || appearing;
+                        // the frames that stream the edges in are slowed down by the uploads
+                        // rather than the drawing, so they aren't timed
+                        tab.ui_state
+                            .display
+                            .edge_lod
+                            .update(ui.input(|i| i.time), moving || response.dragged());
 
                         let clipped_painter = ui.painter().with_clip_rect(rect);
 
@@ -377,48 +1033,189 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                             ));
                         };
 
+                        let display = &tab.ui_state.display;
+                        let zoom = tab.tab_camera.camera.transf.scaling()
+                            / tab.tab_camera.camera_default.transf.scaling();
+                        if display.g_highlight_edges {
+                            let filter = tab.rendered_graph.read().node_filter;
+                            let selected = tab.ui_state.infos.infos_current;
+                            // the selected node's links are drawn last so that they stay on top
+                            let hovered = tab.hover.node.filter(|&h| Some(h) != selected);
+                            let highlighted = hovered
+                                .map(|h| (h, HIGHLIGHT_COLOR.gamma_multiply(0.4)))
+                                .into_iter()
+                                .chain(selected.map(|s| (s, HIGHLIGHT_COLOR)));
+                            for (id, color) in highlighted {
+                                draw_incident_edges(
+                                    &clipped_painter,
+                                    rect,
+                                    cam,
+                                    &data.persons,
+                                    id,
+                                    filter,
+                                    color,
+                                );
+                            }
+                        }
+                        if display.g_show_nodes
+                            && display.g_show_labels
+                            && zoom >= display.g_label_min_zoom
+                        {
+                            let hubs = if display.g_label_rank == LabelRank::PageRank
+                                && let Some(scores) = &data.pagerank
+                            {
+                                if !tab
+                                    .pagerank_hubs
+                                    .as_ref()
+                                    .is_some_and(|(ranked, _)| Arc::ptr_eq(ranked, scores))
+                                {
+                                    let mut hubs = (0..scores.len()).collect_vec();
+                                    hubs.par_sort_unstable_by(|&a, &b| {
+                                        scores[b].total_cmp(&scores[a])
+                                    });
+                                    hubs.truncate(MAX_LABEL_CANDIDATES);
+                                    tab.pagerank_hubs = Some((scores.clone(), hubs));
+                                }
+                                &tab.pagerank_hubs.as_ref().unwrap().1
+                            } else {
+                                &tab.hubs
+                            };
+                            draw_labels(
+                                &clipped_painter,
+                                rect,
+                                cam,
+                                &data.persons,
+                                hubs,
+                                display,
+                                tab.rendered_graph.read().node_filter,
+                            );
+                        }
+
                         let alpha = if tab.ui_state.path.path_loading {
                             Color32::from_white_alpha(30)
                         } else {
                             Color32::from_white_alpha(255)
                         };
 
-                        let path = if let Some(PathStatus::PathFound(ref path)) =
+                        for &p in &tab.ui_state.selection.nodes {
+                            draw_person(p, Color32::from_rgba_unmultiplied(120, 0, 150, 200));
+                        }
+                        let lasso = &tab.ui_state.selection.lasso;
+                        if lasso.len() > 1 {
+                            clipped_painter.add(PathShape::closed_line(
+                                lasso.clone(),
+                                PathStroke::new(
+                                    1.5,
+                                    Color32::from_rgba_unmultiplied(120, 0, 150, 200),
+                                ),
+                            ));
+                        }
+
+                        for &p in tab.ui_state.algorithms.pinned_nodes().iter() {
+                            let pos = (cam * Vector4::from(data.persons[p].position)).xy();
+                            clipped_painter.add(CircleShape::stroke(
+                                rect.center() + vec2(pos.x, -pos.y) * rect.size() * 0.5,
+                                6.0,
+                                Stroke::new(2.0, PIN_COLOR),
+                            ));
+                        }
+
+                        tab.ui_state.ruler.show(
+                            &clipped_painter,
+                            rect,
+                            cam,
+                            tab.ui_state.details.mouse_pos_world,
+                        );
+
+                        for &p in tab.ui_state.path.mutual_friends() {
+                            draw_person(p, Color32::from_rgba_unmultiplied(0, 60, 180, 200));
+                        }
+
+                        let path_color = Color32::from_rgba_unmultiplied(150, 0, 0, 200) * alpha;
+                        let faded_color = Color32::from_rgba_unmultiplied(150, 0, 0, 50) * alpha;
+                        let persons = if let Some(PathStatus::PathFound(ref paths)) =
                             tab.ui_state.path.path_status
                         {
-                            for (a, b) in path.iter().tuple_windows() {
-                                let a = (cam * Vector4::from(data.persons[*a].position)).xy();
-                                let b = (cam * Vector4::from(data.persons[*b].position)).xy();
-                                clipped_painter.add(LineSegment {
-                                    points: [
-                                        rect.center() + vec2(a.x, -a.y) * rect.size() * 0.5,
-                                        rect.center() + vec2(b.x, -b.y) * rect.size() * 0.5,
-                                    ],
-                                    stroke: PathStroke::new(
-                                        2.0,
-                                        Color32::from_rgba_unmultiplied(150, 0, 0, 200) * alpha,
+                            let hovered = tab.ui_state.path.hovered_path;
+                            // the hovered path is drawn last so that it stays on top
+                            let order = paths
+                                .iter()
+                                .enumerate()
+                                .sorted_by_key(|&(i, _)| Some(i) == hovered);
+                            let mut persons = vec![];
+                            for (i, path) in order {
+                                let (stroke, color) = match hovered {
+                                    Some(h) if h == i => (
+                                        PathStroke::new(
+                                            4.0,
+                                            Color32::from_rgba_unmultiplied(230, 120, 0, 230)
+                                                * alpha,
+                                        ),
+                                        path_color,
                                     ),
-                                });
+                                    Some(_) => (PathStroke::new(2.0, faded_color), faded_color),
+                                    None => (PathStroke::new(2.0, path_color), path_color),
+                                };
+                                for (a, b) in path.iter().tuple_windows() {
+                                    let a = (cam * Vector4::from(data.persons[*a].position)).xy();
+                                    let b = (cam * Vector4::from(data.persons[*b].position)).xy();
+                                    clipped_painter.add(LineSegment {
+                                        points: [
+                                            rect.center() + vec2(a.x, -a.y) * rect.size() * 0.5,
+                                            rect.center() + vec2(b.x, -b.y) * rect.size() * 0.5,
+                                        ],
+                                        stroke: stroke.clone(),
+                                    });
+                                }
+                                persons.extend(path.iter().map(|&p| (p, color)));
                             }
-                            path
+                            persons
                         } else {
-                            &tab.ui_state
+                            tab.ui_state
                                 .path
                                 .path_settings
                                 .path_src
                                 .iter()
                                 .chain(tab.ui_state.path.path_settings.path_dest.iter())
-                                .copied()
+                                .map(|&p| (p, path_color))
                                 .collect_vec()
                         };
-                        for &p in path {
-                            draw_person(p, Color32::from_rgba_unmultiplied(150, 0, 0, 200) * alpha);
+                        for (p, color) in persons {
+                            draw_person(p, color);
                         }
 
                         if let Some(sel) = tab.ui_state.infos.infos_current {
                             draw_person(sel, Color32::from_rgba_unmultiplied(0, 100, 0, 200));
                         }
 
+                        if tab.ui_state.display.g_show_minimap {
+                            let generation = tab.rendered_graph.read().generation;
+                            tab.ui_state.minimap.show(
+                                ui,
+                                rect,
+                                &data.persons,
+                                generation,
+                                &mut tab.tab_camera,
+                                cid,
+                            );
+                        }
+
+                        if tab.ui_state.details.show_perf_overlay {
+                            const MARGIN: f32 = 8.0;
+                            egui::Area::new(cid.with("perf"))
+                                .fixed_pos(rect.min + vec2(MARGIN, MARGIN))
+                                .interactable(false)
+                                .show(ui.ctx(), |ui| {
+                                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                                        details::perf_grid(
+                                            ui,
+                                            &tab.ui_state.details.frame_stats,
+                                            &tab.rendered_graph.read(),
+                                        );
+                                    });
+                                });
+                        }
+
                         ui.style_mut().text_styles.insert(
                             TextStyle::Button,
                             egui::FontId::new(24.0, eframe::epaint::FontFamily::Proportional),
@@ -436,12 +1233,41 @@ impl egui_dock::TabViewer for TabViewer<'_, '_> {
                             .on_hover_text(t!("Center camera"))
                             .clicked()
                         {
-                            ui.ctx().animate_bool_with_time(cid, true, 0.0);
-                            let camera = &mut tab.tab_camera;
-                            camera.cam_animating = Some(CamAnimating::PanTo {
-                                from: camera.camera.transf,
-                                to: camera.camera_default.transf,
-                            });
+                            reset_camera(ui, cid, &mut tab.tab_camera);
+                        }
+                        if ui
+                            .put(
+                                Rect::from_min_size(
+                                    rect.max
+                                        - vec2(
+                                            BUTTON_SIZE + PADDING,
+                                            2.0 * (BUTTON_SIZE + PADDING),
+                                        ),
+                                    vec2(BUTTON_SIZE, BUTTON_SIZE),
+                                ),
+                                egui::Button::new("?"),
+                            )
+                            .on_hover_text(t!("Keyboard shortcuts"))
+                            .clicked()
+                        {
+                            tab.ui_state.shortcuts.open_help();
+                        }
+                        if ui
+                            .put(
+                                Rect::from_min_size(
+                                    rect.max
+                                        - vec2(
+                                            BUTTON_SIZE + PADDING,
+                                            3.0 * (BUTTON_SIZE + PADDING),
+                                        ),
+                                    vec2(BUTTON_SIZE, BUTTON_SIZE),
+                                ),
+                                egui::Button::new("📏").selected(tab.ui_state.ruler.active),
+                            )
+                            .on_hover_text(t!("Measure the distance between two points"))
+                            .clicked()
+                        {
+                            tab.ui_state.ruler.toggle();
                         }
                     });
             }
@@ -5,9 +5,12 @@ use eframe::epaint::{Shape, Stroke};
 use egui::style::WidgetVisuals;
 use std::ops::Add;
 
-use egui::{Align, Id, Layout, Painter, PopupCloseBehavior, Response, ScrollArea, SelectableLabel, Sense, Spinner, TextEdit, TextStyle, Ui, UiBuilder, WidgetText};
+use egui::{Align, Id, Key, Layout, Modifiers, Painter, PopupCloseBehavior, Response, ScrollArea, SelectableLabel, Sense, Spinner, TextEdit, TextStyle, Ui, UiBuilder, WidgetText};
 
+use crate::search::{SearchMatch, SearchOptions, DEBOUNCE};
 use crate::threading::MyRwLock;
+use crate::ui::class::ClassSection;
+use ahash::AHashSet;
 use derivative::Derivative;
 use eframe::epaint::text::TextWrapMode;
 use egui::text::{CCursor, CCursorRange};
@@ -80,23 +83,36 @@ fn button_frame(
 
 pub const COMBO_WIDTH: f32 = 300.0;
 
-const RESULTS: usize = 100;
+/// Default number of matches shown in the dropdown.
+pub const DEFAULT_RESULTS: usize = 20;
 
-/// Drop-down combobox with filtering
+/// Drop-down combobox with filtering by name or ID, showing up to `max_results` ranked matches
+///
+/// With `main_pick`, subgraphs also list the matches of the main graph that they don't contain.
+/// Picking one of those sets `main_pick` to its index in the main graph, and leaves the current
+/// item as it is.
 pub fn combo_with_filter(
     ui: &mut Ui,
     label: &str,
     current_item: &mut Option<usize>,
     viewer_data: &Arc<MyRwLock<ViewerData>>,
+    max_results: usize,
+    main_pick: Option<&mut Option<usize>>,
 ) -> Response {
     #[derive(Derivative, Clone)]
     #[derivative(Default)]
     struct ComboFilterState {
-        #[derivative(Default(value = "(0..RESULTS).collect()"))]
-        item_vector: Vec<usize>,
+        #[derivative(Default(value = "(0..DEFAULT_RESULTS).map(SearchMatch::name).collect()"))]
+        item_vector: Vec<SearchMatch>,
         loading: bool,
         pattern: String,
         first_open: bool,
+        /// Time of the last edit of the pattern that hasn't been searched for yet.
+        last_edit: Option<f64>,
+        /// Result selected with the arrow keys.
+        highlighted: Option<usize>,
+        /// Matches in the main graph that aren't in the subgraph, by index in the main graph.
+        main_items: Vec<SearchMatch>,
     }
 
     type StateType = Arc<MyRwLock<ComboFilterState>>;
@@ -192,24 +208,40 @@ pub fn combo_with_filter(
     }
 
     let mut sel_changed = false;
+    let mut picked_main = None;
     let inner = egui::popup::popup_below_widget(ui, popup_id, &button_response, PopupCloseBehavior::CloseOnClick, |ui| {
         ui.vertical(|ui| {
             let binding =
                 ui.memory_mut(|m| m.data.get_persisted_mut_or_default::<StateType>(id).clone());
 
+            // arrow keys have to be taken before the text edit sees them, otherwise they move the
+            // cursor
+            let txt_id = id.with("pattern");
+            let (down, up) = if ui.memory(|m| m.has_focus(txt_id)) {
+                ui.input_mut(|is| {
+                    (
+                        is.consume_key(Modifiers::NONE, Key::ArrowDown),
+                        is.consume_key(Modifiers::NONE, Key::ArrowUp),
+                    )
+                })
+            } else {
+                (false, false)
+            };
+
             let layout = Layout::centered_and_justified(ui.layout().main_dir());
             let txt_box_resp = ui
                 .allocate_ui_with_layout(
                     ui.available_size() * vec2(1.0, 0.0),
                     layout,
                     |ui| {
-                        let r = TextEdit::singleline(&mut binding.write().pattern).show(ui);
+                        let r = TextEdit::singleline(&mut binding.write().pattern).id(txt_id).show(ui);
                         ui.add_space(2.0);
                         r
                     },
                 );
             let mut txt_resp = txt_box_resp.inner;
             let txt = &txt_resp.response;
+            let enter = txt.lost_focus() && ui.input(|is| is.key_pressed(Key::Enter));
 
             let mut state = binding.write();
             if !state.first_open {
@@ -221,23 +253,52 @@ pub fn combo_with_filter(
                 )));
                 txt_resp.state.store(ui.ctx(), txt_resp.response.id);
             }
-            let changed = txt.changed();
 
-            if changed {
-                if state.pattern.is_empty() {
+            let now = ui.input(|is| is.time);
+            if txt.changed() {
+                state.last_edit = Some(now);
+            }
+
+            if let Some(last_edit) = state.last_edit {
+                if now - last_edit < DEBOUNCE {
+                    ui.ctx().request_repaint_after_secs((DEBOUNCE - (now - last_edit)) as f32);
+                } else if state.pattern.is_empty() {
+                    state.last_edit = None;
                     state.loading = false;
+                    state.highlighted = None;
                     state.item_vector = ComboFilterState::default().item_vector;
+                    state.main_items.clear();
                 } else {
+                    state.last_edit = None;
                     state.loading = true;
                     let pattern = state.pattern.clone();
                     let engine = viewer_data.read().engine.clone();
+                    let fallback = if main_pick.is_some() {
+                        let data = viewer_data.read();
+                        data.base_engine.clone().zip(data.base_ids.clone())
+                    } else {
+                        None
+                    };
                     let state = binding.clone();
+                    let options = SearchOptions::get(ui.ctx());
                     let ctx = ContextUpdater::new(ui.ctx());
                     thread::spawn(move || {
-                        let res = engine.get_blocking(|s| s.search(&pattern, RESULTS));
+                        let res = engine.search_n(&pattern, max_results, options);
+                        let main_res = fallback
+                            .map(|(engine, base_ids)| {
+                                let present: AHashSet<usize> = base_ids.into_iter().collect();
+                                engine
+                                    .search_n(&pattern, max_results, options)
+                                    .into_iter()
+                                    .filter(|m| !present.contains(&m.node))
+                                    .collect()
+                            })
+                            .unwrap_or_default();
                         let mut state = state.write();
                         if state.pattern.eq(&pattern) {
-                            state.item_vector = res.iter().map(|&i| i as usize).collect();
+                            state.item_vector = res;
+                            state.main_items = main_res;
+                            state.highlighted = None;
                             state.loading = false;
                             ctx.update();
                         }
@@ -245,40 +306,87 @@ pub fn combo_with_filter(
                 }
             }
 
-            let show_count = RESULTS.min(state.item_vector.len());
-
-            let loading = state.loading;
+            let data = viewer_data.read();
+            let show_count = state
+                .item_vector
+                .iter()
+                .take(max_results)
+                .take_while(|m| m.node < data.persons.len())
+                .count();
+
+            let loading = state.loading || state.last_edit.is_some();
+
+            if show_count > 0 && !loading {
+                if down {
+                    state.highlighted = Some(state.highlighted.map_or(0, |h| (h + 1).min(show_count - 1)));
+                } else if up {
+                    state.highlighted = Some(state.highlighted.map_or(0, |h| h.saturating_sub(1)));
+                }
+                if enter {
+                    *current_item = Some(state.item_vector[state.highlighted.unwrap_or(0)].node);
+                    sel_changed = true;
+                    ui.memory_mut(|m| m.close_popup());
+                }
+            }
 
             ScrollArea::vertical()
                 .max_height(ui.spacing().combo_height)
                 .auto_shrink([false, false])
                 .show(ui, |ui| {
-                    if show_count == 0 {
+                    if show_count == 0 && state.main_items.is_empty() {
                         ui.add_enabled(false, SelectableLabel::new(false, t!("No results found")));
                     } else {
-                        let data = viewer_data.read();
                         for i in 0..show_count {
-                            let idx = state.item_vector[i];
+                            let item = state.item_vector[i];
+                            let idx = item.node;
+                            let person = &data.persons[idx];
 
-                            if ui
+                            let resp = ui
                                 .allocate_ui_with_layout(
                                     ui.available_size() * vec2(1.0, 0.0),
-                                    Layout::centered_and_justified(ui.layout().main_dir())
-                                        .with_cross_align(Align::LEFT),
+                                    Layout::left_to_right(Align::Center),
                                     |ui| {
-                                        ui.add_enabled(!loading, SelectableLabel::new(
-                                            *current_item == Some(idx),
-                                            data.persons[idx].name,
-                                        ))
+                                        ClassSection::class_circle(
+                                            ui,
+                                            &data.modularity_classes[person.modularity_class as usize],
+                                        );
+                                        ui.with_layout(
+                                            Layout::centered_and_justified(ui.layout().main_dir())
+                                                .with_cross_align(Align::LEFT),
+                                            |ui| {
+                                                ui.add_enabled(!loading, SelectableLabel::new(
+                                                    *current_item == Some(idx) || state.highlighted == Some(i),
+                                                    item.label(person),
+                                                ))
+                                            },
+                                        )
+                                        .inner
                                     },
                                 )
-                                .inner
-                                .clicked()
-                            {
+                                .inner;
+                            if (up || down) && state.highlighted == Some(i) {
+                                resp.scroll_to_me(None);
+                            }
+                            if resp.clicked() {
                                 *current_item = Some(idx);
                                 sel_changed = true;
                             }
                         }
+                        if let Some(ref engine) = data.base_engine
+                            && !state.main_items.is_empty()
+                        {
+                            ui.separator();
+                            ui.weak(t!("Not in this subgraph:"));
+                            for item in &state.main_items {
+                                let person = &engine.persons[item.node];
+                                if ui
+                                    .add_enabled(!loading, SelectableLabel::new(false, item.label(person)))
+                                    .clicked()
+                                {
+                                    picked_main = Some(item.node);
+                                }
+                            }
+                        }
                     }
                 });
 
@@ -301,6 +409,11 @@ pub fn combo_with_filter(
     if sel_changed {
         button_response.mark_changed();
     }
+    if let Some(pick) = main_pick
+        && picked_main.is_some()
+    {
+        *pick = picked_main;
+    }
 
     button_response
 }
@@ -0,0 +1,175 @@
+use egui::{pos2, vec2, Rect, Response, Sense, Stroke, Ui};
+
+/// Height of the histogram, in points.
+const HEIGHT: f32 = 48.0;
+/// Radius of the knobs of the handles, in points.
+const KNOB_RADIUS: f32 = 5.0;
+
+/// Logarithmic degree axis. Degree `d` occupies `[d - 0.5, d + 0.5]`, so that the lowest degree
+/// gets a bar as wide as the others around it.
+#[derive(Clone, Copy)]
+struct LogScale {
+    max: f32,
+    ln_span: f32,
+}
+
+impl LogScale {
+    fn new(max: u16) -> Self {
+        let max = max.max(1) as f32;
+        Self {
+            max,
+            ln_span: ((max + 0.5) / 0.5).ln(),
+        }
+    }
+
+    /// Position of a (fractional) degree, between 0 and 1.
+    fn to_x(self, degree: f32) -> f32 {
+        (degree.max(0.5) / 0.5).ln() / self.ln_span
+    }
+
+    fn to_degree(self, x: f32) -> f32 {
+        0.5 * (x.clamp(0.0, 1.0) * self.ln_span).exp()
+    }
+
+    /// Degree whose bar contains `x`.
+    fn degree_at(self, x: f32) -> u16 {
+        self.to_degree(x).round().clamp(1.0, self.max) as u16
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    Min,
+    Max,
+}
+
+/// Double-ended slider over the degree distribution, shown as a log-scale histogram. `counts[d]`
+/// is the number of nodes of degree `d`, and `range` the inclusive range of degrees shown, whose
+/// upper bound may exceed the highest degree.
+///
+/// Both ends snap to integer degrees. Clicking moves the closest end.
+pub fn degree_range(ui: &mut Ui, range: &mut (u16, u16), counts: &[u32]) -> Response {
+    let max = counts.len().saturating_sub(1).clamp(1, u16::MAX as usize) as u16;
+    let scale = LogScale::new(max);
+    let (rect, mut response) = ui.allocate_exact_size(
+        vec2(ui.spacing().slider_width, HEIGHT),
+        Sense::click_and_drag(),
+    );
+    let to_screen = |degree: f32| rect.left() + scale.to_x(degree) * rect.width();
+    let to_unit = |x: f32| (x - rect.left()) / rect.width();
+
+    let handle_id = response.id.with("handle");
+    if let Some(pos) = response.interact_pointer_pos()
+        && (response.clicked() || response.dragged())
+    {
+        let (lo, hi) = (range.0.min(max), range.1.min(max));
+        let handle = if response.clicked() || response.drag_started() {
+            let to_lo = (pos.x - to_screen(lo as f32 - 0.5)).abs();
+            let to_hi = (pos.x - to_screen(hi as f32 + 0.5)).abs();
+            // when the handles overlap, the direction of the pointer decides
+            let handle = if to_lo < to_hi || (to_lo == to_hi && pos.x < to_screen(lo as f32)) {
+                Handle::Min
+            } else {
+                Handle::Max
+            };
+            ui.data_mut(|d| d.insert_temp(handle_id, handle));
+            handle
+        } else {
+            ui.data(|d| d.get_temp(handle_id)).unwrap_or(Handle::Max)
+        };
+        let degree = scale.degree_at(to_unit(pos.x));
+        let new = match handle {
+            Handle::Min => (degree.min(range.1), range.1),
+            Handle::Max => (range.0, degree.max(range.0)),
+        };
+        if new != *range {
+            *range = new;
+            response.mark_changed();
+        }
+    }
+
+    if let Some(pos) = response.hover_pos()
+        && !response.dragged()
+    {
+        let degree = scale.degree_at(to_unit(pos.x));
+        let count = counts.get(degree as usize).copied().unwrap_or(0);
+        response = response.on_hover_text_at_pointer(t!(
+            "Degree %{degree}: %{count} nodes",
+            degree = degree,
+            count = count
+        ));
+    }
+
+    if ui.is_rect_visible(rect) {
+        let shown = (range.0, range.1.min(max));
+        paint(ui, rect, scale, shown, counts, &response);
+    }
+
+    response
+}
+
+fn paint(
+    ui: &Ui,
+    rect: Rect,
+    scale: LogScale,
+    (lo, hi): (u16, u16),
+    counts: &[u32],
+    response: &Response,
+) {
+    let visuals = ui.style().interact(response);
+    // the knobs stick out of the histogram at the ends
+    let painter = ui.painter_at(rect.expand(KNOB_RADIUS));
+    painter.rect_filled(rect, visuals.rounding, ui.visuals().extreme_bg_color);
+
+    // one bar per column of pixels, summing the degrees it overlaps, so that the high degrees
+    // which share a column still show up
+    let columns = rect.width().max(1.0) as usize;
+    let bars: Vec<_> = (0..columns)
+        .map(|c| {
+            let start = scale.to_degree(c as f32 / columns as f32);
+            let end = scale.to_degree((c + 1) as f32 / columns as f32);
+            let degrees = (start - 0.5).floor() as usize + 1..(end + 0.5).ceil() as usize;
+            let count: u64 = degrees
+                .filter_map(|d| counts.get(d))
+                .map(|&n| n as u64)
+                .sum();
+            let center = scale.degree_at((c as f32 + 0.5) / columns as f32);
+            (count, (lo..=hi).contains(&center))
+        })
+        .collect();
+    // counts span several orders of magnitude, so they are shown on a log scale
+    let peak = bars.iter().map(|&(n, _)| n).max().unwrap_or(0) as f32;
+    let peak = peak.ln_1p().max(f32::EPSILON);
+    let column_width = rect.width() / columns as f32;
+    for (c, &(count, selected)) in bars.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let height = (count as f32).ln_1p() / peak * rect.height();
+        let left = rect.left() + c as f32 * column_width;
+        let color = if selected {
+            ui.visuals().selection.bg_fill
+        } else {
+            ui.visuals().widgets.inactive.bg_fill
+        };
+        painter.rect_filled(
+            Rect::from_min_max(
+                pos2(left, rect.bottom() - height),
+                pos2(left + column_width, rect.bottom()),
+            ),
+            0.0,
+            color,
+        );
+    }
+
+    for x in [lo as f32 - 0.5, hi as f32 + 0.5] {
+        let x = rect.left() + scale.to_x(x) * rect.width();
+        painter.vline(x, rect.y_range(), Stroke::new(2.0, visuals.fg_stroke.color));
+        painter.circle(
+            pos2(x, rect.center().y),
+            KNOB_RADIUS,
+            visuals.bg_fill,
+            visuals.fg_stroke,
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use crate::app::Person;
+use crate::ui::tabs::{CamAnimating, TabCamera};
+use eframe::epaint::{PathStroke, Shape};
+use egui::{pos2, vec2, Color32, ColorImage, Id, Rect, Sense, TextureHandle, TextureOptions, Ui};
+use graph_format::nalgebra::{Vector2, Vector4};
+use graph_format::Point;
+
+/// Size of the longest side of the minimap, in points.
+const MINIMAP_SIZE: f32 = 160.0;
+const MARGIN: f32 = 8.0;
+
+/// Overview of the whole graph, with the area currently visible in the main view.
+///
+/// The nodes are rasterized into a texture once, and again only when the node positions change.
+#[derive(Default)]
+pub struct Minimap {
+    texture: Option<TextureHandle>,
+    /// Generation of the vertex buffer the texture was rasterized from.
+    generation: Option<usize>,
+    /// World position of the top-left corner of the texture.
+    origin: Vector2<f32>,
+    /// Minimap points per world unit.
+    scale: f32,
+}
+
+impl Minimap {
+    fn rasterize(&mut self, ui: &Ui, persons: &[Person]) {
+        let (mut min, mut max) = (
+            Point::new(f32::INFINITY, f32::INFINITY),
+            Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        );
+        for p in persons {
+            min = Point::new(min.x.min(p.position.x), min.y.min(p.position.y));
+            max = Point::new(max.x.max(p.position.x), max.y.max(p.position.y));
+        }
+        let extent = (max.x - min.x).max(max.y - min.y);
+        if !extent.is_finite() || extent <= 0.0 {
+            self.texture = None;
+            return;
+        }
+        self.scale = (MINIMAP_SIZE - 1.0) / extent;
+        self.origin = Vector2::new(min.x, max.y);
+
+        let w = ((max.x - min.x) * self.scale) as usize + 1;
+        let h = ((max.y - min.y) * self.scale) as usize + 1;
+        let mut counts = vec![0u32; w * h];
+        for p in persons {
+            let x = (((p.position.x - min.x) * self.scale) as usize).min(w - 1);
+            let y = (((max.y - p.position.y) * self.scale) as usize).min(h - 1);
+            counts[y * w + x] += 1;
+        }
+
+        // densities span several orders of magnitude, so they are shown on a log scale
+        let peak = (*counts.iter().max().unwrap() as f32).ln_1p();
+        let pixels = counts
+            .iter()
+            .map(|&c| {
+                if c == 0 {
+                    Color32::TRANSPARENT
+                } else {
+                    let v = (c as f32).ln_1p() / peak;
+                    Color32::from_white_alpha((80.0 + 175.0 * v) as u8)
+                }
+            })
+            .collect();
+        self.texture = Some(ui.ctx().load_texture(
+            "minimap",
+            ColorImage {
+                size: [w, h],
+                pixels,
+            },
+            TextureOptions::LINEAR,
+        ));
+    }
+
+    /// Draws the minimap in the bottom-left corner of `view`. Clicking or dragging on it moves the
+    /// camera there.
+    pub(crate) fn show(
+        &mut self,
+        ui: &mut Ui,
+        view: Rect,
+        persons: &[Person],
+        generation: usize,
+        tab_camera: &mut TabCamera,
+        cid: Id,
+    ) {
+        if self.generation != Some(generation) {
+            self.generation = Some(generation);
+            self.rasterize(ui, persons);
+        }
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        let size = texture.size_vec2();
+        let rect = Rect::from_min_size(
+            pos2(view.min.x + MARGIN, view.max.y - MARGIN - size.y),
+            size,
+        );
+        let response = ui.interact(rect, cid.with("minimap"), Sense::click_and_drag());
+
+        let painter = ui.painter().with_clip_rect(view);
+        painter.rect_filled(rect.expand(3.0), 4.0, Color32::from_black_alpha(160));
+        painter.image(
+            texture.id(),
+            rect,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+
+        let to_minimap =
+            |x: f32, y: f32| rect.min + vec2(x - self.origin.x, self.origin.y - y) * self.scale;
+        let inverse = tab_camera.camera.get_inverse_matrix();
+        let viewport = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .map(|(x, y)| {
+                let corner = inverse * Vector4::new(x, y, 0.0, 1.0);
+                to_minimap(corner.x, corner.y)
+            })
+            .collect();
+        painter.with_clip_rect(rect).add(Shape::closed_line(
+            viewport,
+            PathStroke::new(1.5, Color32::from_rgb(255, 200, 0)),
+        ));
+
+        if let Some(pos) = response.interact_pointer_pos()
+            && (response.clicked() || response.dragged())
+        {
+            let target = Point::new(
+                self.origin.x + (pos.x - rect.min.x) / self.scale,
+                self.origin.y - (pos.y - rect.min.y) / self.scale,
+            );
+            let to = tab_camera.camera.centered_on(target);
+            if response.dragged() {
+                tab_camera.camera.transf = to;
+                tab_camera.cam_animating = None;
+            } else {
+                ui.ctx().animate_bool_with_time(cid, true, 0.0);
+                tab_camera.cam_animating = Some(CamAnimating::PanTo {
+                    from: tab_camera.camera.transf,
+                    to,
+                });
+            }
+        }
+    }
+}
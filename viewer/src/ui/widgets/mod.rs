@@ -1 +1,4 @@
-pub mod combo_filter;
\ No newline at end of file
+pub mod combo_filter;
+pub mod degree_range;
+pub mod minimap;
+pub mod ruler;
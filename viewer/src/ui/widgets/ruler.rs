@@ -0,0 +1,72 @@
+use eframe::epaint::CircleShape;
+use egui::{vec2, Color32, Painter, Pos2, Rect, Stroke, TextStyle};
+use graph_format::nalgebra::{Matrix4, Vector2, Vector4};
+
+const RULER_COLOR: Color32 = Color32::from_rgb(230, 120, 0);
+
+/// Tool measuring the distance between two points of the graph. While it's active, clicks on the
+/// graph place the ends of the ruler instead of selecting nodes, and a third click clears it.
+#[derive(Default)]
+pub struct Ruler {
+    pub active: bool,
+    /// Ends placed so far, in world coordinates.
+    ends: Vec<Vector2<f32>>,
+}
+
+impl Ruler {
+    pub fn toggle(&mut self) {
+        self.active ^= true;
+        self.ends.clear();
+    }
+
+    /// Places an end of the ruler at `pos`, or clears it once both ends are placed.
+    pub fn click(&mut self, pos: Vector2<f32>) {
+        if self.ends.len() == 2 {
+            self.ends.clear();
+        } else {
+            self.ends.push(pos);
+        }
+    }
+
+    /// Draws the ruler with the distance at its middle. Until the second end is placed, the ruler
+    /// goes to the mouse at `hover`, in world coordinates.
+    pub fn show(
+        &self,
+        painter: &Painter,
+        rect: Rect,
+        cam: Matrix4<f32>,
+        hover: Option<Vector2<f32>>,
+    ) {
+        if !self.active {
+            return;
+        }
+        let to_screen = |p: Vector2<f32>| -> Pos2 {
+            let pos = (cam * Vector4::new(p.x, p.y, 0.0, 1.0)).xy();
+            rect.center() + vec2(pos.x, -pos.y) * rect.size() * 0.5
+        };
+        let (start, end) = match self.ends[..] {
+            [] => return,
+            [start] => match hover {
+                Some(end) => (start, end),
+                None => (start, start),
+            },
+            [start, end, ..] => (start, end),
+        };
+        let (a, b) = (to_screen(start), to_screen(end));
+        for p in [a, b] {
+            painter.add(CircleShape::filled(p, 4.0, RULER_COLOR));
+        }
+        if a == b {
+            return;
+        }
+        painter.line_segment([a, b], Stroke::new(2.0, RULER_COLOR));
+        let galley = painter.layout_no_wrap(
+            format!("{:.3}", (end - start).norm()),
+            TextStyle::Body.resolve(&painter.ctx().style()),
+            Color32::WHITE,
+        );
+        let label = Rect::from_center_size(a + (b - a) * 0.5, galley.size());
+        painter.rect_filled(label.expand(3.0), 3.0, RULER_COLOR);
+        painter.galley(label.min, galley, Color32::WHITE);
+    }
+}
@@ -36,3 +36,43 @@ impl<T> SliceExt for [T] {
         }
     }
 }
+
+/// Separators of the thousands and of the decimals in numbers written for the given locale.
+fn number_separators(locale: &str) -> (&'static str, &'static str) {
+    match locale {
+        // narrow no-break space, so that numbers aren't split across lines
+        "fr" => ("\u{202F}", ","),
+        _ => (",", "."),
+    }
+}
+
+/// Writes a count with the thousands separated, as is usual in the given locale.
+pub fn format_count_in(count: usize, locale: &str) -> String {
+    let (thousands, _) = number_separators(locale);
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() * 2);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push_str(thousands);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Writes a number with the given number of decimals, and the decimal separator of the given
+/// locale.
+pub fn format_decimal_in(value: f64, decimals: usize, locale: &str) -> String {
+    let (_, point) = number_separators(locale);
+    format!("{:.*}", decimals, value).replace('.', point)
+}
+
+/// [`format_count_in`] for the current locale.
+pub fn format_count(count: usize) -> String {
+    format_count_in(count, &rust_i18n::locale())
+}
+
+/// [`format_decimal_in`] for the current locale.
+pub fn format_decimal(value: f64, decimals: usize) -> String {
+    format_decimal_in(value, decimals, &rust_i18n::locale())
+}
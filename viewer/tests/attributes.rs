@@ -0,0 +1,52 @@
+use graph_format::{Color3b, GraphFile, NodeStore, Point, Writable};
+use viewer::attributes::parse_csv;
+use viewer::graph_storage::load_binary;
+use viewer::threading::NullStatusWriter;
+
+fn fixture_buffer() -> Vec<u8> {
+    let node = |i: u32| NodeStore {
+        position: Point::new(i as f32, 0.0),
+        size: 1.0,
+        class: 0,
+        offset_id: 4 * i,
+        offset_name: 2 * i,
+        total_edge_count: 0,
+        edge_count: 0,
+        edges: vec![],
+    };
+    let mut graph = GraphFile {
+        nodes: vec![node(0), node(1), node(2)],
+        ids: b"100\0200\0300\0".to_vec(),
+        names: b"A\0B\0C\0".to_vec(),
+        classes: vec![Color3b::new(255, 0, 0)],
+        ..Default::default()
+    };
+    graph.finish_header();
+    graph.write_to_vec().unwrap()
+}
+
+#[test]
+fn numeric_columns_are_attached() {
+    let data = load_binary(&NullStatusWriter, fixture_buffer().into()).unwrap();
+    let persons = &data.viewer.persons;
+
+    // the id column isn't the first one, and the label column isn't numeric
+    let csv = "score;id;label\n1.5;200;x\n;100;y\n3;999;z\n";
+    let attached = parse_csv(csv, persons).unwrap();
+    assert_eq!((attached.matched, attached.unmatched), (2, 1));
+    assert_eq!(attached.columns.len(), 1);
+    let score = &attached.columns[0];
+    assert_eq!(score.name, "score");
+    assert_eq!(score.values[1], 1.5);
+    assert!(score.values[0].is_nan() && score.values[2].is_nan());
+
+    // quoted fields keep their separators, which don't count when guessing the separator
+    let csv = "\"id\",\"rank; level\"\n\"300\",\"2\"\n\"a, b\",4\n";
+    let attached = parse_csv(csv, persons).unwrap();
+    assert_eq!((attached.matched, attached.unmatched), (1, 1));
+    assert_eq!(attached.columns[0].name, "rank; level");
+    assert_eq!(attached.columns[0].values[2], 2.0);
+
+    assert!(parse_csv("id,label\n100,x\n", persons).is_err());
+    assert!(parse_csv("\n\n", persons).is_err());
+}
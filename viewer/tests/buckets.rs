@@ -0,0 +1,71 @@
+use graph_format::Point;
+use viewer::buckets::EdgeBuckets;
+
+fn edge(a: (f32, f32), b: (f32, f32)) -> (Point, Point) {
+    (
+        Point::new(a.0.min(b.0), a.1.min(b.1)),
+        Point::new(a.0.max(b.0), a.1.max(b.1)),
+    )
+}
+
+/// Short edges in the four corners of a 100×100 square, and a long one across it.
+fn corners() -> Vec<(Point, Point)> {
+    vec![
+        edge((0.0, 0.0), (1.0, 1.0)),
+        edge((99.0, 0.0), (100.0, 1.0)),
+        edge((0.0, 0.0), (100.0, 100.0)),
+        edge((0.0, 99.0), (1.0, 100.0)),
+        edge((99.0, 99.0), (100.0, 100.0)),
+        edge((1.0, 1.0), (2.0, 0.0)),
+    ]
+}
+
+/// Edges drawn in the given ranges, in the order of the original edges.
+fn drawn(order: &[usize], ranges: &[std::ops::Range<usize>]) -> Vec<usize> {
+    let mut edges: Vec<usize> = ranges
+        .iter()
+        .flat_map(|r| order[r.clone()].iter().copied())
+        .collect();
+    edges.sort();
+    edges
+}
+
+#[test]
+fn whole_view_is_a_single_range() {
+    let (order, buckets) = EdgeBuckets::new(&corners());
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..6).collect::<Vec<_>>());
+    let ranges = buckets.visible(Point::new(-10.0, -10.0), Point::new(110.0, 110.0), 1.0, 6);
+    assert_eq!(ranges, vec![0..6]);
+}
+
+#[test]
+fn edges_out_of_view_are_culled() {
+    let (order, buckets) = EdgeBuckets::new(&corners());
+    // long edges come first, so that they're drawn below the others
+    assert_eq!(order[0], 2);
+    let ranges = buckets.visible(Point::new(-1.0, -1.0), Point::new(5.0, 5.0), 1.0, 6);
+    assert_eq!(drawn(&order, &ranges), vec![0, 2, 5]);
+    let ranges = buckets.visible(Point::new(95.0, 95.0), Point::new(105.0, 105.0), 1.0, 6);
+    assert_eq!(drawn(&order, &ranges), vec![2, 4]);
+}
+
+#[test]
+fn buckets_keep_the_order_of_their_edges() {
+    let (order, _) = EdgeBuckets::new(&corners());
+    let first = order.iter().position(|&e| e == 0).unwrap();
+    let last = order.iter().position(|&e| e == 5).unwrap();
+    assert_eq!(last, first + 1);
+}
+
+#[test]
+fn only_loaded_edges_and_the_fraction_are_drawn() {
+    let bounds = vec![edge((0.0, 0.0), (0.1, 0.1)); 10];
+    let (order, buckets) = EdgeBuckets::new(&bounds);
+    assert_eq!(order, (0..10).collect::<Vec<_>>());
+    let view = (Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+    assert_eq!(buckets.visible(view.0, view.1, 0.25, 10), vec![0..3]);
+    assert_eq!(buckets.visible(view.0, view.1, 1.0, 4), vec![0..4]);
+    assert!(buckets.visible(view.0, view.1, 1.0, 0).is_empty());
+}
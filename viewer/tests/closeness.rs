@@ -0,0 +1,22 @@
+mod common;
+
+use common::{grid, Node};
+use viewer::algorithms::closeness::distance_distribution;
+use viewer::threading::CancelFlag;
+
+#[test]
+fn distance_distribution_from_corner() {
+    let cancel = CancelFlag::default();
+    // the far corner of a 3×3 grid is 4 steps away, and the extra node is isolated
+    let mut data = grid(3);
+    data.push(Node { neighbors: vec![] });
+    let dist = distance_distribution(&data, 0, &cancel).unwrap();
+    assert_eq!(dist.counts, vec![2, 3, 2, 1]);
+    assert_eq!(dist.unreachable, 1);
+    assert!((dist.closeness() - 8.0 / 18.0).abs() < 1e-6);
+
+    let isolated = distance_distribution(&data, 9, &cancel).unwrap();
+    assert!(isolated.counts.is_empty());
+    assert_eq!(isolated.unreachable, 9);
+    assert_eq!(isolated.closeness(), 0.0);
+}
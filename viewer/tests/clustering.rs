@@ -0,0 +1,63 @@
+mod common;
+
+use common::{grid, Node};
+use viewer::algorithms::clustering::{count_triangles, local_clustering};
+use viewer::threading::{CancelFlag, NullStatusWriter};
+
+#[test]
+fn clustering_coefficient() {
+    // 0 is linked to 1, 2 and 3, and only 1 and 2 are linked together
+    let data = vec![
+        Node {
+            neighbors: vec![1, 2, 3],
+        },
+        Node {
+            neighbors: vec![0, 2],
+        },
+        Node {
+            neighbors: vec![0, 1],
+        },
+        Node { neighbors: vec![0] },
+    ];
+    assert_eq!(local_clustering(&data, 0), 1.0 / 3.0);
+    assert_eq!(local_clustering(&data, 1), 1.0);
+    assert_eq!(local_clustering(&data, 3), 0.0);
+
+    // no triangles in a grid
+    assert_eq!(local_clustering(&grid(3), 4), 0.0);
+}
+
+#[test]
+fn triangle_count() {
+    let cancel = CancelFlag::default();
+    // 0-1-2 is the only triangle, and closes 3 of the 5 pairs of links sharing a node
+    let data = vec![
+        Node {
+            neighbors: vec![1, 2, 3],
+        },
+        Node {
+            neighbors: vec![0, 2],
+        },
+        Node {
+            neighbors: vec![0, 1],
+        },
+        Node { neighbors: vec![0] },
+    ];
+    let triangles = count_triangles(&NullStatusWriter, &data, &cancel).unwrap();
+    assert_eq!(triangles.count, 1);
+    assert!((triangles.global_clustering - 0.6).abs() < 1e-9);
+
+    // every pair of neighbors is linked in a complete graph, listed in both directions
+    let complete: Vec<Node> = (0..5)
+        .map(|i| Node {
+            neighbors: (0..5).filter(|&j| j != i).chain([(i + 1) % 5]).collect(),
+        })
+        .collect();
+    let triangles = count_triangles(&NullStatusWriter, &complete, &cancel).unwrap();
+    assert_eq!(triangles.count, 10);
+    assert_eq!(triangles.global_clustering, 1.0);
+
+    let triangles = count_triangles(&NullStatusWriter, &grid(4), &cancel).unwrap();
+    assert_eq!(triangles.count, 0);
+    assert_eq!(triangles.global_clustering, 0.0);
+}
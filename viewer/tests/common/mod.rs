@@ -0,0 +1,43 @@
+//! Graphs shared by the tests of the algorithms.
+
+// each test crate only uses some of them
+#![allow(dead_code)]
+
+use viewer::algorithms::AbstractNode;
+
+pub struct Node {
+    pub neighbors: Vec<usize>,
+}
+
+impl AbstractNode for Node {
+    fn neighbors(&self) -> &Vec<usize> {
+        &self.neighbors
+    }
+
+    fn display(&self) -> &str {
+        ""
+    }
+}
+
+/// `size`×`size` grid, where going from one corner to the opposite one has many shortest paths.
+pub fn grid(size: usize) -> Vec<Node> {
+    (0..size * size)
+        .map(|i| {
+            let (x, y) = (i % size, i / size);
+            let mut neighbors = vec![];
+            if x > 0 {
+                neighbors.push(i - 1);
+            }
+            if x + 1 < size {
+                neighbors.push(i + 1);
+            }
+            if y > 0 {
+                neighbors.push(i - size);
+            }
+            if y + 1 < size {
+                neighbors.push(i + size);
+            }
+            Node { neighbors }
+        })
+        .collect()
+}
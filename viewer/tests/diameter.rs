@@ -0,0 +1,45 @@
+mod common;
+
+use common::{grid, Node};
+use viewer::algorithms::diameter::estimate_diameter;
+use viewer::algorithms::pathfinding::{shortest_path, PathOptions};
+use viewer::threading::{CancelFlag, CancelableError, NullStatusWriter};
+
+#[test]
+fn diameter_double_sweep() {
+    // from the middle of a 5×5 grid, the first sweep reaches a corner and the second one the
+    // opposite corner
+    let data = grid(5);
+    let cancel = CancelFlag::default();
+    let diameter = estimate_diameter(&NullStatusWriter, &data, 12, &cancel).unwrap();
+    assert_eq!(diameter.length, 8);
+    assert_eq!(diameter.start_eccentricity, 4);
+    let (a, b) = diameter.endpoints;
+    assert_eq!(a + b, 24);
+    let path = shortest_path(&data, a, b, &PathOptions::default()).unwrap();
+    assert_eq!(path.len(), 9);
+
+    // only the component of the start node is explored
+    let data = vec![
+        Node { neighbors: vec![1] },
+        Node { neighbors: vec![0] },
+        Node { neighbors: vec![] },
+    ];
+    let diameter = |start| {
+        estimate_diameter(&NullStatusWriter, &data, start, &cancel)
+            .unwrap()
+            .length
+    };
+    assert_eq!(diameter(2), 0);
+    assert_eq!(diameter(0), 1);
+}
+
+#[test]
+fn diameter_canceled() {
+    let cancel = CancelFlag::default();
+    cancel.cancel();
+    assert!(matches!(
+        estimate_diameter(&NullStatusWriter, &grid(5), 12, &cancel),
+        Err(CancelableError::Canceled)
+    ));
+}
@@ -0,0 +1,435 @@
+use graph_format::{
+    Color3b, GraphFile, LegacyGraphFile, LenType, NodeStore, Point, Writable, GRAPH_MAGIC,
+    GRAPH_VERSION,
+};
+use std::io::{BufRead, Read};
+use std::sync::Arc;
+use viewer::algorithms::louvain::apply_membership;
+use viewer::graph_storage::{
+    export_graph_file, export_json, load_binary, load_binary_pruned, load_edge_list, GraphSource,
+};
+use viewer::threading::{CancelableError, NullStatusWriter};
+
+const IDS: &[u8] = b"100\0200\0";
+const NAMES: &[u8] = b"Alice\0Bob\0";
+
+fn fixture_nodes() -> Vec<NodeStore> {
+    vec![
+        NodeStore {
+            position: Point::new(0.0, 0.0),
+            size: 1.0,
+            class: 0,
+            offset_id: 0,
+            offset_name: 0,
+            total_edge_count: 1,
+            edge_count: 0,
+            edges: vec![],
+        },
+        NodeStore {
+            position: Point::new(1.0, 1.0),
+            size: 1.0,
+            class: 0,
+            offset_id: 4,
+            offset_name: 6,
+            total_edge_count: 1,
+            edge_count: 1,
+            edges: vec![0],
+        },
+    ]
+}
+
+fn legacy_buffer() -> Vec<u8> {
+    LegacyGraphFile {
+        class_count: 1,
+        classes: vec![Color3b::new(255, 0, 0)],
+        node_count: 2,
+        nodes: fixture_nodes(),
+        ids_size: IDS.len() as LenType,
+        ids: IDS.to_vec(),
+        names_size: NAMES.len() as LenType,
+        names: NAMES.to_vec(),
+    }
+    .write_to_vec()
+    .unwrap()
+}
+
+fn current_buffer(directed: bool) -> Vec<u8> {
+    buffer_with_nodes(fixture_nodes(), directed)
+}
+
+fn buffer_with_nodes(nodes: Vec<NodeStore>, directed: bool) -> Vec<u8> {
+    GraphFile {
+        magic: GRAPH_MAGIC,
+        version: GRAPH_VERSION,
+        directed,
+        class_count: 1,
+        classes: vec![Color3b::new(255, 0, 0)],
+        node_count: nodes.len() as LenType,
+        nodes,
+        ids_size: IDS.len() as LenType,
+        ids: IDS.to_vec(),
+        names_size: NAMES.len() as LenType,
+        names: NAMES.to_vec(),
+    }
+    .write_to_vec()
+    .unwrap()
+}
+
+fn check_loaded(buf: Vec<u8>) {
+    let data = load_binary(&NullStatusWriter, buf.into()).unwrap();
+    let persons = &data.viewer.persons;
+    assert_eq!(persons.len(), 2);
+    assert_eq!(persons[0].name, "Alice");
+    assert_eq!(persons[1].id, "200");
+    assert_eq!(persons[0].neighbors, vec![1]);
+    assert_eq!(persons[1].neighbors, vec![0]);
+    assert_eq!(data.edges.len(), 1);
+}
+
+#[test]
+fn legacy_file_is_loaded() {
+    let buf = legacy_buffer();
+    assert_eq!(GraphFile::detect_version(&buf), 1);
+    check_loaded(buf);
+}
+
+#[test]
+fn current_file_is_loaded() {
+    let buf = current_buffer(false);
+    assert_eq!(GraphFile::detect_version(&buf), GRAPH_VERSION);
+    check_loaded(buf);
+}
+
+#[test]
+fn directed_file_is_loaded() {
+    let data = load_binary(&NullStatusWriter, current_buffer(true).into()).unwrap();
+    assert!(data.viewer.directed);
+    let persons = &data.viewer.persons;
+    // the edge stored in node 1's list goes from 0 to 1
+    assert_eq!(persons[0].out_neighbors(), &[1]);
+    assert!(persons[0].in_neighbors().is_empty());
+    assert!(persons[1].out_neighbors().is_empty());
+    assert_eq!(persons[1].in_neighbors(), &[0]);
+    assert_eq!((data.edges[0].a, data.edges[0].b), (0, 1));
+}
+
+#[test]
+fn non_finite_positions_are_replaced() {
+    let mut nodes = fixture_nodes();
+    for position in [
+        Point::new(f32::NAN, 0.0),
+        Point::new(f32::INFINITY, f32::NEG_INFINITY),
+    ] {
+        nodes.push(NodeStore {
+            position,
+            size: 1.0,
+            class: 0,
+            offset_id: 0,
+            offset_name: 0,
+            total_edge_count: 0,
+            edge_count: 0,
+            edges: vec![],
+        });
+    }
+    let buf = buffer_with_nodes(nodes, false);
+    let positions = |buf: &[u8]| {
+        load_binary(&NullStatusWriter, buf.to_vec().into())
+            .unwrap()
+            .viewer
+            .persons
+            .iter()
+            .map(|p| (p.position.x, p.position.y))
+            .collect::<Vec<_>>()
+    };
+    let loaded = positions(&buf);
+    assert!(loaded.iter().all(|&(x, y)| x.is_finite() && y.is_finite()));
+    // valid positions are left as they are
+    assert_eq!(&loaded[..2], &[(0.0, 0.0), (1.0, 1.0)]);
+    // the replaced nodes don't land on top of each other, nor of the others
+    assert_ne!(loaded[2], loaded[3]);
+    assert!(!loaded[..2].contains(&loaded[2]) && !loaded[..2].contains(&loaded[3]));
+    assert_eq!(positions(&buf), loaded);
+}
+
+#[test]
+fn newer_file_is_rejected() {
+    let mut buf = current_buffer(false);
+    buf[4..6].copy_from_slice(&(GRAPH_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        load_binary(&NullStatusWriter, buf.into()),
+        Err(CancelableError::Custom(_))
+    ));
+}
+
+#[test]
+fn damaged_file_is_rejected() {
+    let rejected = |buf: Vec<u8>| {
+        matches!(
+            load_binary(&NullStatusWriter, buf.into()),
+            Err(CancelableError::Custom(_))
+        )
+    };
+
+    // a class past the end of the class list
+    let mut nodes = fixture_nodes();
+    nodes[1].class = 5;
+    assert!(rejected(buffer_with_nodes(nodes, false)));
+
+    // cut short, in the nodes or in the string tables
+    let buf = current_buffer(false);
+    for len in [buf.len() / 2, buf.len() - 3] {
+        assert!(rejected(buf[..len].to_vec()));
+    }
+
+    // garbage after a valid header
+    let mut buf = buf[..6].to_vec();
+    buf.extend([0xAB; 64]);
+    assert!(rejected(buf));
+}
+
+/// Hands out the file a few bytes at a time, like the chunks received while downloading it.
+struct ChunkedReader {
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+const CHUNK_SIZE: usize = 3;
+
+impl Read for ChunkedReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        let avail = self.fill_buf()?;
+        let n = avail.len().min(out.len());
+        out[..n].copy_from_slice(&avail[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for ChunkedReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let end = (self.pos + CHUNK_SIZE).min(self.buf.len());
+        Ok(&self.buf[self.pos..end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+#[test]
+fn file_is_loaded_from_chunks() {
+    for buf in [legacy_buffer(), current_buffer(false)] {
+        let source = GraphSource {
+            size: buf.len(),
+            reader: Box::new(ChunkedReader { buf, pos: 0 }),
+        };
+        let data = load_binary(&NullStatusWriter, source).unwrap();
+        let persons = &data.viewer.persons;
+        assert_eq!(persons[0].name, "Alice");
+        assert_eq!(persons[1].name, "Bob");
+        assert_eq!(persons[1].neighbors, vec![0]);
+    }
+}
+
+#[test]
+fn file_is_streamed_from_disk() {
+    let buf = current_buffer(false);
+    let path = std::env::temp_dir().join(format!("graphrust_stream_{}.bin", std::process::id()));
+    let url = path.to_str().unwrap().to_string();
+    std::fs::write(&path, &buf).unwrap();
+    let source = GraphSource::download(url.clone(), buf.len());
+    let data = load_binary(&NullStatusWriter, source);
+    std::fs::remove_file(&path).unwrap();
+    let persons = &data.unwrap().viewer.persons;
+    assert_eq!(persons[0].name, "Alice");
+    assert_eq!(persons[1].neighbors, vec![0]);
+
+    // the error reaches the reader instead of looking like the end of the file
+    let source = GraphSource::download(url, buf.len());
+    assert!(load_binary(&NullStatusWriter, source).is_err());
+}
+
+#[test]
+fn edge_list_is_loaded() {
+    let list = b"source,target\n# comment\n10,20\n20 30\n30\t10\n10 20\n40 40\n";
+    let data = load_edge_list(&NullStatusWriter, list.to_vec().into()).unwrap();
+    let persons = &data.viewer.persons;
+    assert_eq!(persons.len(), 3);
+    assert_eq!(persons[0].name, "10");
+    assert_eq!(persons[2].id, "30");
+    // the duplicate edge and the self-loop are dropped
+    assert_eq!(data.edges.len(), 3);
+    assert!(persons.iter().all(|p| p.neighbors.len() == 2));
+    let classes = data.viewer.modularity_classes.len();
+    assert!(persons
+        .iter()
+        .all(|p| (p.modularity_class as usize) < classes));
+}
+
+#[test]
+fn invalid_edge_list_is_rejected() {
+    for list in [&b"1 2\nfoo bar\n"[..], b"# nothing\n"] {
+        assert!(load_edge_list(&NullStatusWriter, list.to_vec().into()).is_err());
+    }
+}
+
+#[test]
+fn truncated_file_is_rejected() {
+    let buf = current_buffer(false);
+    for len in [3, buf.len() / 2, buf.len() - 1] {
+        assert!(load_binary(&NullStatusWriter, buf[..len].to_vec().into()).is_err());
+    }
+}
+
+#[test]
+fn invalid_edges_are_handled() {
+    let file = |edges: Vec<u32>| {
+        let mut nodes = fixture_nodes();
+        nodes[1].edge_count = edges.len() as u16;
+        nodes[1].edges = edges;
+        GraphFile {
+            nodes,
+            ids: IDS.to_vec(),
+            names: NAMES.to_vec(),
+            classes: vec![Color3b::new(255, 0, 0)],
+            ..Default::default()
+        }
+    };
+
+    // self-loops are skipped
+    let mut graph = file(vec![0, 1]);
+    graph.finish_header();
+    assert_eq!(graph.validate().self_loops, 1);
+    check_loaded(graph.write_to_vec().unwrap());
+
+    // duplicates are collapsed, whether they're in the same list or not
+    let mut graph = file(vec![0, 0]);
+    graph.nodes[0].edges = vec![1];
+    graph.nodes[0].edge_count = 1;
+    graph.finish_header();
+    assert_eq!(graph.validate().duplicate_edges, 2);
+    check_loaded(graph.write_to_vec().unwrap());
+
+    // edges to nodes that don't exist abort the loading
+    let mut graph = file(vec![0, 2]);
+    graph.finish_header();
+    assert!(!graph.validate().is_valid());
+    assert!(matches!(
+        load_binary(&NullStatusWriter, graph.write_to_vec().unwrap().into()),
+        Err(CancelableError::Custom(_))
+    ));
+}
+
+#[test]
+fn nodes_below_min_degree_are_pruned() {
+    // 1 is only linked to 0, and the others form a triangle
+    let node = |i: u32, edges: Vec<u32>| NodeStore {
+        position: Point::new(i as f32, 0.0),
+        size: 1.0,
+        class: 0,
+        offset_id: 2 * i,
+        offset_name: 2 * i,
+        total_edge_count: 0,
+        edge_count: edges.len() as u16,
+        edges,
+    };
+    let mut graph = GraphFile {
+        nodes: vec![
+            node(0, vec![]),
+            node(1, vec![0]),
+            node(2, vec![0]),
+            node(3, vec![0, 2]),
+        ],
+        ids: b"0\01\02\03\0".to_vec(),
+        names: b"A\0B\0C\0D\0".to_vec(),
+        classes: vec![Color3b::new(255, 0, 0)],
+        ..Default::default()
+    };
+    graph.finish_header();
+    let buf = graph.write_to_vec().unwrap();
+
+    let data = load_binary_pruned(&NullStatusWriter, buf.clone().into(), 2).unwrap();
+    let persons = &data.viewer.persons;
+    assert_eq!(
+        persons.iter().map(|p| p.name).collect::<Vec<_>>(),
+        ["A", "C", "D"]
+    );
+    assert_eq!(persons[1].id, "2");
+    assert_eq!(persons[2].position.x, 3.0);
+    assert!(persons.iter().all(|p| p.neighbors.len() == 2));
+    assert_eq!(data.edges.len(), 3);
+    assert!(data.edges.iter().all(|e| e.a < e.b && e.b < 3));
+
+    let data = load_binary_pruned(&NullStatusWriter, buf.into(), 0).unwrap();
+    assert_eq!(data.viewer.persons.len(), 4);
+}
+
+#[test]
+fn nodes_are_exported_to_json() {
+    let data = load_binary(&NullStatusWriter, current_buffer(false).into()).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_slice(&export_json(&data.viewer, |_| true).unwrap()).unwrap();
+    let nodes = json["nodes"].as_array().unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0]["name"], "Alice");
+    assert_eq!(nodes[1]["id"], "200");
+    assert_eq!(nodes[1]["x"], 1.0);
+    assert_eq!(nodes[0]["degree"], 1);
+    let class = nodes[0]["class"].as_u64().unwrap() as usize;
+    assert_eq!(json["classes"][class]["id"], class);
+
+    let filtered = export_json(&data.viewer, |p| p.name == "Bob").unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&filtered).unwrap();
+    assert_eq!(json["nodes"].as_array().unwrap().len(), 1);
+    assert_eq!(json["nodes"][0]["name"], "Bob");
+}
+
+#[test]
+fn graph_file_is_exported() {
+    for directed in [false, true] {
+        let data = load_binary(&NullStatusWriter, current_buffer(directed).into()).unwrap();
+        let mut viewer = data.viewer;
+        Arc::make_mut(&mut viewer.persons)[1].position = Point::new(5.0, -2.0);
+
+        let buf = export_graph_file(&viewer).unwrap();
+        assert_eq!(GraphFile::detect_version(&buf), GRAPH_VERSION);
+        let reloaded = load_binary(&NullStatusWriter, buf.into()).unwrap();
+        assert_eq!(reloaded.viewer.directed, directed);
+        let persons = &reloaded.viewer.persons;
+        assert_eq!(persons[1].position.x, 5.0);
+        assert_eq!(persons[1].position.y, -2.0);
+        assert_eq!(persons[0].name, "Alice");
+        assert_eq!(persons[1].id, "200");
+        assert_eq!(persons[0].out_neighbors(), &[1]);
+        assert_eq!(reloaded.edges.len(), 1);
+        assert_eq!(
+            reloaded.viewer.modularity_classes.len(),
+            viewer.modularity_classes.len()
+        );
+    }
+}
+
+#[test]
+fn copies_share_nodes_until_changed() {
+    let data = load_binary(&NullStatusWriter, current_buffer(false).into()).unwrap();
+    let original = data.viewer;
+    let mut copy = original.clone();
+    assert!(Arc::ptr_eq(&original.persons, &copy.persons));
+
+    // community detection rewrites the classes of the copy, the way the algorithms section does
+    copy.modularity_classes = apply_membership(Arc::make_mut(&mut copy.persons), &[1, 0], 2);
+    assert!(!Arc::ptr_eq(&original.persons, &copy.persons));
+    let copy_classes: Vec<_> = copy.persons.iter().map(|p| p.modularity_class).collect();
+    assert_eq!(copy_classes, [1, 0]);
+    let kept_classes: Vec<_> = original
+        .persons
+        .iter()
+        .map(|p| p.modularity_class)
+        .collect();
+    assert_eq!(kept_classes, [0, 0]);
+    assert_eq!(original.modularity_classes.len(), 1);
+    assert_eq!(
+        original.persons.iter().map(|p| p.id).collect::<Vec<_>>(),
+        ["100", "200"]
+    );
+}
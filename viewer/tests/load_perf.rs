@@ -2,8 +2,10 @@ use env_logger;
 use itertools::Itertools;
 use rand::Rng;
 use std::env;
-use viewer::algorithms::pathfinding::{do_pathfinding, PathSectionSettings};
-use viewer::graph_storage::{load_binary, load_file};
+use std::time::Instant;
+use viewer::algorithms::pathfinding::{do_pathfinding, PathSectionSettings, Pathfinder};
+use viewer::graph_storage::{build_neighbor_lists, load_binary, load_file};
+use viewer::lod::edge_draw_order;
 use viewer::threading::NullStatusWriter;
 
 #[test]
@@ -24,6 +26,63 @@ fn init_logs() {
         .init();
 }
 
+#[test]
+fn parallel_neighbor_lists() {
+    let res = load_file(&NullStatusWriter).unwrap();
+    let bin = load_binary(&NullStatusWriter, res).unwrap();
+    let mut persons = bin.viewer.persons.as_ref().clone();
+
+    let mut build = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        pool.install(|| build_neighbor_lists(&mut persons, &bin.edges, bin.viewer.directed));
+        println!(
+            "Neighbor lists with {} threads: {}ms",
+            threads,
+            start.elapsed().as_millis()
+        );
+        persons
+            .iter()
+            .map(|p| (p.neighbors.clone(), p.out_degree))
+            .collect_vec()
+    };
+
+    let single = build(1);
+    let parallel = build(rayon::current_num_threads());
+    assert!(single == parallel);
+}
+
+#[test]
+fn edge_sort_time() {
+    let res = load_file(&NullStatusWriter).unwrap();
+    let bin = load_binary(&NullStatusWriter, res).unwrap();
+    let persons = &bin.viewer.persons;
+
+    let order = |sorted| {
+        let start = Instant::now();
+        let ends = edge_draw_order(persons, bin.edges.iter(), sorted)
+            .map(|(a, b)| (a.id, b.id))
+            .collect_vec();
+        println!(
+            "Edge order of {} edges, sorted: {}: {}ms",
+            ends.len(),
+            sorted,
+            start.elapsed().as_millis()
+        );
+        ends
+    };
+
+    let mut sorted = order(true);
+    let mut unsorted = order(false);
+    // the same edges are drawn either way
+    sorted.sort_unstable();
+    unsorted.sort_unstable();
+    assert!(sorted == unsorted);
+}
+
 #[test]
 fn it_works() {
     // print the current directory
@@ -37,6 +96,7 @@ fn it_works() {
 
     let viewer = &bin.viewer;
     let rng = &mut rand::thread_rng();
+    let mut pathfinder = Pathfinder::default();
     for _ in 0..1000 {
         let node1 = rng.gen_range(0..viewer.persons.len());
         let node2 = rng.gen_range(0..viewer.persons.len());
@@ -48,6 +108,9 @@ fn it_works() {
                 exclude_ids: vec![],
                 path_no_direct: false,
                 path_no_mutual: false,
+                path_directed: false,
+                max_paths: 1,
+                exclude_classes: vec![],
             },
             &viewer.persons,
         )
@@ -60,12 +123,36 @@ fn it_works() {
                 exclude_ids: vec![],
                 path_no_direct: false,
                 path_no_mutual: false,
+                path_directed: false,
+                max_paths: 1,
+                exclude_classes: vec![],
             },
             &viewer.persons,
         )
         .unwrap();
 
-        assert_eq!(path.path, path2.path);
+        assert_eq!(path.paths, path2.paths);
+
+        // reusing the buffers, and excluding a node of the path, must give the same result as a
+        // fresh search
+        let mut settings = PathSectionSettings {
+            path_src: Some(node1),
+            path_dest: Some(node2),
+            ..Default::default()
+        };
+        assert_eq!(
+            pathfinder.find(&settings, &viewer.persons).unwrap().paths,
+            path.paths
+        );
+        if path.paths[0].len() > 2 {
+            settings.exclude_ids.push(path.paths[0][1]);
+            assert_eq!(
+                pathfinder
+                    .find(&settings, &viewer.persons)
+                    .map(|res| res.paths),
+                do_pathfinding(settings.clone(), &viewer.persons).map(|res| res.paths)
+            );
+        }
     }
 
     /* let get = |name| {
@@ -93,6 +180,9 @@ fn it_works() {
             exclude_ids: vec![tom],
             path_no_direct: false,
             path_no_mutual: false,
+            path_directed: false,
+            max_paths: 1,
+            exclude_classes: vec![],
         },
         &viewer.persons,
     )
@@ -0,0 +1,124 @@
+use rust_i18n::MinifyKey;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use viewer::utils::{format_count_in, format_decimal_in};
+
+/// Settings of the keys, as given to `i18n!` in lib.rs.
+const KEY_LEN: usize = 12;
+const KEY_PREFIX: &str = "tr_";
+const KEY_THRESHOLD: usize = 8;
+
+const LOCALES: [&str; 2] = ["en", "fr"];
+
+/// Reads the string literal at the start of `code`, with its escapes resolved.
+fn string_literal(code: &str) -> Option<String> {
+    let mut chars = code.strip_prefix('"')?.chars();
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                '\n' => {
+                    // a line continuation skips the indentation of the next line
+                    let rest = chars.as_str().trim_start();
+                    chars = rest.chars();
+                }
+                c => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+/// Texts passed to `t!` in the Rust files under `dir`, along with the file they're in.
+fn translated_texts(dir: &Path, texts: &mut Vec<(String, String)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            translated_texts(&path, texts);
+            continue;
+        }
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        let code = fs::read_to_string(&path).unwrap();
+        for (i, _) in code.match_indices("t!(") {
+            // not the end of another macro, such as assert!(
+            if code[..i]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+            {
+                continue;
+            }
+            if let Some(text) = string_literal(code[i + 3..].trim_start()) {
+                texts.push((text, path.display().to_string()));
+            }
+        }
+    }
+}
+
+/// Locales each key of the locales file has a translation for.
+fn translations(file: &str) -> HashMap<String, Vec<String>> {
+    let mut keys: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in file.lines() {
+        if let Some(entry) = line.strip_prefix("  ") {
+            if let (Some(key), Some((locale, _))) = (&current, entry.split_once(':')) {
+                keys.get_mut(key).unwrap().push(locale.to_string());
+            }
+        } else if let Some(key) = line.strip_suffix(':') {
+            let key = match key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')) {
+                Some(quoted) => quoted.replace("''", "'"),
+                None => key.to_string(),
+            };
+            keys.insert(key.clone(), Vec::new());
+            current = Some(key);
+        }
+    }
+    keys
+}
+
+#[test]
+fn every_text_is_translated() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut texts = Vec::new();
+    translated_texts(&root.join("src"), &mut texts);
+    assert!(!texts.is_empty());
+    let keys = translations(&fs::read_to_string(root.join("locales/app.yml")).unwrap());
+
+    let mut missing = Vec::new();
+    for (text, file) in &texts {
+        let key = text.as_str().minify_key(KEY_LEN, KEY_PREFIX, KEY_THRESHOLD);
+        for locale in LOCALES {
+            if !keys
+                .get(key.as_ref())
+                .is_some_and(|locales| locales.iter().any(|l| l == locale))
+            {
+                missing.push(format!(
+                    "{file}: {text:?} ({key}) has no {locale} translation"
+                ));
+            }
+        }
+    }
+    assert!(missing.is_empty(), "{}", missing.join("\n"));
+}
+
+#[test]
+fn counts_are_grouped_by_thousands() {
+    assert_eq!(format_count_in(0, "en"), "0");
+    assert_eq!(format_count_in(999, "en"), "999");
+    assert_eq!(format_count_in(1000, "en"), "1,000");
+    assert_eq!(format_count_in(1234567, "en"), "1,234,567");
+    assert_eq!(format_count_in(1234567, "fr"), "1\u{202F}234\u{202F}567");
+}
+
+#[test]
+fn decimals_use_the_separator_of_the_locale() {
+    assert_eq!(format_decimal_in(0.12345, 4, "en"), "0.1235");
+    assert_eq!(format_decimal_in(0.12345, 4, "fr"), "0,1235");
+    assert_eq!(format_decimal_in(2.0, 0, "fr"), "2");
+}
@@ -0,0 +1,41 @@
+use viewer::lod::{EdgeLod, MIN_FRACTION};
+
+/// Draws frames `dt` seconds apart for `secs` seconds, starting at `start`. Returns the end time.
+fn frames(lod: &mut EdgeLod, start: f64, dt: f64, secs: f64, continuous: bool) -> f64 {
+    let mut time = start;
+    while time < start + secs {
+        lod.update(time, continuous);
+        time += dt;
+    }
+    time
+}
+
+#[test]
+fn slow_frames_lower_the_fraction() {
+    let mut lod = EdgeLod::default();
+    assert_eq!(lod.fraction(), 1.0);
+    let time = frames(&mut lod, 0.0, 0.05, 3.0, true);
+    assert!(lod.fraction() < 1.0);
+    let time = frames(&mut lod, time, 0.05, 100.0, true);
+    assert_eq!(lod.fraction(), MIN_FRACTION);
+    // and fast ones bring it back up
+    frames(&mut lod, time, 0.01, 100.0, true);
+    assert_eq!(lod.fraction(), 1.0);
+}
+
+#[test]
+fn sparse_frames_are_ignored() {
+    let mut lod = EdgeLod::default();
+    frames(&mut lod, 0.0, 0.05, 10.0, false);
+    assert_eq!(lod.fraction(), 1.0);
+}
+
+#[test]
+fn manual_fraction_wins() {
+    let mut lod = EdgeLod {
+        manual: Some(0.5),
+        ..Default::default()
+    };
+    frames(&mut lod, 0.0, 0.01, 3.0, true);
+    assert_eq!(lod.fraction(), 0.5);
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::Node;
+use viewer::algorithms::louvain::{merge_smallest, modularity};
+
+#[test]
+fn modularity_of_partitions() {
+    // two triangles, 0-1-2 and 3-4-5, linked by 2-3
+    let data = [
+        vec![1, 2],
+        vec![0, 2],
+        vec![0, 1, 3],
+        vec![2, 4, 5],
+        vec![3, 5],
+        vec![3, 4],
+    ]
+    .map(|neighbors| Node { neighbors });
+    assert!((modularity(&data, &[0, 0, 0, 1, 1, 1]) - 5.0 / 14.0).abs() < 1e-9);
+    // a single community is no better than random
+    assert!(modularity(&data, &[0; 6]).abs() < 1e-9);
+    // splitting the triangles is worse than random
+    assert!(modularity(&data, &[0, 1, 0, 1, 0, 1]) < 0.0);
+}
+
+#[test]
+fn smallest_communities_are_merged() {
+    // sizes 1, 3, 2 and 1
+    let mut membership = [0, 1, 1, 1, 2, 2, 3];
+    assert_eq!(merge_smallest(&mut membership, 4, 4), (4, None));
+    assert_eq!(merge_smallest(&mut membership, 4, 3), (3, Some(2)));
+    // the largest ones come first, the others share the last class
+    assert_eq!(membership, [2, 0, 0, 0, 1, 1, 2]);
+}
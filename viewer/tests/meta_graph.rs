@@ -0,0 +1,49 @@
+use graph_format::{Color3b, GraphFile, NodeStore, Point, Writable};
+use viewer::algorithms::meta_graph::collapse_classes;
+use viewer::graph_storage::load_binary;
+use viewer::threading::NullStatusWriter;
+
+#[test]
+fn classes_are_collapsed() {
+    let node = |i: u32, class: u16, edges: Vec<u32>| NodeStore {
+        position: Point::new(i as f32, 0.0),
+        size: 1.0,
+        class,
+        offset_id: 2 * i,
+        offset_name: 2 * i,
+        total_edge_count: 0,
+        edge_count: edges.len() as u16,
+        edges,
+    };
+    // class 2 has no members, and 1 is linked twice to 0 and once to 3
+    let mut graph = GraphFile {
+        nodes: vec![
+            node(0, 0, vec![]),
+            node(1, 0, vec![0]),
+            node(2, 1, vec![0, 1]),
+            node(3, 3, vec![2]),
+        ],
+        ids: b"0\01\02\03\0".to_vec(),
+        names: b"A\0B\0C\0D\0".to_vec(),
+        classes: vec![Color3b::new(255, 0, 0); 4],
+        ..Default::default()
+    };
+    graph.finish_header();
+    let data = load_binary(&NullStatusWriter, graph.write_to_vec().unwrap().into()).unwrap();
+    let viewer = &data.viewer;
+
+    let collapsed = collapse_classes(
+        &NullStatusWriter,
+        &viewer.persons,
+        viewer.directed,
+        viewer.modularity_classes.len(),
+    );
+    assert_eq!(collapsed.classes, [0, 1, 3]);
+    assert_eq!(collapsed.members, [2, 1, 1]);
+    let centroid = collapsed.centroids[0];
+    assert_eq!((centroid.x, centroid.y), (0.5, 0.0));
+    let mut links = collapsed.link_counts.into_iter().collect::<Vec<_>>();
+    links.sort();
+    // the link inside class 0 isn't counted
+    assert_eq!(links, [((0, 1), 2), ((1, 2), 1)]);
+}
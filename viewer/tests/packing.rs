@@ -0,0 +1,68 @@
+use viewer::packing::{
+    filter_degree, pack_class, pack_core_and_size, pack_degree_filter, pack_side, unpack_class,
+    unpack_core_and_size, unpack_degree_filter, unpack_side, NAME_HIDDEN,
+};
+
+#[test]
+fn degree_filter_round_trip() {
+    for range in [(0, 0), (1, u16::MAX), (12, 345), (u16::MAX, u16::MAX)] {
+        assert_eq!(unpack_degree_filter(pack_degree_filter(range)), range);
+    }
+    // the shader reads the upper bound from the high bits
+    assert_eq!(pack_degree_filter((1, 2)), 0x0002_0001);
+}
+
+#[test]
+fn filter_degree_saturates() {
+    assert_eq!(filter_degree(0), 0);
+    assert_eq!(filter_degree(65_535), u16::MAX);
+    assert_eq!(filter_degree(70_000), u16::MAX);
+}
+
+#[test]
+fn core_and_size_round_trip() {
+    for core in [0, 1, 1234, u16::MAX] {
+        for size in [0.0, 0.5, 1.0, 2.0, 1024.0] {
+            assert_eq!(
+                unpack_core_and_size(pack_core_and_size(core, size)),
+                (core, size)
+            );
+        }
+    }
+}
+
+#[test]
+fn size_keeps_its_high_bits() {
+    let (core, size) = unpack_core_and_size(pack_core_and_size(7, 1.2345));
+    assert_eq!(core, 7);
+    assert!((size - 1.2345).abs() < 0.01);
+    assert!(size <= 1.2345);
+}
+
+#[test]
+fn class_round_trip() {
+    for class in [0, 1, 1234, graph_format::MAX_CLASSES as u32] {
+        for name_match in [false, true] {
+            assert_eq!(
+                unpack_class(pack_class(class, name_match)),
+                (class, name_match)
+            );
+        }
+    }
+    // matching nodes keep their class as it is
+    assert_eq!(pack_class(5, true), 5);
+    assert_eq!(pack_class(5, false), 5 | NAME_HIDDEN);
+}
+
+#[test]
+fn side_round_trip() {
+    for class in [0, 1234, graph_format::MAX_CLASSES as u32] {
+        for name_match in [false, true] {
+            for side in [-1.0, 0.0, 1.0] {
+                let packed = pack_side(pack_class(class, name_match), side);
+                assert_eq!(unpack_side(packed), side);
+                assert_eq!(unpack_class(packed), (class, name_match));
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{grid, Node};
+use viewer::algorithms::pagerank::{pagerank, DEFAULT_DAMPING};
+use viewer::threading::{CancelFlag, CancelableError, NullStatusWriter};
+
+#[test]
+fn pagerank_of_star() {
+    // 0 is linked to 1, 2 and 3
+    let data = [vec![1, 2, 3], vec![0], vec![0], vec![0]].map(|neighbors| Node { neighbors });
+    let cancel = CancelFlag::default();
+    let scores = pagerank(&NullStatusWriter, &data, DEFAULT_DAMPING, &cancel).unwrap();
+    assert!((scores.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    // c = (1 - d) / 4 + 3 d l and l = (1 - d) / 4 + d c / 3
+    assert!((scores[0] - 0.4797).abs() < 1e-3);
+    assert!(scores[1..].iter().all(|&l| (l - 0.1734).abs() < 1e-3));
+
+    // an isolated node only gets random jumps, and gives its score back to all nodes
+    let data = [vec![1], vec![0], vec![]].map(|neighbors| Node { neighbors });
+    let scores = pagerank(&NullStatusWriter, &data, DEFAULT_DAMPING, &cancel).unwrap();
+    assert!((scores.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+    assert!((scores[2] - 0.15 / 2.15).abs() < 1e-4);
+    assert!((scores[0] - scores[1]).abs() < 1e-6);
+
+    cancel.cancel();
+    assert!(matches!(
+        pagerank(&NullStatusWriter, &grid(3), DEFAULT_DAMPING, &cancel),
+        Err(CancelableError::Canceled)
+    ));
+}
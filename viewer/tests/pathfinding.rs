@@ -0,0 +1,316 @@
+mod common;
+
+use common::{grid, Node};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashSet, VecDeque};
+use viewer::algorithms::pathfinding::{
+    do_pathfinding, shortest_path, PathNotFound, PathOptions, PathSectionSettings,
+};
+use viewer::algorithms::AbstractNode;
+
+/// Small-world graph: a ring where each node is linked to its `k` nearest neighbors, with a few
+/// random shortcuts.
+fn fixture_graph(rng: &mut StdRng, n: usize, k: usize, shortcuts: usize) -> Vec<Node> {
+    let mut edges = HashSet::new();
+    for i in 0..n {
+        for j in 1..=k / 2 {
+            let nb = (i + j) % n;
+            edges.insert((i.min(nb), i.max(nb)));
+        }
+    }
+    for _ in 0..shortcuts {
+        let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+        if a != b {
+            edges.insert((a.min(b), a.max(b)));
+        }
+    }
+    let mut nodes = (0..n)
+        .map(|_| Node { neighbors: vec![] })
+        .collect::<Vec<_>>();
+    for (a, b) in edges {
+        nodes[a].neighbors.push(b);
+        nodes[b].neighbors.push(a);
+    }
+    nodes
+}
+
+/// Single-direction BFS from the source, as the pathfinding used to be done. Returns the length of
+/// the shortest path, with the same constraints as [`do_pathfinding`].
+fn reference_distance(settings: &PathSectionSettings, data: &[Node]) -> Option<usize> {
+    let src = settings.path_src.unwrap();
+    let dest = settings.path_dest.unwrap();
+    let mutual: HashSet<usize> = if settings.path_no_mutual {
+        let dest_nb: HashSet<_> = data[dest].neighbors.iter().copied().collect();
+        data[src]
+            .neighbors
+            .iter()
+            .copied()
+            .filter(|nb| dest_nb.contains(nb))
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    let mut dist = vec![None; data.len()];
+    let mut queue = VecDeque::new();
+    dist[src] = Some(0);
+    queue.push_back(src);
+    while let Some(current) = queue.pop_front() {
+        if current == dest {
+            break;
+        }
+        for &nb in &data[current].neighbors {
+            if settings.path_no_direct
+                && ((current, nb) == (src, dest) || (current, nb) == (dest, src))
+            {
+                continue;
+            }
+            if mutual.contains(&nb) || settings.exclude_ids.contains(&nb) {
+                continue;
+            }
+            if dist[nb].is_none() {
+                dist[nb] = Some(dist[current].unwrap() + 1);
+                queue.push_back(nb);
+            }
+        }
+    }
+    dist[dest]
+}
+
+/// Checks that the path goes from the source to the destination through existing edges, and
+/// respects the constraints.
+fn check_path(settings: &PathSectionSettings, data: &[Node], path: &[usize]) {
+    let src = settings.path_src.unwrap();
+    let dest = settings.path_dest.unwrap();
+    assert_eq!(path.first(), Some(&src));
+    assert_eq!(path.last(), Some(&dest));
+    for (&a, &b) in path.iter().zip(&path[1..]) {
+        assert!(data[a].neighbors.contains(&b), "no edge {a}-{b}");
+    }
+    if settings.path_no_direct {
+        assert_ne!(path.len(), 2, "the direct link was used");
+    }
+    for id in &path[1..path.len() - 1] {
+        assert!(!settings.exclude_ids.contains(id), "{id} is excluded");
+        if settings.path_no_mutual {
+            assert!(
+                !(data[src].neighbors.contains(id) && data[dest].neighbors.contains(id)),
+                "{id} is a mutual friend"
+            );
+        }
+    }
+}
+
+#[test]
+fn bidirectional_matches_reference() {
+    let rng = &mut StdRng::seed_from_u64(42);
+    let data = fixture_graph(rng, 500, 4, 40);
+
+    for i in 0..2000 {
+        let src = rng.gen_range(0..data.len());
+        let dest = rng.gen_range(0..data.len());
+        if src == dest {
+            continue;
+        }
+        let settings = PathSectionSettings {
+            path_src: Some(src),
+            path_dest: Some(dest),
+            exclude_ids: (0..rng.gen_range(0..20))
+                .map(|_| rng.gen_range(0..data.len()))
+                .filter(|&id| id != src && id != dest)
+                .collect(),
+            path_no_direct: i % 2 == 0,
+            path_no_mutual: i % 3 == 0,
+            path_directed: false,
+            max_paths: 1,
+            exclude_classes: vec![],
+        };
+
+        let expected = reference_distance(&settings, &data);
+        let path = do_pathfinding(settings.clone(), &data)
+            .map(|res| res.paths[0].clone())
+            .ok();
+        assert_eq!(
+            path.as_ref().map(|p| p.len() - 1),
+            expected,
+            "distance mismatch for {src} -> {dest}"
+        );
+        if let Some(path) = path {
+            check_path(&settings, &data, &path);
+        }
+    }
+}
+
+#[test]
+fn disconnected_has_no_path() {
+    let data = vec![
+        Node { neighbors: vec![1] },
+        Node { neighbors: vec![0] },
+        Node { neighbors: vec![3] },
+        Node { neighbors: vec![2] },
+    ];
+    let settings = PathSectionSettings {
+        path_src: Some(0),
+        path_dest: Some(3),
+        ..Default::default()
+    };
+    assert!(do_pathfinding(settings, &data).is_err());
+    assert_eq!(shortest_path(&data, 0, 3, &PathOptions::default()), None);
+}
+
+#[test]
+fn same_source_and_destination() {
+    let data = grid(3);
+    assert_eq!(
+        shortest_path(&data, 4, 4, &PathOptions::default()),
+        Some(vec![4])
+    );
+    // even for an isolated node
+    let data = vec![Node { neighbors: vec![] }];
+    assert_eq!(
+        shortest_path(&data, 0, 0, &PathOptions::default()),
+        Some(vec![0])
+    );
+}
+
+#[test]
+fn path_constraints() {
+    // 0 and 1 are linked directly, through their mutual friend 2, and through 3 then 4
+    let data = [
+        vec![1, 2, 3],
+        vec![0, 2, 4],
+        vec![0, 1],
+        vec![0, 4],
+        vec![1, 3],
+    ]
+    .map(|neighbors| Node { neighbors });
+    let path = |options: PathOptions| shortest_path(&data, 0, 1, &options);
+
+    assert_eq!(path(PathOptions::default()), Some(vec![0, 1]));
+    assert_eq!(
+        path(PathOptions {
+            no_direct: true,
+            ..Default::default()
+        }),
+        Some(vec![0, 2, 1])
+    );
+    assert_eq!(
+        path(PathOptions {
+            no_direct: true,
+            no_mutual: true,
+            ..Default::default()
+        }),
+        Some(vec![0, 3, 4, 1])
+    );
+    // excluding a node of the path reroutes around it
+    assert_eq!(
+        path(PathOptions {
+            no_direct: true,
+            exclude_ids: vec![2],
+            ..Default::default()
+        }),
+        Some(vec![0, 3, 4, 1])
+    );
+    assert_eq!(
+        path(PathOptions {
+            no_direct: true,
+            no_mutual: true,
+            exclude_ids: vec![4],
+            ..Default::default()
+        }),
+        None
+    );
+}
+
+#[test]
+fn all_shortest_paths() {
+    let data = grid(3);
+    let mut settings = PathSectionSettings {
+        path_src: Some(0),
+        path_dest: Some(8),
+        max_paths: 10,
+        ..Default::default()
+    };
+    let paths = do_pathfinding(settings.clone(), &data).unwrap().paths;
+    // choosing 2 moves to the right among 4
+    assert_eq!(paths.len(), 6);
+    assert_eq!(paths.iter().collect::<HashSet<_>>().len(), 6);
+    for path in &paths {
+        assert_eq!(path.len(), 5);
+        check_path(&settings, &data, path);
+    }
+
+    // the first path is the same as when asking for a single one
+    settings.max_paths = 1;
+    let single = do_pathfinding(settings.clone(), &data).unwrap().paths;
+    assert_eq!(single, vec![paths[0].clone()]);
+
+    // the enumeration stops at the limit
+    settings.max_paths = 4;
+    assert_eq!(
+        do_pathfinding(settings.clone(), &data).unwrap().paths.len(),
+        4
+    );
+
+    // excluding the center leaves the two paths along the border
+    settings.max_paths = 10;
+    settings.exclude_ids = vec![4];
+    let paths = do_pathfinding(settings.clone(), &data).unwrap().paths;
+    assert_eq!(paths.len(), 2);
+    for path in &paths {
+        check_path(&settings, &data, path);
+    }
+}
+
+struct ClassNode {
+    neighbors: Vec<usize>,
+    class: u32,
+}
+
+impl AbstractNode for ClassNode {
+    fn neighbors(&self) -> &Vec<usize> {
+        &self.neighbors
+    }
+
+    fn display(&self) -> &str {
+        ""
+    }
+
+    fn modularity_class(&self) -> u32 {
+        self.class
+    }
+}
+
+#[test]
+fn excluded_classes_are_avoided() {
+    // 0 and 2 are linked through 1 and 4 (class 1), and through 3 (class 2)
+    let node = |neighbors: Vec<usize>, class| ClassNode { neighbors, class };
+    let data = vec![
+        node(vec![1, 3, 4], 1),
+        node(vec![0, 2], 1),
+        node(vec![1, 3, 4], 0),
+        node(vec![0, 2], 2),
+        node(vec![0, 2], 1),
+    ];
+    let mut settings = PathSectionSettings {
+        path_src: Some(0),
+        path_dest: Some(2),
+        max_paths: 10,
+        // the source being in an excluded class doesn't matter
+        exclude_classes: vec![1],
+        ..Default::default()
+    };
+    assert_eq!(
+        do_pathfinding(settings.clone(), &data).unwrap().paths,
+        vec![vec![0, 3, 2]]
+    );
+
+    settings.exclude_classes = vec![1, 2];
+    assert_eq!(
+        do_pathfinding(settings, &data).unwrap_err(),
+        PathNotFound {
+            blocking_class: Some(1)
+        }
+    );
+}
@@ -0,0 +1,122 @@
+use graph_format::{Color3b, GraphFile, LenType, NodeStore, Point, Writable};
+use viewer::graph_storage::{load_binary, ProcessedData};
+use viewer::search::{normalize, SearchOptions};
+use viewer::threading::NullStatusWriter;
+
+const NAMES: &[&str] = &[
+    "Zoé",
+    "Hélène",
+    "Chloé",
+    "Marc",
+    "Marcel",
+    "Marc-Antoine",
+    "Damarcus",
+    "Jérôme Dupont",
+    "Jerome Martin",
+];
+
+/// Graph with the names above. The Marcs have increasing degrees, and Damarcus, which only
+/// contains "marc", has more links than all of them.
+fn fixture() -> ProcessedData {
+    let mut ids = Vec::new();
+    let mut names = Vec::new();
+    let nodes = NAMES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let node = NodeStore {
+                position: Point::new(i as f32, 0.0),
+                size: 1.0,
+                class: 0,
+                offset_id: ids.len() as LenType,
+                offset_name: names.len() as LenType,
+                total_edge_count: 0,
+                edge_count: 0,
+                edges: vec![],
+            };
+            ids.extend_from_slice(format!("{i}\0").as_bytes());
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+            node
+        })
+        .collect();
+    let mut file = GraphFile {
+        magic: 0,
+        version: 0,
+        directed: false,
+        class_count: 0,
+        classes: vec![Color3b::new(255, 0, 0)],
+        node_count: 0,
+        nodes,
+        ids_size: 0,
+        ids,
+        names_size: 0,
+        names,
+    };
+    let mut edges = vec![
+        (0, 3),
+        (0, 4),
+        (1, 4),
+        (0, 5),
+        (1, 5),
+        (2, 5),
+        (0, 6),
+        (1, 6),
+        (2, 6),
+        (7, 6),
+    ];
+    file.set_edges(&mut edges).unwrap();
+    file.finish_header();
+    load_binary(&NullStatusWriter, file.write_to_vec().unwrap().into()).unwrap()
+}
+
+fn search(data: &ProcessedData, query: &str, options: SearchOptions) -> Vec<&'static str> {
+    let persons = &data.viewer.persons;
+    data.viewer
+        .engine
+        .search_n(query, 10, options)
+        .into_iter()
+        .map(|m| persons[m.node].name)
+        .collect()
+}
+
+#[test]
+fn names_are_folded() {
+    assert_eq!(normalize("Jérôme", true), "jerome");
+    assert_eq!(normalize("ÉLODIE", true), "elodie");
+    assert_eq!(normalize("Ångström", true), "angstrom");
+    assert_eq!(normalize("Jérôme", false), "jérôme");
+}
+
+#[test]
+fn accents_are_ignored() {
+    let data = fixture();
+    let options = SearchOptions::default();
+    let found = search(&data, "Jerome", options);
+    assert_eq!(found[..2], ["Jérôme Dupont", "Jerome Martin"]);
+    assert_eq!(search(&data, "helene", options)[0], "Hélène");
+    assert_eq!(search(&data, "CHLOÉ", options)[0], "Chloé");
+}
+
+#[test]
+fn accents_are_matched_when_asked() {
+    let data = fixture();
+    let options = SearchOptions {
+        ignore_accents: false,
+        ..SearchOptions::default()
+    };
+    assert_eq!(search(&data, "jérôme", options)[0], "Jérôme Dupont");
+    assert_eq!(search(&data, "jerome", options)[0], "Jerome Martin");
+}
+
+#[test]
+fn prefixes_come_first_by_degree() {
+    let data = fixture();
+    let found = search(&data, "marc", SearchOptions::default());
+    assert_eq!(found[..4], ["Marc-Antoine", "Marcel", "Marc", "Damarcus"]);
+    // a word starting with the query counts as a prefix
+    assert_eq!(
+        search(&data, "dupont", SearchOptions::default())[0],
+        "Jérôme Dupont"
+    );
+}
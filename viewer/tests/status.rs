@@ -0,0 +1,43 @@
+use viewer::threading::{status_pipe, Progress, StatusWriterInterface, MAX_STATUS_LINES};
+
+#[test]
+fn latest_progress_wins() {
+    let (tx, mut rx) = status_pipe(&egui::Context::default());
+    for val in 0..1000 {
+        tx.send(Progress { max: 1000, val }).unwrap();
+    }
+    rx.recv();
+    let progress = rx.progress().unwrap();
+    assert_eq!((progress.val, progress.max), (999, 1000));
+}
+
+#[test]
+fn message_ends_progress() {
+    let (tx, mut rx) = status_pipe(&egui::Context::default());
+    tx.send(Progress { max: 10, val: 5 }).unwrap();
+    tx.send("Next step".to_string()).unwrap();
+    assert_eq!(rx.recv(), "Next step");
+    assert!(rx.progress().is_none());
+}
+
+#[test]
+fn only_last_lines_are_kept() {
+    let (tx, mut rx) = status_pipe(&egui::Context::default());
+    for i in 0..MAX_STATUS_LINES + 5 {
+        tx.send(format!("line {i}")).unwrap();
+    }
+    let lines = rx.recv().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), MAX_STATUS_LINES);
+    assert_eq!(lines[0], "line 5");
+    assert_eq!(
+        *lines.last().unwrap(),
+        format!("line {}", MAX_STATUS_LINES + 4)
+    );
+}
+
+#[test]
+fn send_fails_once_reader_is_dropped() {
+    let (tx, rx) = status_pipe(&egui::Context::default());
+    drop(rx);
+    assert!(tx.send("lost".to_string()).is_err());
+}